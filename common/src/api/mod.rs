@@ -4,19 +4,90 @@ use crate::errors::Error;
 use crate::utils::zstd_compress;
 use anyhow::{Context, anyhow};
 use async_trait::async_trait;
+use data_encoding::BASE64;
 use log::debug;
+use rand::distr::{Alphanumeric, SampleString};
 use reqwest::header::CONTENT_ENCODING;
 use reqwest::{RequestBuilder, Response};
+use sha2::{Digest, Sha256};
 use std::borrow::Cow;
 use std::env;
+use std::sync::Arc;
 use url::Url;
 
+pub mod multipart;
 pub mod v0;
 pub mod v1;
 
 pub const AUTH_COOKIE_HEADER: &str = "X-Auth-Cookie";
 pub const WORKER_KEY_HEADER: &str = "X-Worker-Key";
 pub const SIGNUP_SECRET_HEADER: &str = "X-Signup-Secret";
+pub const WORKER_SIGNATURE_HEADER: &str = "X-Worker-Signature";
+pub const WORKER_TIMESTAMP_HEADER: &str = "X-Worker-Timestamp";
+pub const WORKER_NONCE_HEADER: &str = "X-Worker-Nonce";
+/// Sent along with attestations, tells whether the attestation passed verification when it was uploaded by the worker.
+pub const ATTESTATION_VERIFIED_HEADER: &str = "X-Attestation-Verified";
+
+/// Signs requests on behalf of a worker, proving ownership of the key sent in [`WORKER_KEY_HEADER`].
+pub trait RequestSigner: Send + Sync {
+    /// Returns the raw Ed25519 signature of `message`.
+    fn sign(&self, message: &[u8]) -> anyhow::Result<Vec<u8>>;
+}
+
+impl<F> RequestSigner for F
+where
+    F: Fn(&[u8]) -> anyhow::Result<Vec<u8>> + Send + Sync,
+{
+    fn sign(&self, message: &[u8]) -> anyhow::Result<Vec<u8>> {
+        self(message)
+    }
+}
+
+/// Builds the message a worker signs for a request. Both the worker and the daemon need to arrive at the exact same
+/// bytes, so the body is hashed before compression and the path is relative to the rebuilderd endpoint. The query
+/// string is signed as sent, the nonce makes sure the daemon accepts every signed request only once.
+/// ```
+/// use rebuilderd_common::api::worker_request_message;
+///
+/// let message = worker_request_message(
+///     "POST",
+///     "/api/v1/queue/1/log",
+///     "lease=abc",
+///     1700000000,
+///     "nonce",
+///     b"",
+/// );
+/// assert_eq!(
+///     message,
+///     b"rebuilderd-worker-request-v1\nPOST\n/api/v1/queue/1/log\nlease=abc\n1700000000\nnonce\n\
+///       e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+/// );
+/// ```
+pub fn worker_request_message(
+    method: &str,
+    path: &str,
+    query: &str,
+    timestamp: i64,
+    nonce: &str,
+    body: &[u8],
+) -> Vec<u8> {
+    let body_hash = Sha256::digest(body)
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect::<String>();
+
+    format!(
+        "rebuilderd-worker-request-v1\n{method}\n{path}\n{query}\n{timestamp}\n{nonce}\n{body_hash}"
+    )
+    .into_bytes()
+}
+
+/// Whether a worker request was rejected because the job has been handed to another worker in the meantime
+pub fn is_lease_lost(err: &Error) -> bool {
+    err.downcast_ref::<reqwest::Error>()
+        .and_then(|err| err.status())
+        == Some(reqwest::StatusCode::CONFLICT)
+}
 
 pub struct Client {
     endpoint: Url,
@@ -24,6 +95,7 @@ pub struct Client {
     is_default_endpoint: bool,
     auth_cookie: Option<String>,
     worker_key: Option<String>,
+    worker_signer: Option<Arc<dyn RequestSigner>>,
     signup_secret: Option<String>,
 }
 
@@ -60,6 +132,7 @@ impl Client {
             is_default_endpoint,
             auth_cookie,
             worker_key: None,
+            worker_signer: None,
             signup_secret: None,
         })
     }
@@ -87,6 +160,10 @@ impl Client {
         self.worker_key = Some(key.into());
     }
 
+    pub fn worker_signer<S: RequestSigner + 'static>(&mut self, signer: S) {
+        self.worker_signer = Some(Arc::new(signer));
+    }
+
     pub fn signup_secret<I: Into<String>>(&mut self, secret: I) {
         self.signup_secret = Some(secret.into());
     }
@@ -120,6 +197,48 @@ impl Client {
         req
     }
 
+    /// Adds a signature over method, path, query, timestamp, a random nonce and body to a request, if a worker signer has
+    /// been configured.
+    /// This needs to happen after the body has been attached, so it can't be done in [`Client::authenticated`].
+    fn signed(&self, req: RequestBuilder) -> anyhow::Result<RequestBuilder> {
+        let Some(signer) = &self.worker_signer else {
+            return Ok(req);
+        };
+
+        let request = req
+            .try_clone()
+            .context("Failed to clone request for signing")?
+            .build()?;
+
+        let prefix = self.endpoint.path().trim_end_matches('/');
+        let path = request
+            .url()
+            .path()
+            .strip_prefix(prefix)
+            .unwrap_or(request.url().path());
+
+        let query = request.url().query().unwrap_or_default();
+
+        let body = request.body().and_then(|b| b.as_bytes()).unwrap_or(&[]);
+        let timestamp = chrono::Utc::now().timestamp();
+        let nonce = Alphanumeric.sample_string(&mut rand::rng(), 32);
+
+        let message = worker_request_message(
+            request.method().as_str(),
+            path,
+            query,
+            timestamp,
+            &nonce,
+            body,
+        );
+        let signature = signer.sign(&message)?;
+
+        Ok(req
+            .header(WORKER_TIMESTAMP_HEADER, timestamp.to_string())
+            .header(WORKER_NONCE_HEADER, nonce)
+            .header(WORKER_SIGNATURE_HEADER, BASE64.encode(&signature)))
+    }
+
     fn get(&self, path: Cow<'static, str>) -> crate::http::RequestBuilder {
         let url = self.url_join(&path);
         debug!("Sending GET request to {}", url.as_str());
@@ -134,6 +253,13 @@ impl Client {
         self.authenticated(req)
     }
 
+    fn put(&self, path: Cow<'static, str>) -> crate::http::RequestBuilder {
+        let url = self.url_join(&path);
+        debug!("Sending PUT request to {}", url.as_str());
+        let req = self.client.put(url);
+        self.authenticated(req)
+    }
+
     fn delete(&self, path: Cow<'static, str>) -> crate::http::RequestBuilder {
         let url = self.url_join(&path);
         debug!("Sending DELETE request to {}", url.as_str());
@@ -0,0 +1,64 @@
+use rand::distr::{Alphanumeric, SampleString};
+
+/// Assembles a multipart/form-data body in memory.
+///
+/// reqwest can do this too, but only as a streaming body, which we can neither sign nor compress before sending.
+/// ```
+/// use rebuilderd_common::api::multipart::MultipartBuilder;
+///
+/// let mut form = MultipartBuilder::new();
+/// form.part("manifest", "application/json", b"{}");
+/// let (content_type, body) = form.finish();
+///
+/// let boundary = content_type.strip_prefix("multipart/form-data; boundary=").unwrap();
+/// let body = String::from_utf8(body).unwrap();
+/// assert!(body.starts_with(&format!("--{boundary}\r\n")));
+/// assert!(body.contains("Content-Disposition: form-data; name=\"manifest\"\r\n"));
+/// assert!(body.ends_with(&format!("\r\n{{}}\r\n--{boundary}--\r\n")));
+/// ```
+pub struct MultipartBuilder {
+    boundary: String,
+    body: Vec<u8>,
+}
+
+impl MultipartBuilder {
+    pub fn new() -> Self {
+        let boundary = format!(
+            "rebuilderd-{}",
+            Alphanumeric.sample_string(&mut rand::rng(), 32)
+        );
+
+        MultipartBuilder {
+            boundary,
+            body: Vec::new(),
+        }
+    }
+
+    pub fn part(&mut self, name: &str, content_type: &str, data: &[u8]) -> &mut Self {
+        self.body.extend(
+            format!(
+                "--{}\r\nContent-Disposition: form-data; name=\"{name}\"\r\nContent-Type: {content_type}\r\n\r\n",
+                self.boundary
+            )
+            .as_bytes(),
+        );
+        self.body.extend(data);
+        self.body.extend(b"\r\n");
+        self
+    }
+
+    /// Returns the value for the Content-Type header and the encoded body.
+    pub fn finish(mut self) -> (String, Vec<u8>) {
+        self.body
+            .extend(format!("--{}--\r\n", self.boundary).as_bytes());
+
+        let content_type = format!("multipart/form-data; boundary={}", self.boundary);
+        (content_type, self.body)
+    }
+}
+
+impl Default for MultipartBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
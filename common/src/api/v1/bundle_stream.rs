@@ -0,0 +1,48 @@
+use crate::bundle::BundleLine;
+use crate::errors::*;
+use reqwest::Response;
+
+/// Reads an attestation bundle line by line while it's being downloaded.
+pub struct BundleStream {
+    response: Response,
+    buf: Vec<u8>,
+    eof: bool,
+}
+
+fn parse_line(line: &[u8]) -> Result<BundleLine> {
+    serde_json::from_slice(line).context("Failed to parse line of attestation bundle")
+}
+
+impl BundleStream {
+    pub(crate) fn new(response: Response) -> Self {
+        BundleStream {
+            response,
+            buf: Vec::new(),
+            eof: false,
+        }
+    }
+
+    /// Returns the next line of the bundle, or `None` once the download is complete.
+    pub async fn next_line(&mut self) -> Result<Option<BundleLine>> {
+        loop {
+            if let Some(pos) = self.buf.iter().position(|b| *b == b'\n') {
+                let line = self.buf.drain(..=pos).collect::<Vec<_>>();
+                return parse_line(&line).map(Some);
+            }
+
+            if self.eof {
+                if self.buf.is_empty() {
+                    return Ok(None);
+                }
+                let line = std::mem::take(&mut self.buf);
+                return parse_line(&line).map(Some);
+            }
+
+            if let Some(chunk) = self.response.chunk().await? {
+                self.buf.extend(&chunk);
+            } else {
+                self.eof = true;
+            }
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use crate::errors::*;
+use reqwest::Response;
+
+/// Follows the log of a running build, as sent by the daemon using server-sent events.
+pub struct LogStream {
+    response: Response,
+    buf: Vec<u8>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Event {
+    Output(String),
+    KeepAlive,
+    End,
+}
+
+fn parse_event(event: &[u8]) -> Event {
+    let event = String::from_utf8_lossy(event);
+
+    let mut data = None::<String>;
+    for line in event.lines() {
+        if line == "event: end" {
+            return Event::End;
+        } else if let Some(line) = line.strip_prefix("data:") {
+            let line = line.strip_prefix(' ').unwrap_or(line);
+            if let Some(data) = &mut data {
+                data.push('\n');
+                data.push_str(line);
+            } else {
+                data = Some(line.to_string());
+            }
+        }
+    }
+
+    data.map(Event::Output).unwrap_or(Event::KeepAlive)
+}
+
+impl LogStream {
+    pub(crate) fn new(response: Response) -> Self {
+        LogStream {
+            response,
+            buf: Vec::new(),
+        }
+    }
+
+    /// Returns the next piece of build output, or `None` once the build has finished.
+    pub async fn next_output(&mut self) -> Result<Option<String>> {
+        loop {
+            if let Some(pos) = self.buf.windows(2).position(|w| w == b"\n\n") {
+                let event = self.buf.drain(..pos + 2).collect::<Vec<_>>();
+                match parse_event(&event) {
+                    Event::Output(output) => return Ok(Some(output)),
+                    Event::KeepAlive => continue,
+                    Event::End => return Ok(None),
+                }
+            }
+
+            let Some(chunk) = self.response.chunk().await? else {
+                return Ok(None);
+            };
+            self.buf.extend(&chunk);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_output_event() {
+        let event = parse_event(b"data: hello\ndata: world\n\n");
+        assert_eq!(event, Event::Output("hello\nworld".to_string()));
+    }
+
+    #[test]
+    fn parse_output_event_with_trailing_newline() {
+        let event = parse_event(b"data: hello\ndata: \n\n");
+        assert_eq!(event, Event::Output("hello\n".to_string()));
+    }
+
+    #[test]
+    fn parse_comment() {
+        let event = parse_event(b": keep-alive\n\n");
+        assert_eq!(event, Event::KeepAlive);
+    }
+
+    #[test]
+    fn parse_end_event() {
+        let event = parse_event(b"event: end\ndata:\n\n");
+        assert_eq!(event, Event::End);
+    }
+}
@@ -1,9 +1,16 @@
+mod bundle_stream;
+mod log_stream;
 mod models;
 
+use crate::api::multipart::MultipartBuilder;
 use crate::api::{Client, ZstdRequestBuilder};
 use crate::errors::*;
 use async_trait::async_trait;
+pub use bundle_stream::*;
+pub use log_stream::*;
 pub use models::*;
+use reqwest::StatusCode;
+use reqwest::header::{ACCEPT, CONTENT_TYPE};
 use std::borrow::Cow;
 
 #[cfg(feature = "diesel")]
@@ -95,6 +102,11 @@ impl From<i32> for Priority {
     }
 }
 
+#[async_trait]
+pub trait AuditRestApi {
+    async fn get_audit_events(&self, page: Option<&Page>) -> Result<ResultPage<AuditEvent>>;
+}
+
 #[async_trait]
 pub trait BuildRestApi {
     async fn get_builds(
@@ -102,9 +114,12 @@ pub trait BuildRestApi {
         page: Option<&Page>,
         origin_filter: Option<&OriginFilter>,
         source_identity_filter: Option<&SourceIdentityFilter>,
+        worker_filter: Option<&WorkerFilter>,
     ) -> Result<ResultPage<Rebuild>>;
 
     async fn submit_build_report(&self, request: RebuildReport) -> Result<()>;
+    /// Submits the report as a single JSON document, for daemons that don't support multipart uploads yet.
+    async fn submit_build_report_json(&self, request: RebuildReport) -> Result<()>;
     async fn get_build(&self, id: i32) -> Result<Rebuild>;
     async fn get_build_log(&self, id: i32) -> Result<String>;
     async fn get_build_artifacts(&self, id: i32) -> Result<Vec<RebuildArtifact>>;
@@ -136,6 +151,8 @@ pub trait MetaRestApi {
 pub trait PackageRestApi {
     async fn submit_package_report(&self, report: &PackageReport) -> Result<()>;
 
+    async fn set_required_labels(&self, request: &RequiredLabelsRequest) -> Result<()>;
+
     async fn get_source_packages(
         &self,
         page: Option<&Page>,
@@ -153,6 +170,16 @@ pub trait PackageRestApi {
     ) -> Result<ResultPage<BinaryPackage>>;
 
     async fn get_binary_package(&self, id: i32) -> Result<BinaryPackage>;
+
+    async fn lookup_binary_packages(
+        &self,
+        query: &ArtifactDigestQuery,
+    ) -> Result<Vec<ArtifactLookupResult>>;
+
+    async fn get_attestation_bundle(
+        &self,
+        origin_filter: Option<&OriginFilter>,
+    ) -> Result<BundleStream>;
 }
 
 #[async_trait]
@@ -173,7 +200,28 @@ pub trait QueueRestApi {
         source_identity_filter: Option<&SourceIdentityFilter>,
     ) -> Result<()>;
     async fn request_work(&self, request: PopQueuedJobRequest) -> Result<JobAssignment>;
-    async fn ping_job(&self, id: i32) -> Result<()>;
+    async fn ping_job(&self, id: i32, lease: &str) -> Result<PingQueuedJobResponse>;
+    async fn push_queued_job_log(&self, id: i32, lease: &str, chunk: Vec<u8>) -> Result<()>;
+    async fn get_queued_job_log(&self, id: i32) -> Result<String>;
+    async fn follow_queued_job_log(&self, id: i32) -> Result<LogStream>;
+}
+
+#[async_trait]
+pub trait TokenRestApi {
+    async fn get_api_tokens(&self) -> Result<Vec<ApiToken>>;
+    async fn create_api_token(&self, request: CreateApiTokenRequest) -> Result<CreatedApiToken>;
+    async fn revoke_api_token(&self, id: i32) -> Result<()>;
+}
+
+#[async_trait]
+pub trait TransparencyRestApi {
+    async fn get_tree_head(&self) -> Result<SignedTreeHead>;
+    async fn get_transparency_log_entry(&self, leaf_index: u64) -> Result<Vec<u8>>;
+    async fn get_inclusion_proof(&self, query: &InclusionProofQuery) -> Result<InclusionProof>;
+    async fn get_consistency_proof(
+        &self,
+        query: &ConsistencyProofQuery,
+    ) -> Result<ConsistencyProof>;
 }
 
 #[async_trait]
@@ -181,9 +229,26 @@ pub trait WorkerRestApi {
     async fn get_workers(&self, page: Option<&Page>) -> Result<ResultPage<Worker>>;
     async fn register_worker(&self, request: RegisterWorkerRequest) -> Result<()>;
     async fn get_worker(&self, id: i32) -> Result<Worker>;
+    async fn set_worker_state(&self, id: i32, state: WorkerState) -> Result<Worker>;
     async fn unregister_worker(&self, id: i32) -> Result<()>;
 }
 
+#[async_trait]
+impl AuditRestApi for Client {
+    async fn get_audit_events(&self, page: Option<&Page>) -> Result<ResultPage<AuditEvent>> {
+        let records = self
+            .get(Cow::Borrowed("api/v1/audit"))
+            .query(&page)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        Ok(records)
+    }
+}
+
 #[async_trait]
 impl BuildRestApi for Client {
     async fn get_builds(
@@ -191,12 +256,14 @@ impl BuildRestApi for Client {
         page: Option<&Page>,
         origin_filter: Option<&OriginFilter>,
         source_identity_filter: Option<&SourceIdentityFilter>,
+        worker_filter: Option<&WorkerFilter>,
     ) -> Result<ResultPage<Rebuild>> {
         let records = self
             .get(Cow::Borrowed("api/v1/builds"))
             .query(&page)
             .query(&origin_filter)
             .query(&source_identity_filter)
+            .query(&worker_filter)
             .send()
             .await?
             .error_for_status()?
@@ -207,8 +274,73 @@ impl BuildRestApi for Client {
     }
 
     async fn submit_build_report(&self, request: RebuildReport) -> Result<()> {
-        self.post(Cow::Borrowed("api/v1/builds"))
-            .json(&request)
+        let mut form = MultipartBuilder::new();
+        let mut artifacts = Vec::new();
+
+        form.part("build_log", "application/octet-stream", &request.build_log);
+
+        for (i, artifact) in request.artifacts.iter().enumerate() {
+            let diffoscope = artifact.diffoscope.as_ref().map(|diffoscope| {
+                let name = format!("diffoscope-{i}");
+                form.part(&name, "application/octet-stream", diffoscope);
+                name
+            });
+
+            let attestation = artifact.attestation.as_ref().map(|attestation| {
+                let name = format!("attestation-{i}");
+                form.part(&name, "application/octet-stream", attestation);
+                name
+            });
+
+            artifacts.push(RebuildArtifactManifest {
+                name: artifact.name.clone(),
+                diffoscope,
+                attestation,
+                status: artifact.status.clone(),
+                upstream_sha256: artifact.upstream_sha256.clone(),
+                rebuilt_sha256: artifact.rebuilt_sha256.clone(),
+                upstream_sha512: artifact.upstream_sha512.clone(),
+                rebuilt_sha512: artifact.rebuilt_sha512.clone(),
+            });
+        }
+
+        let manifest = RebuildReportManifest {
+            queue_id: request.queue_id,
+            lease: request.lease.clone(),
+            built_at: request.built_at,
+            build_log: "build_log".to_string(),
+            status: request.status.clone(),
+            failure_reason: request.failure_reason.clone(),
+            artifacts,
+        };
+        form.part(
+            "manifest",
+            "application/json",
+            &serde_json::to_vec(&manifest)?,
+        );
+
+        let (content_type, body) = form.finish();
+        let response = self
+            .signed(
+                self.post(Cow::Borrowed("api/v1/builds/upload"))
+                    .header(CONTENT_TYPE, content_type)
+                    .body(body),
+            )?
+            .send_encoded()
+            .await?;
+
+        // daemons that predate the upload endpoint only accept json reports
+        if response.status() == StatusCode::NOT_FOUND {
+            debug!("Upload endpoint not found, submitting build report as json");
+            return self.submit_build_report_json(request).await;
+        }
+        response.error_for_status()?;
+
+        Ok(())
+    }
+
+    async fn submit_build_report_json(&self, request: RebuildReport) -> Result<()> {
+        self.signed(self.post(Cow::Borrowed("api/v1/builds")).json(&request))?
             .send_encoded()
             .await?
             .error_for_status()?;
@@ -396,6 +528,16 @@ impl PackageRestApi for Client {
         Ok(())
     }
 
+    async fn set_required_labels(&self, request: &RequiredLabelsRequest) -> Result<()> {
+        self.put(Cow::Borrowed("api/v1/packages/labels"))
+            .json(request)
+            .send_encoded()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+
     async fn get_source_packages(
         &self,
         page: Option<&Page>,
@@ -459,6 +601,36 @@ impl PackageRestApi for Client {
 
         Ok(record)
     }
+
+    async fn lookup_binary_packages(
+        &self,
+        query: &ArtifactDigestQuery,
+    ) -> Result<Vec<ArtifactLookupResult>> {
+        let records = self
+            .get(Cow::Borrowed("api/v1/packages/binary/lookup"))
+            .query(query)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        Ok(records)
+    }
+
+    async fn get_attestation_bundle(
+        &self,
+        origin_filter: Option<&OriginFilter>,
+    ) -> Result<BundleStream> {
+        let response = self
+            .get(Cow::Borrowed("api/v1/packages/binary/attestations"))
+            .query(&origin_filter)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(BundleStream::new(response))
+    }
 }
 
 #[async_trait]
@@ -531,8 +703,7 @@ impl QueueRestApi for Client {
 
     async fn request_work(&self, request: PopQueuedJobRequest) -> Result<JobAssignment> {
         let record = self
-            .post(Cow::Borrowed("api/v1/queue/pop"))
-            .json(&request)
+            .signed(self.post(Cow::Borrowed("api/v1/queue/pop")).json(&request))?
             .send_encoded()
             .await?
             .error_for_status()?
@@ -542,10 +713,152 @@ impl QueueRestApi for Client {
         Ok(record)
     }
 
-    async fn ping_job(&self, id: i32) -> Result<()> {
-        // nginx dies if proxying a request without a Content-Length header
-        self.post(Cow::Owned(format!("api/v1/queue/{id}/ping")))
-            .header("Content-Length", 0)
+    async fn ping_job(&self, id: i32, lease: &str) -> Result<PingQueuedJobResponse> {
+        let request = PingQueuedJobRequest {
+            lease: lease.to_string(),
+        };
+        let response = self
+            .signed(
+                self.post(Cow::Owned(format!("api/v1/queue/{id}/ping")))
+                    .json(&request),
+            )?
+            .send_encoded()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        Ok(response)
+    }
+
+    async fn push_queued_job_log(&self, id: i32, lease: &str, chunk: Vec<u8>) -> Result<()> {
+        let query = PushQueuedJobLogQuery {
+            lease: lease.to_string(),
+        };
+        self.signed(
+            self.post(Cow::Owned(format!("api/v1/queue/{id}/log")))
+                .query(&query)
+                .header(CONTENT_TYPE, "application/octet-stream")
+                .body(chunk),
+        )?
+        .send_encoded()
+        .await?
+        .error_for_status()?;
+
+        Ok(())
+    }
+
+    async fn get_queued_job_log(&self, id: i32) -> Result<String> {
+        let data = self
+            .get(Cow::Owned(format!("api/v1/queue/{id}/log")))
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+
+        Ok(data)
+    }
+
+    async fn follow_queued_job_log(&self, id: i32) -> Result<LogStream> {
+        let response = self
+            .get(Cow::Owned(format!("api/v1/queue/{id}/log")))
+            .header(ACCEPT, "text/event-stream")
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(LogStream::new(response))
+    }
+}
+
+#[async_trait]
+impl TransparencyRestApi for Client {
+    async fn get_tree_head(&self) -> Result<SignedTreeHead> {
+        let tree_head = self
+            .get(Cow::Borrowed("api/v1/transparency/tree-head"))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        Ok(tree_head)
+    }
+
+    async fn get_transparency_log_entry(&self, leaf_index: u64) -> Result<Vec<u8>> {
+        let data = self
+            .get(Cow::Owned(format!(
+                "api/v1/transparency/entries/{leaf_index}"
+            )))
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+
+        Ok(Vec::from(data))
+    }
+
+    async fn get_inclusion_proof(&self, query: &InclusionProofQuery) -> Result<InclusionProof> {
+        let proof = self
+            .get(Cow::Borrowed("api/v1/transparency/proofs/inclusion"))
+            .query(query)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        Ok(proof)
+    }
+
+    async fn get_consistency_proof(
+        &self,
+        query: &ConsistencyProofQuery,
+    ) -> Result<ConsistencyProof> {
+        let proof = self
+            .get(Cow::Borrowed("api/v1/transparency/proofs/consistency"))
+            .query(query)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        Ok(proof)
+    }
+}
+
+#[async_trait]
+impl TokenRestApi for Client {
+    async fn get_api_tokens(&self) -> Result<Vec<ApiToken>> {
+        let tokens = self
+            .get(Cow::Borrowed("api/v1/tokens"))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        Ok(tokens)
+    }
+
+    async fn create_api_token(&self, request: CreateApiTokenRequest) -> Result<CreatedApiToken> {
+        let token = self
+            .post(Cow::Borrowed("api/v1/tokens"))
+            .json(&request)
+            .send_encoded()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        Ok(token)
+    }
+
+    async fn revoke_api_token(&self, id: i32) -> Result<()> {
+        self.delete(Cow::Owned(format!("api/v1/tokens/{id}")))
             .send()
             .await?
             .error_for_status()?;
@@ -570,8 +883,7 @@ impl WorkerRestApi for Client {
     }
 
     async fn register_worker(&self, request: RegisterWorkerRequest) -> Result<()> {
-        self.post(Cow::Borrowed("api/v1/workers"))
-            .json(&request)
+        self.signed(self.post(Cow::Borrowed("api/v1/workers")).json(&request))?
             .send_encoded()
             .await?
             .error_for_status()?;
@@ -591,8 +903,21 @@ impl WorkerRestApi for Client {
         Ok(worker)
     }
 
+    async fn set_worker_state(&self, id: i32, state: WorkerState) -> Result<Worker> {
+        let worker = self
+            .put(Cow::Owned(format!("api/v1/workers/{id}/state")))
+            .json(&SetWorkerStateRequest { state })
+            .send_encoded()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        Ok(worker)
+    }
+
     async fn unregister_worker(&self, id: i32) -> Result<()> {
-        self.delete(Cow::Owned(format!("api/v1/workers/{id}")))
+        self.signed(self.delete(Cow::Owned(format!("api/v1/workers/{id}"))))?
             .send()
             .await?
             .error_for_status()?;
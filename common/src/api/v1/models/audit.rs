@@ -0,0 +1,15 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEvent {
+    pub id: i32,
+    pub created_at: NaiveDateTime,
+    /// `cookie`, `token:<name>` or `worker:<name>`
+    pub actor: String,
+    pub source_ip: Option<String>,
+    /// The http method and the route that has been called, eg. `DELETE /api/v1/queue/{id}`
+    pub endpoint: String,
+    pub parameters: serde_json::Value,
+    pub affected_rows: i64,
+}
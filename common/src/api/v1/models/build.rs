@@ -12,9 +12,13 @@ use std::fmt::Formatter;
 #[derive(Debug, Serialize, Deserialize)]
 pub struct RebuildReport {
     pub queue_id: i32,
+    pub lease: String,
     pub built_at: NaiveDateTime,
     pub build_log: Vec<u8>,
     pub status: BuildStatus,
+    /// Why the build has failed, if the worker could tell.
+    #[serde(default)]
+    pub failure_reason: Option<FailureReason>,
     pub artifacts: Vec<RebuildArtifactReport>,
 }
 
@@ -97,12 +101,108 @@ impl ToSql<Text, Sqlite> for BuildStatus {
     }
 }
 
+/// A known cause of a failed build, that is reported next to the build status
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "diesel", derive(FromSqlRow, AsExpression))]
+#[cfg_attr(feature = "diesel", diesel(sql_type = Text))]
+#[cfg_attr(feature = "diesel", diesel(check_for_backend(diesel::sqlite::Sqlite)))]
+pub enum FailureReason {
+    /// The build has been killed for exceeding the memory limit of the worker.
+    #[serde(rename = "oom")]
+    Oom,
+}
+
+impl FailureReason {
+    pub fn as_str(&self) -> &str {
+        match self {
+            FailureReason::Oom => "oom",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FailureReasonParseError {
+    value: String,
+}
+
+impl fmt::Display for FailureReasonParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let value = &self.value;
+        write!(f, "could not parse \"{value}\" as a failure reason")
+    }
+}
+
+impl Error for FailureReasonParseError {}
+
+impl TryFrom<&str> for FailureReason {
+    type Error = FailureReasonParseError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "oom" => Ok(FailureReason::Oom),
+            _ => Err(FailureReasonParseError {
+                value: value.to_string(),
+            }),
+        }
+    }
+}
+
+#[cfg(feature = "diesel")]
+impl FromSql<Text, Sqlite> for FailureReason {
+    fn from_sql(bytes: SqliteValue) -> diesel::deserialize::Result<Self> {
+        let t = <String as FromSql<Text, Sqlite>>::from_sql(bytes)?;
+        Ok(t.as_str().try_into()?)
+    }
+}
+
+#[cfg(feature = "diesel")]
+impl ToSql<Text, Sqlite> for FailureReason {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Sqlite>) -> diesel::serialize::Result {
+        out.set_value(self.as_str());
+        Ok(diesel::serialize::IsNull::No)
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct RebuildArtifactReport {
     pub name: String,
     pub diffoscope: Option<Vec<u8>>,
     pub attestation: Option<Vec<u8>>,
     pub status: ArtifactStatus,
+    /// Hex-encoded SHA-256 of the artifact we've been asked to reproduce.
+    pub upstream_sha256: Option<String>,
+    /// Hex-encoded SHA-256 of the artifact the build produced, `None` if there was no output.
+    pub rebuilt_sha256: Option<String>,
+    /// Hex-encoded SHA-512 of the artifact we've been asked to reproduce.
+    pub upstream_sha512: Option<String>,
+    /// Hex-encoded SHA-512 of the artifact the build produced, `None` if there was no output.
+    pub rebuilt_sha512: Option<String>,
+}
+
+/// The JSON part of a rebuild report that is uploaded as multipart/form-data. Instead of embedding logs as byte arrays,
+/// it references other parts of the upload by name, which carry the raw bytes.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RebuildReportManifest {
+    pub queue_id: i32,
+    pub lease: String,
+    pub built_at: NaiveDateTime,
+    pub build_log: String,
+    pub status: BuildStatus,
+    #[serde(default)]
+    pub failure_reason: Option<FailureReason>,
+    pub artifacts: Vec<RebuildArtifactManifest>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RebuildArtifactManifest {
+    pub name: String,
+    pub diffoscope: Option<String>,
+    pub attestation: Option<String>,
+    pub status: ArtifactStatus,
+    pub upstream_sha256: Option<String>,
+    pub rebuilt_sha256: Option<String>,
+    pub upstream_sha512: Option<String>,
+    pub rebuilt_sha512: Option<String>,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize, clap::ValueEnum)]
@@ -193,6 +293,15 @@ pub struct Rebuild {
     pub started_at: Option<NaiveDateTime>,
     pub built_at: Option<NaiveDateTime>,
     pub status: Option<BuildStatus>,
+    /// The worker that did the rebuild, `None` for rebuilds from before this was recorded or if the worker has been
+    /// removed since.
+    pub worker_id: Option<i32>,
+    /// The name of the worker at the time of the rebuild.
+    pub worker_name: Option<String>,
+    /// The key of the worker at the time of the rebuild.
+    pub worker_key: Option<String>,
+    /// Why the rebuild has failed, if the worker could tell.
+    pub failure_reason: Option<FailureReason>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -204,4 +313,12 @@ pub struct RebuildArtifact {
     pub has_diffoscope: bool,
     pub has_attestation: bool,
     pub status: Option<ArtifactStatus>,
+    pub upstream_sha256: Option<String>,
+    pub rebuilt_sha256: Option<String>,
+    pub upstream_sha512: Option<String>,
+    pub rebuilt_sha512: Option<String>,
+    /// Whether the attestation was signed by the worker and matched the artifact when it was uploaded, unverified
+    /// attestations are never countersigned. None if there is no attestation.
+    #[serde(default)]
+    pub attestation_verified: Option<bool>,
 }
@@ -1,6 +1,20 @@
+use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PublicKey {
+    /// The keys attestations are signed with right now.
     pub current: Vec<String>,
+    /// Keys that were used in the past, signatures made before they were retired are still valid.
+    #[serde(default)]
+    pub previous: Vec<RetiredPublicKey>,
+    /// Keys that are going to replace the current key in the future.
+    #[serde(default)]
+    pub next: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetiredPublicKey {
+    pub key: String,
+    pub retired_at: NaiveDateTime,
 }
@@ -1,16 +1,22 @@
+mod audit;
 mod build;
 mod dashboard;
 mod meta;
 mod package;
 mod queue;
+mod token;
+mod transparency;
 mod worker;
 
+pub use audit::*;
 pub use build::*;
 pub use dashboard::*;
 pub use meta::*;
 pub use package::*;
 pub use queue::*;
 use serde::{Deserialize, Serialize};
+pub use token::*;
+pub use transparency::*;
 pub use worker::*;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -59,3 +65,8 @@ pub struct BinaryIdentityFilter {
 pub struct FreshnessFilter {
     pub seen_only: Option<bool>,
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkerFilter {
+    pub worker: Option<i32>,
+}
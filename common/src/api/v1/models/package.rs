@@ -18,6 +18,22 @@ pub struct SourcePackageReport {
     pub version: String,
     pub url: String,
     pub artifacts: Vec<BinaryPackageReport>,
+    /// Labels a worker needs to advertise to be assigned this package, `None` keeps the labels that are already set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub required_labels: Option<Vec<String>>,
+}
+
+/// Sets the labels a worker needs to advertise to be assigned the matching packages. An empty list removes the
+/// requirement.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RequiredLabelsRequest {
+    pub distribution: Option<String>,
+    pub release: Option<String>,
+    pub component: Option<String>,
+    pub name: Option<String>,
+    pub version: Option<String>,
+    pub architecture: Option<String>,
+    pub required_labels: Vec<String>,
 }
 
 #[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
@@ -44,7 +60,7 @@ pub struct SourcePackage {
     pub seen_in_last_sync: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[cfg_attr(feature = "diesel", derive(Queryable))]
 #[cfg_attr(feature = "diesel", diesel(check_for_backend(diesel::sqlite::Sqlite)))]
 pub struct BinaryPackage {
@@ -61,6 +77,31 @@ pub struct BinaryPackage {
     pub artifact_id: Option<i32>,
     pub diffoscope_log_id: Option<i32>,
     pub attestation_log_id: Option<i32>,
+    /// Hex-encoded SHA-256 of the upstream artifact, as seen by the worker during the latest rebuild.
+    pub upstream_sha256: Option<String>,
+    /// Hex-encoded SHA-256 of the artifact produced by the latest rebuild.
+    pub rebuilt_sha256: Option<String>,
+    /// Hex-encoded SHA-512 of the upstream artifact, as seen by the worker during the latest rebuild.
+    pub upstream_sha512: Option<String>,
+    /// Hex-encoded SHA-512 of the artifact produced by the latest rebuild.
+    pub rebuilt_sha512: Option<String>,
     pub last_seen: NaiveDateTime,
     pub seen_in_last_sync: bool,
 }
+
+/// Looks up binary packages by the digest of their upstream artifact. At least one digest is required, the filename
+/// narrows the results down to artifacts with the same file name.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ArtifactDigestQuery {
+    pub sha256: Option<String>,
+    pub sha512: Option<String>,
+    pub filename: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArtifactLookupResult {
+    #[serde(flatten)]
+    pub package: BinaryPackage,
+    /// Path of the attestation of the rebuild that matched, relative to the rebuilderd endpoint.
+    pub attestation_url: Option<String>,
+}
@@ -21,6 +21,27 @@ pub struct PopQueuedJobRequest {
     pub supported_backends: Vec<String>,
     pub architecture: String,
     pub supported_architectures: Vec<String>,
+    /// Only build inputs whose required labels are all in this list are assigned to the worker.
+    #[serde(default)]
+    pub labels: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PingQueuedJobRequest {
+    pub lease: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PushQueuedJobLogQuery {
+    pub lease: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PingQueuedJobResponse {
+    /// Keep working on the job
+    Continue,
+    /// The job has been removed from the queue, the build should be stopped
+    Cancel,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -71,6 +92,8 @@ pub struct QueuedJobArtifact {
 pub struct QueuedJobWithArtifacts {
     pub job: QueuedJob,
     pub artifacts: Vec<QueuedJobArtifact>,
+    /// Identifies this assignment of the job, pings and the build report are only accepted with the current lease.
+    pub lease: String,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
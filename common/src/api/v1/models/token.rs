@@ -0,0 +1,85 @@
+use crate::errors::*;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// What an API token is allowed to do. The auth cookie of the instance is allowed to do everything.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, clap::ValueEnum)]
+pub enum ApiTokenScope {
+    /// Submit package reports, as done by `rebuildctl pkgs sync`
+    #[serde(rename = "sync")]
+    #[clap(name = "sync")]
+    Sync,
+
+    /// Request rebuilds and drop jobs from the queue
+    #[serde(rename = "queue:write")]
+    #[clap(name = "queue:write")]
+    QueueWrite,
+
+    /// Manage the registered workers
+    #[serde(rename = "workers:admin")]
+    #[clap(name = "workers:admin")]
+    WorkersAdmin,
+
+    /// Read information that isn't public
+    #[serde(rename = "read-private")]
+    #[clap(name = "read-private")]
+    ReadPrivate,
+}
+
+impl ApiTokenScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiTokenScope::Sync => "sync",
+            ApiTokenScope::QueueWrite => "queue:write",
+            ApiTokenScope::WorkersAdmin => "workers:admin",
+            ApiTokenScope::ReadPrivate => "read-private",
+        }
+    }
+}
+
+impl fmt::Display for ApiTokenScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ApiTokenScope {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "sync" => Ok(ApiTokenScope::Sync),
+            "queue:write" => Ok(ApiTokenScope::QueueWrite),
+            "workers:admin" => Ok(ApiTokenScope::WorkersAdmin),
+            "read-private" => Ok(ApiTokenScope::ReadPrivate),
+            _ => bail!("Unknown API token scope: {s:?}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiToken {
+    pub id: i32,
+    pub name: String,
+    pub scopes: Vec<ApiTokenScope>,
+    pub created_at: NaiveDateTime,
+    pub expires_at: Option<NaiveDateTime>,
+    pub last_used_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateApiTokenRequest {
+    pub name: String,
+    pub scopes: Vec<ApiTokenScope>,
+    pub expires_at: Option<NaiveDateTime>,
+}
+
+/// A newly created token. The secret is only shown this one time, the server only keeps a hash of it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreatedApiToken {
+    #[serde(flatten)]
+    pub token: ApiToken,
+    pub secret: String,
+}
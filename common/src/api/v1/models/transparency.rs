@@ -0,0 +1,102 @@
+use crate::errors::*;
+use crate::transparency::{self, Hash};
+use chrono::NaiveDateTime;
+use in_toto::crypto::{PublicKey, Signature};
+use serde::{Deserialize, Serialize};
+
+/// The size and root hash of the transparency log at some point in time, signed by the rebuilderd instance.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedTreeHead {
+    pub tree_size: u64,
+    /// Hex-encoded root hash of the Merkle tree.
+    pub root_hash: String,
+    pub timestamp: NaiveDateTime,
+    pub signature: Signature,
+}
+
+impl SignedTreeHead {
+    /// The bytes covered by the signature of a tree head
+    pub fn signed_message(tree_size: u64, root_hash: &str, timestamp: &NaiveDateTime) -> Vec<u8> {
+        format!(
+            "rebuilderd transparency log v1\n{}\n{}\n{}\n",
+            tree_size,
+            root_hash,
+            timestamp.and_utc().timestamp()
+        )
+        .into_bytes()
+    }
+
+    pub fn verify(&self, public_key: &PublicKey) -> Result<()> {
+        let message = Self::signed_message(self.tree_size, &self.root_hash, &self.timestamp);
+        public_key
+            .verify(&message, &self.signature)
+            .context("Signature of tree head is invalid")?;
+        Ok(())
+    }
+
+    pub fn root_hash(&self) -> Result<Hash> {
+        transparency::hash_from_hex(&self.root_hash)
+    }
+}
+
+/// Either `leaf_index` or `leaf_hash` is needed to select the leaf. `tree_size` defaults to the current size of the
+/// log.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct InclusionProofQuery {
+    pub leaf_index: Option<u64>,
+    pub leaf_hash: Option<String>,
+    pub tree_size: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InclusionProof {
+    pub leaf_index: u64,
+    pub tree_size: u64,
+    /// Hex-encoded hash of the leaf.
+    pub leaf_hash: String,
+    /// Hex-encoded hashes needed to calculate the root hash, from the leaf up.
+    pub audit_path: Vec<String>,
+}
+
+impl InclusionProof {
+    pub fn verify(&self, root_hash: &Hash) -> Result<()> {
+        let leaf_hash = transparency::hash_from_hex(&self.leaf_hash)?;
+        let audit_path = parse_hashes(&self.audit_path)?;
+        transparency::verify_inclusion(
+            self.leaf_index,
+            self.tree_size,
+            &leaf_hash,
+            &audit_path,
+            root_hash,
+        )
+    }
+}
+
+/// `second` defaults to the current size of the log.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ConsistencyProofQuery {
+    pub first: u64,
+    pub second: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConsistencyProof {
+    pub first: u64,
+    pub second: u64,
+    /// Hex-encoded hashes needed to calculate both root hashes.
+    pub proof: Vec<String>,
+}
+
+impl ConsistencyProof {
+    pub fn verify(&self, first_root: &Hash, second_root: &Hash) -> Result<()> {
+        let proof = parse_hashes(&self.proof)?;
+        transparency::verify_consistency(self.first, self.second, first_root, second_root, &proof)
+    }
+}
+
+fn parse_hashes(hashes: &[String]) -> Result<Vec<Hash>> {
+    hashes
+        .iter()
+        .map(|hash| transparency::hash_from_hex(hash))
+        .collect()
+}
@@ -1,7 +1,13 @@
 use chrono::NaiveDateTime;
 #[cfg(feature = "diesel")]
-use diesel::Queryable;
+use diesel::{
+    AsExpression, FromSqlRow, Queryable, deserialize::FromSql, serialize::Output, serialize::ToSql,
+    sql_types::Text, sqlite::Sqlite, sqlite::SqliteValue,
+};
 use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+use std::fmt::Formatter;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct RegisterWorkerRequest {
@@ -18,4 +24,101 @@ pub struct Worker {
     pub status: Option<String>,
     pub last_ping: NaiveDateTime,
     pub is_online: bool,
+    #[serde(default)]
+    pub state: WorkerState,
+}
+
+/// Whether a worker is in rotation. Only active workers are assigned new jobs.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Serialize, Deserialize, clap::ValueEnum)]
+#[cfg_attr(feature = "diesel", derive(FromSqlRow, AsExpression))]
+#[cfg_attr(feature = "diesel", diesel(sql_type = Text))]
+#[cfg_attr(feature = "diesel", diesel(check_for_backend(diesel::sqlite::Sqlite)))]
+pub enum WorkerState {
+    #[default]
+    #[serde(rename = "active")]
+    #[clap(name = "active")]
+    Active,
+
+    /// Finishes the current job, then becomes paused
+    #[serde(rename = "draining")]
+    #[clap(name = "draining")]
+    Draining,
+
+    /// Stays registered, but isn't assigned any jobs
+    #[serde(rename = "paused")]
+    #[clap(name = "paused")]
+    Paused,
+
+    /// All requests of the worker are rejected
+    #[serde(rename = "disabled")]
+    #[clap(name = "disabled")]
+    Disabled,
+}
+
+impl WorkerState {
+    pub fn as_str(&self) -> &str {
+        match self {
+            WorkerState::Active => "active",
+            WorkerState::Draining => "draining",
+            WorkerState::Paused => "paused",
+            WorkerState::Disabled => "disabled",
+        }
+    }
+}
+
+impl fmt::Display for WorkerState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WorkerStateParseError {
+    value: String,
+}
+
+impl fmt::Display for WorkerStateParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let value = &self.value;
+        write!(f, "could not parse \"{value}\" as a worker state")
+    }
+}
+
+impl Error for WorkerStateParseError {}
+
+impl TryFrom<&str> for WorkerState {
+    type Error = WorkerStateParseError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "active" => Ok(WorkerState::Active),
+            "draining" => Ok(WorkerState::Draining),
+            "paused" => Ok(WorkerState::Paused),
+            "disabled" => Ok(WorkerState::Disabled),
+            _ => Err(WorkerStateParseError {
+                value: value.to_string(),
+            }),
+        }
+    }
+}
+
+#[cfg(feature = "diesel")]
+impl FromSql<Text, Sqlite> for WorkerState {
+    fn from_sql(bytes: SqliteValue) -> diesel::deserialize::Result<Self> {
+        let t = <String as FromSql<Text, Sqlite>>::from_sql(bytes)?;
+        Ok(t.as_str().try_into()?)
+    }
+}
+
+#[cfg(feature = "diesel")]
+impl ToSql<Text, Sqlite> for WorkerState {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Sqlite>) -> diesel::serialize::Result {
+        out.set_value(self.as_str());
+        Ok(diesel::serialize::IsNull::No)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SetWorkerStateRequest {
+    pub state: WorkerState,
 }
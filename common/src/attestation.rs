@@ -0,0 +1,403 @@
+use crate::api::v1::BuildStatus;
+use crate::dsse::{Envelope, IN_TOTO_PAYLOAD_TYPE};
+use crate::errors::*;
+use crate::statement::{
+    REBUILD_PREDICATE_TYPE_V1, ResourceDescriptor, Statement, VERDICT_PREDICATE_TYPE_V1,
+};
+use crate::utils::zstd_compress;
+use data_encoding::{BASE64, HEXLOWER};
+use in_toto::{
+    crypto::{HashAlgorithm, PrivateKey, PublicKey, SignatureScheme},
+    models::{Metablock, MetadataWrapper},
+};
+use pem::Pem;
+use url::Url;
+
+pub const PEM_PUBLIC_KEY: &str = "PUBLIC KEY";
+
+/// The filename a worker stores a downloaded artifact as, which is what the subject of its attestation is named after
+pub fn artifact_filename(artifact_url: &str) -> Result<String> {
+    let url = artifact_url
+        .parse::<Url>()
+        .with_context(|| anyhow!("Failed to parse artifact url: {:?}", artifact_url))?;
+    let filename = url
+        .path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .filter(|filename| !filename.is_empty())
+        .with_context(|| anyhow!("Artifact url has no filename: {:?}", artifact_url))?;
+    Ok(filename.to_string())
+}
+
+pub fn pubkey_to_pem(pubkey: &PublicKey) -> Result<String> {
+    let pubkey = pubkey.as_spki()?;
+    let pem = pem::encode(&Pem::new(PEM_PUBLIC_KEY, pubkey));
+    Ok(pem)
+}
+
+pub fn pem_to_pubkeys(buf: &[u8]) -> Result<impl Iterator<Item = Result<PublicKey>>> {
+    let pems = pem::parse_many(buf).context("Failed to parse pem file")?;
+    let iter = pems
+        .into_iter()
+        .filter(|pem| pem.tag() == PEM_PUBLIC_KEY)
+        .map(|pem| {
+            PublicKey::from_spki(pem.contents(), SignatureScheme::Ed25519)
+                .context("Failed to parse public key")
+        });
+    Ok(iter)
+}
+
+/// The key id of a public key, the way it shows up in signatures
+pub fn key_id(pubkey: &PublicKey) -> Result<String> {
+    let key_id = serde_json::to_value(pubkey.key_id())?
+        .as_str()
+        .context("Key id is not a string")?
+        .to_string();
+    Ok(key_id)
+}
+
+/// Parses the base64-encoded ed25519 key a worker registered with. The key id is calculated the same way as for the
+/// worker's private key, so signatures made with it can be matched up.
+pub fn worker_key_to_pubkey(worker_key: &str) -> Result<PublicKey> {
+    let key = BASE64
+        .decode(worker_key.as_bytes())
+        .context("Failed to decode worker key")?;
+    let pubkey = PublicKey::from_ed25519_with_keyid_hash_algorithms(
+        key,
+        Some(vec!["sha256".to_string(), "sha512".to_string()]),
+    )
+    .context("Failed to parse worker key")?;
+    Ok(pubkey)
+}
+
+/// How an attestation is encoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttestationFormat {
+    /// A signed in-toto link, the format produced by `in_toto_run`
+    Link,
+    /// An in-toto Statement v1 in a DSSE envelope
+    Dsse,
+}
+
+impl AttestationFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            AttestationFormat::Link => "application/json; charset=utf-8",
+            AttestationFormat::Dsse => "application/vnd.dsse.envelope.v1+json; charset=utf-8",
+        }
+    }
+}
+
+/// What an attestation says about its subject
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Claim {
+    /// The subject has been reproduced, an in-toto link or a statement with a rebuild predicate
+    Reproduced,
+    /// The subject has not been reproduced, a statement with a verdict predicate
+    Verdict(BuildStatus),
+    /// A statement with a predicate type we don't know
+    Other(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Attestation {
+    Link(Metablock),
+    Dsse(Envelope),
+}
+
+impl Attestation {
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        let value = serde_json::from_slice::<serde_json::Value>(bytes)?;
+        if value.get("payloadType").is_some() {
+            let envelope = serde_json::from_value::<Envelope>(value)?;
+            Ok(Attestation::Dsse(envelope))
+        } else {
+            let metablock = serde_json::from_value::<Metablock>(value)?;
+            Ok(Attestation::Link(metablock))
+        }
+    }
+
+    pub fn format(&self) -> AttestationFormat {
+        match self {
+            Attestation::Link(_) => AttestationFormat::Link,
+            Attestation::Dsse(_) => AttestationFormat::Dsse,
+        }
+    }
+
+    pub fn has_signature(&self, pubkey: &PublicKey) -> bool {
+        match self {
+            Attestation::Link(metablock) => metablock
+                .signatures
+                .iter()
+                .any(|sig| sig.key_id() == pubkey.key_id()),
+            Attestation::Dsse(envelope) => envelope.has_signature(pubkey),
+        }
+    }
+
+    pub fn sign(&mut self, privkey: &PrivateKey) -> Result<()> {
+        debug!("creating signature on attestation");
+        match self {
+            Attestation::Link(metablock) => {
+                let new = Metablock::new(metablock.metadata.clone(), &[privkey])?;
+                metablock.signatures.extend(new.signatures);
+            }
+            Attestation::Dsse(envelope) => envelope.sign(privkey)?,
+        }
+        Ok(())
+    }
+
+    /// Verifies the signatures and returns the artifacts the attestation vouches for as reproducible
+    pub fn verify<'a, I>(
+        &self,
+        threshold: u32,
+        authorized_keys: I,
+    ) -> Result<Vec<ResourceDescriptor>>
+    where
+        I: IntoIterator<Item = &'a PublicKey>,
+    {
+        let (subject, statement) = self.verify_signatures(threshold, authorized_keys)?;
+        // only statements about successful rebuilds vouch for their subject
+        let vouches =
+            statement.is_none_or(|statement| statement.predicate_type == REBUILD_PREDICATE_TYPE_V1);
+        if vouches { Ok(subject) } else { Ok(Vec::new()) }
+    }
+
+    /// Verifies the signatures and returns the artifacts the attestation is about, along with what it claims about them
+    pub fn verify_subject<'a, I>(
+        &self,
+        threshold: u32,
+        authorized_keys: I,
+    ) -> Result<(Vec<ResourceDescriptor>, Claim)>
+    where
+        I: IntoIterator<Item = &'a PublicKey>,
+    {
+        let (subject, statement) = self.verify_signatures(threshold, authorized_keys)?;
+        let claim = match statement {
+            None => Claim::Reproduced,
+            Some(statement) if statement.predicate_type == REBUILD_PREDICATE_TYPE_V1 => {
+                Claim::Reproduced
+            }
+            Some(statement) if statement.predicate_type == VERDICT_PREDICATE_TYPE_V1 => {
+                let predicate = statement
+                    .verdict_predicate()?
+                    .context("Statement has no verdict predicate")?;
+                Claim::Verdict(predicate.status)
+            }
+            Some(statement) => Claim::Other(statement.predicate_type),
+        };
+        Ok((subject, claim))
+    }
+
+    fn verify_signatures<'a, I>(
+        &self,
+        threshold: u32,
+        authorized_keys: I,
+    ) -> Result<(Vec<ResourceDescriptor>, Option<Statement>)>
+    where
+        I: IntoIterator<Item = &'a PublicKey>,
+    {
+        match self {
+            Attestation::Link(metablock) => {
+                let MetadataWrapper::Link(link) = metablock.verify(threshold, authorized_keys)?
+                else {
+                    bail!("Attestation is not an in-toto link");
+                };
+                let products = link
+                    .products
+                    .iter()
+                    .map(|(path, description)| ResourceDescriptor {
+                        name: Some(path.value().to_string()),
+                        uri: None,
+                        digest: description
+                            .iter()
+                            .map(|(algorithm, value)| {
+                                let algorithm = match algorithm {
+                                    HashAlgorithm::Sha256 => "sha256",
+                                    HashAlgorithm::Sha512 => "sha512",
+                                    HashAlgorithm::Unknown(name) => name,
+                                };
+                                (algorithm.to_string(), HEXLOWER.encode(value.value()))
+                            })
+                            .collect(),
+                    })
+                    .collect();
+                Ok((products, None))
+            }
+            Attestation::Dsse(envelope) => {
+                if envelope.payload_type != IN_TOTO_PAYLOAD_TYPE {
+                    bail!("Unsupported payload type: {:?}", envelope.payload_type);
+                }
+                let payload = envelope.verify(threshold, authorized_keys)?;
+                let mut statement = Statement::parse(&payload)?;
+                let subject = std::mem::take(&mut statement.subject);
+                Ok((subject, Some(statement)))
+            }
+        }
+    }
+
+    pub fn serialize(&self) -> Result<String> {
+        match self {
+            Attestation::Link(metablock) => serde_json::to_string(metablock),
+            Attestation::Dsse(envelope) => serde_json::to_string(envelope),
+        }
+        .context("Failed to serialize attestation")
+    }
+
+    pub async fn to_compressed_bytes(&self) -> Result<Vec<u8>> {
+        let json = self.serialize()?;
+        let compressed = zstd_compress(json.as_bytes()).await?;
+        Ok(compressed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data_encoding::HEXLOWER;
+    use in_toto::{
+        crypto::{HashValue, KeyType, Signature},
+        models::{LinkMetadata, VirtualTargetPath},
+    };
+    use serde_json::Value;
+
+    // temporary until https://github.com/in-toto/in-toto-rs/pull/111 lands
+    fn hashvalue_from_hex(hex: &str) -> Result<HashValue> {
+        let bytes = HEXLOWER.decode(hex.as_bytes())?;
+        Ok(HashValue::new(bytes))
+    }
+
+    // temporary until https://github.com/in-toto/in-toto-rs/pull/111 lands
+    fn signature(keyid: &str, value: &str) -> Signature {
+        let value = Value::Object(
+            [
+                ("keyid".to_string(), Value::String(keyid.to_string())),
+                ("sig".to_string(), Value::String(value.to_string())),
+            ]
+            .into_iter()
+            .collect(),
+        );
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_artifact_filename() {
+        assert_eq!(
+            artifact_filename(
+                "https://deb.debian.org/debian/pool/main/e/empty/empty_0.1.0-1_amd64.deb"
+            )
+            .unwrap(),
+            "empty_0.1.0-1_amd64.deb"
+        );
+        artifact_filename("https://deb.debian.org/debian/").unwrap_err();
+    }
+
+    #[test]
+    fn test_parse() {
+        let json = r#"{"signatures":[{"keyid":"c25d24c04760b6982de77736776edc6600d5f8e1e84d0bba2a7299959ce7d47f","sig":"8cd70318ea1b34c91bf7303e9c8811df43d1b4746aa9adf1d503ebb0241e0fbff9be28f36dac0318825782bf05dbbcea7171eb0ca9a89be3b02666f0f3c84301"}],"signed":{"_type":"link","name":"rebuild spytrap-adb_0.3.5-1_amd64.deb","materials":{"rust-spytrap-adb_0.3.5-1_amd64.buildinfo":{"sha512":"d130dbdbd51480f5cb79c1e6ce09fa61a69766e56725543b9c19bee8248306b2c3c2a2c66b250992bf20b2f5af7cf03bf401255104714bc9d654126fb41bc59f","sha256":"9df2f9a721f5016874c5f78ae88d3df77f9e49ea6070f935bfeeb438cd73a158"}},"products":{"spytrap-adb_0.3.5-1_amd64.deb":{"sha256":"58a7d451d5d59fda6284a05418b99e34fab32d07e63d0b164404eaaed1317edd","sha512":"f38806536701138cb1b2059565e5f73ec07288f9a3013ba986e33d510432e183e7bfe94af31bb8d480b85c84f4c145ed5c28c5949d618a4e94b2c7aecb309642"}},"environment":null,"byproducts":{},"command":[]}}"#;
+        let metablock = Attestation::parse(json.as_bytes()).unwrap();
+        assert_eq!(metablock, Attestation::Link(Metablock {
+                signatures: vec![signature(
+                    "c25d24c04760b6982de77736776edc6600d5f8e1e84d0bba2a7299959ce7d47f",
+                    "8cd70318ea1b34c91bf7303e9c8811df43d1b4746aa9adf1d503ebb0241e0fbff9be28f36dac0318825782bf05dbbcea7171eb0ca9a89be3b02666f0f3c84301",
+                )],
+                metadata: MetadataWrapper::Link(LinkMetadata {
+                    name: "rebuild spytrap-adb_0.3.5-1_amd64.deb".to_string(),
+                    materials: [
+                        (VirtualTargetPath::new("rust-spytrap-adb_0.3.5-1_amd64.buildinfo".to_string()).unwrap(), [
+                            (HashAlgorithm::Sha512, hashvalue_from_hex("d130dbdbd51480f5cb79c1e6ce09fa61a69766e56725543b9c19bee8248306b2c3c2a2c66b250992bf20b2f5af7cf03bf401255104714bc9d654126fb41bc59f").unwrap()),
+                            (HashAlgorithm::Sha256, hashvalue_from_hex("9df2f9a721f5016874c5f78ae88d3df77f9e49ea6070f935bfeeb438cd73a158").unwrap()),
+                        ].into_iter().collect()),
+                    ].into_iter().collect(),
+                    products: [
+                        (VirtualTargetPath::new("spytrap-adb_0.3.5-1_amd64.deb".to_string()).unwrap(), [
+                            (HashAlgorithm::Sha256, hashvalue_from_hex("58a7d451d5d59fda6284a05418b99e34fab32d07e63d0b164404eaaed1317edd").unwrap()),
+                            (HashAlgorithm::Sha512, hashvalue_from_hex("f38806536701138cb1b2059565e5f73ec07288f9a3013ba986e33d510432e183e7bfe94af31bb8d480b85c84f4c145ed5c28c5949d618a4e94b2c7aecb309642").unwrap()),
+                        ].into_iter().collect()),
+                    ].into_iter().collect(),
+                    env: None,
+                    byproducts: Default::default(),
+                    command: vec![].into(),
+                })
+            })
+        );
+    }
+
+    #[test]
+    fn test_append_signature() {
+        // generate keypair
+        let privkey = PrivateKey::new(KeyType::Ed25519).unwrap();
+        let privkey = PrivateKey::from_pkcs8(&privkey, SignatureScheme::Ed25519).unwrap();
+        let pubkey = privkey.public();
+
+        // take a metablock
+        let json = r#"{"signatures":[{"keyid":"c25d24c04760b6982de77736776edc6600d5f8e1e84d0bba2a7299959ce7d47f","sig":"8cd70318ea1b34c91bf7303e9c8811df43d1b4746aa9adf1d503ebb0241e0fbff9be28f36dac0318825782bf05dbbcea7171eb0ca9a89be3b02666f0f3c84301"}],"signed":{"_type":"link","name":"rebuild spytrap-adb_0.3.5-1_amd64.deb","materials":{"rust-spytrap-adb_0.3.5-1_amd64.buildinfo":{"sha512":"d130dbdbd51480f5cb79c1e6ce09fa61a69766e56725543b9c19bee8248306b2c3c2a2c66b250992bf20b2f5af7cf03bf401255104714bc9d654126fb41bc59f","sha256":"9df2f9a721f5016874c5f78ae88d3df77f9e49ea6070f935bfeeb438cd73a158"}},"products":{"spytrap-adb_0.3.5-1_amd64.deb":{"sha256":"58a7d451d5d59fda6284a05418b99e34fab32d07e63d0b164404eaaed1317edd","sha512":"f38806536701138cb1b2059565e5f73ec07288f9a3013ba986e33d510432e183e7bfe94af31bb8d480b85c84f4c145ed5c28c5949d618a4e94b2c7aecb309642"}},"environment":null,"byproducts":{},"command":[]}}"#;
+        let mut attestation = Attestation::parse(json.as_bytes()).unwrap();
+
+        // ensure it's not valid yet
+        attestation.verify(1, [pubkey]).unwrap_err();
+        assert!(!attestation.has_signature(pubkey));
+
+        // append a signature with our key
+        attestation.sign(&privkey).unwrap();
+
+        // ensure it's valid now
+        let products = attestation.verify(1, [pubkey]).unwrap();
+        assert!(attestation.has_signature(pubkey));
+        assert_eq!(
+            products[0].digest["sha256"],
+            "58a7d451d5d59fda6284a05418b99e34fab32d07e63d0b164404eaaed1317edd"
+        );
+    }
+
+    #[test]
+    fn test_statement_envelope() {
+        let privkey = PrivateKey::new(KeyType::Ed25519).unwrap();
+        let privkey = PrivateKey::from_pkcs8(&privkey, SignatureScheme::Ed25519).unwrap();
+
+        let statement = r#"{"_type":"https://in-toto.io/Statement/v1","subject":[{"name":"spytrap-adb_0.3.5-1_amd64.deb","digest":{"sha256":"58a7d451d5d59fda6284a05418b99e34fab32d07e63d0b164404eaaed1317edd"}}],"predicateType":"https://github.com/kpcyrd/rebuilderd/rebuild/v1","predicate":{}}"#;
+        let envelope = Envelope::new(IN_TOTO_PAYLOAD_TYPE, statement.as_bytes());
+        let json = serde_json::to_string(&envelope).unwrap();
+
+        let mut attestation = Attestation::parse(json.as_bytes()).unwrap();
+        assert_eq!(attestation.format(), AttestationFormat::Dsse);
+        attestation.verify(1, [privkey.public()]).unwrap_err();
+
+        attestation.sign(&privkey).unwrap();
+        let subject = attestation.verify(1, [privkey.public()]).unwrap();
+        assert_eq!(
+            subject[0].name.as_deref(),
+            Some("spytrap-adb_0.3.5-1_amd64.deb")
+        );
+
+        // the signature survives serialization
+        let attestation = Attestation::parse(attestation.serialize().unwrap().as_bytes()).unwrap();
+        assert!(attestation.has_signature(privkey.public()));
+    }
+
+    #[test]
+    fn test_verdict_does_not_vouch() {
+        let privkey = PrivateKey::new(KeyType::Ed25519).unwrap();
+        let privkey = PrivateKey::from_pkcs8(&privkey, SignatureScheme::Ed25519).unwrap();
+
+        let statement = r#"{"_type":"https://in-toto.io/Statement/v1","subject":[{"name":"spytrap-adb_0.3.5-1_amd64.deb","digest":{"sha256":"58a7d451d5d59fda6284a05418b99e34fab32d07e63d0b164404eaaed1317edd"}}],"predicateType":"https://github.com/kpcyrd/rebuilderd/verdict/v1","predicate":{"status":"BAD","buildInput":{"digest":{}},"upstream":{"digest":{}},"rebuilt":null,"backend":"debian","worker":"worker"}}"#;
+        let mut attestation =
+            Attestation::Dsse(Envelope::new(IN_TOTO_PAYLOAD_TYPE, statement.as_bytes()));
+        attestation.sign(&privkey).unwrap();
+
+        let subject = attestation.verify(1, [privkey.public()]).unwrap();
+        assert!(subject.is_empty());
+
+        let (subject, claim) = attestation.verify_subject(1, [privkey.public()]).unwrap();
+        assert_eq!(subject.len(), 1);
+        assert_eq!(claim, Claim::Verdict(BuildStatus::Bad));
+    }
+
+    #[test]
+    fn test_worker_key_matches_private_key() {
+        let privkey = PrivateKey::new(KeyType::Ed25519).unwrap();
+        let privkey = PrivateKey::from_pkcs8(&privkey, SignatureScheme::Ed25519).unwrap();
+        let worker_key = BASE64.encode(privkey.public().as_bytes());
+
+        let pubkey = worker_key_to_pubkey(&worker_key).unwrap();
+        assert_eq!(pubkey.key_id(), privkey.public().key_id());
+    }
+}
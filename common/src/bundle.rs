@@ -0,0 +1,280 @@
+//! Bundles of attestations, sent as newline-delimited json. Every line but the last carries one attestation along with
+//! the package it is about. The last line is a manifest listing the digests of all attestations in the bundle, in a
+//! DSSE envelope signed by the rebuilderd instance.
+
+use crate::api::v1::{BinaryPackage, OriginFilter};
+use crate::attestation::artifact_filename;
+use crate::dsse::Envelope;
+use crate::errors::*;
+use chrono::NaiveDateTime;
+use data_encoding::{BASE64, HEXLOWER};
+use in_toto::crypto::{PrivateKey, PublicKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+pub const MANIFEST_PAYLOAD_TYPE: &str = "application/vnd.rebuilderd.attestation-bundle+json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum BundleLine {
+    Attestation(Box<BundleEntry>),
+    Manifest(Envelope),
+}
+
+impl BundleLine {
+    pub fn to_line(&self) -> Result<String> {
+        let mut line = serde_json::to_string(self)?;
+        line.push('\n');
+        Ok(line)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleEntry {
+    pub package: BinaryPackage,
+    /// Base64-encoded attestation, byte for byte as served by the attestation endpoint without compression.
+    pub attestation: String,
+}
+
+impl BundleEntry {
+    pub fn new(package: BinaryPackage, attestation: &[u8]) -> Self {
+        Self {
+            package,
+            attestation: BASE64.encode(attestation),
+        }
+    }
+
+    pub fn attestation(&self) -> Result<Vec<u8>> {
+        BASE64
+            .decode(self.attestation.as_bytes())
+            .context("Attestation is not valid base64")
+    }
+
+    pub fn manifest_entry(&self) -> Result<ManifestEntry> {
+        let (Some(build_id), Some(artifact_id)) = (self.package.build_id, self.package.artifact_id)
+        else {
+            bail!("Bundled package has no build");
+        };
+        let sha256 = HEXLOWER.encode(&Sha256::digest(self.attestation()?));
+        Ok(ManifestEntry {
+            build_id,
+            artifact_id,
+            name: self.package.name.clone(),
+            version: self.package.version.clone(),
+            distribution: self.package.distribution.clone(),
+            release: self.package.release.clone(),
+            architecture: self.package.architecture.clone(),
+            filename: artifact_filename(&self.package.url)?,
+            sha256,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    /// The scope the bundle was requested for.
+    pub origin: OriginFilter,
+    pub created_at: NaiveDateTime,
+    pub attestations: Vec<ManifestEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub build_id: i32,
+    pub artifact_id: i32,
+    pub name: String,
+    pub version: String,
+    pub distribution: String,
+    pub release: Option<String>,
+    pub architecture: String,
+    /// The filename of the artifact, which is what the subject of the attestation is named after.
+    pub filename: String,
+    /// Hex-encoded SHA-256 of the attestation.
+    pub sha256: String,
+}
+
+impl Manifest {
+    pub fn sign(&self, privkey: &PrivateKey) -> Result<Envelope> {
+        let payload = serde_json::to_vec(self)?;
+        let mut envelope = Envelope::new(MANIFEST_PAYLOAD_TYPE, &payload);
+        envelope.sign(privkey)?;
+        Ok(envelope)
+    }
+}
+
+/// Checks the lines of a bundle as they are read, the bundle is only complete once the signed manifest has been
+/// verified.
+#[derive(Debug, Default)]
+pub struct BundleVerifier {
+    entries: Vec<ManifestEntry>,
+    manifest: Option<Manifest>,
+}
+
+impl BundleVerifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push<'a, I>(&mut self, line: &BundleLine, public_keys: I) -> Result<()>
+    where
+        I: IntoIterator<Item = &'a PublicKey>,
+    {
+        if self.manifest.is_some() {
+            bail!("Bundle continues after the manifest");
+        }
+
+        match line {
+            BundleLine::Attestation(entry) => {
+                self.entries.push(entry.manifest_entry()?);
+            }
+            BundleLine::Manifest(envelope) => {
+                if envelope.payload_type != MANIFEST_PAYLOAD_TYPE {
+                    bail!(
+                        "Unexpected manifest payload type: {:?}",
+                        envelope.payload_type
+                    );
+                }
+                let payload = envelope
+                    .verify(1, public_keys)
+                    .context("Failed to verify signature of bundle manifest")?;
+                let manifest = serde_json::from_slice::<Manifest>(&payload)
+                    .context("Failed to parse bundle manifest")?;
+                if manifest.attestations != self.entries {
+                    bail!("Bundle manifest does not match the bundled attestations");
+                }
+                self.manifest = Some(manifest);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the verified manifest, fails if the bundle has been cut short
+    pub fn finish(self) -> Result<Manifest> {
+        self.manifest.context("Bundle has ended without a manifest")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use in_toto::crypto::{KeyType, SignatureScheme};
+
+    fn keygen() -> PrivateKey {
+        let privkey = PrivateKey::new(KeyType::Ed25519).unwrap();
+        PrivateKey::from_pkcs8(&privkey, SignatureScheme::Ed25519).unwrap()
+    }
+
+    fn entry(build_id: i32, attestation: &[u8]) -> BundleEntry {
+        let package = BinaryPackage {
+            id: build_id,
+            name: "empty".to_string(),
+            version: "0.1.0-1".to_string(),
+            distribution: "debian".to_string(),
+            release: Some("trixie".to_string()),
+            component: Some("main".to_string()),
+            architecture: "amd64".to_string(),
+            url: "https://example.com/empty_0.1.0-1_amd64.deb".to_string(),
+            status: None,
+            build_id: Some(build_id),
+            artifact_id: Some(build_id),
+            diffoscope_log_id: None,
+            attestation_log_id: Some(build_id),
+            upstream_sha256: None,
+            rebuilt_sha256: None,
+            upstream_sha512: None,
+            rebuilt_sha512: None,
+            last_seen: NaiveDateTime::default(),
+            seen_in_last_sync: true,
+        };
+        BundleEntry::new(package, attestation)
+    }
+
+    fn bundle(privkey: &PrivateKey, entries: &[BundleEntry]) -> Vec<BundleLine> {
+        let manifest = Manifest {
+            origin: OriginFilter {
+                distribution: Some("debian".to_string()),
+                release: None,
+                component: None,
+                architecture: None,
+            },
+            created_at: NaiveDateTime::default(),
+            attestations: entries
+                .iter()
+                .map(|entry| entry.manifest_entry().unwrap())
+                .collect(),
+        };
+        let mut lines = entries
+            .iter()
+            .cloned()
+            .map(|entry| BundleLine::Attestation(Box::new(entry)))
+            .collect::<Vec<_>>();
+        lines.push(BundleLine::Manifest(manifest.sign(privkey).unwrap()));
+        lines
+    }
+
+    fn verify(privkey: &PrivateKey, lines: &[BundleLine]) -> Result<Manifest> {
+        let mut verifier = BundleVerifier::new();
+        for line in lines {
+            let line = line.to_line().unwrap();
+            let line = serde_json::from_str::<BundleLine>(&line).unwrap();
+            verifier.push(&line, [privkey.public()])?;
+        }
+        verifier.finish()
+    }
+
+    #[test]
+    fn test_verify_bundle() {
+        let privkey = keygen();
+        let lines = bundle(&privkey, &[entry(1, b"{}"), entry(2, b"[]")]);
+        let manifest = verify(&privkey, &lines).unwrap();
+        assert_eq!(manifest.attestations.len(), 2);
+        assert_eq!(
+            manifest.attestations[0].sha256,
+            "44136fa355b3678a1146ad16f7e8649e94fb4fc21fe77e8310c060f61caaff8a"
+        );
+        assert_eq!(manifest.attestations[0].filename, "empty_0.1.0-1_amd64.deb");
+    }
+
+    #[test]
+    fn test_verify_empty_bundle() {
+        let privkey = keygen();
+        let lines = bundle(&privkey, &[]);
+        verify(&privkey, &lines).unwrap();
+    }
+
+    #[test]
+    fn test_reject_bundle_signed_by_other_key() {
+        let lines = bundle(&keygen(), &[entry(1, b"{}")]);
+        verify(&keygen(), &lines).unwrap_err();
+    }
+
+    #[test]
+    fn test_reject_tampered_attestation() {
+        let privkey = keygen();
+        let mut lines = bundle(&privkey, &[entry(1, b"{}")]);
+        lines[0] = BundleLine::Attestation(Box::new(entry(1, b"[]")));
+        verify(&privkey, &lines).unwrap_err();
+    }
+
+    #[test]
+    fn test_reject_tampered_package() {
+        let privkey = keygen();
+        let mut lines = bundle(&privkey, &[entry(1, b"{}")]);
+        let mut tampered = entry(1, b"{}");
+        tampered.package.version = "0.2.0-1".to_string();
+        lines[0] = BundleLine::Attestation(Box::new(tampered));
+        verify(&privkey, &lines).unwrap_err();
+    }
+
+    #[test]
+    fn test_reject_truncated_bundle() {
+        let privkey = keygen();
+        let mut lines = bundle(&privkey, &[entry(1, b"{}"), entry(2, b"[]")]);
+        // missing manifest
+        verify(&privkey, &lines[..2]).unwrap_err();
+        // missing attestation
+        lines.remove(1);
+        verify(&privkey, &lines).unwrap_err();
+    }
+}
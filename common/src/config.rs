@@ -16,6 +16,10 @@ pub const DEFAULT_RETRY_DELAY_BASE: i64 = 24;
 
 pub const DEFAULT_INITIAL_DELAY: i64 = 0;
 
+pub const DEFAULT_FAIR_SHARE_WINDOW: i64 = 24;
+
+pub const DEFAULT_MAX_LOG_BYTES: usize = 10 * 1024 * 1024;
+
 pub fn load<P: AsRef<Path>>(path: Option<P>) -> Result<ConfigFile> {
     let mut config = ConfigFile::default();
 
@@ -65,6 +69,8 @@ pub struct ConfigFile {
     pub worker: WorkerConfig,
     #[serde(default)]
     pub schedule: ScheduleConfig,
+    #[serde(default)]
+    pub verify: VerifyConfig,
 }
 
 impl ConfigFile {
@@ -80,6 +86,7 @@ impl ConfigFile {
         }
         self.worker.update(c.worker);
         self.schedule.update(c.schedule);
+        self.verify.update(c.verify);
     }
 }
 
@@ -122,6 +129,8 @@ pub struct WorkerConfig {
     #[serde(default)]
     pub authorized_workers: Vec<String>,
     pub signup_secret: Option<String>,
+    pub allow_unsigned_requests: Option<bool>,
+    pub max_log_bytes: Option<usize>,
 }
 
 impl WorkerConfig {
@@ -132,6 +141,22 @@ impl WorkerConfig {
         if c.signup_secret.is_some() {
             self.signup_secret = c.signup_secret;
         }
+        if c.allow_unsigned_requests.is_some() {
+            self.allow_unsigned_requests = c.allow_unsigned_requests;
+        }
+        if c.max_log_bytes.is_some() {
+            self.max_log_bytes = c.max_log_bytes;
+        }
+    }
+
+    /// Whether workers may still authenticate with their public key alone, without signing their requests.
+    pub fn allow_unsigned_requests(&self) -> bool {
+        self.allow_unsigned_requests.unwrap_or(false)
+    }
+
+    /// How much of the live build log of a single job is kept, further output is dropped.
+    pub fn max_log_bytes(&self) -> usize {
+        self.max_log_bytes.unwrap_or(DEFAULT_MAX_LOG_BYTES)
     }
 }
 
@@ -140,6 +165,22 @@ pub struct ScheduleConfig {
     pub retry_delay_base: Option<i64>,
     pub max_retries: Option<i32>,
     pub initial_delay: Option<i64>,
+    pub policy: Option<SchedulePolicy>,
+    pub fair_share_window: Option<i64>,
+    /// Weights keyed by `distribution` or `distribution/release`
+    #[serde(default)]
+    pub shares: HashMap<String, u32>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SchedulePolicy {
+    /// Jobs are handed out by priority and the day they were queued
+    #[default]
+    Priority,
+    /// Within a priority, jobs are handed out from the distribution or release that is furthest below its share of
+    /// recent worker time
+    FairShare,
 }
 
 impl ScheduleConfig {
@@ -155,6 +196,18 @@ impl ScheduleConfig {
         if c.max_retries.is_some() {
             self.max_retries = c.max_retries;
         }
+
+        if c.policy.is_some() {
+            self.policy = c.policy;
+        }
+
+        if c.fair_share_window.is_some() {
+            self.fair_share_window = c.fair_share_window;
+        }
+
+        if !c.shares.is_empty() {
+            self.shares = c.shares;
+        }
     }
 
     pub fn retry_delay_base(&self) -> i64 {
@@ -169,4 +222,58 @@ impl ScheduleConfig {
     pub fn max_retries(&self) -> Option<i32> {
         self.max_retries
     }
+
+    pub fn policy(&self) -> SchedulePolicy {
+        self.policy.unwrap_or_default()
+    }
+
+    /// How far back worker time is considered for fair-share scheduling
+    pub fn fair_share_window(&self) -> Duration {
+        let hours = self.fair_share_window.unwrap_or(DEFAULT_FAIR_SHARE_WINDOW);
+        Duration::hours(hours)
+    }
+
+    /// The scope a release is accounted to, along with its weight. A release only has its own scope if it has a
+    /// share configured, otherwise it's part of its distribution. Distributions without a share have a weight of 1.
+    pub fn share(&self, distribution: &str, release: Option<&str>) -> (String, u32) {
+        if let Some(release) = release {
+            let scope = format!("{distribution}/{release}");
+            if let Some(weight) = self.shares.get(&scope) {
+                return (scope, *weight);
+            }
+        }
+
+        let weight = self.shares.get(distribution).copied().unwrap_or(1);
+        (distribution.to_string(), weight)
+    }
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct VerifyConfig {
+    pub threshold: Option<usize>,
+    #[serde(default)]
+    pub rebuilders: Vec<RebuilderConfig>,
+}
+
+impl VerifyConfig {
+    pub fn update(&mut self, c: VerifyConfig) {
+        if c.threshold.is_some() {
+            self.threshold = c.threshold;
+        }
+        if !c.rebuilders.is_empty() {
+            self.rebuilders = c.rebuilders;
+        }
+    }
+
+    /// How many of the configured rebuilders need to vouch for an artifact, all of them unless configured otherwise.
+    pub fn threshold(&self) -> usize {
+        self.threshold.unwrap_or(self.rebuilders.len())
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RebuilderConfig {
+    pub endpoint: String,
+    /// PEM encoded public keys the rebuilder signs its attestations with, the first one identifies the rebuilder.
+    pub public_key: String,
 }
@@ -0,0 +1,204 @@
+//! Dead Simple Signing Envelope (DSSE) v1, the signature wrapper used for in-toto statements.
+//!
+//! Signatures are calculated over the pre-authentication encoding of the payload type and the payload, never over the
+//! json of the envelope itself.
+
+use crate::errors::*;
+use data_encoding::{BASE64, HEXLOWER};
+use in_toto::crypto::{PrivateKey, PublicKey, Signature};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashSet;
+
+/// The payload type of in-toto statements
+pub const IN_TOTO_PAYLOAD_TYPE: &str = "application/vnd.in-toto+json";
+
+/// Pre-authentication encoding, as defined by DSSE v1
+pub fn pae(payload_type: &str, payload: &[u8]) -> Vec<u8> {
+    let mut buf = format!(
+        "DSSEv1 {} {} {} ",
+        payload_type.len(),
+        payload_type,
+        payload.len()
+    )
+    .into_bytes();
+    buf.extend(payload);
+    buf
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Envelope {
+    /// Base64-encoded payload.
+    pub payload: String,
+    pub payload_type: String,
+    pub signatures: Vec<EnvelopeSignature>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EnvelopeSignature {
+    pub keyid: String,
+    /// Base64-encoded signature.
+    pub sig: String,
+}
+
+impl EnvelopeSignature {
+    fn from_in_toto(signature: &Signature) -> Result<Self> {
+        let keyid = serde_json::to_value(signature.key_id())?
+            .as_str()
+            .context("Key id is not a string")?
+            .to_string();
+        let sig = BASE64.encode(signature.value().as_bytes());
+        Ok(Self { keyid, sig })
+    }
+
+    // in-toto doesn't offer a constructor, go through its serde representation instead
+    fn to_in_toto(&self) -> Result<Signature> {
+        let sig = BASE64
+            .decode(self.sig.as_bytes())
+            .context("Signature is not valid base64")?;
+        let value = json!({
+            "keyid": self.keyid,
+            "sig": HEXLOWER.encode(&sig),
+        });
+        serde_json::from_value(value).context("Failed to parse signature")
+    }
+}
+
+impl Envelope {
+    /// Creates an envelope without any signatures
+    pub fn new(payload_type: &str, payload: &[u8]) -> Self {
+        Self {
+            payload: BASE64.encode(payload),
+            payload_type: payload_type.to_string(),
+            signatures: Vec::new(),
+        }
+    }
+
+    /// The decoded payload, this has not been verified yet
+    pub fn payload(&self) -> Result<Vec<u8>> {
+        BASE64
+            .decode(self.payload.as_bytes())
+            .context("Envelope payload is not valid base64")
+    }
+
+    pub fn has_signature(&self, pubkey: &PublicKey) -> bool {
+        let Ok(keyid) = serde_json::to_value(pubkey.key_id()) else {
+            return false;
+        };
+        self.signatures
+            .iter()
+            .any(|sig| keyid == sig.keyid.as_str())
+    }
+
+    pub fn sign(&mut self, privkey: &PrivateKey) -> Result<()> {
+        let message = pae(&self.payload_type, &self.payload()?);
+        let signature = privkey.sign(&message)?;
+        self.signatures
+            .push(EnvelopeSignature::from_in_toto(&signature)?);
+        Ok(())
+    }
+
+    /// Returns the payload if it has valid signatures by at least `threshold` of the authorized keys
+    pub fn verify<'a, I>(&self, threshold: u32, authorized_keys: I) -> Result<Vec<u8>>
+    where
+        I: IntoIterator<Item = &'a PublicKey>,
+    {
+        if threshold < 1 {
+            bail!("Threshold needs to be at least 1");
+        }
+
+        let payload = self.payload()?;
+        let message = pae(&self.payload_type, &payload);
+        let authorized_keys = authorized_keys.into_iter().collect::<Vec<_>>();
+
+        let mut valid = HashSet::new();
+        for sig in &self.signatures {
+            let Ok(signature) = sig.to_in_toto() else {
+                continue;
+            };
+            let Some(key) = authorized_keys
+                .iter()
+                .find(|key| key.key_id() == signature.key_id())
+            else {
+                continue;
+            };
+            if key.verify(&message, &signature).is_ok() {
+                valid.insert(key.key_id());
+            }
+        }
+
+        if valid.len() < threshold as usize {
+            bail!(
+                "Envelope has {} valid signatures, {} are required",
+                valid.len(),
+                threshold
+            );
+        }
+        Ok(payload)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use in_toto::crypto::{KeyType, SignatureScheme};
+
+    fn keygen() -> PrivateKey {
+        let privkey = PrivateKey::new(KeyType::Ed25519).unwrap();
+        PrivateKey::from_pkcs8(&privkey, SignatureScheme::Ed25519).unwrap()
+    }
+
+    #[test]
+    fn test_pae() {
+        // the example from the DSSE protocol specification
+        assert_eq!(
+            pae("http://example.com/HelloWorld", b"hello world"),
+            b"DSSEv1 29 http://example.com/HelloWorld 11 hello world"
+        );
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let privkey = keygen();
+        let other = keygen();
+        let mut envelope = Envelope::new(IN_TOTO_PAYLOAD_TYPE, b"{}");
+
+        envelope.verify(1, [privkey.public()]).unwrap_err();
+        assert!(!envelope.has_signature(privkey.public()));
+
+        envelope.sign(&privkey).unwrap();
+        assert!(envelope.has_signature(privkey.public()));
+        assert!(!envelope.has_signature(other.public()));
+        assert_eq!(envelope.verify(1, [privkey.public()]).unwrap(), b"{}");
+        envelope.verify(1, [other.public()]).unwrap_err();
+        envelope
+            .verify(2, [privkey.public(), other.public()])
+            .unwrap_err();
+
+        envelope.sign(&other).unwrap();
+        envelope
+            .verify(2, [privkey.public(), other.public()])
+            .unwrap();
+    }
+
+    #[test]
+    fn test_tampered_payload() {
+        let privkey = keygen();
+        let mut envelope = Envelope::new(IN_TOTO_PAYLOAD_TYPE, b"{}");
+        envelope.sign(&privkey).unwrap();
+
+        envelope.payload = BASE64.encode(b"[]");
+        envelope.verify(1, [privkey.public()]).unwrap_err();
+    }
+
+    #[test]
+    fn test_signature_covers_payload_type() {
+        let privkey = keygen();
+        let mut envelope = Envelope::new(IN_TOTO_PAYLOAD_TYPE, b"{}");
+        envelope.sign(&privkey).unwrap();
+
+        envelope.payload_type = "application/json".to_string();
+        envelope.verify(1, [privkey.public()]).unwrap_err();
+    }
+}
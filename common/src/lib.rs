@@ -1,6 +1,11 @@
 pub mod api;
+pub mod attestation;
 pub mod auth;
+pub mod bundle;
 pub mod config;
+pub mod dsse;
 pub mod errors;
 pub mod http;
+pub mod statement;
+pub mod transparency;
 pub mod utils;
@@ -0,0 +1,180 @@
+//! in-toto Statement v1 with the predicates rebuilderd uses to describe the outcome of a rebuild.
+
+use crate::api::v1::BuildStatus;
+use crate::errors::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+pub const STATEMENT_TYPE_V1: &str = "https://in-toto.io/Statement/v1";
+pub const REBUILD_PREDICATE_TYPE_V1: &str = "https://github.com/kpcyrd/rebuilderd/rebuild/v1";
+pub const VERDICT_PREDICATE_TYPE_V1: &str = "https://github.com/kpcyrd/rebuilderd/verdict/v1";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Statement {
+    #[serde(rename = "_type")]
+    pub type_: String,
+    pub subject: Vec<ResourceDescriptor>,
+    pub predicate_type: String,
+    pub predicate: serde_json::Value,
+}
+
+impl Statement {
+    pub fn new<P: Serialize>(
+        subject: Vec<ResourceDescriptor>,
+        predicate_type: &str,
+        predicate: &P,
+    ) -> Result<Self> {
+        Ok(Self {
+            type_: STATEMENT_TYPE_V1.to_string(),
+            subject,
+            predicate_type: predicate_type.to_string(),
+            predicate: serde_json::to_value(predicate)?,
+        })
+    }
+
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        let statement = serde_json::from_slice::<Self>(bytes)?;
+        if statement.type_ != STATEMENT_TYPE_V1 {
+            bail!("Unsupported statement type: {:?}", statement.type_);
+        }
+        Ok(statement)
+    }
+
+    /// Returns the predicate, if this statement describes a rebuild
+    pub fn rebuild_predicate(&self) -> Result<Option<RebuildPredicate>> {
+        if self.predicate_type != REBUILD_PREDICATE_TYPE_V1 {
+            return Ok(None);
+        }
+        let predicate = serde_json::from_value(self.predicate.clone())
+            .context("Failed to parse rebuild predicate")?;
+        Ok(Some(predicate))
+    }
+
+    /// Returns the predicate, if this statement records a rebuild that didn't reproduce the artifact
+    pub fn verdict_predicate(&self) -> Result<Option<VerdictPredicate>> {
+        if self.predicate_type != VERDICT_PREDICATE_TYPE_V1 {
+            return Ok(None);
+        }
+        let predicate = serde_json::from_value(self.predicate.clone())
+            .context("Failed to parse verdict predicate")?;
+        Ok(Some(predicate))
+    }
+}
+
+/// Describes a file by name or location, along with its digests (keyed by lowercase algorithm name, hex-encoded).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResourceDescriptor {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uri: Option<String>,
+    pub digest: BTreeMap<String, String>,
+}
+
+/// The artifact was rebuilt from `build_input` by `worker` with `backend`, and the result is identical to `upstream`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RebuildPredicate {
+    pub build_input: ResourceDescriptor,
+    pub upstream: ResourceDescriptor,
+    pub backend: String,
+    pub worker: String,
+}
+
+/// The outcome of an attempt to reproduce `upstream` that did not succeed. The status is `BAD` if the rebuilt artifact
+/// differs (or is missing), `FAIL` if the build itself failed. This never vouches for the artifact.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerdictPredicate {
+    pub status: BuildStatus,
+    pub build_input: ResourceDescriptor,
+    pub upstream: ResourceDescriptor,
+    /// The artifact that was built instead, if any.
+    pub rebuilt: Option<ResourceDescriptor>,
+    pub backend: String,
+    pub worker: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_statement_roundtrip() {
+        let statement = Statement::new(
+            vec![ResourceDescriptor {
+                name: Some("empty_0.1.0-1_amd64.deb".to_string()),
+                uri: None,
+                digest: [("sha256".to_string(), "abcd".to_string())].into(),
+            }],
+            REBUILD_PREDICATE_TYPE_V1,
+            &RebuildPredicate {
+                build_input: ResourceDescriptor {
+                    uri: Some("https://example.com/empty_0.1.0-1_amd64.buildinfo".to_string()),
+                    ..Default::default()
+                },
+                upstream: ResourceDescriptor {
+                    uri: Some("https://example.com/empty_0.1.0-1_amd64.deb".to_string()),
+                    ..Default::default()
+                },
+                backend: "debian".to_string(),
+                worker: "worker".to_string(),
+            },
+        )
+        .unwrap();
+
+        let json = serde_json::to_string(&statement).unwrap();
+        assert!(json.contains(r#""_type":"https://in-toto.io/Statement/v1""#));
+        assert!(json.contains(r#""predicateType":"#));
+        assert!(json.contains(r#""buildInput":"#));
+
+        let parsed = Statement::parse(json.as_bytes()).unwrap();
+        assert_eq!(parsed, statement);
+        assert_eq!(
+            parsed.rebuild_predicate().unwrap().unwrap().backend,
+            "debian"
+        );
+        assert_eq!(parsed.verdict_predicate().unwrap(), None);
+    }
+
+    #[test]
+    fn test_verdict_statement() {
+        let statement = Statement::new(
+            vec![ResourceDescriptor {
+                name: Some("empty_0.1.0-1_amd64.deb".to_string()),
+                uri: None,
+                digest: [("sha256".to_string(), "abcd".to_string())].into(),
+            }],
+            VERDICT_PREDICATE_TYPE_V1,
+            &VerdictPredicate {
+                status: BuildStatus::Bad,
+                build_input: ResourceDescriptor::default(),
+                upstream: ResourceDescriptor::default(),
+                rebuilt: Some(ResourceDescriptor {
+                    name: None,
+                    uri: None,
+                    digest: [("sha256".to_string(), "ef01".to_string())].into(),
+                }),
+                backend: "debian".to_string(),
+                worker: "worker".to_string(),
+            },
+        )
+        .unwrap();
+
+        let json = serde_json::to_string(&statement).unwrap();
+        assert!(json.contains(r#""status":"BAD""#));
+
+        let parsed = Statement::parse(json.as_bytes()).unwrap();
+        assert_eq!(parsed.rebuild_predicate().unwrap(), None);
+        let predicate = parsed.verdict_predicate().unwrap().unwrap();
+        assert_eq!(predicate.status, BuildStatus::Bad);
+        assert_eq!(predicate.rebuilt.unwrap().digest["sha256"], "ef01");
+    }
+
+    #[test]
+    fn test_reject_other_statement_type() {
+        let json = r#"{"_type":"https://in-toto.io/Statement/v0.1","subject":[],"predicateType":"x","predicate":{}}"#;
+        Statement::parse(json.as_bytes()).unwrap_err();
+    }
+}
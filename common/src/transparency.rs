@@ -0,0 +1,458 @@
+//! Merkle tree hashing for the transparency log, following RFC 9162 (Certificate Transparency 2.0).
+//!
+//! Leaves and inner nodes are hashed with different prefixes, so an inner node can't be passed off as a leaf. The tree
+//! of `n` leaves is split at the largest power of two smaller than `n`.
+
+use crate::errors::*;
+use data_encoding::HEXLOWER;
+use sha2::{Digest, Sha256};
+
+pub type Hash = [u8; 32];
+
+pub fn leaf_hash(data: &[u8]) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.update([0x00]);
+    hasher.update(data);
+    hasher.finalize().into()
+}
+
+fn node_hash(left: &Hash, right: &Hash) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.update([0x01]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+/// The largest power of two smaller than `n`, `n` needs to be at least 2
+fn split(n: u64) -> u64 {
+    1 << (u64::BITS - (n - 1).leading_zeros() - 1)
+}
+
+/// A perfect subtree of the log with `2^level` leaves, starting at leaf `index << level`
+pub type NodeId = (u32, u64);
+
+/// Appends a leaf to a tree with `tree_size` leaves. `nodes` looks up the hashes of existing perfect subtrees, only the
+/// ones on the right edge of the tree are needed.
+///
+/// Returns the perfect subtrees that have been completed by the new leaf, starting with the leaf itself.
+pub fn append_leaf<F>(tree_size: u64, leaf: Hash, nodes: &mut F) -> Result<Vec<(NodeId, Hash)>>
+where
+    F: FnMut(NodeId) -> Result<Hash>,
+{
+    let mut id = (0, tree_size);
+    let mut hash = leaf;
+    let mut completed = vec![(id, hash)];
+    while id.1 & 1 == 1 {
+        let left = nodes((id.0, id.1 - 1))?;
+        hash = node_hash(&left, &hash);
+        id = (id.0 + 1, id.1 >> 1);
+        completed.push((id, hash));
+    }
+    Ok(completed)
+}
+
+/// Calculates the hash of the leaves `start..end`, `start` needs to be a multiple of a power of two that is at least
+/// `end - start`, like it's the case for every subtree of the log. The range is made up of perfect subtrees, from the
+/// largest to the smallest.
+fn range_hash<F>(start: u64, end: u64, nodes: &mut F) -> Result<Hash>
+where
+    F: FnMut(NodeId) -> Result<Hash>,
+{
+    let mut subtrees = Vec::new();
+    let mut pos = start;
+    while pos < end {
+        let level = (end - pos).ilog2();
+        subtrees.push(nodes((level, pos >> level))?);
+        pos += 1 << level;
+    }
+
+    let Some(mut hash) = subtrees.pop() else {
+        return Ok(Sha256::digest([]).into());
+    };
+    while let Some(left) = subtrees.pop() {
+        hash = node_hash(&left, &hash);
+    }
+    Ok(hash)
+}
+
+/// Calculates the root hash of a tree with `tree_size` leaves
+pub fn root_hash<F>(tree_size: u64, nodes: &mut F) -> Result<Hash>
+where
+    F: FnMut(NodeId) -> Result<Hash>,
+{
+    range_hash(0, tree_size, nodes)
+}
+
+/// Calculates the audit path that proves the leaf at `index` is included in a tree with `tree_size` leaves
+pub fn inclusion_proof<F>(tree_size: u64, index: u64, nodes: &mut F) -> Result<Vec<Hash>>
+where
+    F: FnMut(NodeId) -> Result<Hash>,
+{
+    if index >= tree_size {
+        bail!(
+            "Leaf index {} is out of range for tree size {}",
+            index,
+            tree_size
+        );
+    }
+
+    let mut proof = Vec::new();
+    let (mut start, mut n, mut index) = (0, tree_size, index);
+    while n > 1 {
+        let k = split(n);
+        if index < k {
+            proof.push(range_hash(start + k, start + n, nodes)?);
+            n = k;
+        } else {
+            proof.push(range_hash(start, start + k, nodes)?);
+            start += k;
+            n -= k;
+            index -= k;
+        }
+    }
+    // the audit path goes from the leaf up to the root
+    proof.reverse();
+    Ok(proof)
+}
+
+/// Calculates the proof that the tree of the first `first` leaves is a prefix of the tree with `tree_size` leaves
+pub fn consistency_proof<F>(tree_size: u64, first: u64, nodes: &mut F) -> Result<Vec<Hash>>
+where
+    F: FnMut(NodeId) -> Result<Hash>,
+{
+    if first > tree_size {
+        bail!("Tree size {} is larger than tree size {}", first, tree_size);
+    }
+
+    let mut proof = Vec::new();
+    if first == 0 {
+        return Ok(proof);
+    }
+
+    let (mut start, mut n, mut m) = (0, tree_size, first);
+    let mut complete = true;
+    while m != n {
+        let k = split(n);
+        if m <= k {
+            proof.push(range_hash(start + k, start + n, nodes)?);
+            n = k;
+        } else {
+            proof.push(range_hash(start, start + k, nodes)?);
+            start += k;
+            n -= k;
+            m -= k;
+            complete = false;
+        }
+    }
+    if !complete {
+        proof.push(range_hash(start, start + n, nodes)?);
+    }
+    proof.reverse();
+    Ok(proof)
+}
+
+/// Verifies an audit path for the leaf at `index` against the root hash of a tree with `tree_size` leaves
+pub fn verify_inclusion(
+    index: u64,
+    tree_size: u64,
+    leaf_hash: &Hash,
+    proof: &[Hash],
+    root: &Hash,
+) -> Result<()> {
+    if index >= tree_size {
+        bail!(
+            "Leaf index {} is out of range for tree size {}",
+            index,
+            tree_size
+        );
+    }
+
+    let mut fn_ = index;
+    let mut sn = tree_size - 1;
+    let mut r = *leaf_hash;
+    for p in proof {
+        if sn == 0 {
+            bail!("Inclusion proof is too long");
+        }
+        if fn_ & 1 == 1 || fn_ == sn {
+            r = node_hash(p, &r);
+            while fn_ & 1 == 0 && fn_ != 0 {
+                fn_ >>= 1;
+                sn >>= 1;
+            }
+        } else {
+            r = node_hash(&r, p);
+        }
+        fn_ >>= 1;
+        sn >>= 1;
+    }
+
+    if sn != 0 {
+        bail!("Inclusion proof is too short");
+    }
+    if r != *root {
+        bail!("Inclusion proof does not match root hash");
+    }
+    Ok(())
+}
+
+/// Verifies that the tree with `first` leaves is a prefix of the tree with `second` leaves
+pub fn verify_consistency(
+    first: u64,
+    second: u64,
+    first_root: &Hash,
+    second_root: &Hash,
+    proof: &[Hash],
+) -> Result<()> {
+    if first > second {
+        bail!("Tree size {} is larger than tree size {}", first, second);
+    }
+    if first == second || first == 0 {
+        if !proof.is_empty() {
+            bail!("Consistency proof is expected to be empty");
+        }
+        if first == second && first_root != second_root {
+            bail!("Root hashes of trees with the same size differ");
+        }
+        return Ok(());
+    }
+
+    // if the old tree is a complete subtree, its root is the start of the path
+    let mut path = Vec::with_capacity(proof.len() + 1);
+    if first.is_power_of_two() {
+        path.push(*first_root);
+    }
+    path.extend_from_slice(proof);
+
+    let Some((start, rest)) = path.split_first() else {
+        bail!("Consistency proof is empty");
+    };
+
+    let mut fn_ = first - 1;
+    let mut sn = second - 1;
+    while fn_ & 1 == 1 {
+        fn_ >>= 1;
+        sn >>= 1;
+    }
+
+    let mut fr = *start;
+    let mut sr = *start;
+    for c in rest {
+        if sn == 0 {
+            bail!("Consistency proof is too long");
+        }
+        if fn_ & 1 == 1 || fn_ == sn {
+            fr = node_hash(c, &fr);
+            sr = node_hash(c, &sr);
+            while fn_ & 1 == 0 && fn_ != 0 {
+                fn_ >>= 1;
+                sn >>= 1;
+            }
+        } else {
+            sr = node_hash(&sr, c);
+        }
+        fn_ >>= 1;
+        sn >>= 1;
+    }
+
+    if sn != 0 {
+        bail!("Consistency proof is too short");
+    }
+    if fr != *first_root || sr != *second_root {
+        bail!("Consistency proof does not match root hashes");
+    }
+    Ok(())
+}
+
+pub fn hash_to_hex(hash: &Hash) -> String {
+    HEXLOWER.encode(hash)
+}
+
+pub fn hash_from_hex(hex: &str) -> Result<Hash> {
+    let bytes = HEXLOWER
+        .decode(hex.as_bytes())
+        .with_context(|| anyhow!("Invalid hash: {:?}", hex))?;
+    let hash = bytes
+        .try_into()
+        .map_err(|_| anyhow!("Hash has wrong length: {:?}", hex))?;
+    Ok(hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    // the test vectors from the certificate transparency reference implementation
+    fn leaves() -> Vec<Hash> {
+        let data: [&[u8]; 8] = [
+            b"",
+            b"\x00",
+            b"\x10",
+            b"\x20\x21",
+            b"\x30\x31",
+            b"\x40\x41\x42\x43",
+            b"\x50\x51\x52\x53\x54\x55\x56\x57",
+            b"\x60\x61\x62\x63\x64\x65\x66\x67\x68\x69\x6a\x6b\x6c\x6d\x6e\x6f",
+        ];
+        data.iter().map(|d| leaf_hash(d)).collect()
+    }
+
+    /// Stores the perfect subtrees of the leaves the same way the daemon does, one leaf at a time
+    fn nodes(leaves: &[Hash]) -> impl FnMut(NodeId) -> Result<Hash> {
+        let mut nodes = HashMap::new();
+        for (index, leaf) in leaves.iter().enumerate() {
+            let completed = append_leaf(index as u64, *leaf, &mut |id| {
+                nodes.get(&id).copied().context("Missing node")
+            })
+            .unwrap();
+            nodes.extend(completed);
+        }
+        move |id| nodes.get(&id).copied().context("Missing node")
+    }
+
+    fn root(leaves: &[Hash]) -> Hash {
+        root_hash(leaves.len() as u64, &mut nodes(leaves)).unwrap()
+    }
+
+    fn inclusion(leaves: &[Hash], index: usize) -> Result<Vec<Hash>> {
+        inclusion_proof(leaves.len() as u64, index as u64, &mut nodes(leaves))
+    }
+
+    fn consistency(leaves: &[Hash], first: usize) -> Result<Vec<Hash>> {
+        consistency_proof(leaves.len() as u64, first as u64, &mut nodes(leaves))
+    }
+
+    #[test]
+    fn test_root_hash() {
+        let roots = [
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+            "6e340b9cffb37a989ca544e6bb780a2c78901d3fb33738768511a30617afa01d",
+            "fac54203e7cc696cf0dfcb42c92a1d9dbaf70ad9e621f4bd8d98662f00e3c125",
+            "aeb6bcfe274b70a14fb067a5e5578264db0fa9b51af5e0ba159158f329e06e77",
+            "d37ee418976dd95753c1c73862b9398fa2a2cf9b4ff0fdfe8b30cd95209614b7",
+            "4e3bbb1f7b478dcfe71fb631631519a3bca12c9aefca1612bfce4c13a86264d4",
+            "76e67dadbcdf1e10e1b74ddc608abd2f98dfb16fbce75277b5232a127f2087ef",
+            "ddb89be403809e325750d3d263cd78929c2942b7942a34b77e122c9594a74c8c",
+            "5dc9da79a70659a9ad559cb701ded9a2ab9d823aad2f4960cfe370eff4604328",
+        ];
+        let leaves = leaves();
+        for (size, expected) in roots.iter().enumerate() {
+            assert_eq!(hash_to_hex(&root(&leaves[..size])), *expected);
+        }
+    }
+
+    #[test]
+    fn test_append_leaf_completes_subtrees() {
+        let leaves = leaves();
+        let completed = append_leaf(7, leaves[7], &mut nodes(&leaves[..7])).unwrap();
+        let ids = completed.iter().map(|(id, _)| *id).collect::<Vec<_>>();
+        assert_eq!(ids, [(0, 7), (1, 3), (2, 1), (3, 0)]);
+        assert_eq!(completed[3].1, root(&leaves));
+
+        let completed = append_leaf(6, leaves[6], &mut nodes(&leaves[..6])).unwrap();
+        assert_eq!(completed, [((0, 6), leaves[6])]);
+    }
+
+    #[test]
+    fn test_inclusion_proofs() {
+        let leaves = leaves();
+        for size in 1..=leaves.len() {
+            let root = root(&leaves[..size]);
+            for index in 0..size {
+                let proof = inclusion(&leaves[..size], index).unwrap();
+                verify_inclusion(index as u64, size as u64, &leaves[index], &proof, &root).unwrap();
+
+                // the proof doesn't work for any other leaf
+                let other = (index + 1) % leaves.len();
+                verify_inclusion(index as u64, size as u64, &leaves[other], &proof, &root)
+                    .unwrap_err();
+            }
+        }
+    }
+
+    #[test]
+    fn test_inclusion_proof_known_path() {
+        let leaves = leaves();
+        let proof = inclusion(&leaves, 5)
+            .unwrap()
+            .iter()
+            .map(hash_to_hex)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            proof,
+            [
+                "bc1a0643b12e4d2d7c77918f44e0f4f79a838b6cf9ec5b5c283e1f4d88599e6b",
+                "ca854ea128ed050b41b35ffc1b87b8eb2bde461e9e3b5596ece6b9d5975a0ae0",
+                "d37ee418976dd95753c1c73862b9398fa2a2cf9b4ff0fdfe8b30cd95209614b7",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_inclusion_proof_out_of_range() {
+        let leaves = leaves();
+        inclusion(&leaves, 8).unwrap_err();
+        verify_inclusion(8, 8, &leaves[0], &[], &root(&leaves)).unwrap_err();
+    }
+
+    #[test]
+    fn test_consistency_proofs() {
+        let leaves = leaves();
+        for second in 0..=leaves.len() {
+            let second_root = root(&leaves[..second]);
+            for first in 0..=second {
+                let first_root = root(&leaves[..first]);
+                let proof = consistency(&leaves[..second], first).unwrap();
+                verify_consistency(
+                    first as u64,
+                    second as u64,
+                    &first_root,
+                    &second_root,
+                    &proof,
+                )
+                .unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn test_consistency_proof_detects_rewritten_history() {
+        let leaves = leaves();
+        let first_root = root(&leaves[..3]);
+
+        let mut rewritten = leaves.clone();
+        rewritten[1] = leaf_hash(b"something else");
+        let second_root = root(&rewritten);
+        let proof = consistency(&rewritten, 3).unwrap();
+
+        verify_consistency(3, 8, &first_root, &second_root, &proof).unwrap_err();
+    }
+
+    #[test]
+    fn test_consistency_proof_known_path() {
+        let leaves = leaves();
+        let proof = consistency(&leaves, 6)
+            .unwrap()
+            .iter()
+            .map(hash_to_hex)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            proof,
+            [
+                "0ebc5d3437fbe2db158b9f126a1d118e308181031d0a949f8dededebc558ef6a",
+                "ca854ea128ed050b41b35ffc1b87b8eb2bde461e9e3b5596ece6b9d5975a0ae0",
+                "d37ee418976dd95753c1c73862b9398fa2a2cf9b4ff0fdfe8b30cd95209614b7",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_hash_hex_roundtrip() {
+        let hash = leaf_hash(b"");
+        assert_eq!(hash_from_hex(&hash_to_hex(&hash)).unwrap(), hash);
+        hash_from_hex("abcd").unwrap_err();
+        hash_from_hex("not hex").unwrap_err();
+    }
+}
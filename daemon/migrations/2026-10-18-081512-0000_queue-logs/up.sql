@@ -0,0 +1,8 @@
+CREATE TABLE queue_logs
+(
+    id       INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    queue_id INTEGER NOT NULL REFERENCES queue ON DELETE CASCADE,
+    chunk    BLOB    NOT NULL
+);
+
+CREATE INDEX queue_logs_queue_id_idx ON queue_logs (queue_id);
@@ -0,0 +1,35 @@
+PRAGMA foreign_keys= OFF;
+
+CREATE TABLE _new_rebuilds
+(
+    id             INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    build_input_id INTEGER NOT NULL REFERENCES build_inputs ON DELETE CASCADE,
+    started_at     DATETIME,
+    built_at       DATETIME,
+    build_log_id   INTEGER NOT NULL REFERENCES build_logs ON DELETE CASCADE,
+    status         TEXT,
+    worker_id      INTEGER REFERENCES workers ON DELETE SET NULL,
+    worker_name    TEXT,
+    worker_key     TEXT
+);
+
+INSERT INTO _new_rebuilds (id, build_input_id, started_at, built_at, build_log_id, status)
+SELECT id,
+       build_input_id,
+       started_at,
+       built_at,
+       build_log_id,
+       status
+FROM rebuilds;
+
+DROP TABLE rebuilds;
+ALTER TABLE _new_rebuilds
+    RENAME TO rebuilds;
+
+CREATE INDEX rebuilds_build_input_id_idx ON rebuilds (build_input_id);
+CREATE INDEX rebuilds_started_at_idx ON rebuilds (started_at);
+CREATE INDEX rebuilds_built_at_id_idx ON rebuilds (built_at);
+CREATE INDEX rebuilds_status_idx ON rebuilds (status);
+CREATE INDEX rebuilds_worker_id_idx ON rebuilds (worker_id);
+
+PRAGMA foreign_keys= ON;
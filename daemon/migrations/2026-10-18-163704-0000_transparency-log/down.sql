@@ -0,0 +1,5 @@
+DROP TABLE transparency_tree_heads;
+DROP TABLE transparency_tree_nodes;
+DROP TRIGGER transparency_log_no_delete;
+DROP TRIGGER transparency_log_no_update;
+DROP TABLE transparency_log;
@@ -0,0 +1,61 @@
+CREATE TABLE transparency_log
+(
+    id                 INTEGER  NOT NULL PRIMARY KEY AUTOINCREMENT,
+    leaf_index         BIGINT   NOT NULL UNIQUE,
+    leaf_hash          BLOB     NOT NULL,
+    entry              BLOB     NOT NULL,
+    attestation_log_id INTEGER REFERENCES attestation_logs ON DELETE SET NULL,
+    created_at         DATETIME NOT NULL
+);
+
+CREATE INDEX transparency_log_leaf_hash_idx ON transparency_log (leaf_hash);
+CREATE INDEX transparency_log_attestation_log_id_idx ON transparency_log (attestation_log_id);
+
+-- the log is append-only, only the reference to the attestation may go away
+CREATE TRIGGER transparency_log_no_update
+    BEFORE UPDATE OF id, leaf_index, leaf_hash, entry, created_at
+    ON transparency_log
+BEGIN
+    SELECT RAISE(ABORT, 'transparency_log is append-only');
+END;
+
+CREATE TRIGGER transparency_log_no_delete
+    BEFORE DELETE
+    ON transparency_log
+BEGIN
+    SELECT RAISE(ABORT, 'transparency_log is append-only');
+END;
+
+-- the hashes of all perfect subtrees of the log, so proofs don't need to load every leaf
+CREATE TABLE transparency_tree_nodes
+(
+    level      INTEGER NOT NULL,
+    node_index BIGINT  NOT NULL,
+    hash       BLOB    NOT NULL,
+    PRIMARY KEY (level, node_index)
+);
+
+CREATE TRIGGER transparency_tree_nodes_no_update
+    BEFORE UPDATE
+    ON transparency_tree_nodes
+BEGIN
+    SELECT RAISE(ABORT, 'transparency_tree_nodes is append-only');
+END;
+
+CREATE TRIGGER transparency_tree_nodes_no_delete
+    BEFORE DELETE
+    ON transparency_tree_nodes
+BEGIN
+    SELECT RAISE(ABORT, 'transparency_tree_nodes is append-only');
+END;
+
+CREATE TABLE transparency_tree_heads
+(
+    id             INTEGER  NOT NULL PRIMARY KEY AUTOINCREMENT,
+    tree_size      BIGINT   NOT NULL,
+    root_hash      TEXT     NOT NULL,
+    timestamp      DATETIME NOT NULL,
+    signing_key_id TEXT     NOT NULL,
+    signature      TEXT     NOT NULL,
+    UNIQUE (tree_size, signing_key_id)
+);
@@ -0,0 +1,2 @@
+ALTER TABLE attestation_logs DROP COLUMN verified;
+ALTER TABLE attestation_logs DROP COLUMN signing_key_id;
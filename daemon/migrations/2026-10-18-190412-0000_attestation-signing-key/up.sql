@@ -0,0 +1,3 @@
+ALTER TABLE attestation_logs ADD COLUMN signing_key_id TEXT;
+-- attestations from before ingest verification was introduced are trusted, like they have been until now
+ALTER TABLE attestation_logs ADD COLUMN verified BOOLEAN NOT NULL DEFAULT 1;
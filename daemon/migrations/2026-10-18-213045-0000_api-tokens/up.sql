@@ -0,0 +1,10 @@
+CREATE TABLE api_tokens
+(
+    id           INTEGER  NOT NULL PRIMARY KEY AUTOINCREMENT,
+    name         TEXT     NOT NULL UNIQUE,
+    token_hash   TEXT     NOT NULL UNIQUE,
+    scopes       TEXT     NOT NULL,
+    created_at   DATETIME NOT NULL,
+    expires_at   DATETIME,
+    last_used_at DATETIME
+);
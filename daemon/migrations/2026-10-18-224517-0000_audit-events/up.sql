@@ -0,0 +1,12 @@
+CREATE TABLE audit_events
+(
+    id            INTEGER  NOT NULL PRIMARY KEY AUTOINCREMENT,
+    created_at    DATETIME NOT NULL,
+    actor         TEXT     NOT NULL,
+    source_ip     TEXT,
+    endpoint      TEXT     NOT NULL,
+    parameters    TEXT     NOT NULL,
+    affected_rows BIGINT   NOT NULL
+);
+
+CREATE INDEX audit_events_created_at_idx ON audit_events (created_at);
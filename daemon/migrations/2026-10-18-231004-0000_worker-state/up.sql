@@ -0,0 +1 @@
+ALTER TABLE workers ADD COLUMN state TEXT NOT NULL DEFAULT 'active';
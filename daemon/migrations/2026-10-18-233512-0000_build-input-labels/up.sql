@@ -0,0 +1,6 @@
+CREATE TABLE build_input_labels
+(
+    build_input_id INTEGER NOT NULL REFERENCES build_inputs ON DELETE CASCADE,
+    label          TEXT    NOT NULL,
+    PRIMARY KEY (build_input_id, label)
+);
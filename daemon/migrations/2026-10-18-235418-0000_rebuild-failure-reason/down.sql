@@ -0,0 +1 @@
+ALTER TABLE rebuilds DROP COLUMN failure_reason;
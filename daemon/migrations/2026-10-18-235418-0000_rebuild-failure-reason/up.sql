@@ -0,0 +1 @@
+ALTER TABLE rebuilds ADD COLUMN failure_reason TEXT;
@@ -0,0 +1 @@
+DROP TABLE worker_request_nonces;
@@ -0,0 +1,10 @@
+-- nonces of signed worker requests, kept until their signature timestamp is no longer accepted
+CREATE TABLE worker_request_nonces
+(
+    worker_key TEXT     NOT NULL,
+    nonce      TEXT     NOT NULL,
+    expires_at DATETIME NOT NULL,
+    PRIMARY KEY (worker_key, nonce)
+);
+
+CREATE INDEX worker_request_nonces_expires_at_idx ON worker_request_nonces (expires_at);
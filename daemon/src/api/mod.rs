@@ -1,8 +1,10 @@
+use crate::config::Config;
 use crate::web;
 use actix_web::http::header::{AcceptEncoding, ContentEncoding, Encoding, Header};
 use actix_web::{HttpRequest, HttpResponse};
 use rebuilderd_common::errors::{Context, Error, format_err};
 use rebuilderd_common::utils::{is_zstd_compressed, zstd_decompress};
+use std::net::IpAddr;
 
 pub mod v0;
 pub mod v1;
@@ -55,3 +57,19 @@ pub fn header<'a>(req: &'a HttpRequest, key: &str) -> rebuilderd_common::errors:
 
     Ok(value)
 }
+
+/// The address of the client, taken from `real_ip_header` if rebuilderd is running behind a reverse proxy
+pub fn client_ip(cfg: &Config, req: &HttpRequest) -> rebuilderd_common::errors::Result<IpAddr> {
+    if let Some(real_ip_header) = &cfg.real_ip_header {
+        let ip = header(req, real_ip_header).context("Failed to locate real ip header")?;
+        let ip = ip
+            .parse::<IpAddr>()
+            .context("Can't parse real ip header as ip address")?;
+        Ok(ip)
+    } else {
+        let ci = req
+            .peer_addr()
+            .ok_or_else(|| format_err!("Can't determine client ip"))?;
+        Ok(ci.ip())
+    }
+}
@@ -2,11 +2,11 @@ use diesel::{
     BoolExpressionMethods, ExpressionMethods, JoinOnDsl, NullableExpressionMethods,
     OptionalExtension, SelectableHelper, SqliteConnection,
 };
-mod auth;
 mod dashboard;
 
 use crate::api::forward_compressed_data;
 use crate::api::v0::aliases::{r1, r2};
+use crate::api::v1::util::auth;
 use crate::attestation::{self};
 use crate::config::Config;
 use crate::db::Pool;
@@ -22,6 +22,7 @@ use diesel::dsl::auto_type;
 use diesel::{QueryDsl, RunQueryDsl};
 use in_toto::crypto::PrivateKey;
 use rebuilderd_common::api::v0::*;
+use rebuilderd_common::api::v1::ApiTokenScope;
 use rebuilderd_common::config::PING_DEADLINE;
 use rebuilderd_common::errors::*;
 use std::sync::{Arc, RwLock};
@@ -62,12 +63,11 @@ pub async fn list_workers(
     cfg: web::Data<Config>,
     pool: web::Data<Pool>,
 ) -> web::Result<impl Responder> {
-    if auth::admin(&cfg, &req).is_err() {
+    let mut connection = pool.get().map_err(Error::from)?;
+    if auth::scoped(&cfg, &req, connection.as_mut(), ApiTokenScope::ReadPrivate).is_err() {
         return Ok(forbidden());
     }
 
-    let mut connection = pool.get().map_err(Error::from)?;
-
     // mark stale workers as offline before returning any results
     let now = Utc::now().naive_utc();
     let deadline = now - Duration::seconds(PING_DEADLINE);
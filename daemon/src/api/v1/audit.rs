@@ -0,0 +1,40 @@
+use crate::api::v1::util::auth;
+use crate::api::v1::util::pagination::PaginateDsl;
+use crate::config::Config;
+use crate::db::Pool;
+use crate::models::AuditEvent;
+use crate::schema::audit_events;
+use crate::web;
+use actix_web::{HttpRequest, HttpResponse, Responder, get};
+use diesel::{QueryDsl, RunQueryDsl, SelectableHelper};
+use rebuilderd_common::api::v1::{ApiTokenScope, Page, ResultPage};
+use rebuilderd_common::errors::Error;
+
+#[get("")]
+pub async fn get_audit_events(
+    req: HttpRequest,
+    cfg: web::Data<Config>,
+    pool: web::Data<Pool>,
+    page: web::Query<Page>,
+) -> web::Result<impl Responder> {
+    let mut connection = pool.get().map_err(Error::from)?;
+    if auth::scoped(&cfg, &req, connection.as_mut(), ApiTokenScope::ReadPrivate).is_err() {
+        return Ok(HttpResponse::Forbidden().finish());
+    }
+
+    let records = audit_events::table
+        .select(AuditEvent::as_select())
+        .paginate(page.into_inner())
+        .load::<AuditEvent>(connection.as_mut())
+        .map_err(Error::from)?
+        .into_iter()
+        .map(AuditEvent::into_api_event)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let total = audit_events::table
+        .count()
+        .get_result::<i64>(connection.as_mut())
+        .map_err(Error::from)?;
+
+    Ok(HttpResponse::Ok().json(ResultPage { total, records }))
+}
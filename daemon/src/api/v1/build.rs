@@ -1,36 +1,41 @@
 use crate::api::forward_compressed_data;
 use crate::api::v1::util::auth;
-use crate::api::v1::util::filters::{IntoOriginFilter, IntoSourceIdentityFilter};
+use crate::api::v1::util::digest::is_hex_digest;
+use crate::api::v1::util::filters::{IntoFilter, IntoOriginFilter, IntoSourceIdentityFilter};
 use crate::api::v1::util::friends::{
     get_build_input_friends, get_largest_retry_count_among_friends,
     mark_build_input_friends_as_non_retriable,
 };
+use crate::api::v1::util::multipart;
 use crate::api::v1::util::pagination::PaginateDsl;
 use crate::config::Config;
 use crate::db::Pool;
+use crate::keyring::KeyRing;
 use crate::models::{
     NewAttestationLog, NewBuildLog, NewDiffoscopeLog, NewQueued, NewRebuild, NewRebuildArtifact,
-    Queued,
+    NewTransparencyLogEntry, Queued, Worker,
 };
 use crate::schema::{
-    attestation_logs, build_inputs, build_logs, diffoscope_logs, queue, rebuild_artifacts,
-    rebuilds, source_packages,
+    attestation_logs, binary_packages, build_inputs, build_logs, diffoscope_logs, queue,
+    rebuild_artifacts, rebuilds, source_packages,
 };
 use crate::{attestation, web};
+use actix_web::http::header::{HeaderName, HeaderValue};
 use actix_web::{HttpRequest, HttpResponse, Responder, get, post};
 use chrono::{Duration, Utc};
 use diesel::{
     ExpressionMethods, NullableExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl,
-    SqliteExpressionMethods, dsl::update,
+    SqliteConnection, SqliteExpressionMethods, dsl::update,
 };
-use in_toto::crypto::PrivateKey;
 use rebuilderd_common::api;
+use rebuilderd_common::api::ATTESTATION_VERIFIED_HEADER;
 use rebuilderd_common::api::v1::{
-    BuildStatus, OriginFilter, Page, Priority, Rebuild, RebuildReport, ResultPage,
-    SourceIdentityFilter,
+    BuildStatus, OriginFilter, Page, Priority, Rebuild, RebuildArtifactReport, RebuildReport,
+    RebuildReportManifest, ResultPage, SourceIdentityFilter, WorkerFilter,
 };
-use rebuilderd_common::errors::Error;
-use rebuilderd_common::utils::{is_zstd_compressed, zstd_compress};
+use rebuilderd_common::config::PING_DEADLINE;
+use rebuilderd_common::errors::{Context, Error, Result, warn};
+use rebuilderd_common::utils::{is_zstd_compressed, zstd_compress, zstd_decompress};
 use std::collections::HashMap;
 use std::collections::hash_map::Entry;
 use std::sync::Arc;
@@ -51,6 +56,10 @@ fn builds_base() -> _ {
             rebuilds::started_at,
             rebuilds::built_at,
             rebuilds::status,
+            rebuilds::worker_id,
+            rebuilds::worker_name,
+            rebuilds::worker_key,
+            rebuilds::failure_reason,
         ))
 }
 
@@ -60,6 +69,7 @@ pub async fn get_builds(
     page: web::Query<Page>,
     origin_filter: web::Query<OriginFilter>,
     source_identity_filter: web::Query<SourceIdentityFilter>,
+    worker_filter: web::Query<WorkerFilter>,
 ) -> web::Result<impl Responder> {
     let mut connection = pool.get().map_err(Error::from)?;
 
@@ -76,6 +86,7 @@ pub async fn get_builds(
                 .into_inner()
                 .into_filter(source_packages::name, source_packages::version),
         )
+        .filter(worker_filter.clone().into_inner().into_filter())
         .paginate(page.into_inner())
         .load::<Rebuild>(connection.as_mut())
         .map_err(Error::from)?;
@@ -93,6 +104,7 @@ pub async fn get_builds(
                 .into_inner()
                 .into_filter(source_packages::name, source_packages::version),
         )
+        .filter(worker_filter.into_inner().into_filter())
         .count()
         .get_result::<i64>(connection.as_mut())
         .map_err(Error::from)?;
@@ -105,23 +117,225 @@ pub async fn submit_rebuild_report(
     req: HttpRequest,
     cfg: web::Data<Config>,
     pool: web::Data<Pool>,
-    request: web::Json<RebuildReport>,
+    body: web::Bytes,
 ) -> web::Result<impl Responder> {
     let mut connection = pool.get().map_err(Error::from)?;
-    if auth::worker(&cfg, &req, connection.as_mut()).is_err() {
-        return Ok(HttpResponse::Forbidden());
+    let Ok(worker) = auth::worker(&cfg, &req, &body, connection.as_mut()) else {
+        return Ok(HttpResponse::Forbidden().finish());
+    };
+
+    let Ok(report) = serde_json::from_slice::<RebuildReport>(&body) else {
+        return Ok(HttpResponse::BadRequest().finish());
+    };
+
+    accept_rebuild_report(&cfg, connection.as_mut(), &worker, report).await
+}
+
+/// Accepts a rebuild report as multipart/form-data. The manifest is JSON, but logs, diffoscope output and attestations
+/// are sent as raw bytes in separate parts, which is a lot more compact than JSON byte arrays.
+#[post("/upload")]
+pub async fn submit_rebuild_report_upload(
+    req: HttpRequest,
+    cfg: web::Data<Config>,
+    pool: web::Data<Pool>,
+    body: web::Bytes,
+) -> web::Result<impl Responder> {
+    let mut connection = pool.get().map_err(Error::from)?;
+    let Ok(worker) = auth::worker(&cfg, &req, &body, connection.as_mut()) else {
+        return Ok(HttpResponse::Forbidden().finish());
+    };
+
+    let Ok(parts) = multipart::parts(&req, body).await else {
+        return Ok(HttpResponse::BadRequest().finish());
+    };
+
+    let Some(report) = report_from_parts(parts) else {
+        return Ok(HttpResponse::BadRequest().finish());
+    };
+
+    accept_rebuild_report(&cfg, connection.as_mut(), &worker, report).await
+}
+
+/// Validates a parsed rebuild report, no matter which endpoint it has been uploaded to, and stores it if the worker
+/// still holds the lease for the job.
+async fn accept_rebuild_report(
+    cfg: &Config,
+    connection: &mut SqliteConnection,
+    worker: &Worker,
+    report: RebuildReport,
+) -> web::Result<HttpResponse> {
+    if !has_valid_digests(&report) {
+        return Ok(HttpResponse::BadRequest().finish());
+    }
+
+    let Some(queued) = leased_job(connection, worker, &report)? else {
+        return Ok(HttpResponse::Conflict().finish());
+    };
+
+    // attestations that fail verification are only accepted if we are not countersigning, and even then they are
+    // stored as unverified so they never get countersigned later on, and they are served marked as unverified
+    let verified = match verify_attestations(connection, worker, &queued, &report).await {
+        Ok(()) => true,
+        Err(err) if cfg.transparently_sign_attestations => {
+            warn!(
+                "Rejecting rebuild report from worker {:?}: {:#}",
+                worker.name, err
+            );
+            return Ok(HttpResponse::BadRequest().body(format!("{:#}\n", err)));
+        }
+        Err(err) => {
+            warn!(
+                "Storing unverified attestations from worker {:?}: {:#}",
+                worker.name, err
+            );
+            false
+        }
+    };
+
+    store_rebuild_report(cfg, connection, worker, queued, report, verified).await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+/// Makes sure we only ever countersign attestations that were signed by the submitting worker for the artifacts of the
+/// job it has been assigned.
+async fn verify_attestations(
+    connection: &mut SqliteConnection,
+    worker: &Worker,
+    queued: &Queued,
+    report: &RebuildReport,
+) -> Result<()> {
+    let worker_key = attestation::worker_key_to_pubkey(&worker.key)?;
+    for artifact in &report.artifacts {
+        if let Some(bytes) = &artifact.attestation {
+            let artifact_url = binary_packages::table
+                .filter(binary_packages::build_input_id.is(queued.build_input_id))
+                .filter(binary_packages::name.is(&artifact.name))
+                .select(binary_packages::artifact_url)
+                .first::<String>(connection)
+                .optional()?
+                .with_context(|| {
+                    format!(
+                        "Artifact {:?} is not part of job #{}",
+                        artifact.name, queued.id
+                    )
+                })?;
+            let artifact_filename = attestation::artifact_filename(&artifact_url)?;
+
+            attestation::verify_worker_attestation(
+                bytes,
+                &worker_key,
+                &artifact_filename,
+                artifact,
+                &report.status,
+            )
+            .await
+            .with_context(|| format!("Invalid attestation for artifact {:?}", artifact.name))?;
+        }
+    }
+    Ok(())
+}
+
+fn has_valid_digests(report: &RebuildReport) -> bool {
+    report.artifacts.iter().all(|artifact| {
+        [&artifact.upstream_sha256, &artifact.rebuilt_sha256]
+            .into_iter()
+            .flatten()
+            .all(|digest| is_hex_digest(digest, 32))
+            && [&artifact.upstream_sha512, &artifact.rebuilt_sha512]
+                .into_iter()
+                .flatten()
+                .all(|digest| is_hex_digest(digest, 64))
+    })
+}
+
+fn report_from_parts(mut parts: HashMap<String, Vec<u8>>) -> Option<RebuildReport> {
+    let manifest = parts.remove("manifest")?;
+    let manifest = serde_json::from_slice::<RebuildReportManifest>(&manifest).ok()?;
+
+    let build_log = parts.remove(&manifest.build_log)?;
+
+    let mut artifacts = Vec::new();
+    for artifact in manifest.artifacts {
+        let diffoscope = match artifact.diffoscope {
+            Some(name) => Some(parts.remove(&name)?),
+            None => None,
+        };
+
+        let attestation = match artifact.attestation {
+            Some(name) => Some(parts.remove(&name)?),
+            None => None,
+        };
+
+        artifacts.push(RebuildArtifactReport {
+            name: artifact.name,
+            diffoscope,
+            attestation,
+            status: artifact.status,
+            upstream_sha256: artifact.upstream_sha256,
+            rebuilt_sha256: artifact.rebuilt_sha256,
+            upstream_sha512: artifact.upstream_sha512,
+            rebuilt_sha512: artifact.rebuilt_sha512,
+        });
     }
 
-    let report = request.into_inner();
+    Some(RebuildReport {
+        queue_id: manifest.queue_id,
+        lease: manifest.lease,
+        built_at: manifest.built_at,
+        build_log,
+        status: manifest.status,
+        failure_reason: manifest.failure_reason,
+        artifacts,
+    })
+}
+
+/// Loads the job the report is about, as long as the worker still holds the lease for it. A worker that missed too
+/// many pings may have lost the job to another worker, its report is discarded then.
+fn leased_job(
+    connection: &mut SqliteConnection,
+    worker: &Worker,
+    report: &RebuildReport,
+) -> Result<Option<Queued>> {
     let queued = queue::table
         .filter(queue::id.is(report.queue_id))
-        .get_result::<Queued>(connection.as_mut())
-        .map_err(Error::from)?;
+        .get_result::<Queued>(connection)
+        .optional()?;
+
+    let Some(queued) = queued.filter(|queued| queued.is_leased_to(worker.id, &report.lease)) else {
+        warn!(
+            "Rejecting rebuild report from worker {:?}, its lease for job #{} is no longer valid",
+            worker.name, report.queue_id
+        );
+        return Ok(None);
+    };
 
+    let deadline = Utc::now().naive_utc() - Duration::seconds(PING_DEADLINE);
+    if queued
+        .last_ping
+        .is_some_and(|last_ping| last_ping < deadline)
+    {
+        warn!(
+            "Accepting rebuild report from worker {:?} with expired lease for job #{}, it has not been reassigned yet",
+            worker.name, report.queue_id
+        );
+    }
+
+    Ok(Some(queued))
+}
+
+async fn store_rebuild_report(
+    cfg: &Config,
+    connection: &mut SqliteConnection,
+    worker: &Worker,
+    queued: Queued,
+    report: RebuildReport,
+    verified: bool,
+) -> web::Result<()> {
     // figure out any other build inputs that should share this result (same input, backend, and arch). Will include the
     // enqueued build ID as well, so no need to add it later.
     let friends =
-        get_build_input_friends(connection.as_mut(), queued.build_input_id).map_err(Error::from)?;
+        get_build_input_friends(connection, queued.build_input_id).map_err(Error::from)?;
 
     let encoded_log = if is_zstd_compressed(&report.build_log) {
         report.build_log
@@ -135,7 +349,7 @@ pub async fn submit_rebuild_report(
         build_log: encoded_log,
     };
 
-    let new_log_id = new_log.insert(connection.as_mut())?;
+    let new_log_id = new_log.insert(connection)?;
 
     let mut artifact_logs: HashMap<&String, (Option<i32>, Option<i32>)> = HashMap::new();
 
@@ -146,9 +360,16 @@ pub async fn submit_rebuild_report(
             built_at: Some(report.built_at),
             build_log_id: new_log_id,
             status: Some(report.status.as_str().to_string()),
+            worker_id: Some(worker.id),
+            worker_name: Some(worker.name.clone()),
+            worker_key: Some(worker.key.clone()),
+            failure_reason: report
+                .failure_reason
+                .as_ref()
+                .map(|reason| reason.as_str().to_string()),
         };
 
-        let new_rebuild_id = new_rebuild.insert(connection.as_mut())?;
+        let new_rebuild_id = new_rebuild.insert(connection)?;
 
         for artifact_report in &report.artifacts {
             let entry = artifact_logs.entry(&artifact_report.name);
@@ -182,7 +403,7 @@ pub async fn submit_rebuild_report(
                             diffoscope_log: encoded_diffoscope.clone(),
                         };
 
-                        Some(new_diffoscope_log.insert(connection.as_mut())?)
+                        Some(new_diffoscope_log.insert(connection)?)
                     } else {
                         None::<i32>
                     };
@@ -191,9 +412,10 @@ pub async fn submit_rebuild_report(
                     {
                         let new_attestation_log = NewAttestationLog {
                             attestation_log: encoded_attestation.clone(),
+                            verified,
                         };
 
-                        Some(new_attestation_log.insert(connection.as_mut())?)
+                        Some(new_attestation_log.insert(connection)?)
                     } else {
                         None::<i32>
                     };
@@ -208,34 +430,50 @@ pub async fn submit_rebuild_report(
                 diffoscope_log_id: logs.0,
                 attestation_log_id: logs.1,
                 status: Some(artifact_report.status.as_str().to_string()),
+                upstream_sha256: artifact_report
+                    .upstream_sha256
+                    .as_deref()
+                    .map(str::to_ascii_lowercase),
+                rebuilt_sha256: artifact_report
+                    .rebuilt_sha256
+                    .as_deref()
+                    .map(str::to_ascii_lowercase),
+                upstream_sha512: artifact_report
+                    .upstream_sha512
+                    .as_deref()
+                    .map(str::to_ascii_lowercase),
+                rebuilt_sha512: artifact_report
+                    .rebuilt_sha512
+                    .as_deref()
+                    .map(str::to_ascii_lowercase),
             };
 
-            new_rebuild_artifact.insert(connection.as_mut())?;
+            new_rebuild_artifact.insert(connection)?;
         }
     }
 
-    queued.delete(connection.as_mut())?;
+    queued.delete(connection)?;
+    Worker::refresh_status(worker.id, connection)?;
 
     if report.status != BuildStatus::Good {
         // increment retries
         update(build_inputs::table)
             .filter(build_inputs::id.eq_any(&friends))
             .set(build_inputs::retries.eq(build_inputs::retries + 1))
-            .execute(connection.as_mut())
+            .execute(connection)
             .map_err(Error::from)?;
 
-        let retry_count =
-            get_largest_retry_count_among_friends(connection.as_mut(), queued.build_input_id)
-                .map_err(Error::from)?;
+        let retry_count = get_largest_retry_count_among_friends(connection, queued.build_input_id)
+            .map_err(Error::from)?;
 
         // bail if we have a max retry count set and requeueing this package would exceed it
         if let Some(max_retries) = cfg.schedule.max_retries()
             && retry_count >= max_retries
         {
-            mark_build_input_friends_as_non_retriable(connection.as_mut(), queued.build_input_id)
+            mark_build_input_friends_as_non_retriable(connection, queued.build_input_id)
                 .map_err(Error::from)?;
 
-            return Ok(HttpResponse::NoContent());
+            return Ok(());
         }
 
         let now = Utc::now();
@@ -245,7 +483,7 @@ pub async fn submit_rebuild_report(
         update(build_inputs::table)
             .filter(build_inputs::id.eq_any(friends))
             .set(build_inputs::next_retry.eq(then.naive_utc()))
-            .execute(connection.as_mut())
+            .execute(connection)
             .map_err(Error::from)?;
 
         // only requeue this build ID
@@ -255,10 +493,10 @@ pub async fn submit_rebuild_report(
             queued_at: now.naive_utc(),
         };
 
-        new_queue.upsert(connection.as_mut())?;
+        new_queue.upsert(connection)?;
     }
 
-    Ok(HttpResponse::NoContent())
+    Ok(())
 }
 
 #[get("/{id}")]
@@ -280,6 +518,10 @@ pub async fn get_build(pool: web::Data<Pool>, id: web::Path<i32>) -> web::Result
             rebuilds::started_at,
             rebuilds::built_at,
             rebuilds::status,
+            rebuilds::worker_id,
+            rebuilds::worker_name,
+            rebuilds::worker_key,
+            rebuilds::failure_reason,
         ))
         .get_result::<Rebuild>(connection.as_mut())
         .optional()
@@ -328,6 +570,11 @@ pub async fn get_build_artifacts(
             diffoscope_logs::diffoscope_log.nullable().is_not_null(),
             attestation_logs::attestation_log.nullable().is_not_null(),
             rebuild_artifacts::status,
+            rebuild_artifacts::upstream_sha256,
+            rebuild_artifacts::rebuilt_sha256,
+            rebuild_artifacts::upstream_sha512,
+            rebuild_artifacts::rebuilt_sha512,
+            attestation_logs::verified.nullable(),
         ))
         .get_results::<api::v1::RebuildArtifact>(connection.as_mut())
         .map_err(Error::from)?;
@@ -356,6 +603,11 @@ pub async fn get_build_artifact(
             diffoscope_logs::diffoscope_log.nullable().is_not_null(),
             attestation_logs::attestation_log.nullable().is_not_null(),
             rebuild_artifacts::status,
+            rebuild_artifacts::upstream_sha256,
+            rebuild_artifacts::rebuilt_sha256,
+            rebuild_artifacts::upstream_sha512,
+            rebuild_artifacts::rebuilt_sha512,
+            attestation_logs::verified.nullable(),
         ))
         .first::<api::v1::RebuildArtifact>(connection.as_mut())
         .optional()
@@ -392,13 +644,67 @@ pub async fn get_build_artifact_diffoscope(
     }
 }
 
+/// Makes sure a stored attestation is countersigned by us. If it isn't yet, the signed attestation replaces the stored
+/// one and is appended to the transparency log. Attestations that failed verification on ingest are returned as-is.
+pub(crate) async fn countersign_attestation(
+    connection: &mut SqliteConnection,
+    attestation_id: i32,
+    attestation: Vec<u8>,
+    keyring: &KeyRing,
+) -> Result<Vec<u8>> {
+    let verified = attestation_logs::table
+        .filter(attestation_logs::id.is(attestation_id))
+        .select(attestation_logs::verified)
+        .get_result::<bool>(connection)?;
+
+    if !verified {
+        return Ok(attestation);
+    }
+
+    let (bytes, new_signature) =
+        attestation::compressed_attestation_sign_if_necessary(attestation.clone(), keyring).await?;
+
+    let Some(signing_key_id) = new_signature else {
+        return Ok(attestation);
+    };
+
+    let signed = zstd_decompress(&bytes).await?;
+
+    // TODO: GET with side effects?
+    connection.immediate_transaction(|conn| {
+        // only the request that actually replaced the unsigned attestation appends it to the log
+        let updated = update(attestation_logs::table)
+            .filter(attestation_logs::id.is(attestation_id))
+            .filter(attestation_logs::attestation_log.eq(&attestation))
+            .set((
+                attestation_logs::attestation_log.eq(&bytes),
+                attestation_logs::signing_key_id.eq(&signing_key_id),
+            ))
+            .execute(conn)?;
+
+        if updated > 0 {
+            NewTransparencyLogEntry::append(
+                conn,
+                attestation_id,
+                &signed,
+                bytes.clone(),
+                &keyring.active,
+            )?;
+        }
+
+        Ok::<_, Error>(())
+    })?;
+
+    Ok(bytes)
+}
+
 #[get("/{id}/artifacts/{artifact_id}/attestation")]
 pub async fn get_build_artifact_attestation(
     req: HttpRequest,
     pool: web::Data<Pool>,
     path: web::Path<(i32, i32)>,
     cfg: web::Data<Config>,
-    private_key: web::Data<Arc<PrivateKey>>,
+    keyring: web::Data<Arc<KeyRing>>,
 ) -> web::Result<impl Responder> {
     let mut connection = pool.get().map_err(Error::from)?;
 
@@ -406,39 +712,35 @@ pub async fn get_build_artifact_attestation(
         .inner_join(rebuild_artifacts::table.left_join(attestation_logs::table))
         .filter(rebuilds::id.is(path.0))
         .filter(rebuild_artifacts::id.is(path.1))
-        .select(attestation_logs::attestation_log.nullable())
-        .first::<Option<Vec<u8>>>(connection.as_mut())
+        .select((
+            rebuild_artifacts::attestation_log_id,
+            attestation_logs::attestation_log.nullable(),
+            attestation_logs::verified.nullable(),
+        ))
+        .first::<(Option<i32>, Option<Vec<u8>>, Option<bool>)>(connection.as_mut())
         .optional()
         .map_err(Error::from)?;
 
-    let Some(mut attestation) = attestation.flatten() else {
+    let Some((Some(attestation_id), Some(mut attestation), verified)) = attestation else {
         return Ok(HttpResponse::NotFound().finish());
     };
 
     if cfg.transparently_sign_attestations {
-        let (bytes, has_new_signature) = attestation::compressed_attestation_sign_if_necessary(
-            attestation.clone(),
-            &private_key,
-        )
-        .await?;
-
-        if has_new_signature {
-            let attestation_id = rebuild_artifacts::table
-                .filter(rebuild_artifacts::id.is(path.1))
-                .select(rebuild_artifacts::attestation_log_id.assume_not_null())
-                .get_result::<i32>(connection.as_mut())
-                .map_err(Error::from)?;
-
-            // TODO: GET with side effects?
-            update(attestation_logs::table)
-                .filter(attestation_logs::id.is(attestation_id))
-                .set(attestation_logs::attestation_log.eq(bytes.clone()))
-                .execute(connection.as_mut())
-                .map_err(Error::from)?;
-
-            attestation = bytes
-        }
+        attestation =
+            countersign_attestation(connection.as_mut(), attestation_id, attestation, &keyring)
+                .await?;
     }
 
-    forward_compressed_data(req, "application/json; charset=utf-8", attestation).await
+    let content_type = attestation::compressed_attestation_content_type(&attestation).await;
+    let mut response = forward_compressed_data(req, content_type, attestation).await?;
+    let verified = if verified == Some(true) {
+        "true"
+    } else {
+        "false"
+    };
+    response.headers_mut().insert(
+        HeaderName::try_from(ATTESTATION_VERIFIED_HEADER).map_err(Error::from)?,
+        HeaderValue::from_static(verified),
+    );
+    Ok(response)
 }
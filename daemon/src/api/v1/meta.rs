@@ -1,13 +1,12 @@
 use crate::api::v1::util::filters::IntoFilter;
 use crate::db::Pool;
+use crate::keyring::KeyRing;
 use crate::schema::{build_inputs, source_packages};
-use crate::{attestation, web};
+use crate::web;
 use actix_web::{HttpResponse, Responder, get};
 use diesel::{QueryDsl, RunQueryDsl, SqliteExpressionMethods};
-use in_toto::crypto::PrivateKey;
 use rebuilderd_common::api::v1::FreshnessFilter;
 use rebuilderd_common::errors::Error;
-use serde_json::json;
 use std::sync::Arc;
 
 const NO_RELEASE: &str = "-";
@@ -95,12 +94,6 @@ pub async fn get_distribution_release_architectures(
 }
 
 #[get("/public-keys")]
-pub async fn get_public_key(
-    private_key: web::Data<Arc<PrivateKey>>,
-) -> web::Result<impl Responder> {
-    let public_key = attestation::pubkey_to_pem(private_key.public())?;
-
-    Ok(HttpResponse::Ok().json(json!({
-        "current": vec![public_key],
-    })))
+pub async fn get_public_key(keyring: web::Data<Arc<KeyRing>>) -> web::Result<impl Responder> {
+    Ok(HttpResponse::Ok().json(keyring.public_keys()?))
 }
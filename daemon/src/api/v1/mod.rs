@@ -1,14 +1,20 @@
+mod audit;
 mod build;
 mod dashboard;
 mod meta;
 mod package;
 mod queue;
-mod util;
+mod token;
+mod transparency;
+pub(crate) mod util;
 mod worker;
 
+pub use audit::*;
 pub use build::*;
 pub use dashboard::*;
 pub use meta::*;
 pub use package::*;
 pub use queue::*;
+pub use token::*;
+pub use transparency::*;
 pub use worker::*;
@@ -1,4 +1,5 @@
-use crate::api::v1::util::auth;
+use crate::api::v1::countersign_attestation;
+use crate::api::v1::util::digest::is_hex_digest;
 use crate::api::v1::util::filters::{
     IntoBinaryIdentityFilter, IntoFilter, IntoOriginFilter, IntoSourceIdentityFilter,
 };
@@ -7,14 +8,20 @@ use crate::api::v1::util::friends::{
     mark_build_input_friends_as_non_retriable,
 };
 use crate::api::v1::util::pagination::PaginateDsl;
+use crate::api::v1::util::{audit, auth};
 use crate::config::Config;
 use crate::db::{Pool, SqliteConnectionWrap};
-use crate::models::{BuildInput, NewBinaryPackage, NewBuildInput, NewQueued, NewSourcePackage};
+use crate::keyring::KeyRing;
+use crate::models::{
+    BuildInput, BuildInputLabel, NewBinaryPackage, NewBuildInput, NewQueued, NewSourcePackage,
+};
 use crate::schema::{
-    binary_packages, build_inputs, queue, rebuild_artifacts, rebuilds, source_packages,
+    attestation_logs, binary_packages, build_inputs, queue, rebuild_artifacts, rebuilds,
+    source_packages,
 };
 use crate::web;
-use actix_web::{HttpRequest, HttpResponse, Responder, get, post};
+use actix_web::web::Bytes;
+use actix_web::{HttpRequest, HttpResponse, Responder, get, post, put};
 use aliases::*;
 use chrono::{Duration, Utc};
 use diesel::dsl::{delete, exists, not, select, update};
@@ -25,10 +32,18 @@ use diesel::{
     OptionalExtension, QueryDsl, RunQueryDsl, SqliteConnection, SqliteExpressionMethods,
 };
 use rebuilderd_common::api::v1::{
-    BinaryIdentityFilter, BuildStatus, FreshnessFilter, OriginFilter, PackageReport, Page,
-    Priority, ResultPage, SourceIdentityFilter, SourcePackageReport,
+    ApiTokenScope, ArtifactDigestQuery, ArtifactLookupResult, ArtifactStatus, BinaryIdentityFilter,
+    BinaryPackage, BuildStatus, FreshnessFilter, OriginFilter, PackageReport, Page, Priority,
+    RequiredLabelsRequest, ResultPage, SourceIdentityFilter, SourcePackageReport,
 };
-use rebuilderd_common::errors::Error;
+use rebuilderd_common::bundle::{BundleEntry, BundleLine, Manifest};
+use rebuilderd_common::errors::{Error, error};
+use rebuilderd_common::utils::{is_zstd_compressed, zstd_decompress};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// How many attestations are loaded from the database at once while streaming a bundle
+const BUNDLE_CHUNK_SIZE: i64 = 100;
 
 mod aliases {
     diesel::alias!(crate::schema::rebuilds as r1: RebuildsAlias1, crate::schema::rebuilds as r2: RebuildsAlias2);
@@ -102,6 +117,10 @@ fn binary_packages_base() -> _ {
             rebuild_artifacts::id.nullable(),
             rebuild_artifacts::diffoscope_log_id.nullable(),
             rebuild_artifacts::attestation_log_id.nullable(),
+            rebuild_artifacts::upstream_sha256.nullable(),
+            rebuild_artifacts::rebuilt_sha256.nullable(),
+            rebuild_artifacts::upstream_sha512.nullable(),
+            rebuild_artifacts::rebuilt_sha512.nullable(),
             source_packages::last_seen,
             source_packages::seen_in_last_sync,
         ))
@@ -147,14 +166,14 @@ fn mark_scoped_packages_unseen(
 /// given report that were not seen in the last sync.
 ///
 /// The expectation is that all jobs belonging to an unseen package are dropped
-/// after a sync completes. Jobs that have already been picked up by a worker
-/// are unaffected, however.
+/// after a sync completes. Workers that have already picked up one of these jobs
+/// are told to cancel it on their next ping.
 fn drop_unseen_scoped_jobs(
     connection: &mut SqliteConnection,
     report: &PackageReport,
 ) -> Result<(), Error> {
     delete(
-        queue::table.filter(queue::worker.is_null()).filter(
+        queue::table.filter(
             queue::build_input_id.eq_any(
                 build_inputs::table
                     .inner_join(source_packages::table)
@@ -180,11 +199,10 @@ pub async fn submit_package_report(
     pool: web::Data<Pool>,
     request: web::Json<PackageReport>,
 ) -> web::Result<impl Responder> {
-    if auth::admin(&cfg, &req).is_err() {
-        return Ok(HttpResponse::Forbidden().finish());
-    }
-
     let mut connection = pool.get().map_err(Error::from)?;
+    let Ok(actor) = auth::scoped(&cfg, &req, connection.as_mut(), ApiTokenScope::Sync) else {
+        return Ok(HttpResponse::Forbidden().finish());
+    };
 
     let now = Utc::now();
     let report = request.into_inner();
@@ -225,8 +243,15 @@ pub async fn submit_package_report(
                 next_retry,
             };
 
+            let is_new_build_input = is_new_build_input(conn, &new_build_input)?;
             let build_input = new_build_input.upsert(conn.as_mut())?;
 
+            if let Some(required_labels) = &package_report.required_labels {
+                BuildInputLabel::replace(conn.as_mut(), build_input.id, required_labels)?;
+            } else if is_new_build_input {
+                inherit_required_labels(conn, &report, package_report, &build_input)?;
+            }
+
             for artifact_report in &package_report.artifacts {
                 let new_binary_package = NewBinaryPackage {
                     source_package_id: source_package.id,
@@ -280,12 +305,104 @@ pub async fn submit_package_report(
 
         drop_unseen_scoped_jobs(conn.as_mut(), &report)?;
 
+        audit::record(
+            conn.as_mut(),
+            &cfg,
+            &req,
+            &actor,
+            serde_json::json!({
+                "distribution": report.distribution,
+                "release": report.release,
+                "architecture": report.architecture,
+            }),
+            report.packages.len(),
+        )?;
+
         Ok::<(), Error>(())
     })?;
 
     Ok(HttpResponse::NoContent().finish())
 }
 
+#[put("/labels")]
+pub async fn set_required_labels(
+    req: HttpRequest,
+    cfg: web::Data<Config>,
+    pool: web::Data<Pool>,
+    request: web::Json<RequiredLabelsRequest>,
+) -> web::Result<impl Responder> {
+    let mut connection = pool.get().map_err(Error::from)?;
+    let Ok(actor) = auth::scoped(&cfg, &req, connection.as_mut(), ApiTokenScope::QueueWrite) else {
+        return Ok(HttpResponse::Forbidden());
+    };
+
+    let labels_request = request.into_inner();
+    let parameters = serde_json::to_value(&labels_request).map_err(Error::from)?;
+
+    // a request without any filter would relabel every package
+    let has_filter = [
+        &labels_request.distribution,
+        &labels_request.release,
+        &labels_request.component,
+        &labels_request.name,
+        &labels_request.version,
+        &labels_request.architecture,
+    ]
+    .iter()
+    .any(|filter| filter.is_some());
+    if !has_filter {
+        return Ok(HttpResponse::BadRequest());
+    }
+
+    let origin_filter = OriginFilter {
+        distribution: labels_request.distribution,
+        release: labels_request.release,
+        component: labels_request.component,
+        architecture: labels_request.architecture,
+    };
+
+    let source_identity_filter = SourceIdentityFilter {
+        name: labels_request.name,
+        version: labels_request.version,
+    };
+
+    connection.transaction(|conn| {
+        let mut build_input_ids = source_packages::table
+            .inner_join(build_inputs::table)
+            .inner_join(binary_packages::table)
+            .filter(origin_filter.into_filter(build_inputs::architecture))
+            .filter(
+                source_identity_filter.into_filter(source_packages::name, source_packages::version),
+            )
+            .select(build_inputs::id)
+            .get_results::<i32>(conn.as_mut())
+            .map_err(Error::from)?;
+        build_input_ids.sort_unstable();
+        build_input_ids.dedup();
+
+        for build_input_id in &build_input_ids {
+            BuildInputLabel::replace(
+                conn.as_mut(),
+                *build_input_id,
+                &labels_request.required_labels,
+            )?;
+        }
+
+        audit::record(
+            conn.as_mut(),
+            &cfg,
+            &req,
+            &actor,
+            parameters,
+            build_input_ids.len(),
+        )?;
+
+        Ok::<(), Error>(())
+    })?;
+
+    Ok(HttpResponse::NoContent())
+}
+
 fn is_new_package(
     report: &PackageReport,
     conn: &mut PooledConnection<ConnectionManager<SqliteConnectionWrap>>,
@@ -303,6 +420,53 @@ fn is_new_package(
     Ok(is_new_package)
 }
 
+fn is_new_build_input(
+    conn: &mut PooledConnection<ConnectionManager<SqliteConnectionWrap>>,
+    new_build_input: &NewBuildInput,
+) -> Result<bool, Error> {
+    let is_new_build_input = select(not(exists(
+        build_inputs::table
+            .filter(build_inputs::source_package_id.is(new_build_input.source_package_id))
+            .filter(build_inputs::url.is(&new_build_input.url))
+            .filter(build_inputs::backend.is(&new_build_input.backend))
+            .filter(build_inputs::architecture.is(&new_build_input.architecture)),
+    )))
+    .get_result::<bool>(conn.as_mut())?;
+
+    Ok(is_new_build_input)
+}
+
+/// Labels that were set for a package through the api are carried over to its new versions, unless the sync sets
+/// them explicitly.
+fn inherit_required_labels(
+    conn: &mut PooledConnection<ConnectionManager<SqliteConnectionWrap>>,
+    report: &PackageReport,
+    source_package_report: &SourcePackageReport,
+    build_input: &BuildInput,
+) -> Result<(), Error> {
+    let previous_build_input = build_inputs::table
+        .inner_join(source_packages::table)
+        .filter(source_packages::name.is(&source_package_report.name))
+        .filter(source_packages::distribution.is(&report.distribution))
+        .filter(source_packages::release.is(&report.release))
+        .filter(build_inputs::backend.is(&build_input.backend))
+        .filter(build_inputs::architecture.is(&build_input.architecture))
+        .filter(build_inputs::id.ne(build_input.id))
+        .order_by(build_inputs::id.desc())
+        .select(build_inputs::id)
+        .first::<i32>(conn.as_mut())
+        .optional()?;
+
+    if let Some(previous_build_input) = previous_build_input {
+        let labels = BuildInputLabel::list(conn.as_mut(), previous_build_input)?;
+        if !labels.is_empty() {
+            BuildInputLabel::replace(conn.as_mut(), build_input.id, &labels)?;
+        }
+    }
+
+    Ok(())
+}
+
 fn get_current_rebuild_status(
     conn: &mut PooledConnection<ConnectionManager<SqliteConnectionWrap>>,
     build_input: &BuildInput,
@@ -359,6 +523,10 @@ fn copy_existing_rebuilds(
                                 rebuilds::built_at,
                                 rebuilds::build_log_id,
                                 rebuilds::status,
+                                rebuilds::worker_id,
+                                rebuilds::worker_name,
+                                rebuilds::worker_key,
+                                rebuilds::failure_reason,
                             )),
                     )
                     .into_columns((
@@ -367,6 +535,10 @@ fn copy_existing_rebuilds(
                         rebuilds::built_at,
                         rebuilds::build_log_id,
                         rebuilds::status,
+                        rebuilds::worker_id,
+                        rebuilds::worker_name,
+                        rebuilds::worker_key,
+                        rebuilds::failure_reason,
                     ))
                     .returning(rebuilds::id)
                     .get_result::<i32>(connection.as_mut())
@@ -383,6 +555,10 @@ fn copy_existing_rebuilds(
                                 rebuild_artifacts::diffoscope_log_id,
                                 rebuild_artifacts::attestation_log_id,
                                 rebuild_artifacts::status,
+                                rebuild_artifacts::upstream_sha256,
+                                rebuild_artifacts::rebuilt_sha256,
+                                rebuild_artifacts::upstream_sha512,
+                                rebuild_artifacts::rebuilt_sha512,
                             )),
                     )
                     .into_columns((
@@ -391,6 +567,10 @@ fn copy_existing_rebuilds(
                         rebuild_artifacts::diffoscope_log_id,
                         rebuild_artifacts::attestation_log_id,
                         rebuild_artifacts::status,
+                        rebuild_artifacts::upstream_sha256,
+                        rebuild_artifacts::rebuilt_sha256,
+                        rebuild_artifacts::upstream_sha512,
+                        rebuild_artifacts::rebuilt_sha512,
                     ))
                     .execute(connection.as_mut())
                     .map_err(Error::from)?;
@@ -516,6 +696,216 @@ pub async fn get_binary_packages(
     Ok(HttpResponse::Ok().json(ResultPage { total, records }))
 }
 
+/// Finds binary packages by the digest of their upstream artifact, as recorded by the workers that rebuilt them. Any
+/// rebuild counts, so packages whose latest build failed before the artifact could be hashed are still found. Status,
+/// digests and attestation are the ones of the most recent rebuild that matched.
+#[get("/binary/lookup")]
+pub async fn lookup_binary_packages(
+    pool: web::Data<Pool>,
+    query: web::Query<ArtifactDigestQuery>,
+) -> web::Result<impl Responder> {
+    let query = query.into_inner();
+    let sha256 = query.sha256.map(|digest| digest.to_ascii_lowercase());
+    let sha512 = query.sha512.map(|digest| digest.to_ascii_lowercase());
+
+    if sha256.is_none() && sha512.is_none() {
+        return Ok(HttpResponse::BadRequest().finish());
+    }
+    if sha256.as_ref().is_some_and(|d| !is_hex_digest(d, 32))
+        || sha512.as_ref().is_some_and(|d| !is_hex_digest(d, 64))
+    {
+        return Ok(HttpResponse::BadRequest().finish());
+    }
+
+    let mut connection = pool.get().map_err(Error::from)?;
+
+    let mut matching = rebuild_artifacts::table
+        .inner_join(rebuilds::table)
+        .inner_join(
+            binary_packages::table.on(binary_packages::build_input_id
+                .is(rebuilds::build_input_id)
+                .and(binary_packages::name.is(rebuild_artifacts::name))),
+        )
+        .inner_join(
+            source_packages::table.on(source_packages::id.is(binary_packages::source_package_id)),
+        )
+        .select((
+            binary_packages::id,
+            binary_packages::name,
+            binary_packages::version,
+            source_packages::distribution,
+            source_packages::release,
+            binary_packages::component,
+            binary_packages::architecture,
+            binary_packages::artifact_url,
+            rebuild_artifacts::status,
+            rebuilds::id.nullable(),
+            rebuild_artifacts::id.nullable(),
+            rebuild_artifacts::diffoscope_log_id,
+            rebuild_artifacts::attestation_log_id,
+            rebuild_artifacts::upstream_sha256,
+            rebuild_artifacts::rebuilt_sha256,
+            rebuild_artifacts::upstream_sha512,
+            rebuild_artifacts::rebuilt_sha512,
+            source_packages::last_seen,
+            source_packages::seen_in_last_sync,
+        ))
+        // the most recent matching rebuild of each package comes first
+        .order_by((
+            binary_packages::id,
+            rebuilds::built_at.desc(),
+            rebuilds::id.desc(),
+        ))
+        .into_boxed();
+
+    if let Some(sha256) = sha256 {
+        matching = matching.filter(rebuild_artifacts::upstream_sha256.is(sha256));
+    }
+    if let Some(sha512) = sha512 {
+        matching = matching.filter(rebuild_artifacts::upstream_sha512.is(sha512));
+    }
+
+    let mut packages = matching
+        .load::<BinaryPackage>(connection.as_mut())
+        .map_err(Error::from)?
+        .into_iter()
+        .filter(|package| match &query.filename {
+            Some(filename) => package.url.rsplit('/').next() == Some(filename.as_str()),
+            None => true,
+        })
+        .collect::<Vec<_>>();
+    packages.dedup_by_key(|package| package.id);
+
+    let records = packages
+        .into_iter()
+        .map(|package| {
+            let attestation_url = match (
+                package.build_id,
+                package.artifact_id,
+                package.attestation_log_id,
+            ) {
+                (Some(build_id), Some(artifact_id), Some(_)) => Some(format!(
+                    "api/v1/builds/{build_id}/artifacts/{artifact_id}/attestation"
+                )),
+                _ => None,
+            };
+
+            ArtifactLookupResult {
+                package,
+                attestation_url,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    Ok(HttpResponse::Ok().json(records))
+}
+
+/// Streams the current attestations of all GOOD binary packages in the given scope as newline-delimited json, followed
+/// by a signed manifest of the bundle. Attestations that failed verification on upload are left out, so the manifest
+/// never vouches for them. The packages are loaded page by page while the bundle is streamed.
+#[get("/binary/attestations")]
+pub async fn get_attestation_bundle(
+    pool: web::Data<Pool>,
+    cfg: web::Data<Config>,
+    keyring: web::Data<Arc<KeyRing>>,
+    origin_filter: web::Query<OriginFilter>,
+) -> web::Result<impl Responder> {
+    let manifest = Manifest {
+        origin: origin_filter.into_inner(),
+        created_at: Utc::now().naive_utc(),
+        attestations: Vec::new(),
+    };
+    let pool = pool.into_inner();
+    let cfg = cfg.into_inner();
+    let keyring = keyring.into_inner();
+
+    let lines = futures_util::stream::unfold(Some((0, manifest)), move |state| {
+        let pool = pool.clone();
+        let cfg = cfg.clone();
+        let keyring = keyring.clone();
+        async move {
+            let (last_id, mut manifest) = state?;
+            let lines = async move {
+                let mut connection = pool.get()?;
+                let chunk = binary_packages_base()
+                    .filter(
+                        manifest
+                            .origin
+                            .clone()
+                            .into_filter(binary_packages::architecture),
+                    )
+                    .filter(rebuild_artifacts::status.eq(ArtifactStatus::Good))
+                    .filter(rebuild_artifacts::attestation_log_id.is_not_null())
+                    .filter(binary_packages::id.gt(last_id))
+                    .order_by(binary_packages::id)
+                    .limit(BUNDLE_CHUNK_SIZE)
+                    .load::<BinaryPackage>(connection.as_mut())?;
+                let Some(last_id) = chunk.last().map(|package| package.id) else {
+                    let envelope = manifest.sign(&keyring.active)?;
+                    return Ok((BundleLine::Manifest(envelope).to_line()?, None));
+                };
+
+                let ids = chunk
+                    .iter()
+                    .filter_map(|package| package.attestation_log_id)
+                    .collect::<Vec<_>>();
+                // rebuilds copied between releases share their attestation. Only verified attestations are bundled,
+                // which are also the only ones that get countersigned
+                let mut attestations = attestation_logs::table
+                    .filter(attestation_logs::id.eq_any(&ids))
+                    .filter(attestation_logs::verified.eq(true))
+                    .select((attestation_logs::id, attestation_logs::attestation_log))
+                    .load::<(i32, Vec<u8>)>(connection.as_mut())?
+                    .into_iter()
+                    .collect::<HashMap<_, _>>();
+
+                let mut lines = String::new();
+                for package in chunk {
+                    let Some(attestation_id) = package.attestation_log_id else {
+                        continue;
+                    };
+                    let Some(mut attestation) = attestations.get(&attestation_id).cloned() else {
+                        continue;
+                    };
+
+                    if cfg.transparently_sign_attestations {
+                        attestation = countersign_attestation(
+                            connection.as_mut(),
+                            attestation_id,
+                            attestation,
+                            &keyring,
+                        )
+                        .await?;
+                        attestations.insert(attestation_id, attestation.clone());
+                    }
+                    if is_zstd_compressed(&attestation) {
+                        attestation = zstd_decompress(&attestation).await?;
+                    }
+
+                    let entry = BundleEntry::new(package, &attestation);
+                    manifest.attestations.push(entry.manifest_entry()?);
+                    lines.push_str(&BundleLine::Attestation(Box::new(entry)).to_line()?);
+                }
+
+                Ok::<_, Error>((lines, Some((last_id, manifest))))
+            };
+
+            match lines.await {
+                Ok((lines, next)) => Some((Ok::<_, actix_web::Error>(Bytes::from(lines)), next)),
+                Err(err) => {
+                    // the bundle ends without a manifest, so clients notice it's incomplete
+                    error!("Failed to stream attestation bundle: {err:#}");
+                    None
+                }
+            }
+        }
+    });
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/x-ndjson")
+        .streaming(lines))
+}
+
 #[get("/binary/{id}")]
 pub async fn get_binary_package(
     pool: web::Data<Pool>,
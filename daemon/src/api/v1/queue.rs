@@ -1,25 +1,38 @@
-use crate::api::v1::util::auth;
 use crate::api::v1::util::filters::{IntoOriginFilter, IntoSourceIdentityFilter};
 use crate::api::v1::util::friends::{build_input_friends, has_queued_friend};
 use crate::api::v1::util::pagination::PaginateDsl;
+use crate::api::v1::util::{audit, auth, fair_share};
 use crate::config::Config;
 use crate::db::Pool;
-use crate::models::NewQueued;
-use crate::schema::{binary_packages, build_inputs, queue, rebuilds, source_packages, workers};
+use crate::models::{NewQueueLog, NewQueued, Queued, Worker};
+use crate::schema::{
+    binary_packages, build_input_labels, build_inputs, queue, queue_logs, rebuilds,
+    source_packages, workers,
+};
 use crate::web;
+use actix_web::http::header::{self, ContentEncoding};
+use actix_web::web::Bytes;
 use actix_web::{HttpRequest, HttpResponse, Responder, delete, get, post};
 use chrono::{Duration, NaiveDateTime, Utc};
-use diesel::dsl::update;
-use diesel::{BoolExpressionMethods, JoinOnDsl};
-use diesel::{Connection, OptionalExtension, QueryDsl, RunQueryDsl};
+use diesel::dsl::{exists, min, not, sql, update};
+use diesel::sql_types::{BigInt, Nullable};
+use diesel::{BoolExpressionMethods, JoinOnDsl, NullableExpressionMethods};
+use diesel::{Connection, OptionalExtension, QueryDsl, RunQueryDsl, SqliteConnection};
 use diesel::{ExpressionMethods, SqliteExpressionMethods, define_sql_function};
 use rebuilderd_common::api::v1::{
-    BuildStatus, JobAssignment, OriginFilter, Page, PopQueuedJobRequest, Priority, QueueJobRequest,
+    ApiTokenScope, BuildStatus, JobAssignment, OriginFilter, Page, PingQueuedJobRequest,
+    PingQueuedJobResponse, PopQueuedJobRequest, Priority, PushQueuedJobLogQuery, QueueJobRequest,
     QueuedJob, QueuedJobArtifact, QueuedJobWithArtifacts, ResultPage, SourceIdentityFilter,
+    WorkerState,
 };
-use rebuilderd_common::config::PING_DEADLINE;
+use rebuilderd_common::config::{PING_DEADLINE, SchedulePolicy};
 use rebuilderd_common::errors::*;
+use std::cmp;
 use std::collections::HashSet;
+use std::time;
+
+/// How often followers of a live build log check for new output.
+const LOG_FOLLOW_INTERVAL: time::Duration = time::Duration::from_secs(2);
 
 #[diesel::dsl::auto_type]
 fn queue_base() -> _ {
@@ -41,6 +54,90 @@ fn queue_base() -> _ {
         ))
 }
 
+/// Matches the jobs that can be assigned to a worker with these capabilities
+#[diesel::dsl::auto_type(no_type_alias)]
+fn available_jobs<'a>(
+    supported_architectures: &'a [String],
+    supported_backends: &'a [String],
+    labels: &'a [String],
+) -> _ {
+    queue::worker
+        .is_null()
+        .and(
+            build_inputs::next_retry
+                .is_null()
+                .or(build_inputs::next_retry.le(diesel::dsl::now)),
+        )
+        .and(build_inputs::architecture.eq_any(supported_architectures))
+        .and(build_inputs::backend.eq_any(supported_backends))
+        .and(not(exists(
+            build_input_labels::table
+                .filter(build_input_labels::build_input_id.eq(build_inputs::id))
+                .filter(build_input_labels::label.ne_all(labels)),
+        )))
+}
+
+/// The jobs that should be handed out next according to the fair-share policy
+struct FairShareSelection {
+    priority: i32,
+    distribution: String,
+    releases: Vec<Option<String>>,
+}
+
+/// Finds the best priority among the available jobs, and the scope that should be served next at this priority
+fn select_fair_share(
+    conn: &mut SqliteConnection,
+    cfg: &Config,
+    supported_architectures: &[String],
+    pop_request: &PopQueuedJobRequest,
+) -> Result<Option<FairShareSelection>> {
+    let releases = queue::table
+        .inner_join(build_inputs::table.inner_join(source_packages::table))
+        .filter(available_jobs(
+            supported_architectures,
+            &pop_request.supported_backends,
+            &pop_request.labels,
+        ))
+        .group_by((source_packages::distribution, source_packages::release))
+        .select((
+            source_packages::distribution,
+            source_packages::release,
+            min(queue::priority).assume_not_null(),
+        ))
+        .load::<(String, Option<String>, i32)>(conn)?;
+
+    let Some(priority) = releases.iter().map(|(_, _, priority)| *priority).min() else {
+        return Ok(None);
+    };
+
+    let candidates = releases
+        .into_iter()
+        .filter(|(_, _, p)| *p == priority)
+        .map(|(distribution, release, _)| fair_share::Candidate {
+            distribution,
+            release,
+        })
+        .collect::<Vec<_>>();
+
+    let now = Utc::now().naive_utc();
+    let usage = fair_share::usage(conn, &cfg.schedule, now)?;
+    let picked = fair_share::pick(&cfg.schedule, &candidates, &usage);
+    debug!("Fair-share scheduling picked {picked:?} (usage: {usage:?})");
+
+    let Some(first) = picked.first() else {
+        return Ok(None);
+    };
+
+    Ok(Some(FairShareSelection {
+        priority,
+        distribution: first.distribution.clone(),
+        releases: picked
+            .iter()
+            .map(|candidate| candidate.release.clone())
+            .collect(),
+    }))
+}
+
 #[get("")]
 pub async fn get_queued_jobs(
     pool: web::Data<Pool>,
@@ -99,13 +196,13 @@ pub async fn request_rebuild(
     pool: web::Data<Pool>,
     request: web::Json<QueueJobRequest>,
 ) -> web::Result<impl Responder> {
-    if auth::admin(&cfg, &req).is_err() {
-        return Ok(HttpResponse::Forbidden());
-    }
-
     let mut connection = pool.get().map_err(Error::from)?;
+    let Ok(actor) = auth::scoped(&cfg, &req, connection.as_mut(), ApiTokenScope::QueueWrite) else {
+        return Ok(HttpResponse::Forbidden());
+    };
 
     let queue_request = request.into_inner();
+    let parameters = serde_json::to_value(&queue_request).map_err(Error::from)?;
 
     let origin_filter = OriginFilter {
         distribution: queue_request.distribution,
@@ -143,53 +240,64 @@ pub async fn request_rebuild(
         }
     }
 
-    let build_input_ids = sql
+    let mut build_input_ids = sql
         .get_results::<i32>(connection.as_mut())
         .map_err(Error::from)?;
-
-    let now = Utc::now();
-    for build_input_id in build_input_ids {
-        let next_retry = (now - Duration::minutes(1)).naive_utc();
-        let priority = queue_request.priority.unwrap_or(Priority::manual());
-        if has_queued_friend(connection.as_mut(), build_input_id)? {
-            // set the priority of the queued item
-            diesel::update(
-                queue::table
-                    .filter(queue::build_input_id.eq_any(build_input_friends(build_input_id))),
-            )
-            .set(queue::priority.eq(priority))
-            .execute(connection.as_mut())
-            .map_err(Error::from)?;
-
-            // reset the next_retry where applicable
-            let friends_in_queue = queue::table
-                .filter(queue::build_input_id.eq_any(build_input_friends(build_input_id)))
-                .select(queue::build_input_id)
-                .load::<i32>(connection.as_mut())
+    build_input_ids.sort_unstable();
+    build_input_ids.dedup();
+    let requeued = build_input_ids.len();
+
+    connection.transaction(|conn| {
+        let now = Utc::now();
+        for build_input_id in build_input_ids {
+            let next_retry = (now - Duration::minutes(1)).naive_utc();
+            let priority = queue_request.priority.unwrap_or(Priority::manual());
+            if has_queued_friend(conn.as_mut(), build_input_id)? {
+                // set the priority of the queued item
+                diesel::update(
+                    queue::table
+                        .filter(queue::build_input_id.eq_any(build_input_friends(build_input_id))),
+                )
+                .set(queue::priority.eq(priority))
+                .execute(conn.as_mut())
                 .map_err(Error::from)?;
 
-            diesel::update(build_inputs::table.filter(build_inputs::id.eq_any(friends_in_queue)))
-                .set(build_inputs::next_retry.eq(next_retry))
-                .execute(connection.as_mut())
-                .map_err(Error::from)?;
-            continue;
-        } else {
-            // no applicable queued item, set directly and upsert a new queued job
-            diesel::update(build_inputs::table)
-                .filter(build_inputs::id.eq(build_input_id))
+                // reset the next_retry where applicable
+                let friends_in_queue = queue::table
+                    .filter(queue::build_input_id.eq_any(build_input_friends(build_input_id)))
+                    .select(queue::build_input_id)
+                    .load::<i32>(conn.as_mut())
+                    .map_err(Error::from)?;
+
+                diesel::update(
+                    build_inputs::table.filter(build_inputs::id.eq_any(friends_in_queue)),
+                )
                 .set(build_inputs::next_retry.eq(next_retry))
-                .execute(connection.as_mut())
+                .execute(conn.as_mut())
                 .map_err(Error::from)?;
+                continue;
+            } else {
+                // no applicable queued item, set directly and upsert a new queued job
+                diesel::update(build_inputs::table)
+                    .filter(build_inputs::id.eq(build_input_id))
+                    .set(build_inputs::next_retry.eq(next_retry))
+                    .execute(conn.as_mut())
+                    .map_err(Error::from)?;
 
-            let new_queued_job = NewQueued {
-                build_input_id,
-                priority,
-                queued_at: now.naive_utc(),
-            };
+                let new_queued_job = NewQueued {
+                    build_input_id,
+                    priority,
+                    queued_at: now.naive_utc(),
+                };
 
-            new_queued_job.upsert(connection.as_mut())?;
+                new_queued_job.upsert(conn.as_mut())?;
+            }
         }
-    }
+
+        audit::record(conn.as_mut(), &cfg, &req, &actor, parameters, requeued)?;
+
+        Ok::<(), Error>(())
+    })?;
 
     Ok(HttpResponse::NoContent())
 }
@@ -202,11 +310,10 @@ pub async fn drop_queued_jobs(
     origin_filter: web::Query<OriginFilter>,
     source_identity_filter: web::Query<SourceIdentityFilter>,
 ) -> web::Result<impl Responder> {
-    if auth::admin(&cfg, &req).is_err() {
-        return Ok(HttpResponse::Forbidden());
-    }
-
     let mut connection = pool.get().map_err(Error::from)?;
+    let Ok(actor) = auth::scoped(&cfg, &req, connection.as_mut(), ApiTokenScope::QueueWrite) else {
+        return Ok(HttpResponse::Forbidden());
+    };
 
     let ids = queue::table
         .inner_join(build_inputs::table.inner_join(source_packages::table))
@@ -226,9 +333,25 @@ pub async fn drop_queued_jobs(
         .load::<i32>(connection.as_mut())
         .map_err(Error::from)?;
 
-    diesel::delete(queue::table.filter(queue::id.eq_any(ids)))
-        .execute(connection.as_mut())
-        .map_err(Error::from)?;
+    connection.transaction(|conn| {
+        let dropped_jobs = diesel::delete(queue::table.filter(queue::id.eq_any(ids)))
+            .execute(conn.as_mut())
+            .map_err(Error::from)?;
+
+        audit::record(
+            conn.as_mut(),
+            &cfg,
+            &req,
+            &actor,
+            serde_json::json!({
+                "origin_filter": origin_filter.into_inner(),
+                "source_identity_filter": source_identity_filter.into_inner(),
+            }),
+            dropped_jobs,
+        )?;
+
+        Ok::<(), Error>(())
+    })?;
 
     Ok(HttpResponse::NoContent())
 }
@@ -259,15 +382,30 @@ pub async fn drop_queued_job(
     pool: web::Data<Pool>,
     id: web::Path<i32>,
 ) -> web::Result<impl Responder> {
-    if auth::admin(&cfg, &req).is_err() {
+    let mut connection = pool.get().map_err(Error::from)?;
+    let Ok(actor) = auth::scoped(&cfg, &req, connection.as_mut(), ApiTokenScope::QueueWrite) else {
         return Ok(HttpResponse::Forbidden());
-    }
+    };
 
-    let mut connection = pool.get().map_err(Error::from)?;
+    let id = id.into_inner();
+    let dropped_jobs = connection.transaction(|conn| {
+        let dropped_jobs = diesel::delete(queue::table.filter(queue::id.is(id)))
+            .execute(conn.as_mut())
+            .map_err(Error::from)?;
 
-    let dropped_jobs = diesel::delete(queue::table.filter(queue::id.is(id.into_inner())))
-        .execute(connection.as_mut())
-        .map_err(Error::from)?;
+        if dropped_jobs > 0 {
+            audit::record(
+                conn.as_mut(),
+                &cfg,
+                &req,
+                &actor,
+                serde_json::json!({ "id": id }),
+                dropped_jobs,
+            )?;
+        }
+
+        Ok::<_, Error>(dropped_jobs)
+    })?;
 
     if dropped_jobs < 1 {
         Ok(HttpResponse::NotFound())
@@ -282,33 +420,49 @@ pub async fn ping_job(
     cfg: web::Data<Config>,
     pool: web::Data<Pool>,
     id: web::Path<i32>,
+    body: web::Bytes,
 ) -> web::Result<impl Responder> {
     let mut connection = pool.get().map_err(Error::from)?;
 
-    let check_worker = auth::worker(&cfg, &req, connection.as_mut());
+    let check_worker = auth::worker(&cfg, &req, &body, connection.as_mut());
     if check_worker.is_err() {
         return Ok(HttpResponse::Forbidden().finish());
     }
 
     let worker = check_worker?;
 
+    let Ok(ping) = serde_json::from_slice::<PingQueuedJobRequest>(&body) else {
+        return Ok(HttpResponse::BadRequest().finish());
+    };
+
+    let id = id.into_inner();
     let now = Utc::now();
 
     let affected_jobs = diesel::update(queue::table)
         .set(queue::last_ping.eq(now.naive_utc()))
         .filter(
             queue::id
-                .is(id.into_inner())
-                .and(queue::worker.is(worker.id)),
+                .is(id)
+                .and(queue::worker.is(worker.id))
+                .and(queue::lease.is(&ping.lease)),
         )
         .execute(connection.as_mut())
         .map_err(Error::from)?;
 
     // schema does not allow for more than one record to match
-    if affected_jobs < 1 {
-        Ok(HttpResponse::NotFound().finish())
+    if affected_jobs >= 1 {
+        Ok(HttpResponse::Ok().json(PingQueuedJobResponse::Continue))
+    } else if is_queued(connection.as_mut(), id)? {
+        warn!(
+            "Rejecting ping from worker {:?}, its lease for job #{id} is no longer valid",
+            worker.name
+        );
+        Ok(HttpResponse::Conflict().finish())
     } else {
-        Ok(HttpResponse::NoContent().finish())
+        // the job has been dropped from the queue while the worker was building it
+        debug!("Cancelling job #{id} of worker {:?}", worker.name);
+        Worker::refresh_status(worker.id, connection.as_mut())?;
+        Ok(HttpResponse::Ok().json(PingQueuedJobResponse::Cancel))
     }
 }
 
@@ -346,55 +500,105 @@ pub async fn request_work(
     req: HttpRequest,
     cfg: web::Data<Config>,
     pool: web::Data<Pool>,
-    request: web::Json<PopQueuedJobRequest>,
+    body: web::Bytes,
 ) -> web::Result<impl Responder> {
     let mut connection = pool.get().map_err(Error::from)?;
 
-    let check_worker = auth::worker(&cfg, &req, connection.as_mut());
+    let check_worker = auth::worker(&cfg, &req, &body, connection.as_mut());
     let Ok(worker) = check_worker else {
         return Ok(HttpResponse::Forbidden().finish());
     };
 
+    let Ok(pop_request) = serde_json::from_slice::<PopQueuedJobRequest>(&body) else {
+        return Ok(HttpResponse::BadRequest().finish());
+    };
+
+    if worker.state != WorkerState::Active {
+        // once the worker has finished its last job, a draining worker is fully drained
+        if worker.state == WorkerState::Draining
+            && !has_assigned_jobs(connection.as_mut(), worker.id)?
+        {
+            diesel::update(workers::table)
+                .filter(workers::id.is(worker.id))
+                .set(workers::state.eq(WorkerState::Paused))
+                .execute(connection.as_mut())
+                .map_err(Error::from)?;
+        }
+
+        debug!(
+            "Not assigning work to worker {:?}, it's {}",
+            worker.name, worker.state
+        );
+        return Ok(HttpResponse::Ok().json(JobAssignment::Nothing));
+    }
+
     // clear any stale jobs before we consider available jobs in the queue
     let now = Utc::now();
     let then = now - Duration::seconds(PING_DEADLINE);
 
     debug!("Clearing stale jobs last pinged before {then:?}...");
-    update(
-        queue::table.filter(
-            queue::last_ping
-                .is_not_null()
-                .and(queue::last_ping.lt(then.naive_utc())),
-        ),
-    )
-    .set((
-        queue::worker.eq(None::<i32>),
-        queue::started_at.eq(None::<NaiveDateTime>),
-        queue::last_ping.eq(None::<NaiveDateTime>),
-    ))
-    .execute(connection.as_mut())
-    .map_err(Error::from)?;
+    let is_stale = queue::last_ping
+        .is_not_null()
+        .and(queue::last_ping.lt(then.naive_utc()));
+    let stale_workers = queue::table
+        .filter(is_stale)
+        .select(queue::worker)
+        .distinct()
+        .load::<Option<i32>>(connection.as_mut())
+        .map_err(Error::from)?;
+    update(queue::table.filter(is_stale))
+        .set((
+            queue::worker.eq(None::<i32>),
+            queue::started_at.eq(None::<NaiveDateTime>),
+            queue::last_ping.eq(None::<NaiveDateTime>),
+            queue::lease.eq(None::<String>),
+        ))
+        .execute(connection.as_mut())
+        .map_err(Error::from)?;
+    for worker_id in stale_workers.into_iter().flatten() {
+        Worker::refresh_status(worker_id, connection.as_mut())?;
+    }
 
     // see if we can dig up any available work for this worker
-    let pop_request = request.into_inner();
     let supported_architectures = standardize_architectures(&pop_request.supported_architectures);
 
     debug!(
-        "Trying to find work for worker {:?}... ({supported_architectures:?})",
-        worker.name
+        "Trying to find work for worker {:?}... ({supported_architectures:?}, labels={:?})",
+        worker.name, pop_request.labels
     );
 
     if let Some(record) =
         connection.transaction::<Option<QueuedJobWithArtifacts>, _, _>(|conn| {
-            if let Some(record) = queue_base()
-                .filter(queue::worker.is_null())
-                .filter(
-                    build_inputs::next_retry
-                        .is_null()
-                        .or(build_inputs::next_retry.le(diesel::dsl::now)),
-                )
-                .filter(build_inputs::architecture.eq_any(supported_architectures))
-                .filter(build_inputs::backend.eq_any(pop_request.supported_backends))
+            let mut query = queue_base()
+                .filter(available_jobs(
+                    &supported_architectures,
+                    &pop_request.supported_backends,
+                    &pop_request.labels,
+                ))
+                .into_boxed();
+
+            if cfg.schedule.policy() == SchedulePolicy::FairShare
+                && let Some(selection) =
+                    select_fair_share(conn.as_mut(), &cfg, &supported_architectures, &pop_request)?
+            {
+                let has_no_release = selection.releases.contains(&None);
+                let releases = selection.releases.into_iter().flatten().collect::<Vec<_>>();
+
+                query = query
+                    .filter(queue::priority.eq(selection.priority))
+                    .filter(source_packages::distribution.eq(selection.distribution));
+                query = if has_no_release {
+                    query.filter(
+                        source_packages::release
+                            .is_null()
+                            .or(source_packages::release.eq_any(releases)),
+                    )
+                } else {
+                    query.filter(source_packages::release.eq_any(releases))
+                };
+            }
+
+            if let Some(record) = query
                 .order_by((
                     queue::priority,
                     diesel::dsl::date(queue::queued_at),
@@ -420,7 +624,7 @@ pub async fn request_work(
                     .map_err(Error::from)?;
 
                 let now = Utc::now().naive_utc();
-                let status = format!("working hard on {} {}", record.name, record.version);
+                let lease = Queued::generate_lease();
 
                 debug!(
                     "Marking job as taken for worker {:?}: {:?}",
@@ -432,23 +636,28 @@ pub async fn request_work(
                         queue::started_at.eq(now),
                         queue::worker.eq(worker.id),
                         queue::last_ping.eq(now),
+                        queue::lease.eq(&lease),
                     ))
                     .execute(conn)
                     .map_err(Error::from)?;
 
+                // discard the partial log of a previous worker that abandoned this job
+                diesel::delete(queue_logs::table)
+                    .filter(queue_logs::queue_id.is(record.id))
+                    .execute(conn)
+                    .map_err(Error::from)?;
+
                 diesel::update(workers::table)
                     .filter(workers::id.is(worker.id))
-                    .set((
-                        workers::online.eq(true),
-                        workers::last_ping.eq(now),
-                        workers::status.eq(status),
-                    ))
+                    .set((workers::online.eq(true), workers::last_ping.eq(now)))
                     .execute(conn)
                     .map_err(Error::from)?;
+                Worker::refresh_status(worker.id, conn.as_mut())?;
 
                 Ok::<Option<QueuedJobWithArtifacts>, Error>(Some(QueuedJobWithArtifacts {
                     job: record,
                     artifacts,
+                    lease,
                 }))
             } else {
                 debug!(
@@ -464,3 +673,208 @@ pub async fn request_work(
         Ok(HttpResponse::Ok().json(JobAssignment::Nothing))
     }
 }
+
+#[post("/{id}/log")]
+pub async fn push_queued_job_log(
+    req: HttpRequest,
+    cfg: web::Data<Config>,
+    pool: web::Data<Pool>,
+    id: web::Path<i32>,
+    query: web::Query<PushQueuedJobLogQuery>,
+    body: web::Bytes,
+) -> web::Result<impl Responder> {
+    let mut connection = pool.get().map_err(Error::from)?;
+
+    let Ok(worker) = auth::worker(&cfg, &req, &body, connection.as_mut()) else {
+        return Ok(HttpResponse::Forbidden().finish());
+    };
+
+    let id = id.into_inner();
+    let is_assigned = diesel::dsl::select(diesel::dsl::exists(
+        queue::table.filter(
+            queue::id
+                .is(id)
+                .and(queue::worker.is(worker.id))
+                .and(queue::lease.is(&query.lease)),
+        ),
+    ))
+    .get_result::<bool>(connection.as_mut())
+    .map_err(Error::from)?;
+
+    if !is_assigned {
+        return if is_queued(connection.as_mut(), id)? {
+            Ok(HttpResponse::Conflict().finish())
+        } else {
+            Ok(HttpResponse::NotFound().finish())
+        };
+    }
+
+    // anything past the limit is dropped, the complete log is part of the build report anyway
+    let stored = queue_logs::table
+        .filter(queue_logs::queue_id.is(id))
+        .select(sql::<Nullable<BigInt>>("SUM(LENGTH(chunk))"))
+        .get_result::<Option<i64>>(connection.as_mut())
+        .map_err(Error::from)?
+        .unwrap_or(0);
+    let remaining = cfg.worker.max_log_bytes().saturating_sub(stored as usize);
+    if remaining == 0 {
+        return Ok(HttpResponse::NoContent().finish());
+    }
+
+    let new_chunk = NewQueueLog {
+        queue_id: id,
+        chunk: body[..cmp::min(body.len(), remaining)].to_vec(),
+    };
+    new_chunk.insert(connection.as_mut())?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+fn load_log_chunks(
+    connection: &mut SqliteConnection,
+    queue_id: i32,
+    after: i32,
+) -> Result<Vec<(i32, Vec<u8>)>> {
+    let chunks = queue_logs::table
+        .filter(queue_logs::queue_id.is(queue_id))
+        .filter(queue_logs::id.gt(after))
+        .order_by(queue_logs::id)
+        .select((queue_logs::id, queue_logs::chunk))
+        .load::<(i32, Vec<u8>)>(connection)?;
+
+    Ok(chunks)
+}
+
+fn has_assigned_jobs(connection: &mut SqliteConnection, worker_id: i32) -> Result<bool> {
+    let exists = diesel::dsl::select(diesel::dsl::exists(
+        queue::table.filter(queue::worker.is(worker_id)),
+    ))
+    .get_result::<bool>(connection)?;
+
+    Ok(exists)
+}
+
+fn is_queued(connection: &mut SqliteConnection, queue_id: i32) -> Result<bool> {
+    let exists = diesel::dsl::select(diesel::dsl::exists(
+        queue::table.filter(queue::id.is(queue_id)),
+    ))
+    .get_result::<bool>(connection)?;
+
+    Ok(exists)
+}
+
+/// Encodes log output as a server-sent event. Every line needs its own `data:` field, the client joins them with
+/// newlines again.
+fn log_event(chunk: &[u8]) -> String {
+    let mut event = String::new();
+    for line in String::from_utf8_lossy(chunk).split('\n') {
+        event.push_str("data: ");
+        event.push_str(line);
+        event.push('\n');
+    }
+    event.push('\n');
+    event
+}
+
+/// The length of `bytes` without a character that has been cut off at the end. The output is pushed in chunks of
+/// arbitrary size, so the rest of the character is only going to arrive with the next chunk.
+fn complete_utf8_len(bytes: &[u8]) -> usize {
+    let mut valid = 0;
+    loop {
+        match std::str::from_utf8(&bytes[valid..]) {
+            Ok(_) => return bytes.len(),
+            Err(err) => match err.error_len() {
+                // invalid bytes are replaced when decoding
+                Some(len) => valid += err.valid_up_to() + len,
+                None => return valid + err.valid_up_to(),
+            },
+        }
+    }
+}
+
+#[get("/{id}/log")]
+pub async fn get_queued_job_log(
+    req: HttpRequest,
+    pool: web::Data<Pool>,
+    id: web::Path<i32>,
+) -> web::Result<impl Responder> {
+    let mut connection = pool.get().map_err(Error::from)?;
+    let id = id.into_inner();
+
+    if !is_queued(connection.as_mut(), id)? {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+
+    let wants_events = req
+        .headers()
+        .get(header::ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .is_some_and(|accept| accept.contains("text/event-stream"));
+
+    if !wants_events {
+        let log = load_log_chunks(connection.as_mut(), id, 0)?
+            .into_iter()
+            .flat_map(|(_, chunk)| chunk)
+            .collect::<Vec<_>>();
+
+        return Ok(HttpResponse::Ok()
+            .content_type("text/plain; charset=utf-8")
+            .append_header(("X-Content-Type-Options", "nosniff"))
+            .body(log));
+    }
+    drop(connection);
+
+    let pool = pool.into_inner();
+    let events = futures_util::stream::unfold(Some((0, Vec::new())), move |state| {
+        let pool = pool.clone();
+        async move {
+            let (last_id, mut pending) = state?;
+            let event = async {
+                let mut connection = pool.get()?;
+                let chunks = load_log_chunks(connection.as_mut(), id, last_id)?;
+                if let Some((chunk_id, _)) = chunks.last() {
+                    let chunk_id = *chunk_id;
+                    pending.extend(chunks.into_iter().flat_map(|(_, chunk)| chunk));
+                    let complete = complete_utf8_len(&pending);
+                    let rest = pending.split_off(complete);
+                    let event = if pending.is_empty() {
+                        String::new()
+                    } else {
+                        log_event(&pending)
+                    };
+                    return Ok::<_, Error>((event, Some((chunk_id, rest))));
+                }
+
+                if !is_queued(connection.as_mut(), id)? {
+                    let mut event = String::new();
+                    if !pending.is_empty() {
+                        event.push_str(&log_event(&pending));
+                    }
+                    event.push_str("event: end\ndata:\n\n");
+                    return Ok((event, None));
+                }
+                // don't hold on to a database connection while waiting for new output
+                drop(connection);
+
+                // the keep-alive lets us notice if the client went away
+                tokio::time::sleep(LOG_FOLLOW_INTERVAL).await;
+                Ok((": keep-alive\n\n".to_string(), Some((last_id, pending))))
+            };
+
+            match event.await {
+                Ok((event, next)) => Some((Ok::<_, actix_web::Error>(Bytes::from(event)), next)),
+                Err(err) => {
+                    error!("Failed to follow build log: {err:#}");
+                    None
+                }
+            }
+        }
+    });
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(header::CacheControl(vec![header::CacheDirective::NoCache]))
+        // compressing would buffer the events instead of passing them through
+        .insert_header(ContentEncoding::Identity)
+        .streaming(events))
+}
@@ -0,0 +1,105 @@
+use crate::api::v1::util::{audit, auth};
+use crate::config::Config;
+use crate::db::Pool;
+use crate::models::{ApiToken, NewApiToken};
+use crate::web;
+use actix_web::{HttpRequest, HttpResponse, Responder, delete, get, post};
+use diesel::Connection;
+use rebuilderd_common::api::v1::{CreateApiTokenRequest, CreatedApiToken};
+use rebuilderd_common::errors::Error;
+
+#[get("")]
+pub async fn get_api_tokens(
+    req: HttpRequest,
+    cfg: web::Data<Config>,
+    pool: web::Data<Pool>,
+) -> web::Result<impl Responder> {
+    if auth::admin(&cfg, &req).is_err() {
+        return Ok(HttpResponse::Forbidden().finish());
+    }
+
+    let mut connection = pool.get().map_err(Error::from)?;
+
+    let tokens = ApiToken::list(connection.as_mut())?
+        .into_iter()
+        .map(ApiToken::into_api_token)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(HttpResponse::Ok().json(tokens))
+}
+
+#[post("")]
+pub async fn create_api_token(
+    req: HttpRequest,
+    cfg: web::Data<Config>,
+    pool: web::Data<Pool>,
+    request: web::Json<CreateApiTokenRequest>,
+) -> web::Result<impl Responder> {
+    let Ok(actor) = auth::admin(&cfg, &req) else {
+        return Ok(HttpResponse::Forbidden().finish());
+    };
+
+    let mut connection = pool.get().map_err(Error::from)?;
+
+    let request = request.into_inner();
+    let created = connection.transaction(|conn| {
+        let created = match NewApiToken::create(
+            conn.as_mut(),
+            &request.name,
+            &request.scopes,
+            request.expires_at,
+        ) {
+            Ok(created) => created,
+            Err(err) => return Ok(Err(err)),
+        };
+
+        audit::record(conn.as_mut(), &cfg, &req, &actor, &request, 1)?;
+
+        Ok::<_, Error>(Ok(created))
+    })?;
+    let (token, secret) = match created {
+        Ok(created) => created,
+        Err(err) => return Ok(HttpResponse::BadRequest().body(format!("{:#}\n", err))),
+    };
+
+    Ok(HttpResponse::Created().json(CreatedApiToken {
+        token: token.into_api_token()?,
+        secret,
+    }))
+}
+
+#[delete("/{id}")]
+pub async fn revoke_api_token(
+    req: HttpRequest,
+    cfg: web::Data<Config>,
+    pool: web::Data<Pool>,
+    id: web::Path<i32>,
+) -> web::Result<impl Responder> {
+    let Ok(actor) = auth::admin(&cfg, &req) else {
+        return Ok(HttpResponse::Forbidden().finish());
+    };
+
+    let mut connection = pool.get().map_err(Error::from)?;
+
+    let id = id.into_inner();
+    let revoked = connection.transaction(|conn| {
+        let revoked = ApiToken::revoke(conn.as_mut(), id)?;
+        if revoked {
+            audit::record(
+                conn.as_mut(),
+                &cfg,
+                &req,
+                &actor,
+                serde_json::json!({ "id": id }),
+                1,
+            )?;
+        }
+        Ok::<_, Error>(revoked)
+    })?;
+
+    if revoked {
+        Ok(HttpResponse::NoContent().finish())
+    } else {
+        Ok(HttpResponse::NotFound().finish())
+    }
+}
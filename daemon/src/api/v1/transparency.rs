@@ -0,0 +1,122 @@
+use crate::api::forward_compressed_data;
+use crate::attestation;
+use crate::db::Pool;
+use crate::models::{self, TreeHead};
+use crate::schema::transparency_log;
+use crate::web;
+use actix_web::{HttpRequest, HttpResponse, Responder, get};
+use diesel::{OptionalExtension, QueryDsl, RunQueryDsl, SqliteExpressionMethods};
+use in_toto::crypto::PrivateKey;
+use rebuilderd_common::api::v1::{
+    ConsistencyProof, ConsistencyProofQuery, InclusionProof, InclusionProofQuery,
+};
+use rebuilderd_common::errors::*;
+use rebuilderd_common::transparency;
+use std::sync::Arc;
+
+#[get("/tree-head")]
+pub async fn get_tree_head(
+    pool: web::Data<Pool>,
+    private_key: web::Data<Arc<PrivateKey>>,
+) -> web::Result<impl Responder> {
+    let mut connection = pool.get().map_err(Error::from)?;
+
+    // the tree head is signed whenever the log grows, and on startup
+    let signing_key_id = attestation::key_id(private_key.public())?;
+    let Some(tree_head) = TreeHead::latest(connection.as_mut(), &signing_key_id)? else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+    Ok(HttpResponse::Ok().json(tree_head.into_api_tree_head()?))
+}
+
+#[get("/entries/{leaf_index}")]
+pub async fn get_transparency_log_entry(
+    req: HttpRequest,
+    pool: web::Data<Pool>,
+    leaf_index: web::Path<i64>,
+) -> web::Result<impl Responder> {
+    let mut connection = pool.get().map_err(Error::from)?;
+
+    let entry = transparency_log::table
+        .filter(transparency_log::leaf_index.is(leaf_index.into_inner()))
+        .select(transparency_log::entry)
+        .first::<Vec<u8>>(connection.as_mut())
+        .optional()
+        .map_err(Error::from)?;
+
+    let Some(entry) = entry else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+
+    let content_type = attestation::compressed_attestation_content_type(&entry).await;
+    forward_compressed_data(req, content_type, entry).await
+}
+
+#[get("/proofs/inclusion")]
+pub async fn get_inclusion_proof(
+    pool: web::Data<Pool>,
+    query: web::Query<InclusionProofQuery>,
+) -> web::Result<impl Responder> {
+    let query = query.into_inner();
+    let mut connection = pool.get().map_err(Error::from)?;
+
+    let current_size = models::tree_size(connection.as_mut())? as u64;
+    let tree_size = query.tree_size.unwrap_or(current_size);
+    if tree_size > current_size {
+        return Ok(HttpResponse::BadRequest().finish());
+    }
+
+    let leaf_index = match (query.leaf_index, query.leaf_hash) {
+        (Some(leaf_index), None) => leaf_index,
+        (None, Some(leaf_hash)) => {
+            let Ok(leaf_hash) = transparency::hash_from_hex(&leaf_hash.to_ascii_lowercase()) else {
+                return Ok(HttpResponse::BadRequest().finish());
+            };
+            let Some(leaf_index) = models::find_leaf_index(connection.as_mut(), &leaf_hash)? else {
+                return Ok(HttpResponse::NotFound().finish());
+            };
+            leaf_index as u64
+        }
+        _ => return Ok(HttpResponse::BadRequest().finish()),
+    };
+    if leaf_index >= tree_size {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+
+    let leaf_hash = models::load_node(connection.as_mut(), (0, leaf_index))?;
+    let audit_path = transparency::inclusion_proof(tree_size, leaf_index, &mut |id| {
+        models::load_node(connection.as_mut(), id)
+    })?;
+
+    Ok(HttpResponse::Ok().json(InclusionProof {
+        leaf_index,
+        tree_size,
+        leaf_hash: transparency::hash_to_hex(&leaf_hash),
+        audit_path: audit_path.iter().map(transparency::hash_to_hex).collect(),
+    }))
+}
+
+#[get("/proofs/consistency")]
+pub async fn get_consistency_proof(
+    pool: web::Data<Pool>,
+    query: web::Query<ConsistencyProofQuery>,
+) -> web::Result<impl Responder> {
+    let query = query.into_inner();
+    let mut connection = pool.get().map_err(Error::from)?;
+
+    let current_size = models::tree_size(connection.as_mut())? as u64;
+    let second = query.second.unwrap_or(current_size);
+    if query.first > second || second > current_size {
+        return Ok(HttpResponse::BadRequest().finish());
+    }
+
+    let proof = transparency::consistency_proof(second, query.first, &mut |id| {
+        models::load_node(connection.as_mut(), id)
+    })?;
+
+    Ok(HttpResponse::Ok().json(ConsistencyProof {
+        first: query.first,
+        second,
+        proof: proof.iter().map(transparency::hash_to_hex).collect(),
+    }))
+}
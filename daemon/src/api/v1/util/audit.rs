@@ -0,0 +1,45 @@
+use crate::api;
+use crate::api::v1::util::auth::Actor;
+use crate::config::Config;
+use crate::models::NewAuditEvent;
+use actix_web::HttpRequest;
+use chrono::Utc;
+use diesel::SqliteConnection;
+use rebuilderd_common::errors::*;
+use serde::Serialize;
+
+/// Records an administrative action in the audit log. `affected_rows` is the number of packages, jobs, workers or
+/// tokens the action has changed.
+pub fn record<P: Serialize>(
+    connection: &mut SqliteConnection,
+    cfg: &Config,
+    req: &HttpRequest,
+    actor: &Actor,
+    parameters: P,
+    affected_rows: usize,
+) -> Result<()> {
+    let source_ip = match api::client_ip(cfg, req) {
+        Ok(ip) => Some(ip.to_string()),
+        Err(err) => {
+            warn!("Failed to determine source ip for audit log: {err:#}");
+            None
+        }
+    };
+    let route = req
+        .match_pattern()
+        .unwrap_or_else(|| req.path().to_string());
+
+    let event = NewAuditEvent {
+        created_at: Utc::now().naive_utc(),
+        actor: actor.to_string(),
+        source_ip,
+        endpoint: format!("{} {route}", req.method()),
+        parameters: serde_json::to_string(&parameters)?,
+        affected_rows: affected_rows as i64,
+    };
+    event
+        .insert(connection)
+        .context("Failed to write audit event")?;
+
+    Ok(())
+}
@@ -1,28 +1,167 @@
 use crate::api;
 use crate::config::Config;
-use crate::models::Worker;
-use crate::schema::workers;
+use crate::models::{ApiToken, Worker};
+use crate::schema::{worker_request_nonces, workers};
 use actix_web::HttpRequest;
-use diesel::QueryDsl;
-use diesel::SqliteExpressionMethods;
-use diesel::{RunQueryDsl, SqliteConnection};
+use chrono::{DateTime, Utc};
+use data_encoding::BASE64;
+use diesel::{ExpressionMethods, SqliteExpressionMethods};
+use diesel::{OptionalExtension, QueryDsl, RunQueryDsl, SqliteConnection};
 use log::debug;
-use rebuilderd_common::api::{AUTH_COOKIE_HEADER, SIGNUP_SECRET_HEADER, WORKER_KEY_HEADER};
-use rebuilderd_common::errors::{Context, bail};
+use rebuilderd_common::api::v1::{ApiTokenScope, WorkerState};
+use rebuilderd_common::api::{
+    AUTH_COOKIE_HEADER, SIGNUP_SECRET_HEADER, WORKER_KEY_HEADER, WORKER_NONCE_HEADER,
+    WORKER_SIGNATURE_HEADER, WORKER_TIMESTAMP_HEADER, worker_request_message,
+};
+use rebuilderd_common::errors::{Context, bail, format_err};
+use ring::signature::{ED25519, UnparsedPublicKey};
+use std::fmt;
 
-pub fn admin(cfg: &Config, req: &HttpRequest) -> rebuilderd_common::errors::Result<()> {
+/// Who made an authenticated request, as recorded in the audit log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Actor {
+    AuthCookie,
+    ApiToken(String),
+    Worker(String),
+}
+
+impl fmt::Display for Actor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Actor::AuthCookie => f.write_str("cookie"),
+            Actor::ApiToken(name) => write!(f, "token:{name}"),
+            Actor::Worker(name) => write!(f, "worker:{name}"),
+        }
+    }
+}
+
+/// How far the timestamp of a signed worker request may deviate from our own clock, in seconds.
+const MAX_SIGNATURE_CLOCK_SKEW: i64 = 300;
+
+pub fn admin(cfg: &Config, req: &HttpRequest) -> rebuilderd_common::errors::Result<Actor> {
     let auth_cookie = api::header(req, AUTH_COOKIE_HEADER).context("Failed to get auth cookie")?;
 
     if cfg.auth_cookie != auth_cookie {
         bail!("Wrong auth cookie")
     }
 
+    Ok(Actor::AuthCookie)
+}
+
+/// Accepts the auth cookie, which is allowed to do everything, or an api token that has been granted `scope`.
+pub fn scoped(
+    cfg: &Config,
+    req: &HttpRequest,
+    connection: &mut SqliteConnection,
+    scope: ApiTokenScope,
+) -> rebuilderd_common::errors::Result<Actor> {
+    let secret = api::header(req, AUTH_COOKIE_HEADER).context("Failed to get auth cookie")?;
+
+    if cfg.auth_cookie == secret {
+        return Ok(Actor::AuthCookie);
+    }
+
+    let token = ApiToken::authenticate(connection, secret)?;
+    if !token.has_scope(scope) {
+        bail!("Api token {:?} is missing scope {scope}", token.name);
+    }
+    debug!("authenticated with api token {:?}", token.name);
+
+    Ok(Actor::ApiToken(token.name))
+}
+
+/// Verifies that the request was signed by the private key belonging to `worker_key`.
+///
+/// If the worker didn't send a signature at all, this is only accepted while unsigned requests are explicitly allowed
+/// in the config. A signature that is present but invalid is always rejected, and so is a signed request that has
+/// been seen before.
+fn verify_signature(
+    cfg: &Config,
+    req: &HttpRequest,
+    worker_key: &str,
+    body: &[u8],
+    connection: &mut SqliteConnection,
+) -> rebuilderd_common::errors::Result<()> {
+    let Ok(signature) = api::header(req, WORKER_SIGNATURE_HEADER) else {
+        if cfg.worker.allow_unsigned_requests() {
+            debug!("worker request is not signed, accepting because unsigned requests are allowed");
+            return Ok(());
+        }
+        bail!("Worker request is not signed");
+    };
+
+    let timestamp = api::header(req, WORKER_TIMESTAMP_HEADER)
+        .context("Failed to get signature timestamp")?
+        .parse::<i64>()
+        .context("Failed to parse signature timestamp")?;
+
+    let skew = (Utc::now().timestamp() - timestamp).abs();
+    if skew > MAX_SIGNATURE_CLOCK_SKEW {
+        bail!("Signature timestamp is too far off ({skew}s)");
+    }
+
+    let nonce = api::header(req, WORKER_NONCE_HEADER).context("Failed to get signature nonce")?;
+
+    let key = BASE64
+        .decode(worker_key.as_bytes())
+        .context("Failed to decode worker key")?;
+    let signature = BASE64
+        .decode(signature.as_bytes())
+        .context("Failed to decode worker signature")?;
+
+    let message = worker_request_message(
+        req.method().as_str(),
+        req.path(),
+        req.query_string(),
+        timestamp,
+        nonce,
+        body,
+    );
+    UnparsedPublicKey::new(&ED25519, key)
+        .verify(&message, &signature)
+        .map_err(|_| format_err!("Worker signature is invalid"))?;
+
+    // only remembered once the signature is valid, so unauthenticated requests can't fill up the table
+    use_nonce(connection, worker_key, nonce, timestamp)?;
+
+    Ok(())
+}
+
+/// Records the nonce of a signed request until its timestamp is no longer accepted, fails if the worker has used it
+/// before.
+fn use_nonce(
+    connection: &mut SqliteConnection,
+    worker_key: &str,
+    nonce: &str,
+    timestamp: i64,
+) -> rebuilderd_common::errors::Result<()> {
+    diesel::delete(
+        worker_request_nonces::table
+            .filter(worker_request_nonces::expires_at.lt(Utc::now().naive_utc())),
+    )
+    .execute(connection)?;
+
+    let expires_at = DateTime::from_timestamp(timestamp + MAX_SIGNATURE_CLOCK_SKEW, 0)
+        .context("Signature timestamp is out of range")?
+        .naive_utc();
+    let inserted = diesel::insert_or_ignore_into(worker_request_nonces::table)
+        .values((
+            worker_request_nonces::worker_key.eq(worker_key),
+            worker_request_nonces::nonce.eq(nonce),
+            worker_request_nonces::expires_at.eq(expires_at),
+        ))
+        .execute(connection)?;
+    if inserted == 0 {
+        bail!("Worker request has been replayed");
+    }
+
     Ok(())
 }
 
 pub fn worker(
     cfg: &Config,
     req: &HttpRequest,
+    body: &[u8],
     connection: &mut SqliteConnection,
 ) -> rebuilderd_common::errors::Result<Worker> {
     let worker_key = api::header(req, WORKER_KEY_HEADER).context("Failed to get worker key")?;
@@ -37,20 +176,30 @@ pub fn worker(
         bail!("Worker key is not on allow-list");
     }
 
-    let key_is_registered = diesel::dsl::select(diesel::dsl::exists(
-        workers::table.filter(workers::key.is(worker_key)),
-    ))
-    .get_result::<bool>(connection)?;
+    let state = workers::table
+        .filter(workers::key.is(worker_key))
+        .select(workers::state)
+        .get_result::<WorkerState>(connection)
+        .optional()?;
 
-    if !key_is_registered {
-        bail!("Worker is not registered")
+    match state {
+        None => bail!("Worker is not registered"),
+        Some(WorkerState::Disabled) => bail!("Worker has been disabled"),
+        Some(_) => (),
     }
 
+    verify_signature(cfg, req, worker_key, body, connection)?;
+
     let worker = Worker::get_and_refresh(worker_key, connection)?;
     Ok(worker)
 }
 
-pub fn signup(cfg: &Config, req: &HttpRequest) -> rebuilderd_common::errors::Result<()> {
+pub fn signup(
+    cfg: &Config,
+    req: &HttpRequest,
+    body: &[u8],
+    connection: &mut SqliteConnection,
+) -> rebuilderd_common::errors::Result<()> {
     let worker_key = api::header(req, WORKER_KEY_HEADER).context("Failed to get worker key")?;
 
     if !cfg.worker.authorized_workers.is_empty()
@@ -63,6 +212,9 @@ pub fn signup(cfg: &Config, req: &HttpRequest) -> rebuilderd_common::errors::Res
         bail!("Worker key is not on allow-list");
     }
 
+    // make sure workers can't register a key they don't own
+    verify_signature(cfg, req, worker_key, body, connection)?;
+
     if let Some(expected_signup_secret) = &cfg.worker.signup_secret {
         let signup_secret =
             api::header(req, SIGNUP_SECRET_HEADER).context("Failed to get signup secret")?;
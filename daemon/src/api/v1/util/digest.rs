@@ -0,0 +1,4 @@
+/// Checks if `digest` is a hex-encoded digest of `len` bytes.
+pub fn is_hex_digest(digest: &str, len: usize) -> bool {
+    digest.len() == len * 2 && digest.bytes().all(|b| b.is_ascii_hexdigit())
+}
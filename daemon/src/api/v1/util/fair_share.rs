@@ -0,0 +1,226 @@
+//! Fair-share scheduling: jobs are handed out from the scope that received the smallest part of the recent worker
+//! time, relative to its weight. A scope is either a whole distribution or a release that has its own share.
+
+use crate::schema::{build_inputs, queue, rebuilds, source_packages};
+use chrono::NaiveDateTime;
+use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl, SqliteConnection};
+use rebuilderd_common::config::ScheduleConfig;
+use rebuilderd_common::errors::Error;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+
+/// A release that has jobs available for the worker
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Candidate {
+    pub distribution: String,
+    pub release: Option<String>,
+}
+
+/// Worker time spent on each scope within the fair-share window in milliseconds, including jobs that are still running
+pub fn usage(
+    connection: &mut SqliteConnection,
+    schedule: &ScheduleConfig,
+    now: NaiveDateTime,
+) -> Result<HashMap<String, i64>, Error> {
+    let since = now - schedule.fair_share_window();
+    let mut usage = HashMap::<String, i64>::new();
+
+    let finished = rebuilds::table
+        .inner_join(build_inputs::table.inner_join(source_packages::table))
+        .filter(rebuilds::built_at.gt(since))
+        .order_by(rebuilds::id)
+        .select((
+            rebuilds::build_log_id,
+            source_packages::distribution,
+            source_packages::release,
+            rebuilds::started_at,
+            rebuilds::built_at,
+        ))
+        .load::<(
+            i32,
+            String,
+            Option<String>,
+            Option<NaiveDateTime>,
+            Option<NaiveDateTime>,
+        )>(connection)?;
+
+    // rebuilds that were copied to other releases share the build log of the original
+    let mut seen = HashSet::new();
+    for (build_log_id, distribution, release, started_at, built_at) in finished {
+        let (Some(started_at), Some(built_at)) = (started_at, built_at) else {
+            continue;
+        };
+        if !seen.insert(build_log_id) {
+            continue;
+        }
+
+        let (scope, _) = schedule.share(&distribution, release.as_deref());
+        let duration = built_at - started_at.max(since);
+        *usage.entry(scope).or_default() += duration.num_milliseconds().max(0);
+    }
+
+    let running = queue::table
+        .inner_join(build_inputs::table.inner_join(source_packages::table))
+        .filter(queue::started_at.is_not_null())
+        .select((
+            source_packages::distribution,
+            source_packages::release,
+            queue::started_at,
+        ))
+        .load::<(String, Option<String>, Option<NaiveDateTime>)>(connection)?;
+
+    for (distribution, release, started_at) in running {
+        let Some(started_at) = started_at else {
+            continue;
+        };
+
+        let (scope, _) = schedule.share(&distribution, release.as_deref());
+        let duration = now - started_at.max(since);
+        *usage.entry(scope).or_default() += duration.num_milliseconds().max(0);
+    }
+
+    Ok(usage)
+}
+
+/// Compares the usage of two scopes relative to their weights, the scope that is further below its share comes
+/// first. Scopes with a weight of 0 come after all others.
+fn compare_usage((a_used, a_weight): (i64, u32), (b_used, b_weight): (i64, u32)) -> Ordering {
+    match (a_weight, b_weight) {
+        (0, 0) => a_used.cmp(&b_used),
+        (0, _) => Ordering::Greater,
+        (_, 0) => Ordering::Less,
+        _ => {
+            let a = i128::from(a_used) * i128::from(b_weight);
+            let b = i128::from(b_used) * i128::from(a_weight);
+            a.cmp(&b)
+        }
+    }
+}
+
+/// Picks the scope that is furthest below its share and returns the candidates that belong to it
+pub fn pick<'a>(
+    schedule: &ScheduleConfig,
+    candidates: &'a [Candidate],
+    usage: &HashMap<String, i64>,
+) -> Vec<&'a Candidate> {
+    let best = candidates
+        .iter()
+        .map(|candidate| {
+            let (scope, weight) =
+                schedule.share(&candidate.distribution, candidate.release.as_deref());
+            let used = usage.get(&scope).copied().unwrap_or(0);
+            (scope, weight, used)
+        })
+        .min_by(|(a_scope, a_weight, a_used), (b_scope, b_weight, b_used)| {
+            compare_usage((*a_used, *a_weight), (*b_used, *b_weight))
+                .then_with(|| a_scope.cmp(b_scope))
+        });
+
+    let Some((best, _, _)) = best else {
+        return Vec::new();
+    };
+
+    candidates
+        .iter()
+        .filter(|candidate| {
+            let (scope, _) = schedule.share(&candidate.distribution, candidate.release.as_deref());
+            scope == best
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(distribution: &str, release: Option<&str>) -> Candidate {
+        Candidate {
+            distribution: distribution.to_string(),
+            release: release.map(String::from),
+        }
+    }
+
+    fn schedule(shares: &[(&str, u32)]) -> ScheduleConfig {
+        ScheduleConfig {
+            shares: shares
+                .iter()
+                .map(|(scope, weight)| (scope.to_string(), *weight))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn usage(entries: &[(&str, i64)]) -> HashMap<String, i64> {
+        entries
+            .iter()
+            .map(|(scope, used)| (scope.to_string(), *used))
+            .collect()
+    }
+
+    #[test]
+    fn test_pick_least_used_scope() {
+        let schedule = schedule(&[]);
+        let candidates = [
+            candidate("debian", Some("trixie")),
+            candidate("debian", Some("sid")),
+            candidate("archlinux", None),
+        ];
+        let usage = usage(&[("debian", 1000), ("archlinux", 5000)]);
+
+        let picked = pick(&schedule, &candidates, &usage);
+        assert_eq!(picked, vec![&candidates[0], &candidates[1]]);
+    }
+
+    #[test]
+    fn test_pick_relative_to_weight() {
+        let schedule = schedule(&[("debian", 4), ("archlinux", 1)]);
+        let candidates = [
+            candidate("debian", Some("sid")),
+            candidate("archlinux", None),
+        ];
+
+        let picked = pick(
+            &schedule,
+            &candidates,
+            &usage(&[("debian", 3000), ("archlinux", 1000)]),
+        );
+        assert_eq!(picked, vec![&candidates[0]]);
+
+        let picked = pick(
+            &schedule,
+            &candidates,
+            &usage(&[("debian", 5000), ("archlinux", 1000)]),
+        );
+        assert_eq!(picked, vec![&candidates[1]]);
+    }
+
+    #[test]
+    fn test_pick_release_with_own_share() {
+        let schedule = schedule(&[("debian/trixie", 1)]);
+        let candidates = [
+            candidate("debian", Some("trixie")),
+            candidate("debian", Some("sid")),
+        ];
+        let usage = usage(&[("debian/trixie", 1000), ("debian", 500)]);
+
+        let picked = pick(&schedule, &candidates, &usage);
+        assert_eq!(picked, vec![&candidates[1]]);
+    }
+
+    #[test]
+    fn test_pick_zero_weight_only_without_other_work() {
+        let schedule = schedule(&[("tails", 0)]);
+        let candidates = [
+            candidate("tails", Some("stable")),
+            candidate("debian", None),
+        ];
+
+        let picked = pick(&schedule, &candidates, &usage(&[("debian", 1000)]));
+        assert_eq!(picked, vec![&candidates[1]]);
+
+        let picked = pick(&schedule, &candidates[..1], &usage(&[]));
+        assert_eq!(picked, vec![&candidates[0]]);
+
+        assert!(pick(&schedule, &[], &usage(&[])).is_empty());
+    }
+}
@@ -1,4 +1,4 @@
-use crate::schema::source_packages;
+use crate::schema::{rebuilds, source_packages};
 use diesel::backend::Backend;
 use diesel::dsl::sql;
 use diesel::expression::is_aggregate::No;
@@ -9,7 +9,7 @@ use diesel::sqlite::Sqlite;
 use diesel::{BoolExpressionMethods, BoxableExpression, Expression, SelectableExpression};
 use diesel::{ExpressionMethods, SqliteExpressionMethods};
 use rebuilderd_common::api::v1::{
-    BinaryIdentityFilter, FreshnessFilter, OriginFilter, SourceIdentityFilter,
+    BinaryIdentityFilter, FreshnessFilter, OriginFilter, SourceIdentityFilter, WorkerFilter,
 };
 
 pub trait IntoSourceIdentityFilter<QS, DB>
@@ -282,3 +282,19 @@ where
         }
     }
 }
+
+impl<T: 'static> IntoFilter<T, Sqlite> for WorkerFilter
+where
+    rebuilds::worker_id: SelectableExpression<T>,
+{
+    type SqlType = Bool;
+
+    type Output = Box<dyn BoxableExpression<T, Sqlite, SqlType = Self::SqlType>>;
+
+    fn into_filter(self) -> Self::Output {
+        match self.worker {
+            Some(worker) => Box::new(rebuilds::worker_id.is(worker)),
+            None => Box::new(AsExpression::<Bool>::as_expression(true)),
+        }
+    }
+}
@@ -1,4 +1,8 @@
+pub mod audit;
 pub mod auth;
+pub mod digest;
+pub mod fair_share;
 pub mod filters;
 pub mod friends;
+pub mod multipart;
 pub mod pagination;
@@ -0,0 +1,40 @@
+use actix_multipart::Multipart;
+use actix_web::HttpRequest;
+use actix_web::error::PayloadError;
+use actix_web::web::Bytes;
+use futures_util::{StreamExt, stream};
+use rebuilderd_common::errors::*;
+use std::collections::HashMap;
+
+/// Splits an already received multipart/form-data body into its named parts.
+///
+/// The body is buffered before this is called because worker signatures cover the whole body.
+pub async fn parts(req: &HttpRequest, body: Bytes) -> Result<HashMap<String, Vec<u8>>> {
+    let mut multipart = Multipart::new(
+        req.headers(),
+        stream::once(async move { Ok::<_, PayloadError>(body) }),
+    );
+
+    let mut parts = HashMap::new();
+    while let Some(field) = multipart.next().await {
+        let mut field =
+            field.map_err(|err| format_err!("Failed to read multipart field: {err}"))?;
+        let name = field
+            .name()
+            .context("Multipart field has no name")?
+            .to_string();
+
+        let mut data = Vec::new();
+        while let Some(chunk) = field.next().await {
+            let chunk =
+                chunk.map_err(|err| format_err!("Failed to read multipart field: {err}"))?;
+            data.extend_from_slice(&chunk);
+        }
+
+        if parts.insert(name.clone(), data).is_some() {
+            bail!("Duplicate multipart field: {name:?}");
+        }
+    }
+
+    Ok(parts)
+}
@@ -1,18 +1,23 @@
-use crate::api::header;
-use crate::api::v1::util::auth;
+use crate::api::v1::util::auth::Actor;
 use crate::api::v1::util::pagination::PaginateDsl;
+use crate::api::v1::util::{audit, auth};
+use crate::api::{client_ip, header};
 use crate::config::Config;
 use crate::db::Pool;
-use crate::models::NewWorker;
-use crate::schema::workers;
+use crate::models::{NewWorker, Worker};
+use crate::schema::{queue, workers};
 use crate::web;
-use actix_web::{HttpRequest, HttpResponse, Responder, delete, get, post};
-use chrono::Utc;
-use diesel::{OptionalExtension, QueryDsl, RunQueryDsl, SqliteExpressionMethods};
+use actix_web::{HttpRequest, HttpResponse, Responder, delete, get, post, put};
+use chrono::{NaiveDateTime, Utc};
+use diesel::{
+    Connection, ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl,
+    SqliteExpressionMethods,
+};
 use rebuilderd_common::api::WORKER_KEY_HEADER;
-use rebuilderd_common::api::v1::{Page, RegisterWorkerRequest, ResultPage};
-use rebuilderd_common::errors::{Context, Error, format_err};
-use std::net::IpAddr;
+use rebuilderd_common::api::v1::{
+    ApiTokenScope, Page, RegisterWorkerRequest, ResultPage, SetWorkerStateRequest, WorkerState,
+};
+use rebuilderd_common::errors::{Context, Error};
 
 #[diesel::dsl::auto_type]
 fn workers_base() -> _ {
@@ -23,6 +28,7 @@ fn workers_base() -> _ {
         workers::status,
         workers::last_ping,
         workers::online,
+        workers::state,
     ))
 }
 
@@ -51,35 +57,56 @@ pub async fn register_worker(
     req: HttpRequest,
     cfg: web::Data<Config>,
     pool: web::Data<Pool>,
-    request: web::Json<RegisterWorkerRequest>,
+    body: web::Bytes,
 ) -> web::Result<impl Responder> {
     let mut connection = pool.get().map_err(Error::from)?;
-    if auth::signup(&cfg, &req).is_err() {
+    if auth::signup(&cfg, &req, &body, connection.as_mut()).is_err() {
         return Ok(HttpResponse::Forbidden().finish());
     }
 
-    let key = header(&req, WORKER_KEY_HEADER).context("Failed to get worker key")?;
-    let ip = if let Some(real_ip_header) = &cfg.real_ip_header {
-        let ip = header(&req, real_ip_header).context("Failed to locate real ip header")?;
-        ip.parse::<IpAddr>()
-            .context("Can't parse real ip header as ip address")?
-    } else {
-        let ci = req
-            .peer_addr()
-            .ok_or_else(|| format_err!("Can't determine client ip"))?;
-        ci.ip()
+    let Ok(request) = serde_json::from_slice::<RegisterWorkerRequest>(&body) else {
+        return Ok(HttpResponse::BadRequest().finish());
     };
 
+    let key = header(&req, WORKER_KEY_HEADER).context("Failed to get worker key")?;
+    let ip = client_ip(&cfg, &req)?;
+
+    // registering again must not bring a disabled worker back
+    let state = workers::table
+        .filter(workers::key.is(key))
+        .select(workers::state)
+        .get_result::<WorkerState>(connection.as_mut())
+        .optional()
+        .map_err(Error::from)?;
+    if state == Some(WorkerState::Disabled) {
+        return Ok(HttpResponse::Forbidden().finish());
+    }
+
     let new_worker = NewWorker {
         key: key.to_string(),
-        name: request.name.clone(),
+        name: request.name,
         address: ip.to_string(),
         status: None,
         last_ping: Utc::now().naive_utc(),
         online: true,
     };
 
-    new_worker.upsert(connection.as_mut())?;
+    connection.transaction(|conn| {
+        let worker = new_worker.upsert(conn.as_mut())?;
+        // a worker that registers again may still have jobs assigned
+        Worker::refresh_status(worker.id, conn.as_mut())?;
+
+        audit::record(
+            conn.as_mut(),
+            &cfg,
+            &req,
+            &Actor::Worker(worker.name.clone()),
+            serde_json::json!({ "id": worker.id, "name": worker.name, "address": worker.address }),
+            1,
+        )?;
+
+        Ok::<(), Error>(())
+    })?;
 
     Ok(HttpResponse::NoContent().finish())
 }
@@ -100,22 +127,117 @@ pub async fn get_worker(pool: web::Data<Pool>, id: web::Path<i32>) -> web::Resul
     }
 }
 
-#[delete("/{id}")]
-pub async fn unregister_worker(
+#[put("/{id}/state")]
+pub async fn set_worker_state(
     req: HttpRequest,
     cfg: web::Data<Config>,
     pool: web::Data<Pool>,
     id: web::Path<i32>,
+    request: web::Json<SetWorkerStateRequest>,
 ) -> web::Result<impl Responder> {
     let mut connection = pool.get().map_err(Error::from)?;
-    if auth::worker(&cfg, &req, connection.as_mut()).is_err() {
+    let Ok(actor) = auth::scoped(&cfg, &req, connection.as_mut(), ApiTokenScope::WorkersAdmin)
+    else {
         return Ok(HttpResponse::Forbidden().finish());
+    };
+
+    let id = id.into_inner();
+    let requested = request.into_inner().state;
+
+    let record = connection.transaction(|conn| {
+        let has_job = diesel::dsl::select(diesel::dsl::exists(
+            queue::table.filter(queue::worker.is(id)),
+        ))
+        .get_result::<bool>(conn.as_mut())?;
+
+        let state = match requested {
+            // there's nothing to wait for
+            WorkerState::Draining if !has_job => WorkerState::Paused,
+            state => state,
+        };
+
+        let updated = diesel::update(workers::table.filter(workers::id.is(id)))
+            .set(workers::state.eq(state))
+            .execute(conn.as_mut())?;
+        if updated < 1 {
+            return Ok::<_, Error>(None);
+        }
+
+        if state == WorkerState::Disabled {
+            // the worker can't report this job anymore, hand it to somebody else right away
+            diesel::update(queue::table.filter(queue::worker.is(id)))
+                .set((
+                    queue::worker.eq(None::<i32>),
+                    queue::started_at.eq(None::<NaiveDateTime>),
+                    queue::last_ping.eq(None::<NaiveDateTime>),
+                    queue::lease.eq(None::<String>),
+                ))
+                .execute(conn.as_mut())?;
+            Worker::refresh_status(id, conn.as_mut())?;
+        }
+
+        audit::record(
+            conn.as_mut(),
+            &cfg,
+            &req,
+            &actor,
+            serde_json::json!({ "id": id, "state": requested }),
+            updated,
+        )?;
+
+        let record = workers_base()
+            .filter(workers::id.is(id))
+            .get_result::<rebuilderd_common::api::v1::Worker>(conn.as_mut())?;
+        Ok(Some(record))
+    })?;
+
+    if let Some(record) = record {
+        Ok(HttpResponse::Ok().json(record))
+    } else {
+        Ok(HttpResponse::NotFound().finish())
     }
+}
 
-    let unregistered_count = diesel::delete(workers::table)
-        .filter(workers::id.is(id.into_inner()))
-        .execute(connection.as_mut())
-        .map_err(Error::from)?;
+#[delete("/{id}")]
+pub async fn unregister_worker(
+    req: HttpRequest,
+    cfg: web::Data<Config>,
+    pool: web::Data<Pool>,
+    id: web::Path<i32>,
+) -> web::Result<impl Responder> {
+    let mut connection = pool.get().map_err(Error::from)?;
+    let actor = match auth::worker(&cfg, &req, &[], connection.as_mut()) {
+        Ok(worker) => Actor::Worker(worker.name),
+        Err(_) => {
+            let Ok(actor) =
+                auth::scoped(&cfg, &req, connection.as_mut(), ApiTokenScope::WorkersAdmin)
+            else {
+                return Ok(HttpResponse::Forbidden().finish());
+            };
+            actor
+        }
+    };
+
+    let id = id.into_inner();
+    let unregistered_count = connection.transaction(|conn| {
+        let unregistered_count = diesel::delete(workers::table)
+            .filter(workers::id.is(id))
+            .execute(conn.as_mut())
+            .map_err(Error::from)?;
+
+        if unregistered_count > 0 {
+            audit::record(
+                conn.as_mut(),
+                &cfg,
+                &req,
+                &actor,
+                serde_json::json!({ "id": id }),
+                unregistered_count,
+            )?;
+        }
+
+        Ok::<_, Error>(unregistered_count)
+    })?;
 
     if unregistered_count < 1 {
         Ok(HttpResponse::NotFound().finish())
@@ -1,4 +1,5 @@
 use clap::{ArgAction, Parser};
+use rebuilderd_common::api::v1::ApiTokenScope;
 use std::path::PathBuf;
 
 #[derive(Debug, Parser)]
@@ -13,9 +14,30 @@ pub struct Args {
     /// Generate a signing keypair (this usually happens automatically)
     #[arg(long, group = "action")]
     pub keygen: bool,
+    /// Generate the next signing key and announce its public key, without using it yet
+    #[arg(long, group = "action")]
+    pub prepare_key: bool,
+    /// Replace the signing key with the prepared next key (or a new one), the old key stays published
+    #[arg(long, group = "action")]
+    pub rotate_key: bool,
     /// Derive the public key from a private key file
     #[arg(long, group = "action")]
     pub derive_pubkey: Option<PathBuf>,
+    /// Create a named api token and print its secret
+    #[arg(long, group = "action", value_name = "NAME", requires = "scopes")]
+    pub create_token: Option<String>,
+    /// Grant a scope to the token that is created, can be used multiple times
+    #[arg(long = "scope", value_name = "SCOPE", requires = "create_token")]
+    pub scopes: Vec<ApiTokenScope>,
+    /// Let the token that is created expire after this many days
+    #[arg(long, value_name = "DAYS", requires = "create_token")]
+    pub token_expires: Option<i64>,
+    /// List the api tokens, their scopes and when they've been used last
+    #[arg(long, group = "action")]
+    pub list_tokens: bool,
+    /// Revoke the api token with this name
+    #[arg(long, group = "action", value_name = "NAME")]
+    pub revoke_token: Option<String>,
     /// Configuration file path
     #[arg(short, long)]
     pub config: Option<PathBuf>,
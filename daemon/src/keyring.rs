@@ -0,0 +1,305 @@
+//! The signing keys of this rebuilderd instance.
+//!
+//! The active private key lives in the `--signing-key` file. Keys that were used before and keys that are announced to
+//! be used next are tracked in `<signing-key>.history`. A prepared next key keeps its private key in
+//! `<signing-key>.next` until a rotation promotes it.
+
+use crate::attestation;
+use chrono::{NaiveDateTime, Utc};
+use in_toto::crypto::{self, PrivateKey};
+use rebuilderd_common::api::v1::{PublicKey, RetiredPublicKey};
+use rebuilderd_common::errors::*;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyHistory {
+    #[serde(default)]
+    pub previous: Vec<RetiredKey>,
+    /// Public keys in PEM format.
+    #[serde(default)]
+    pub next: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetiredKey {
+    /// Public key in PEM format.
+    pub public_key: String,
+    pub retired_at: NaiveDateTime,
+}
+
+impl KeyHistory {
+    pub fn load(path: &Path) -> Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(buf) => toml::from_str(&buf)
+                .with_context(|| anyhow!("Failed to parse key history: {path:?}")),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(Error::from(err))
+                .with_context(|| anyhow!("Failed to read key history: {path:?}")),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let buf = toml::to_string_pretty(self)?;
+        let tmp_path = sibling(path, ".tmp");
+        fs::write(&tmp_path, buf)
+            .with_context(|| anyhow!("Failed to write key history: {tmp_path:?}"))?;
+        fs::rename(&tmp_path, path)
+            .with_context(|| anyhow!("Failed to replace key history: {path:?}"))?;
+        Ok(())
+    }
+}
+
+pub struct KeyRing {
+    pub active: Arc<PrivateKey>,
+    pub history: KeyHistory,
+}
+
+impl KeyRing {
+    /// A key ring without any history
+    pub fn new(active: PrivateKey) -> Self {
+        Self {
+            active: Arc::new(active),
+            history: KeyHistory::default(),
+        }
+    }
+
+    pub fn load_or_create(signing_key: &Path) -> Result<Self> {
+        let active = attestation::load_or_create_privkey_pem(signing_key)?;
+        let history = KeyHistory::load(&history_path(signing_key))?;
+        Ok(Self {
+            active: Arc::new(active),
+            history,
+        })
+    }
+
+    /// The public keys of the active and all previous signing keys, a signature by any of them is our own
+    pub fn own_keys(&self) -> Result<Vec<crypto::PublicKey>> {
+        let mut keys = vec![self.active.public().clone()];
+        for key in &self.history.previous {
+            let pubkey = attestation::pem_to_pubkeys(key.public_key.as_bytes())?
+                .next()
+                .context("No public key found in key history")??;
+            keys.push(pubkey);
+        }
+        Ok(keys)
+    }
+
+    /// The keys as they are published on the public-keys endpoint
+    pub fn public_keys(&self) -> Result<PublicKey> {
+        let current = attestation::pubkey_to_pem(self.active.public())?;
+        let previous = self
+            .history
+            .previous
+            .iter()
+            .map(|key| RetiredPublicKey {
+                key: key.public_key.clone(),
+                retired_at: key.retired_at,
+            })
+            .collect();
+        let next = self
+            .history
+            .next
+            .iter()
+            .filter(|key| **key != current)
+            .cloned()
+            .collect();
+
+        Ok(PublicKey {
+            current: vec![current],
+            previous,
+            next,
+        })
+    }
+}
+
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+pub fn history_path(signing_key: &Path) -> PathBuf {
+    sibling(signing_key, ".history")
+}
+
+pub fn next_key_path(signing_key: &Path) -> PathBuf {
+    sibling(signing_key, ".next")
+}
+
+fn write_new_file(path: &Path, data: &[u8]) -> Result<()> {
+    let mut file = OpenOptions::new()
+        .mode(0o640)
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .with_context(|| anyhow!("Failed to create file: {path:?}"))?;
+    file.write_all(data)?;
+    Ok(())
+}
+
+fn pubkey_from_privkey_pem(buf: &[u8]) -> Result<String> {
+    let privkey = attestation::pem_to_privkeys(buf)?
+        .next()
+        .context("No private key found in PEM file")??;
+    attestation::pubkey_to_pem(privkey.public())
+}
+
+/// Generates the key that is going to be used after the next rotation and announces its public key
+pub fn prepare_next_key(signing_key: &Path) -> Result<String> {
+    let next_path = next_key_path(signing_key);
+    let (privkey, pubkey) = attestation::keygen_pem()?;
+    write_new_file(&next_path, privkey.as_bytes())
+        .context("Failed to store next key, maybe there's already one prepared")?;
+
+    let history_path = history_path(signing_key);
+    let mut history = KeyHistory::load(&history_path)?;
+    history.next.push(pubkey.clone());
+    history.save(&history_path)?;
+
+    Ok(pubkey)
+}
+
+/// Replaces the active signing key with the prepared next key, or a newly generated one if there is none. The public
+/// key of the old one stays published as a previous key, its private key is kept in `<signing-key>.retired-<timestamp>`.
+///
+/// Returns the new public key.
+pub fn rotate(signing_key: &Path) -> Result<String> {
+    let old = fs::read(signing_key)
+        .with_context(|| anyhow!("Failed to read signing key: {signing_key:?}"))?;
+    let old_pubkey = pubkey_from_privkey_pem(&old)?;
+
+    let next_path = next_key_path(signing_key);
+    let (new, is_prepared) = match fs::read(&next_path) {
+        Ok(new) => (new, true),
+        Err(err) if err.kind() == ErrorKind::NotFound => {
+            let (privkey, _) = attestation::keygen_pem()?;
+            (privkey.into_bytes(), false)
+        }
+        Err(err) => {
+            return Err(Error::from(err))
+                .with_context(|| anyhow!("Failed to read next key: {next_path:?}"));
+        }
+    };
+    let new_pubkey = pubkey_from_privkey_pem(&new)?;
+
+    let now = Utc::now().naive_utc();
+    let retired_path = sibling(
+        signing_key,
+        &format!(".retired-{}", now.and_utc().timestamp()),
+    );
+    let tmp_path = sibling(signing_key, ".tmp");
+
+    write_new_file(&tmp_path, &new)?;
+    // keep a copy of the old key first, so there's a valid key at `signing_key` at all times
+    fs::hard_link(signing_key, &retired_path)
+        .with_context(|| anyhow!("Failed to link old signing key to {retired_path:?}"))?;
+    fs::rename(&tmp_path, signing_key)
+        .with_context(|| anyhow!("Failed to move new signing key to {signing_key:?}"))?;
+    info!("Old signing key has been kept as {retired_path:?}");
+
+    if is_prepared {
+        fs::remove_file(&next_path)
+            .with_context(|| anyhow!("Failed to remove promoted key: {next_path:?}"))?;
+    }
+
+    let history_path = history_path(signing_key);
+    let mut history = KeyHistory::load(&history_path)?;
+    history.previous.push(RetiredKey {
+        public_key: old_pubkey,
+        retired_at: now,
+    });
+    history.next.retain(|key| *key != new_pubkey);
+    history.save(&history_path)?;
+
+    Ok(new_pubkey)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::SubsecRound;
+
+    #[test]
+    fn test_rotate_generates_new_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let signing_key = dir.path().join("rebuilderd.sign.key");
+
+        let old = KeyRing::load_or_create(&signing_key).unwrap();
+        let old_pubkey = attestation::pubkey_to_pem(old.active.public()).unwrap();
+        let old_privkey = fs::read(&signing_key).unwrap();
+
+        let new_pubkey = rotate(&signing_key).unwrap();
+        assert_ne!(old_pubkey, new_pubkey);
+
+        let retired = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.to_string_lossy().contains(".retired-"))
+            .collect::<Vec<_>>();
+        assert_eq!(retired.len(), 1);
+        assert_eq!(fs::read(&retired[0]).unwrap(), old_privkey);
+
+        let keyring = KeyRing::load_or_create(&signing_key).unwrap();
+        let keys = keyring.public_keys().unwrap();
+        assert_eq!(keys.current, vec![new_pubkey]);
+        assert_eq!(keys.previous.len(), 1);
+        assert_eq!(keys.previous[0].key, old_pubkey);
+        assert!(keys.next.is_empty());
+
+        let own_keys = keyring.own_keys().unwrap();
+        assert_eq!(own_keys.len(), 2);
+        assert_eq!(&own_keys[0], keyring.active.public());
+        assert_eq!(&own_keys[1], old.active.public());
+    }
+
+    #[test]
+    fn test_rotate_promotes_prepared_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let signing_key = dir.path().join("rebuilderd.sign.key");
+        KeyRing::load_or_create(&signing_key).unwrap();
+
+        let next_pubkey = prepare_next_key(&signing_key).unwrap();
+        // only one key can be prepared at a time
+        prepare_next_key(&signing_key).unwrap_err();
+
+        let keys = KeyRing::load_or_create(&signing_key)
+            .unwrap()
+            .public_keys()
+            .unwrap();
+        assert_eq!(keys.next, vec![next_pubkey.clone()]);
+
+        let new_pubkey = rotate(&signing_key).unwrap();
+        assert_eq!(new_pubkey, next_pubkey);
+        assert!(!next_key_path(&signing_key).exists());
+
+        let keys = KeyRing::load_or_create(&signing_key)
+            .unwrap()
+            .public_keys()
+            .unwrap();
+        assert_eq!(keys.current, vec![next_pubkey]);
+        assert!(keys.next.is_empty());
+    }
+
+    #[test]
+    fn test_history_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rebuilderd.sign.key.history");
+
+        assert_eq!(KeyHistory::load(&path).unwrap(), KeyHistory::default());
+
+        let history = KeyHistory {
+            previous: vec![RetiredKey {
+                public_key: "-----BEGIN PUBLIC KEY-----\r\n".to_string(),
+                retired_at: Utc::now().naive_utc().trunc_subsecs(0),
+            }],
+            next: vec![],
+        };
+        history.save(&path).unwrap();
+        assert_eq!(KeyHistory::load(&path).unwrap(), history);
+    }
+}
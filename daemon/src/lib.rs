@@ -1,9 +1,9 @@
 use crate::config::Config;
+use crate::keyring::KeyRing;
 use actix_web::dev::Server;
 use actix_web::middleware::{Logger, TrailingSlash};
-use actix_web::web::{Data, JsonConfig, scope};
+use actix_web::web::{Data, JsonConfig, PayloadConfig, scope};
 use actix_web::{App, HttpServer, middleware};
-use in_toto::crypto::PrivateKey;
 use rebuilderd_common::errors::*;
 use std::net::SocketAddr;
 use std::sync::{Arc, RwLock};
@@ -13,6 +13,7 @@ pub mod attestation;
 pub mod code_migrations;
 pub mod config;
 pub mod db;
+pub mod keyring;
 pub mod models;
 pub mod schema;
 pub mod web;
@@ -20,14 +21,21 @@ pub mod web;
 pub fn build_server(
     pool: db::Pool,
     config: Config,
-    privkey: PrivateKey,
+    keyring: KeyRing,
 ) -> Result<(Server, SocketAddr)> {
     let bind_addr = config.bind_addr.clone();
 
-    let privkey = Arc::new(privkey);
+    let privkey = keyring.active.clone();
+    let keyring = Arc::new(keyring);
+
+    // the log might not have a tree head signed with this key yet
+    let mut connection = pool.get()?;
+    models::sign_tree_head(connection.as_mut(), &privkey)?;
+    drop(connection);
 
     let server = HttpServer::new(move || {
         let json_config = JsonConfig::default().limit(config.post_body_size_limit);
+        let payload_config = PayloadConfig::default().limit(config.post_body_size_limit);
 
         let v0_dashboard_cache = Arc::new(RwLock::new(api::v0::DashboardState::new()));
 
@@ -36,9 +44,11 @@ pub fn build_server(
             .wrap(middleware::Compress::default())
             .wrap(middleware::NormalizePath::new(TrailingSlash::Trim))
             .app_data(json_config)
+            .app_data(payload_config)
             .app_data(Data::new(pool.clone()))
             .app_data(Data::new(config.clone()))
             .app_data(Data::new(privkey.clone()))
+            .app_data(Data::new(keyring.clone()))
             .app_data(Data::new(v0_dashboard_cache.clone()))
             .service(
                 scope("/api")
@@ -62,10 +72,12 @@ pub fn build_server(
                     )
                     .service(
                         scope("/v1")
+                            .service(scope("/audit").service(api::v1::get_audit_events))
                             .service(
                                 scope("/builds")
                                     .service(api::v1::get_builds)
                                     .service(api::v1::submit_rebuild_report)
+                                    .service(api::v1::submit_rebuild_report_upload)
                                     .service(api::v1::get_build)
                                     .service(api::v1::get_build_log)
                                     .service(api::v1::get_build_artifacts)
@@ -85,9 +97,12 @@ pub fn build_server(
                             .service(
                                 scope("/packages")
                                     .service(api::v1::submit_package_report)
+                                    .service(api::v1::set_required_labels)
                                     .service(api::v1::get_source_packages)
                                     .service(api::v1::get_source_package)
                                     .service(api::v1::get_binary_packages)
+                                    .service(api::v1::lookup_binary_packages)
+                                    .service(api::v1::get_attestation_bundle)
                                     .service(api::v1::get_binary_package),
                             )
                             .service(
@@ -98,13 +113,29 @@ pub fn build_server(
                                     .service(api::v1::drop_queued_job)
                                     .service(api::v1::drop_queued_jobs)
                                     .service(api::v1::ping_job)
+                                    .service(api::v1::push_queued_job_log)
+                                    .service(api::v1::get_queued_job_log)
                                     .service(api::v1::request_work),
                             )
+                            .service(
+                                scope("/tokens")
+                                    .service(api::v1::get_api_tokens)
+                                    .service(api::v1::create_api_token)
+                                    .service(api::v1::revoke_api_token),
+                            )
+                            .service(
+                                scope("/transparency")
+                                    .service(api::v1::get_tree_head)
+                                    .service(api::v1::get_transparency_log_entry)
+                                    .service(api::v1::get_inclusion_proof)
+                                    .service(api::v1::get_consistency_proof),
+                            )
                             .service(
                                 scope("/workers")
                                     .service(api::v1::get_workers)
                                     .service(api::v1::register_worker)
                                     .service(api::v1::get_worker)
+                                    .service(api::v1::set_worker_state)
                                     .service(api::v1::unregister_worker),
                             ),
                     ),
@@ -120,8 +151,8 @@ pub fn build_server(
     Ok((server.run(), address))
 }
 
-pub async fn run_config(pool: db::Pool, config: Config, privkey: PrivateKey) -> Result<()> {
-    let (server, _) = build_server(pool, config, privkey)?;
+pub async fn run_config(pool: db::Pool, config: Config, keyring: KeyRing) -> Result<()> {
+    let (server, _) = build_server(pool, config, keyring)?;
 
     server.await?;
     Ok(())
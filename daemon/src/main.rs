@@ -1,11 +1,14 @@
 mod args;
 
 use crate::args::Args;
+use chrono::{Duration, Utc};
 use clap::Parser;
 use env_logger::Env;
 use rebuilderd::attestation;
 use rebuilderd::config;
 use rebuilderd::db;
+use rebuilderd::keyring::{self, KeyRing};
+use rebuilderd::models::{ApiToken, NewApiToken};
 use rebuilderd_common::errors::*;
 use std::fs;
 
@@ -32,6 +35,12 @@ async fn main() -> Result<()> {
 
         println!("{}", privkey.trim_end());
         println!("{}", pubkey.trim_end());
+    } else if args.prepare_key {
+        let pubkey = keyring::prepare_next_key(&args.signing_key)?;
+        println!("{}", pubkey.trim_end());
+    } else if args.rotate_key {
+        let pubkey = keyring::rotate(&args.signing_key)?;
+        println!("{}", pubkey.trim_end());
     } else if let Some(path) = args.derive_pubkey {
         let privkey =
             fs::read(&path).with_context(|| anyhow!("Failed to read from file: {path:?}"))?;
@@ -42,11 +51,40 @@ async fn main() -> Result<()> {
 
             println!("{}", pubkey.trim_end());
         }
+    } else if let Some(name) = args.create_token {
+        let mut connection = db::setup("rebuilderd.db")?;
+        let expires_at = args
+            .token_expires
+            .map(|days| Utc::now().naive_utc() + Duration::days(days));
+        let (_, secret) = NewApiToken::create(&mut connection, &name, &args.scopes, expires_at)?;
+        println!("{secret}");
+    } else if args.list_tokens {
+        let mut connection = db::setup("rebuilderd.db")?;
+        for token in ApiToken::list(&mut connection)? {
+            let expires_at = token
+                .expires_at
+                .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string());
+            let last_used_at = token
+                .last_used_at
+                .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string());
+            println!(
+                "{}\t{}\texpires={}\tlast_used={}",
+                token.name,
+                token.scopes.replace(' ', ","),
+                expires_at.as_deref().unwrap_or("never"),
+                last_used_at.as_deref().unwrap_or("never"),
+            );
+        }
+    } else if let Some(name) = args.revoke_token {
+        let mut connection = db::setup("rebuilderd.db")?;
+        if !ApiToken::revoke_by_name(&mut connection, &name)? {
+            bail!("No api token named {name:?}");
+        }
     } else {
-        let privkey = attestation::load_or_create_privkey_pem(&args.signing_key)?;
+        let keyring = KeyRing::load_or_create(&args.signing_key)?;
         let pool = db::setup_pool("rebuilderd.db")?;
 
-        let (server, address) = rebuilderd::build_server(pool, config, privkey)?;
+        let (server, address) = rebuilderd::build_server(pool, config, keyring)?;
 
         info!("Listening on {}", address);
         server.await?;
@@ -0,0 +1,145 @@
+use crate::schema::*;
+use chrono::{NaiveDateTime, Utc};
+use data_encoding::HEXLOWER;
+use diesel::prelude::*;
+use rand::distr::{Alphanumeric, SampleString};
+use rebuilderd_common::api::v1::{ApiToken as ApiTokenResponse, ApiTokenScope};
+use rebuilderd_common::errors::*;
+use ring::digest::{SHA256, digest};
+
+/// Prefix of generated token secrets, so they are easy to recognize (and to grep for in leaked files)
+const TOKEN_PREFIX: &str = "rebuilderd_";
+
+#[derive(Identifiable, Queryable, Selectable, PartialEq, Eq, Debug)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+#[diesel(table_name = api_tokens)]
+pub struct ApiToken {
+    pub id: i32,
+    pub name: String,
+    pub token_hash: String,
+    /// Space-separated list of scopes
+    pub scopes: String,
+    pub created_at: NaiveDateTime,
+    pub expires_at: Option<NaiveDateTime>,
+    pub last_used_at: Option<NaiveDateTime>,
+}
+
+impl ApiToken {
+    pub fn scopes(&self) -> Result<Vec<ApiTokenScope>> {
+        self.scopes.split_whitespace().map(str::parse).collect()
+    }
+
+    pub fn has_scope(&self, scope: ApiTokenScope) -> bool {
+        self.scopes
+            .split_whitespace()
+            .any(|s| s == scope.as_str())
+    }
+
+    pub fn list(connection: &mut SqliteConnection) -> Result<Vec<ApiToken>> {
+        let tokens = api_tokens::table
+            .order_by(api_tokens::id)
+            .select(ApiToken::as_select())
+            .load(connection)?;
+        Ok(tokens)
+    }
+
+    /// Looks up the token belonging to this secret and records that it has been used. Unknown and expired tokens are
+    /// rejected.
+    pub fn authenticate(connection: &mut SqliteConnection, secret: &str) -> Result<ApiToken> {
+        let now = Utc::now().naive_utc();
+        let token = diesel::update(api_tokens::table)
+            .filter(api_tokens::token_hash.eq(hash_secret(secret)))
+            .filter(
+                api_tokens::expires_at
+                    .is_null()
+                    .or(api_tokens::expires_at.gt(now)),
+            )
+            .set(api_tokens::last_used_at.eq(now))
+            .returning(ApiToken::as_select())
+            .get_result(connection)
+            .optional()?
+            .context("Unknown or expired api token")?;
+        Ok(token)
+    }
+
+    /// Deletes the token, returns false if there was no such token
+    pub fn revoke(connection: &mut SqliteConnection, id: i32) -> Result<bool> {
+        let deleted = diesel::delete(api_tokens::table.filter(api_tokens::id.eq(id)))
+            .execute(connection)?;
+        Ok(deleted > 0)
+    }
+
+    pub fn revoke_by_name(connection: &mut SqliteConnection, name: &str) -> Result<bool> {
+        let deleted = diesel::delete(api_tokens::table.filter(api_tokens::name.eq(name)))
+            .execute(connection)?;
+        Ok(deleted > 0)
+    }
+
+    pub fn into_api_token(self) -> Result<ApiTokenResponse> {
+        let scopes = self.scopes()?;
+        Ok(ApiTokenResponse {
+            id: self.id,
+            name: self.name,
+            scopes,
+            created_at: self.created_at,
+            expires_at: self.expires_at,
+            last_used_at: self.last_used_at,
+        })
+    }
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = api_tokens)]
+pub struct NewApiToken {
+    pub name: String,
+    pub token_hash: String,
+    pub scopes: String,
+    pub created_at: NaiveDateTime,
+    pub expires_at: Option<NaiveDateTime>,
+}
+
+impl NewApiToken {
+    /// Generates a new secret and stores its hash. The secret is returned along with the token and can't be recovered
+    /// afterwards.
+    pub fn create(
+        connection: &mut SqliteConnection,
+        name: &str,
+        scopes: &[ApiTokenScope],
+        expires_at: Option<NaiveDateTime>,
+    ) -> Result<(ApiToken, String)> {
+        if name.is_empty() {
+            bail!("Token name can't be empty");
+        }
+        if scopes.is_empty() {
+            bail!("Token needs at least one scope");
+        }
+
+        let mut scope_names = scopes.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+        scope_names.sort_unstable();
+        scope_names.dedup();
+
+        let secret = format!(
+            "{TOKEN_PREFIX}{}",
+            Alphanumeric.sample_string(&mut rand::rng(), 40)
+        );
+        let new = NewApiToken {
+            name: name.to_string(),
+            token_hash: hash_secret(&secret),
+            scopes: scope_names.join(" "),
+            created_at: Utc::now().naive_utc(),
+            expires_at,
+        };
+
+        let token = diesel::insert_into(api_tokens::table)
+            .values(&new)
+            .returning(ApiToken::as_select())
+            .get_result(connection)
+            .with_context(|| anyhow!("Failed to create token, maybe {name:?} already exists"))?;
+
+        Ok((token, secret))
+    }
+}
+
+fn hash_secret(secret: &str) -> String {
+    HEXLOWER.encode(digest(&SHA256, secret.as_bytes()).as_ref())
+}
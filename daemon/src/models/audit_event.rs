@@ -0,0 +1,55 @@
+use crate::schema::*;
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use rebuilderd_common::api::v1::AuditEvent as AuditEventResponse;
+use rebuilderd_common::errors::*;
+
+#[derive(Identifiable, Queryable, Selectable, PartialEq, Eq, Debug)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+#[diesel(table_name = audit_events)]
+pub struct AuditEvent {
+    pub id: i32,
+    pub created_at: NaiveDateTime,
+    pub actor: String,
+    pub source_ip: Option<String>,
+    pub endpoint: String,
+    /// The parameters of the request, json encoded
+    pub parameters: String,
+    pub affected_rows: i64,
+}
+
+impl AuditEvent {
+    pub fn into_api_event(self) -> Result<AuditEventResponse> {
+        let parameters = serde_json::from_str(&self.parameters)
+            .with_context(|| anyhow!("Failed to parse parameters of audit event {}", self.id))?;
+        Ok(AuditEventResponse {
+            id: self.id,
+            created_at: self.created_at,
+            actor: self.actor,
+            source_ip: self.source_ip,
+            endpoint: self.endpoint,
+            parameters,
+            affected_rows: self.affected_rows,
+        })
+    }
+}
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = audit_events)]
+pub struct NewAuditEvent {
+    pub created_at: NaiveDateTime,
+    pub actor: String,
+    pub source_ip: Option<String>,
+    pub endpoint: String,
+    pub parameters: String,
+    pub affected_rows: i64,
+}
+
+impl NewAuditEvent {
+    pub fn insert(&self, connection: &mut SqliteConnection) -> Result<()> {
+        diesel::insert_into(audit_events::table)
+            .values(self)
+            .execute(connection)?;
+        Ok(())
+    }
+}
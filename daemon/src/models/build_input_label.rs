@@ -0,0 +1,53 @@
+use crate::schema::*;
+use diesel::prelude::*;
+use rebuilderd_common::errors::*;
+use std::collections::BTreeSet;
+
+#[derive(Identifiable, Queryable, Selectable, Insertable, PartialEq, Eq, Debug)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+#[diesel(primary_key(build_input_id, label))]
+#[diesel(table_name = build_input_labels)]
+pub struct BuildInputLabel {
+    pub build_input_id: i32,
+    pub label: String,
+}
+
+impl BuildInputLabel {
+    pub fn list(connection: &mut SqliteConnection, build_input_id: i32) -> Result<Vec<String>> {
+        let labels = build_input_labels::table
+            .filter(build_input_labels::build_input_id.eq(build_input_id))
+            .order_by(build_input_labels::label)
+            .select(build_input_labels::label)
+            .load::<String>(connection)?;
+        Ok(labels)
+    }
+
+    /// Replaces the labels a worker needs to advertise to be assigned this build input
+    pub fn replace(
+        connection: &mut SqliteConnection,
+        build_input_id: i32,
+        labels: &[String],
+    ) -> Result<()> {
+        diesel::delete(build_input_labels::table)
+            .filter(build_input_labels::build_input_id.eq(build_input_id))
+            .execute(connection)?;
+
+        let labels = labels
+            .iter()
+            .map(|label| label.trim())
+            .filter(|label| !label.is_empty())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .map(|label| BuildInputLabel {
+                build_input_id,
+                label: label.to_string(),
+            })
+            .collect::<Vec<_>>();
+
+        diesel::insert_into(build_input_labels::table)
+            .values(&labels)
+            .execute(connection)?;
+
+        Ok(())
+    }
+}
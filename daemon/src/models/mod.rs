@@ -9,6 +9,10 @@ import_models!(rebuild);
 import_models!(rebuild_artifact);
 import_models!(binary_package);
 import_models!(build_input);
+import_models!(build_input_label);
 import_models!(source_package);
 import_models!(worker);
 import_models!(queue);
+import_models!(transparency_log);
+import_models!(api_token);
+import_models!(audit_event);
@@ -2,6 +2,7 @@ use crate::schema::*;
 use chrono::prelude::*;
 use diesel::prelude::*;
 use diesel::upsert::excluded;
+use rand::distr::{Alphanumeric, SampleString};
 use rebuilderd_common::api::v1::Priority;
 use rebuilderd_common::errors::*;
 use serde::{Deserialize, Serialize};
@@ -18,9 +19,19 @@ pub struct Queued {
     pub started_at: Option<NaiveDateTime>,
     pub worker: Option<i32>,
     pub last_ping: Option<NaiveDateTime>,
+    pub lease: Option<String>,
 }
 
 impl Queued {
+    /// A new lease for assigning this job to a worker
+    pub fn generate_lease() -> String {
+        Alphanumeric.sample_string(&mut rand::rng(), 32)
+    }
+
+    pub fn is_leased_to(&self, worker_id: i32, lease: &str) -> bool {
+        self.worker == Some(worker_id) && self.lease.as_deref() == Some(lease)
+    }
+
     pub fn delete(&self, connection: &mut SqliteConnection) -> Result<()> {
         use crate::schema::queue::columns::*;
         diesel::delete(queue::table.filter(id.is(self.id))).execute(connection)?;
@@ -55,3 +66,19 @@ impl NewQueued {
         Ok(result)
     }
 }
+
+#[derive(Insertable, Debug)]
+#[diesel(table_name = queue_logs)]
+pub struct NewQueueLog {
+    pub queue_id: i32,
+    pub chunk: Vec<u8>,
+}
+
+impl NewQueueLog {
+    pub fn insert(&self, connection: &mut SqliteConnection) -> Result<()> {
+        diesel::insert_into(queue_logs::table)
+            .values(self)
+            .execute(connection)?;
+        Ok(())
+    }
+}
@@ -42,6 +42,10 @@ pub struct Rebuild {
     pub built_at: Option<NaiveDateTime>,
     pub build_log_id: i32,
     pub status: Option<String>,
+    pub worker_id: Option<i32>,
+    pub worker_name: Option<String>,
+    pub worker_key: Option<String>,
+    pub failure_reason: Option<String>,
 }
 
 #[derive(Insertable, PartialEq, Eq, Debug, Clone)]
@@ -52,6 +56,10 @@ pub struct NewRebuild {
     pub built_at: Option<NaiveDateTime>,
     pub build_log_id: i32,
     pub status: Option<String>,
+    pub worker_id: Option<i32>,
+    pub worker_name: Option<String>,
+    pub worker_key: Option<String>,
+    pub failure_reason: Option<String>,
 }
 
 impl NewRebuild {
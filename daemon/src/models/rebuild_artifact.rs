@@ -36,12 +36,14 @@ impl NewDiffoscopeLog {
 pub struct AttestationLog {
     pub id: i32,
     pub attestation_log: Vec<u8>,
+    pub signing_key_id: Option<String>,
 }
 
 #[derive(Insertable, PartialEq, Eq, Debug, Clone)]
 #[diesel(table_name = attestation_logs)]
 pub struct NewAttestationLog {
     pub attestation_log: Vec<u8>,
+    pub verified: bool,
 }
 
 impl NewAttestationLog {
@@ -67,6 +69,10 @@ pub struct RebuildArtifact {
     pub diffoscope_log_id: Option<i32>,
     pub attestation_log_id: Option<i32>,
     pub status: Option<String>,
+    pub upstream_sha256: Option<String>,
+    pub rebuilt_sha256: Option<String>,
+    pub upstream_sha512: Option<String>,
+    pub rebuilt_sha512: Option<String>,
 }
 
 #[derive(Insertable, PartialEq, Eq, Debug, Clone)]
@@ -78,6 +84,10 @@ pub struct NewRebuildArtifact {
     pub diffoscope_log_id: Option<i32>,
     pub attestation_log_id: Option<i32>,
     pub status: Option<String>,
+    pub upstream_sha256: Option<String>,
+    pub rebuilt_sha256: Option<String>,
+    pub upstream_sha512: Option<String>,
+    pub rebuilt_sha512: Option<String>,
 }
 
 impl NewRebuildArtifact {
@@ -0,0 +1,199 @@
+use crate::attestation;
+use crate::schema::*;
+use chrono::{NaiveDateTime, SubsecRound, Utc};
+use diesel::dsl::max;
+use diesel::prelude::*;
+use in_toto::crypto::PrivateKey;
+use rebuilderd_common::api::v1::SignedTreeHead;
+use rebuilderd_common::errors::*;
+use rebuilderd_common::transparency::{self, Hash, NodeId};
+
+#[derive(Insertable, PartialEq, Eq, Debug, Clone)]
+#[diesel(table_name = transparency_log)]
+pub struct NewTransparencyLogEntry {
+    pub leaf_index: i64,
+    pub leaf_hash: Vec<u8>,
+    pub entry: Vec<u8>,
+    pub attestation_log_id: Option<i32>,
+    pub created_at: NaiveDateTime,
+}
+
+impl NewTransparencyLogEntry {
+    /// Appends the attestation as the next leaf of the log. `attestation` is hashed for the leaf, `compressed` is the
+    /// same attestation as it's stored. The perfect subtrees completed by the leaf and the tree head of the grown log,
+    /// signed with `private_key`, are stored along with it.
+    ///
+    /// This needs to run in an immediate transaction, so nobody else takes the same leaf index in the meantime.
+    pub fn append(
+        connection: &mut SqliteConnection,
+        attestation_log_id: i32,
+        attestation: &[u8],
+        compressed: Vec<u8>,
+        private_key: &PrivateKey,
+    ) -> Result<i64> {
+        let leaf_index = tree_size(connection)?;
+        let leaf_hash = transparency::leaf_hash(attestation);
+        let entry = NewTransparencyLogEntry {
+            leaf_index,
+            leaf_hash: leaf_hash.to_vec(),
+            entry: compressed,
+            attestation_log_id: Some(attestation_log_id),
+            created_at: Utc::now().naive_utc(),
+        };
+
+        diesel::insert_into(transparency_log::table)
+            .values(&entry)
+            .execute(connection)?;
+
+        let completed = transparency::append_leaf(leaf_index as u64, leaf_hash, &mut |id| {
+            load_node(connection, id)
+        })?;
+        insert_nodes(connection, &completed)?;
+        sign_tree_head(connection, private_key)?;
+
+        Ok(leaf_index)
+    }
+}
+
+/// Signs the tree head of the log at its current size, unless it has been signed with this key already
+pub fn sign_tree_head(connection: &mut SqliteConnection, private_key: &PrivateKey) -> Result<()> {
+    let tree_size = tree_size(connection)?;
+    let signing_key_id = attestation::key_id(private_key.public())?;
+    if TreeHead::find(connection, tree_size, &signing_key_id)?.is_some() {
+        return Ok(());
+    }
+
+    let root_hash = transparency::root_hash(tree_size as u64, &mut |id| load_node(connection, id))?;
+    let root_hash = transparency::hash_to_hex(&root_hash);
+
+    // the signature only covers full seconds
+    let timestamp = Utc::now().naive_utc().trunc_subsecs(0);
+    let message = SignedTreeHead::signed_message(tree_size as u64, &root_hash, &timestamp);
+    let signature = private_key.sign(&message)?;
+
+    NewTreeHead {
+        tree_size,
+        root_hash,
+        timestamp,
+        signing_key_id,
+        signature: serde_json::to_string(&signature)?,
+    }
+    .insert_or_ignore(connection)
+}
+
+pub fn tree_size(connection: &mut SqliteConnection) -> Result<i64> {
+    let last = transparency_log::table
+        .select(max(transparency_log::leaf_index))
+        .get_result::<Option<i64>>(connection)?;
+    Ok(last.map_or(0, |last| last + 1))
+}
+
+/// Loads the hash of a perfect subtree of the log, see [`transparency::NodeId`]
+pub fn load_node(connection: &mut SqliteConnection, (level, index): NodeId) -> Result<Hash> {
+    let hash = transparency_tree_nodes::table
+        .filter(transparency_tree_nodes::level.eq(level as i32))
+        .filter(transparency_tree_nodes::node_index.eq(index as i64))
+        .select(transparency_tree_nodes::hash)
+        .first::<Vec<u8>>(connection)
+        .optional()?
+        .with_context(|| anyhow!("Subtree {index} at level {level} is missing from the log"))?;
+    Hash::try_from(hash).map_err(|_| anyhow!("Node hash in database has invalid length"))
+}
+
+pub fn insert_nodes(connection: &mut SqliteConnection, nodes: &[(NodeId, Hash)]) -> Result<()> {
+    let values = nodes
+        .iter()
+        .map(|((level, index), hash)| {
+            (
+                transparency_tree_nodes::level.eq(*level as i32),
+                transparency_tree_nodes::node_index.eq(*index as i64),
+                transparency_tree_nodes::hash.eq(hash.to_vec()),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    diesel::insert_into(transparency_tree_nodes::table)
+        .values(&values)
+        .execute(connection)?;
+    Ok(())
+}
+
+pub fn find_leaf_index(connection: &mut SqliteConnection, leaf_hash: &Hash) -> Result<Option<i64>> {
+    let leaf_index = transparency_log::table
+        .filter(transparency_log::leaf_hash.eq(leaf_hash.as_slice()))
+        .order_by(transparency_log::leaf_index)
+        .select(transparency_log::leaf_index)
+        .first::<i64>(connection)
+        .optional()?;
+    Ok(leaf_index)
+}
+
+#[derive(Queryable, Selectable, PartialEq, Eq, Debug, Clone)]
+#[diesel(table_name = transparency_tree_heads)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+pub struct TreeHead {
+    pub id: i32,
+    pub tree_size: i64,
+    pub root_hash: String,
+    pub timestamp: NaiveDateTime,
+    pub signing_key_id: String,
+    pub signature: String,
+}
+
+impl TreeHead {
+    /// The tree head for `tree_size` that has been signed with the given key, if there is one yet
+    pub fn find(
+        connection: &mut SqliteConnection,
+        tree_size: i64,
+        signing_key_id: &str,
+    ) -> Result<Option<Self>> {
+        let tree_head = transparency_tree_heads::table
+            .filter(transparency_tree_heads::tree_size.eq(tree_size))
+            .filter(transparency_tree_heads::signing_key_id.eq(signing_key_id))
+            .select(TreeHead::as_select())
+            .first(connection)
+            .optional()?;
+        Ok(tree_head)
+    }
+
+    /// The tree head of the largest tree that has been signed with the given key
+    pub fn latest(connection: &mut SqliteConnection, signing_key_id: &str) -> Result<Option<Self>> {
+        let tree_head = transparency_tree_heads::table
+            .filter(transparency_tree_heads::signing_key_id.eq(signing_key_id))
+            .order_by(transparency_tree_heads::tree_size.desc())
+            .select(TreeHead::as_select())
+            .first(connection)
+            .optional()?;
+        Ok(tree_head)
+    }
+
+    pub fn into_api_tree_head(self) -> Result<SignedTreeHead> {
+        Ok(SignedTreeHead {
+            tree_size: self.tree_size as u64,
+            root_hash: self.root_hash,
+            timestamp: self.timestamp,
+            signature: serde_json::from_str(&self.signature)
+                .context("Failed to parse signature of tree head")?,
+        })
+    }
+}
+
+#[derive(Insertable, PartialEq, Eq, Debug, Clone)]
+#[diesel(table_name = transparency_tree_heads)]
+pub struct NewTreeHead {
+    pub tree_size: i64,
+    pub root_hash: String,
+    pub timestamp: NaiveDateTime,
+    pub signing_key_id: String,
+    pub signature: String,
+}
+
+impl NewTreeHead {
+    /// Stores the tree head, unless another one has been signed for the same size and key in the meantime
+    pub fn insert_or_ignore(&self, connection: &mut SqliteConnection) -> Result<()> {
+        diesel::insert_or_ignore_into(transparency_tree_heads::table)
+            .values(self)
+            .execute(connection)?;
+        Ok(())
+    }
+}
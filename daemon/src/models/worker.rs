@@ -2,6 +2,7 @@ use crate::schema::*;
 use chrono::prelude::*;
 use diesel::prelude::*;
 use diesel::upsert::excluded;
+use rebuilderd_common::api::v1::WorkerState;
 use rebuilderd_common::errors::*;
 use serde::{Deserialize, Serialize};
 
@@ -17,6 +18,7 @@ pub struct Worker {
     pub status: Option<String>,
     pub last_ping: NaiveDateTime,
     pub online: bool,
+    pub state: WorkerState,
 }
 
 impl Worker {
@@ -31,6 +33,32 @@ impl Worker {
 
         Ok(worker)
     }
+
+    /// Lists the jobs currently assigned to the worker in its status, a worker without any jobs is shown as idle
+    pub fn refresh_status(id: i32, connection: &mut SqliteConnection) -> Result<()> {
+        let jobs = queue::table
+            .inner_join(build_inputs::table.inner_join(source_packages::table))
+            .filter(queue::worker.is(id))
+            .order_by(queue::started_at)
+            .select((source_packages::name, source_packages::version))
+            .load::<(String, String)>(connection)?;
+
+        let status = if jobs.is_empty() {
+            None
+        } else {
+            let jobs = jobs
+                .iter()
+                .map(|(name, version)| format!("{name} {version}"))
+                .collect::<Vec<_>>();
+            Some(format!("working hard on {}", jobs.join(", ")))
+        };
+
+        diesel::update(workers::table.filter(workers::id.is(id)))
+            .set(workers::status.eq(status))
+            .execute(connection)?;
+
+        Ok(())
+    }
 }
 
 #[derive(Insertable, Serialize, Deserialize, Debug)]
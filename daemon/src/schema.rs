@@ -1,9 +1,35 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    api_tokens (id) {
+        id -> Integer,
+        name -> Text,
+        token_hash -> Text,
+        scopes -> Text,
+        created_at -> Timestamp,
+        expires_at -> Nullable<Timestamp>,
+        last_used_at -> Nullable<Timestamp>,
+    }
+}
+
 diesel::table! {
     attestation_logs (id) {
         id -> Integer,
         attestation_log -> Binary,
+        signing_key_id -> Nullable<Text>,
+        verified -> Bool,
+    }
+}
+
+diesel::table! {
+    audit_events (id) {
+        id -> Integer,
+        created_at -> Timestamp,
+        actor -> Text,
+        source_ip -> Nullable<Text>,
+        endpoint -> Text,
+        parameters -> Text,
+        affected_rows -> BigInt,
     }
 }
 
@@ -20,6 +46,13 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    build_input_labels (build_input_id, label) {
+        build_input_id -> Integer,
+        label -> Text,
+    }
+}
+
 diesel::table! {
     build_inputs (id) {
         id -> Integer,
@@ -55,6 +88,15 @@ diesel::table! {
         started_at -> Nullable<Timestamp>,
         worker -> Nullable<Integer>,
         last_ping -> Nullable<Timestamp>,
+        lease -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    queue_logs (id) {
+        id -> Integer,
+        queue_id -> Integer,
+        chunk -> Binary,
     }
 }
 
@@ -66,6 +108,10 @@ diesel::table! {
         diffoscope_log_id -> Nullable<Integer>,
         attestation_log_id -> Nullable<Integer>,
         status -> Nullable<Text>,
+        upstream_sha256 -> Nullable<Text>,
+        rebuilt_sha256 -> Nullable<Text>,
+        upstream_sha512 -> Nullable<Text>,
+        rebuilt_sha512 -> Nullable<Text>,
     }
 }
 
@@ -77,6 +123,10 @@ diesel::table! {
         built_at -> Nullable<Timestamp>,
         build_log_id -> Integer,
         status -> Nullable<Text>,
+        worker_id -> Nullable<Integer>,
+        worker_name -> Nullable<Text>,
+        worker_key -> Nullable<Text>,
+        failure_reason -> Nullable<Text>,
     }
 }
 
@@ -92,6 +142,44 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    transparency_log (id) {
+        id -> Integer,
+        leaf_index -> BigInt,
+        leaf_hash -> Binary,
+        entry -> Binary,
+        attestation_log_id -> Nullable<Integer>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    transparency_tree_heads (id) {
+        id -> Integer,
+        tree_size -> BigInt,
+        root_hash -> Text,
+        timestamp -> Timestamp,
+        signing_key_id -> Text,
+        signature -> Text,
+    }
+}
+
+diesel::table! {
+    transparency_tree_nodes (level, node_index) {
+        level -> Integer,
+        node_index -> BigInt,
+        hash -> Binary,
+    }
+}
+
+diesel::table! {
+    worker_request_nonces (worker_key, nonce) {
+        worker_key -> Text,
+        nonce -> Text,
+        expires_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     workers (id) {
         id -> Integer,
@@ -101,28 +189,41 @@ diesel::table! {
         status -> Nullable<Text>,
         last_ping -> Timestamp,
         online -> Bool,
+        state -> Text,
     }
 }
 
 diesel::joinable!(binary_packages -> build_inputs (build_input_id));
 diesel::joinable!(binary_packages -> source_packages (source_package_id));
+diesel::joinable!(build_input_labels -> build_inputs (build_input_id));
 diesel::joinable!(build_inputs -> source_packages (source_package_id));
 diesel::joinable!(queue -> build_inputs (build_input_id));
+diesel::joinable!(queue_logs -> queue (queue_id));
 diesel::joinable!(rebuild_artifacts -> attestation_logs (attestation_log_id));
 diesel::joinable!(rebuild_artifacts -> diffoscope_logs (diffoscope_log_id));
 diesel::joinable!(rebuild_artifacts -> rebuilds (rebuild_id));
 diesel::joinable!(rebuilds -> build_inputs (build_input_id));
 diesel::joinable!(rebuilds -> build_logs (build_log_id));
+diesel::joinable!(rebuilds -> workers (worker_id));
+diesel::joinable!(transparency_log -> attestation_logs (attestation_log_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    api_tokens,
     attestation_logs,
+    audit_events,
     binary_packages,
+    build_input_labels,
     build_inputs,
     build_logs,
     diffoscope_logs,
     queue,
+    queue_logs,
     rebuild_artifacts,
     rebuilds,
     source_packages,
+    transparency_log,
+    transparency_tree_heads,
+    transparency_tree_nodes,
+    worker_request_nonces,
     workers,
 );
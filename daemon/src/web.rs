@@ -1,4 +1,4 @@
-pub use actix_web::web::{Data, Json, JsonConfig, Path, Query, post, resource};
+pub use actix_web::web::{Bytes, Data, Json, JsonConfig, Path, Query, post, resource};
 use rebuilderd_common::errors;
 use std::fmt;
 
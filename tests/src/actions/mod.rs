@@ -1,9 +1,14 @@
 use crate::data::*;
+use chrono::NaiveDateTime;
+use in_toto::crypto::PublicKey;
+use rebuilderd::attestation;
 use rebuilderd_common::api::Client;
 use rebuilderd_common::api::v1::{
-    BuildRestApi, BuildStatus, JobAssignment, PackageRestApi, PopQueuedJobRequest, QueueJobRequest,
-    QueueRestApi, QueuedJobWithArtifacts, RegisterWorkerRequest, WorkerRestApi,
+    ApiTokenScope, BuildRestApi, BuildStatus, CreateApiTokenRequest, CreatedApiToken,
+    JobAssignment, MetaRestApi, OriginFilter, PackageRestApi, PopQueuedJobRequest, QueueJobRequest,
+    QueueRestApi, QueuedJobWithArtifacts, RegisterWorkerRequest, TokenRestApi, WorkerRestApi,
 };
+use rebuilderd_common::bundle::{BundleEntry, BundleLine, BundleVerifier, Manifest};
 
 pub async fn register_worker(client: &Client) {
     client
@@ -63,6 +68,7 @@ pub async fn pick_up_job(client: &Client) -> QueuedJobWithArtifacts {
                 DUMMY_ARCHITECTURE.to_string(),
                 DUMMY_OTHER_ARCHITECTURE.to_string(),
             ],
+            labels: Vec::new(),
         })
         .await
         .unwrap()
@@ -86,6 +92,20 @@ pub async fn report_failed_rebuild(client: &Client) {
     client.submit_build_report(report).await.unwrap();
 }
 
+pub async fn report_bad_rebuild_with_signed_verdict(client: &Client) {
+    let job = pick_up_job(client).await;
+    let report = bad_rebuild_report_with_signed_verdict(&job).await;
+
+    client.submit_build_report(report).await.unwrap();
+}
+
+pub async fn report_failed_rebuild_with_signed_verdict(client: &Client) {
+    let job = pick_up_job(client).await;
+    let report = failed_rebuild_report_with_signed_verdict(&job).await;
+
+    client.submit_build_report(report).await.unwrap();
+}
+
 pub async fn report_good_rebuild(client: &Client) {
     let job = pick_up_job(client).await;
     let report = good_rebuild_report(&job);
@@ -100,6 +120,13 @@ pub async fn report_good_rebuild_with_signed_attestation(client: &Client) {
     client.submit_build_report(report).await.unwrap();
 }
 
+pub async fn report_good_rebuild_with_signed_statement_attestation(client: &Client) {
+    let job = pick_up_job(client).await;
+    let report = good_rebuild_report_with_signed_statement_attestation(&job).await;
+
+    client.submit_build_report(report).await.unwrap();
+}
+
 pub async fn report_good_rebuild_with_unsigned_attestation(client: &Client) {
     let job = pick_up_job(client).await;
     let report = good_rebuild_report_with_unsigned_attestation(&job).await;
@@ -122,3 +149,69 @@ pub async fn request_rebuild_of_all_bad_packages(client: &Client) {
         .await
         .unwrap();
 }
+
+/// Fetches the attestation of every binary package that has one, which makes the server countersign them
+pub async fn fetch_all_attestations(client: &Client) -> Vec<Vec<u8>> {
+    let mut packages = client
+        .get_binary_packages(None, None, None)
+        .await
+        .unwrap()
+        .records;
+    packages.retain(|p| p.attestation_log_id.is_some());
+    packages.sort_by_key(|p| p.id);
+
+    let mut attestations = Vec::new();
+    for package in packages {
+        let attestation = client
+            .get_build_artifact_attestation(package.build_id.unwrap(), package.artifact_id.unwrap())
+            .await
+            .unwrap();
+        attestations.push(attestation);
+    }
+    attestations
+}
+
+/// Downloads an attestation bundle and verifies its manifest with the public key of the server
+pub async fn fetch_attestation_bundle(
+    client: &Client,
+    origin_filter: Option<&OriginFilter>,
+) -> (Vec<BundleEntry>, Manifest) {
+    let public_key = get_server_public_key(client).await;
+    let mut bundle = client.get_attestation_bundle(origin_filter).await.unwrap();
+
+    let mut verifier = BundleVerifier::new();
+    let mut entries = Vec::new();
+    while let Some(line) = bundle.next_line().await.unwrap() {
+        verifier.push(&line, [&public_key]).unwrap();
+        if let BundleLine::Attestation(entry) = line {
+            entries.push(*entry);
+        }
+    }
+
+    (entries, verifier.finish().unwrap())
+}
+
+pub async fn get_server_public_key(client: &Client) -> PublicKey {
+    let response = client.get_public_keys().await.unwrap();
+    attestation::pem_to_pubkeys(response.current[0].as_bytes())
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+}
+
+pub async fn create_api_token(
+    client: &Client,
+    name: &str,
+    scopes: &[ApiTokenScope],
+    expires_at: Option<NaiveDateTime>,
+) -> CreatedApiToken {
+    client
+        .create_api_token(CreateApiTokenRequest {
+            name: name.to_string(),
+            scopes: scopes.to_vec(),
+            expires_at,
+        })
+        .await
+        .unwrap()
+}
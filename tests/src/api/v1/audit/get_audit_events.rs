@@ -0,0 +1,130 @@
+use crate::actions::*;
+use crate::data::*;
+use crate::fixtures::server::IsolatedServer;
+use crate::fixtures::*;
+use rebuilderd_common::api::v1::{
+    ApiTokenScope, AuditEvent, AuditRestApi, QueueRestApi, WorkerRestApi,
+};
+use rebuilderd_common::config::ConfigFile;
+use rstest::rstest;
+
+async fn audit_events(client: &rebuilderd_common::api::Client) -> Vec<AuditEvent> {
+    client.get_audit_events(None).await.unwrap().records
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn records_package_reports(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    import_single_package(client).await;
+
+    let events = audit_events(client).await;
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].actor, "cookie");
+    assert_eq!(events[0].endpoint, "POST /api/v1/packages");
+    assert_eq!(events[0].source_ip.as_deref(), Some("127.0.0.1"));
+    assert_eq!(events[0].parameters["distribution"], DUMMY_DISTRIBUTION);
+    assert_eq!(events[0].affected_rows, 1);
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn records_queue_changes(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    import_multiple_packages(client).await;
+    client.drop_queued_job(1).await.unwrap();
+    client.drop_queued_jobs(None, None).await.unwrap();
+
+    let events = audit_events(client).await;
+    assert_eq!(events.len(), 3);
+    assert_eq!(events[1].endpoint, "DELETE /api/v1/queue/{id}");
+    assert_eq!(events[1].parameters["id"], 1);
+    assert_eq!(events[1].affected_rows, 1);
+    assert_eq!(events[2].endpoint, "DELETE /api/v1/queue");
+    assert!(events[2].affected_rows > 0);
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn records_worker_management(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    register_worker(client).await;
+    client.unregister_worker(1).await.unwrap();
+
+    let events = audit_events(client).await;
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].endpoint, "POST /api/v1/workers");
+    assert!(events[0].actor.starts_with("worker:"));
+    assert_eq!(events[1].endpoint, "DELETE /api/v1/workers/{id}");
+    assert_eq!(events[1].actor, events[0].actor);
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn records_token_as_actor(
+    config_file: ConfigFile,
+    #[with(config_file.clone())] mut isolated_server: IsolatedServer,
+) {
+    let client = &isolated_server.client;
+
+    let created = create_api_token(client, "sync-timer", &[ApiTokenScope::Sync], None).await;
+    let token_client = make_token_client(
+        config_file,
+        isolated_server.endpoint.clone(),
+        &created.secret,
+    );
+    import_single_package(&token_client).await;
+
+    let events = audit_events(client).await;
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].endpoint, "POST /api/v1/tokens");
+    assert_eq!(events[0].parameters["name"], "sync-timer");
+    assert_eq!(events[1].actor, "token:sync-timer");
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn does_not_record_rejected_requests(
+    config_file: ConfigFile,
+    #[with(config_file.clone())] mut isolated_server: IsolatedServer,
+) {
+    let client = &isolated_server.client;
+
+    let created = create_api_token(client, "reader", &[ApiTokenScope::ReadPrivate], None).await;
+    let token_client = make_token_client(
+        config_file,
+        isolated_server.endpoint.clone(),
+        &created.secret,
+    );
+    assert!(token_client.drop_queued_jobs(None, None).await.is_err());
+
+    // only the creation of the token itself
+    let events = audit_events(&token_client).await;
+    assert_eq!(events.len(), 1);
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn fails_if_no_authentication_is_provided(mut isolated_server: IsolatedServer) {
+    let client = &mut isolated_server.client;
+
+    client.auth_cookie("");
+    let result = client.get_audit_events(None).await;
+
+    assert!(result.is_err());
+
+    isolated_server.shutdown().await;
+}
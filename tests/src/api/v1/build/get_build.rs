@@ -1,7 +1,8 @@
+use crate::data::*;
 use crate::fixtures::server::IsolatedServer;
 use crate::fixtures::*;
 use crate::setup;
-use rebuilderd_common::api::v1::BuildRestApi;
+use rebuilderd_common::api::v1::{BuildRestApi, WorkerRestApi};
 use rstest::rstest;
 
 #[rstest]
@@ -56,3 +57,55 @@ pub async fn does_not_need_authentication(mut isolated_server: IsolatedServer) {
 
     isolated_server.shutdown().await;
 }
+
+#[rstest]
+#[tokio::test]
+pub async fn returns_worker_that_did_the_rebuild(mut isolated_server: IsolatedServer) {
+    let client = &mut isolated_server.client;
+    let worker_key = use_new_worker_key(client);
+
+    setup::single_good_rebuild(client).await;
+
+    let worker = client
+        .get_workers(None)
+        .await
+        .unwrap()
+        .records
+        .pop()
+        .unwrap();
+    let build = client.get_build(1).await.unwrap();
+
+    assert_eq!(Some(worker.id), build.worker_id);
+    assert_eq!(Some(DUMMY_WORKER), build.worker_name.as_deref());
+    assert_eq!(Some(worker_key), build.worker_key);
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn keeps_worker_name_and_key_after_worker_is_removed(
+    mut isolated_server: IsolatedServer,
+) {
+    let client = &mut isolated_server.client;
+    let worker_key = use_new_worker_key(client);
+
+    setup::single_good_rebuild(client).await;
+
+    let worker = client
+        .get_workers(None)
+        .await
+        .unwrap()
+        .records
+        .pop()
+        .unwrap();
+    client.unregister_worker(worker.id).await.unwrap();
+
+    let build = client.get_build(1).await.unwrap();
+
+    assert_eq!(None, build.worker_id);
+    assert_eq!(Some(DUMMY_WORKER), build.worker_name.as_deref());
+    assert_eq!(Some(worker_key), build.worker_key);
+
+    isolated_server.shutdown().await;
+}
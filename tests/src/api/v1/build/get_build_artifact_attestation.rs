@@ -1,10 +1,14 @@
 use crate::actions::*;
+use crate::data::{DUMMY_UPSTREAM_SHA256, dummy_worker_private_key};
 use crate::fixtures::server::IsolatedServer;
 use crate::fixtures::*;
 use crate::setup;
 use rebuilderd::attestation;
-use rebuilderd::attestation::Attestation;
-use rebuilderd_common::api::v1::{BuildRestApi, MetaRestApi, PackageRestApi};
+use rebuilderd::attestation::{Attestation, AttestationFormat};
+use rebuilderd_common::api::ATTESTATION_VERIFIED_HEADER;
+use rebuilderd_common::api::v1::{BuildRestApi, BuildStatus, MetaRestApi, PackageRestApi};
+use rebuilderd_common::config::ConfigFile;
+use rebuilderd_common::statement::Statement;
 use rstest::rstest;
 
 #[rstest]
@@ -100,6 +104,7 @@ pub async fn artifact_has_attestation_for_good_build_with_signed_attestation(
         .unwrap();
 
     assert!(artifact.has_attestation);
+    assert_eq!(artifact.attestation_verified, Some(true));
 
     client
         .get_build_artifact_attestation(build_id, artifact_id)
@@ -112,9 +117,11 @@ pub async fn artifact_has_attestation_for_good_build_with_signed_attestation(
 #[rstest]
 #[tokio::test]
 pub async fn artifact_has_attestation_for_good_build_with_unsigned_attestation(
-    mut isolated_server: IsolatedServer,
+    #[with(None, None, None, None, Some(false))] config_file: ConfigFile,
+    #[with(config_file.clone())] mut isolated_server: IsolatedServer,
 ) {
     let client = &isolated_server.client;
+    let _config_file = config_file;
 
     register_worker(client).await;
     import_single_package(client).await;
@@ -137,11 +144,25 @@ pub async fn artifact_has_attestation_for_good_build_with_unsigned_attestation(
         .unwrap();
 
     assert!(artifact.has_attestation);
+    assert_eq!(artifact.attestation_verified, Some(false));
 
-    client
-        .get_build_artifact_attestation(build_id, artifact_id)
+    // still served like before, but marked as unverified
+    let response = rebuilderd_common::http::client()
+        .unwrap()
+        .get(format!(
+            "{}/api/v1/builds/{build_id}/artifacts/{artifact_id}/attestation",
+            isolated_server.endpoint
+        ))
+        .send()
         .await
         .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        response.headers()[ATTESTATION_VERIFIED_HEADER]
+            .to_str()
+            .unwrap(),
+        "false"
+    );
 
     isolated_server.shutdown().await;
 }
@@ -194,7 +215,7 @@ pub async fn server_transparently_signs_attestations(mut isolated_server: Isolat
 
     register_worker(client).await;
     import_single_package(client).await;
-    report_good_rebuild_with_unsigned_attestation(client).await;
+    report_good_rebuild_with_signed_attestation(client).await;
 
     let package = client
         .get_binary_packages(None, None, None)
@@ -225,5 +246,115 @@ pub async fn server_transparently_signs_attestations(mut isolated_server: Isolat
 
     attestation.verify(1, &keys).unwrap();
 
+    // the signature of the worker is kept
+    let worker_key = dummy_worker_private_key().public().clone();
+    keys.push(worker_key);
+    attestation.verify(2, &keys).unwrap();
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn server_transparently_signs_statement_attestations(
+    mut isolated_server: IsolatedServer,
+) {
+    let client = &isolated_server.client;
+
+    register_worker(client).await;
+    import_single_package(client).await;
+    report_good_rebuild_with_signed_statement_attestation(client).await;
+
+    let attestation = client.get_build_artifact_attestation(1, 1).await.unwrap();
+    let attestation = Attestation::parse(&attestation).unwrap();
+
+    assert_eq!(attestation.format(), AttestationFormat::Dsse);
+
+    let public_key = get_server_public_key(client).await;
+    let subject = attestation.verify(1, [&public_key]).unwrap();
+
+    assert_eq!(subject.len(), 1);
+    assert_eq!(subject[0].digest["sha256"], DUMMY_UPSTREAM_SHA256);
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn statement_attestation_is_only_signed_once(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    register_worker(client).await;
+    import_single_package(client).await;
+    report_good_rebuild_with_signed_statement_attestation(client).await;
+
+    let first = client.get_build_artifact_attestation(1, 1).await.unwrap();
+    let second = client.get_build_artifact_attestation(1, 1).await.unwrap();
+
+    assert_eq!(first, second);
+
+    let Attestation::Dsse(envelope) = Attestation::parse(&second).unwrap() else {
+        panic!("Expected a DSSE envelope");
+    };
+    // one signature by the worker, one by the server
+    assert_eq!(envelope.signatures.len(), 2);
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn server_countersigns_verdict_of_bad_rebuild(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    register_worker(client).await;
+    import_single_package(client).await;
+    report_bad_rebuild_with_signed_verdict(client).await;
+
+    let attestation = client.get_build_artifact_attestation(1, 1).await.unwrap();
+    let attestation = Attestation::parse(&attestation).unwrap();
+
+    // a verdict is signed, but doesn't vouch for the artifact
+    let public_key = get_server_public_key(client).await;
+    let subject = attestation.verify(1, [&public_key]).unwrap();
+    assert!(subject.is_empty());
+
+    let Attestation::Dsse(envelope) = attestation else {
+        panic!("Expected a DSSE envelope");
+    };
+    assert_eq!(envelope.signatures.len(), 2);
+
+    let statement = Statement::parse(&envelope.payload().unwrap()).unwrap();
+    assert_eq!(statement.subject[0].digest["sha256"], DUMMY_UPSTREAM_SHA256);
+    let predicate = statement.verdict_predicate().unwrap().unwrap();
+    assert_eq!(predicate.status, BuildStatus::Bad);
+    assert!(predicate.rebuilt.is_some());
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn server_countersigns_verdict_of_failed_rebuild(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    register_worker(client).await;
+    import_single_package(client).await;
+    report_failed_rebuild_with_signed_verdict(client).await;
+
+    let attestation = client.get_build_artifact_attestation(1, 1).await.unwrap();
+    let attestation = Attestation::parse(&attestation).unwrap();
+
+    let public_key = get_server_public_key(client).await;
+    assert!(attestation.verify(1, [&public_key]).unwrap().is_empty());
+
+    let Attestation::Dsse(envelope) = attestation else {
+        panic!("Expected a DSSE envelope");
+    };
+    let statement = Statement::parse(&envelope.payload().unwrap()).unwrap();
+    let predicate = statement.verdict_predicate().unwrap().unwrap();
+    assert_eq!(predicate.status, BuildStatus::Fail);
+    assert_eq!(predicate.rebuilt, None);
+
     isolated_server.shutdown().await;
 }
@@ -5,6 +5,7 @@ use crate::fixtures::*;
 use crate::setup;
 use rebuilderd_common::api::v1::{
     BuildRestApi, OriginFilter, PackageReport, PackageRestApi, Page, SourceIdentityFilter,
+    WorkerFilter, WorkerRestApi,
 };
 use rstest::rstest;
 
@@ -14,7 +15,7 @@ pub async fn returns_no_results_for_empty_database(mut isolated_server: Isolated
     let client = &isolated_server.client;
 
     let results = client
-        .get_builds(None, None, None)
+        .get_builds(None, None, None, None)
         .await
         .map(|p| p.records)
         .unwrap();
@@ -34,7 +35,7 @@ pub async fn returns_single_result_for_database_with_single_build(
     setup::single_good_rebuild(client).await;
 
     let results = client
-        .get_builds(None, None, None)
+        .get_builds(None, None, None, None)
         .await
         .map(|p| p.records)
         .unwrap();
@@ -56,7 +57,7 @@ pub async fn returns_multiple_results_for_database_with_multiple_builds(
     report_good_rebuild(client).await;
 
     let results = client
-        .get_builds(None, None, None)
+        .get_builds(None, None, None, None)
         .await
         .map(|p| p.records)
         .unwrap();
@@ -78,7 +79,7 @@ pub async fn does_not_need_authentication(mut isolated_server: IsolatedServer) {
     client.worker_key("");
     client.signup_secret("");
 
-    let result = client.get_builds(None, None, None).await;
+    let result = client.get_builds(None, None, None, None).await;
 
     assert!(result.is_ok());
 
@@ -103,7 +104,7 @@ pub async fn can_paginate(mut isolated_server: IsolatedServer) {
     };
 
     let mut first_page = client
-        .get_builds(Some(&page), None, None)
+        .get_builds(Some(&page), None, None, None)
         .await
         .map(|p| p.records)
         .unwrap();
@@ -114,7 +115,7 @@ pub async fn can_paginate(mut isolated_server: IsolatedServer) {
     page.after = Some(result.id);
 
     let mut next_page = client
-        .get_builds(Some(&page), None, None)
+        .get_builds(Some(&page), None, None, None)
         .await
         .map(|p| p.records)
         .unwrap();
@@ -125,7 +126,7 @@ pub async fn can_paginate(mut isolated_server: IsolatedServer) {
     page.after = Some(result.id);
 
     let next_page = client
-        .get_builds(Some(&page), None, None)
+        .get_builds(Some(&page), None, None, None)
         .await
         .map(|p| p.records)
         .unwrap();
@@ -203,7 +204,7 @@ pub async fn returns_result_for_matching_origin_filter(
     }
 
     let mut results = client
-        .get_builds(None, Some(&origin_filter), None)
+        .get_builds(None, Some(&origin_filter), None, None)
         .await
         .map(|p| p.records)
         .unwrap();
@@ -269,7 +270,7 @@ pub async fn returns_result_for_matching_identity_filter(
     report_good_rebuild(client).await;
 
     let results = client
-        .get_builds(None, None, Some(&identity_filter))
+        .get_builds(None, None, Some(&identity_filter), None)
         .await
         .map(|p| p.records)
         .unwrap();
@@ -290,3 +291,40 @@ pub async fn returns_result_for_matching_identity_filter(
 
     isolated_server.shutdown().await;
 }
+
+#[rstest]
+#[tokio::test]
+pub async fn returns_result_for_matching_worker_filter(mut isolated_server: IsolatedServer) {
+    let client = &mut isolated_server.client;
+
+    setup::multiple_imported_packages(client).await;
+
+    register_worker(client).await;
+    report_good_rebuild(client).await;
+
+    use_new_worker_key(client);
+    register_other_worker(client).await;
+    report_bad_rebuild(client).await;
+
+    let workers = client.get_workers(None).await.unwrap().records;
+    let other_worker = workers
+        .iter()
+        .find(|w| w.name == DUMMY_OTHER_WORKER)
+        .unwrap();
+
+    let worker_filter = WorkerFilter {
+        worker: Some(other_worker.id),
+    };
+
+    let results = client
+        .get_builds(None, None, None, Some(&worker_filter))
+        .await
+        .map(|p| p.records)
+        .unwrap();
+
+    assert_eq!(1, results.len());
+    assert_eq!(Some(other_worker.id), results[0].worker_id);
+    assert_eq!(Some(DUMMY_OTHER_WORKER), results[0].worker_name.as_deref());
+
+    isolated_server.shutdown().await;
+}
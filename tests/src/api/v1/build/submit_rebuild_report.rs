@@ -5,10 +5,13 @@ use crate::fixtures::server::IsolatedServer;
 use crate::fixtures::*;
 use crate::setup;
 use chrono::Utc;
+use rebuilderd_common::api::is_lease_lost;
 use rebuilderd_common::api::v1::{
-    ArtifactStatus, BuildRestApi, BuildStatus, PackageRestApi, Priority, QueueRestApi,
+    ArtifactStatus, BuildRestApi, BuildStatus, FailureReason, PackageRestApi, Priority,
+    QueueRestApi, WorkerRestApi, WorkerState,
 };
 use rebuilderd_common::config::ConfigFile;
+use rebuilderd_common::utils::zstd_compress;
 use rstest::rstest;
 
 #[rstest]
@@ -47,6 +50,27 @@ pub async fn can_report_failed_rebuild(mut isolated_server: IsolatedServer) {
     isolated_server.shutdown().await;
 }
 
+#[rstest]
+#[tokio::test]
+pub async fn failure_reason_is_stored(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    register_worker(client).await;
+    import_single_package(client).await;
+
+    let job = pick_up_job(client).await;
+    let mut report = failed_rebuild_report(&job);
+    report.failure_reason = Some(FailureReason::Oom);
+
+    client.submit_build_report(report).await.unwrap();
+
+    let build = client.get_build(1).await.unwrap();
+    assert_eq!(Some(BuildStatus::Fail), build.status);
+    assert_eq!(Some(FailureReason::Oom), build.failure_reason);
+
+    isolated_server.shutdown().await;
+}
+
 #[rstest]
 #[tokio::test]
 pub async fn source_package_is_marked_failed_after_failed_report(
@@ -355,7 +379,87 @@ pub async fn can_report_good_rebuild_with_signed_attestation(mut isolated_server
 
 #[rstest]
 #[tokio::test]
-pub async fn can_report_good_rebuild_with_unsigned_attestation(
+pub async fn can_report_good_rebuild_with_unsigned_attestation_if_not_countersigning(
+    #[with(None, None, None, None, Some(false))] config_file: ConfigFile,
+    #[with(config_file.clone())] mut isolated_server: IsolatedServer,
+) {
+    let client = &isolated_server.client;
+    let _config_file = config_file;
+
+    register_worker(client).await;
+    import_single_package(client).await;
+
+    let job = pick_up_job(client).await;
+    let report = good_rebuild_report_with_unsigned_attestation(&job).await;
+
+    client.submit_build_report(report).await.unwrap();
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn rejects_unsigned_attestation(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    register_worker(client).await;
+    import_single_package(client).await;
+
+    let job = pick_up_job(client).await;
+    let report = good_rebuild_report_with_unsigned_attestation(&job).await;
+
+    let result = client.submit_build_report(report).await;
+
+    assert!(result.is_err());
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn rejects_attestation_signed_by_other_worker(mut isolated_server: IsolatedServer) {
+    let client = &mut isolated_server.client;
+
+    use_new_worker_key(client);
+    register_worker(client).await;
+    import_single_package(client).await;
+
+    let job = pick_up_job(client).await;
+    // signed by the dummy worker key, not the one we're registered with
+    let report = good_rebuild_report_with_signed_attestation(&job).await;
+
+    let result = client.submit_build_report(report).await;
+
+    assert!(result.is_err());
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn rejects_attestation_about_other_artifact(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    register_worker(client).await;
+    import_single_package(client).await;
+
+    let job = pick_up_job(client).await;
+    let mut report = good_rebuild_report_with_signed_attestation(&job).await;
+    for artifact in &mut report.artifacts {
+        artifact.upstream_sha256 = Some(DUMMY_MISMATCHED_SHA256.to_string());
+        artifact.rebuilt_sha256 = Some(DUMMY_MISMATCHED_SHA256.to_string());
+    }
+
+    let result = client.submit_build_report(report).await;
+
+    assert!(result.is_err());
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn rejects_attestation_about_artifact_of_other_package(
     mut isolated_server: IsolatedServer,
 ) {
     let client = &isolated_server.client;
@@ -363,10 +467,368 @@ pub async fn can_report_good_rebuild_with_unsigned_attestation(
     register_worker(client).await;
     import_single_package(client).await;
 
+    let job = pick_up_job(client).await;
+    let mut report = good_rebuild_report_with_signed_attestation(&job).await;
+    // validly signed and with matching digests, but for a file the job didn't ask for
+    let attestation = create_dummy_signed_attestation("other-1.buildinfo.txt", "other-1.tar.zst");
+    for artifact in &mut report.artifacts {
+        artifact.attestation = Some(attestation.clone().into_bytes());
+    }
+
+    let result = client.submit_build_report(report).await;
+
+    assert!(result.is_err());
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn can_report_signed_attestation_without_digests(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    register_worker(client).await;
+    import_single_package(client).await;
+
+    let job = pick_up_job(client).await;
+    let mut report = good_rebuild_report_with_signed_attestation(&job).await;
+    for artifact in &mut report.artifacts {
+        artifact.upstream_sha256 = None;
+        artifact.rebuilt_sha256 = None;
+        artifact.upstream_sha512 = None;
+        artifact.rebuilt_sha512 = None;
+    }
+
+    client.submit_build_report(report).await.unwrap();
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn rejects_reproduced_attestation_for_bad_artifact(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    register_worker(client).await;
+    import_single_package(client).await;
+
+    let job = pick_up_job(client).await;
+    let mut report = good_rebuild_report_with_signed_statement_attestation(&job).await;
+    report.status = BuildStatus::Bad;
+    for artifact in &mut report.artifacts {
+        artifact.status = ArtifactStatus::Bad;
+    }
+
+    let result = client.submit_build_report(report).await;
+
+    assert!(result.is_err());
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn rejects_verdict_for_good_artifact(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    register_worker(client).await;
+    import_single_package(client).await;
+
+    let job = pick_up_job(client).await;
+    let mut report = bad_rebuild_report_with_signed_verdict(&job).await;
+    report.status = BuildStatus::Good;
+    for artifact in &mut report.artifacts {
+        artifact.status = ArtifactStatus::Good;
+        artifact.rebuilt_sha256 = artifact.upstream_sha256.clone();
+        artifact.rebuilt_sha512 = artifact.upstream_sha512.clone();
+    }
+
+    let result = client.submit_build_report(report).await;
+
+    assert!(result.is_err());
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn rejects_verdict_with_other_status(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    register_worker(client).await;
+    import_single_package(client).await;
+
+    let job = pick_up_job(client).await;
+    let mut report = failed_rebuild_report_with_signed_verdict(&job).await;
+    report.status = BuildStatus::Bad;
+    for artifact in &mut report.artifacts {
+        artifact.status = ArtifactStatus::Bad;
+    }
+
+    let result = client.submit_build_report(report).await;
+
+    assert!(result.is_err());
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn rejected_report_keeps_job_in_queue(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    register_worker(client).await;
+    import_single_package(client).await;
+
     let job = pick_up_job(client).await;
     let report = good_rebuild_report_with_unsigned_attestation(&job).await;
 
+    assert!(client.submit_build_report(report).await.is_err());
+
+    let jobs = client
+        .get_queued_jobs(None, None, None)
+        .await
+        .unwrap()
+        .records;
+    assert_eq!(jobs.len(), 1);
+
+    let package = client
+        .get_binary_packages(None, None, None)
+        .await
+        .unwrap()
+        .records
+        .pop()
+        .unwrap();
+    assert_eq!(package.build_id, None);
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn rejects_report_with_unknown_lease(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    register_worker(client).await;
+    import_single_package(client).await;
+
+    let job = pick_up_job(client).await;
+    let mut report = good_rebuild_report(&job);
+    report.lease = "invalid".to_string();
+
+    let err = client.submit_build_report(report).await.unwrap_err();
+    assert!(is_lease_lost(&err));
+
+    let jobs = client
+        .get_queued_jobs(None, None, None)
+        .await
+        .unwrap()
+        .records;
+    assert_eq!(jobs.len(), 1);
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn rejects_report_for_previous_assignment(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    register_worker(client).await;
+    import_single_package(client).await;
+
+    let stale_job = pick_up_job(client).await;
+
+    // disabling the worker hands the job back to the queue
+    client
+        .set_worker_state(1, WorkerState::Disabled)
+        .await
+        .unwrap();
+    client
+        .set_worker_state(1, WorkerState::Active)
+        .await
+        .unwrap();
+
+    let job = pick_up_job(client).await;
+    assert_eq!(job.job.id, stale_job.job.id);
+    assert_ne!(job.lease, stale_job.lease);
+
+    let err = client
+        .submit_build_report(good_rebuild_report(&stale_job))
+        .await
+        .unwrap_err();
+    assert!(is_lease_lost(&err));
+
+    client
+        .submit_build_report(good_rebuild_report(&job))
+        .await
+        .unwrap();
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn can_report_bad_rebuild_as_json(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    register_worker(client).await;
+    import_single_package(client).await;
+
+    let job = pick_up_job(client).await;
+    let report = bad_rebuild_report(&job);
+
+    client.submit_build_report_json(report).await.unwrap();
+
+    let build_log = client.get_build_log(1).await.unwrap();
+    let diffoscope = client.get_build_artifact_diffoscope(1, 1).await.unwrap();
+
+    assert_eq!(DUMMY_BUILD_LOG, build_log);
+    assert_eq!(DUMMY_DIFFOSCOPE, diffoscope);
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn uploaded_report_keeps_binary_data_intact(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    register_worker(client).await;
+    import_single_package(client).await;
+
+    let job = pick_up_job(client).await;
+    let mut report = bad_rebuild_report(&job);
+    report.build_log = zstd_compress(DUMMY_BUILD_LOG.as_bytes()).await.unwrap();
+
+    client.submit_build_report(report).await.unwrap();
+
+    let build_log = client.get_build_log(1).await.unwrap();
+    let diffoscope = client.get_build_artifact_diffoscope(1, 1).await.unwrap();
+
+    assert_eq!(DUMMY_BUILD_LOG, build_log);
+    assert_eq!(DUMMY_DIFFOSCOPE, diffoscope);
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn artifact_digests_are_stored(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    setup::single_bad_rebuild(client).await;
+
+    let artifact = client.get_build_artifact(1, 1).await.unwrap();
+    assert_eq!(
+        Some(DUMMY_UPSTREAM_SHA256),
+        artifact.upstream_sha256.as_deref()
+    );
+    assert_eq!(
+        Some(DUMMY_MISMATCHED_SHA256),
+        artifact.rebuilt_sha256.as_deref()
+    );
+    assert_eq!(
+        Some(DUMMY_UPSTREAM_SHA512),
+        artifact.upstream_sha512.as_deref()
+    );
+    assert_eq!(
+        Some(DUMMY_MISMATCHED_SHA512),
+        artifact.rebuilt_sha512.as_deref()
+    );
+
+    let package = client.get_binary_package(1).await.unwrap();
+    assert_eq!(
+        Some(DUMMY_UPSTREAM_SHA256),
+        package.upstream_sha256.as_deref()
+    );
+    assert_eq!(
+        Some(DUMMY_MISMATCHED_SHA256),
+        package.rebuilt_sha256.as_deref()
+    );
+    assert_eq!(
+        Some(DUMMY_UPSTREAM_SHA512),
+        package.upstream_sha512.as_deref()
+    );
+    assert_eq!(
+        Some(DUMMY_MISMATCHED_SHA512),
+        package.rebuilt_sha512.as_deref()
+    );
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn artifact_digests_are_stored_from_json_report(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    register_worker(client).await;
+    import_single_package(client).await;
+
+    let job = pick_up_job(client).await;
+    let report = good_rebuild_report(&job);
+
+    client.submit_build_report_json(report).await.unwrap();
+
+    let artifact = client.get_build_artifact(1, 1).await.unwrap();
+    assert_eq!(
+        Some(DUMMY_UPSTREAM_SHA256),
+        artifact.upstream_sha256.as_deref()
+    );
+    assert_eq!(
+        Some(DUMMY_UPSTREAM_SHA256),
+        artifact.rebuilt_sha256.as_deref()
+    );
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn artifact_digests_are_optional(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    register_worker(client).await;
+    import_single_package(client).await;
+
+    let job = pick_up_job(client).await;
+    let mut report = bad_rebuild_report(&job);
+    for artifact in &mut report.artifacts {
+        artifact.upstream_sha256 = None;
+        artifact.rebuilt_sha256 = None;
+        artifact.upstream_sha512 = None;
+        artifact.rebuilt_sha512 = None;
+    }
+
     client.submit_build_report(report).await.unwrap();
 
+    let artifact = client.get_build_artifact(1, 1).await.unwrap();
+    assert_eq!(None, artifact.upstream_sha256);
+    assert_eq!(None, artifact.rebuilt_sha256);
+    assert_eq!(None, artifact.upstream_sha512);
+    assert_eq!(None, artifact.rebuilt_sha512);
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn fails_if_artifact_digest_is_malformed(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    register_worker(client).await;
+    import_single_package(client).await;
+
+    let job = pick_up_job(client).await;
+    let mut report = bad_rebuild_report(&job);
+    for artifact in &mut report.artifacts {
+        artifact.rebuilt_sha256 = Some("not-a-digest".to_string());
+    }
+
+    let result = client.submit_build_report(report).await;
+
+    assert!(result.is_err());
+
     isolated_server.shutdown().await;
 }
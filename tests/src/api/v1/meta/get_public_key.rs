@@ -37,3 +37,16 @@ pub async fn does_not_need_authentication(mut isolated_server: IsolatedServer) {
 
     isolated_server.shutdown().await;
 }
+
+#[rstest]
+#[tokio::test]
+pub async fn has_no_key_history_by_default(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    let result = client.get_public_keys().await.unwrap();
+
+    assert!(result.previous.is_empty());
+    assert!(result.next.is_empty());
+
+    isolated_server.shutdown().await;
+}
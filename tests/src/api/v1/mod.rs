@@ -1,6 +1,9 @@
+mod audit;
 mod build;
 mod dashboard;
 mod meta;
 mod package;
 mod queue;
+mod token;
+mod transparency;
 mod worker;
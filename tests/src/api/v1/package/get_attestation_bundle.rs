@@ -0,0 +1,130 @@
+use crate::actions::*;
+use crate::data::*;
+use crate::fixtures::server::IsolatedServer;
+use crate::fixtures::*;
+use rebuilderd::attestation::Attestation;
+use rebuilderd_common::api::v1::{BuildRestApi, OriginFilter};
+use rebuilderd_common::config::ConfigFile;
+use rstest::rstest;
+
+#[rstest]
+#[tokio::test]
+pub async fn returns_empty_bundle_for_empty_database(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    let (entries, manifest) = fetch_attestation_bundle(client, None).await;
+
+    assert!(entries.is_empty());
+    assert!(manifest.attestations.is_empty());
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn contains_countersigned_attestation_of_good_build(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    register_worker(client).await;
+    import_single_package(client).await;
+    report_good_rebuild_with_signed_attestation(client).await;
+
+    let (entries, manifest) = fetch_attestation_bundle(client, None).await;
+
+    assert_eq!(1, entries.len());
+    assert_eq!(1, manifest.attestations.len());
+
+    let entry = &entries[0];
+    assert_eq!(DUMMY_BINARY_PACKAGE, entry.package.name);
+
+    let bundled = entry.attestation().unwrap();
+    let attestation = Attestation::parse(&bundled).unwrap();
+    let public_key = get_server_public_key(client).await;
+    attestation.verify(1, [&public_key]).unwrap();
+
+    // the bundle has the same attestation as the single artifact endpoint
+    let single = client
+        .get_build_artifact_attestation(
+            entry.package.build_id.unwrap(),
+            entry.package.artifact_id.unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(single, bundled);
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn does_not_contain_verdict_of_bad_build(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    register_worker(client).await;
+    import_single_package(client).await;
+    report_bad_rebuild_with_signed_verdict(client).await;
+
+    let (entries, manifest) = fetch_attestation_bundle(client, None).await;
+
+    assert!(entries.is_empty());
+    assert!(manifest.attestations.is_empty());
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn does_not_contain_unverified_attestation(
+    #[with(None, None, None, None, Some(false))] config_file: ConfigFile,
+    #[with(config_file.clone())] mut isolated_server: IsolatedServer,
+) {
+    let client = &isolated_server.client;
+    let _config_file = config_file;
+
+    register_worker(client).await;
+    import_single_package(client).await;
+    report_good_rebuild_with_unsigned_attestation(client).await;
+
+    let (entries, manifest) = fetch_attestation_bundle(client, None).await;
+
+    assert!(entries.is_empty());
+    assert!(manifest.attestations.is_empty());
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn is_scoped_by_origin(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    register_worker(client).await;
+    import_single_package(client).await;
+    report_good_rebuild_with_signed_attestation(client).await;
+
+    let origin_filter = OriginFilter {
+        distribution: Some(DUMMY_OTHER_DISTRIBUTION.to_string()),
+        release: None,
+        component: None,
+        architecture: None,
+    };
+    let (entries, manifest) = fetch_attestation_bundle(client, Some(&origin_filter)).await;
+
+    assert!(entries.is_empty());
+    assert_eq!(
+        Some(DUMMY_OTHER_DISTRIBUTION.to_string()),
+        manifest.origin.distribution
+    );
+
+    let origin_filter = OriginFilter {
+        distribution: Some(DUMMY_DISTRIBUTION.to_string()),
+        release: Some(DUMMY_RELEASE.to_string()),
+        component: None,
+        architecture: Some(DUMMY_ARCHITECTURE.to_string()),
+    };
+    let (entries, _) = fetch_attestation_bundle(client, Some(&origin_filter)).await;
+
+    assert_eq!(1, entries.len());
+
+    isolated_server.shutdown().await;
+}
@@ -0,0 +1,228 @@
+use crate::actions::*;
+use crate::data::*;
+use crate::fixtures::server::IsolatedServer;
+use crate::fixtures::*;
+use crate::setup;
+use rebuilderd_common::api::v1::{
+    ArtifactDigestQuery, ArtifactStatus, BuildRestApi, PackageRestApi,
+};
+use rstest::rstest;
+
+#[rstest]
+#[tokio::test]
+pub async fn returns_no_results_for_unknown_digest(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    setup::single_good_rebuild(client).await;
+
+    let results = client
+        .lookup_binary_packages(&ArtifactDigestQuery {
+            sha256: Some(DUMMY_MISMATCHED_SHA256.to_string()),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+    assert!(results.is_empty());
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[case(ArtifactDigestQuery {
+    sha256: Some(DUMMY_UPSTREAM_SHA256.to_string()),
+    ..Default::default()
+})]
+#[case(ArtifactDigestQuery {
+    sha512: Some(DUMMY_UPSTREAM_SHA512.to_string()),
+    ..Default::default()
+})]
+#[case(ArtifactDigestQuery {
+    sha256: Some(DUMMY_UPSTREAM_SHA256.to_uppercase()),
+    ..Default::default()
+})]
+#[case(ArtifactDigestQuery {
+    sha256: Some(DUMMY_UPSTREAM_SHA256.to_string()),
+    sha512: Some(DUMMY_UPSTREAM_SHA512.to_string()),
+    filename: Some("foo-1.tar.zst".to_string()),
+})]
+#[tokio::test]
+pub async fn returns_result_for_matching_digest(
+    mut isolated_server: IsolatedServer,
+    #[case] query: ArtifactDigestQuery,
+) {
+    let client = &isolated_server.client;
+
+    setup::single_bad_rebuild(client).await;
+
+    let mut results = client.lookup_binary_packages(&query).await.unwrap();
+
+    assert_eq!(1, results.len());
+
+    let result = results.pop().unwrap();
+    assert_eq!(DUMMY_BINARY_PACKAGE, result.package.name);
+    assert_eq!(Some(ArtifactStatus::Bad), result.package.status);
+    assert_eq!(None, result.attestation_url);
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn does_not_match_rebuilt_digest(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    setup::single_bad_rebuild(client).await;
+
+    let results = client
+        .lookup_binary_packages(&ArtifactDigestQuery {
+            sha256: Some(DUMMY_MISMATCHED_SHA256.to_string()),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+    assert!(results.is_empty());
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn returns_no_results_for_other_filename(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    setup::single_good_rebuild(client).await;
+
+    let results = client
+        .lookup_binary_packages(&ArtifactDigestQuery {
+            sha256: Some(DUMMY_UPSTREAM_SHA256.to_string()),
+            filename: Some("bar-3.tar.zst".to_string()),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+    assert!(results.is_empty());
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn links_to_attestation_of_good_rebuild(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    setup::single_good_rebuild_with_signed_attestation(client).await;
+
+    let mut results = client
+        .lookup_binary_packages(&ArtifactDigestQuery {
+            sha256: Some(DUMMY_UPSTREAM_SHA256.to_string()),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(1, results.len());
+
+    let result = results.pop().unwrap();
+    assert_eq!(Some(ArtifactStatus::Good), result.package.status);
+    assert_eq!(
+        Some("api/v1/builds/1/artifacts/1/attestation"),
+        result.attestation_url.as_deref()
+    );
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn returns_status_of_matching_rebuild(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    setup::single_bad_rebuild(client).await;
+    request_rebuild_of_all_bad_packages(client).await;
+
+    // the upstream artifact has changed since the first rebuild
+    let job = pick_up_job(client).await;
+    let mut report = good_rebuild_report(&job);
+    for artifact in &mut report.artifacts {
+        artifact.upstream_sha256 = Some(DUMMY_MISMATCHED_SHA256.to_string());
+        artifact.rebuilt_sha256 = Some(DUMMY_MISMATCHED_SHA256.to_string());
+        artifact.upstream_sha512 = None;
+        artifact.rebuilt_sha512 = None;
+    }
+    client.submit_build_report(report).await.unwrap();
+
+    let mut results = client
+        .lookup_binary_packages(&ArtifactDigestQuery {
+            sha256: Some(DUMMY_UPSTREAM_SHA256.to_string()),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(1, results.len());
+
+    let result = results.pop().unwrap();
+    assert_eq!(Some(ArtifactStatus::Bad), result.package.status);
+    assert_eq!(Some(1), result.package.build_id);
+    assert_eq!(
+        Some(DUMMY_UPSTREAM_SHA256),
+        result.package.upstream_sha256.as_deref()
+    );
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[case(ArtifactDigestQuery::default())]
+#[case(ArtifactDigestQuery {
+    filename: Some("foo-1.tar.zst".to_string()),
+    ..Default::default()
+})]
+#[case(ArtifactDigestQuery {
+    sha256: Some("not-a-digest".to_string()),
+    ..Default::default()
+})]
+#[case(ArtifactDigestQuery {
+    sha512: Some(DUMMY_UPSTREAM_SHA256.to_string()),
+    ..Default::default()
+})]
+#[tokio::test]
+pub async fn fails_for_invalid_query(
+    mut isolated_server: IsolatedServer,
+    #[case] query: ArtifactDigestQuery,
+) {
+    let client = &isolated_server.client;
+
+    let result = client.lookup_binary_packages(&query).await;
+
+    assert!(result.is_err());
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn does_not_need_authentication(mut isolated_server: IsolatedServer) {
+    let client = &mut isolated_server.client;
+
+    setup::single_good_rebuild(client).await;
+
+    // zero out keys
+    client.auth_cookie("");
+    client.worker_key("");
+    client.signup_secret("");
+
+    let result = client
+        .lookup_binary_packages(&ArtifactDigestQuery {
+            sha256: Some(DUMMY_UPSTREAM_SHA256.to_string()),
+            ..Default::default()
+        })
+        .await;
+
+    assert!(result.is_ok());
+
+    isolated_server.shutdown().await;
+}
@@ -1,5 +1,8 @@
+mod get_attestation_bundle;
 mod get_binary_package;
 mod get_binary_packages;
 mod get_source_package;
 mod get_source_packages;
+mod lookup_binary_packages;
+mod set_required_labels;
 mod submit_package_report;
@@ -0,0 +1,212 @@
+use crate::actions::*;
+use crate::data::*;
+use crate::fixtures::server::IsolatedServer;
+use crate::fixtures::*;
+use rebuilderd_common::api::v1::{
+    JobAssignment, PackageRestApi, PopQueuedJobRequest, QueueRestApi, RequiredLabelsRequest,
+};
+use rstest::rstest;
+
+fn labels_request(required_labels: &[&str]) -> RequiredLabelsRequest {
+    RequiredLabelsRequest {
+        distribution: Some(DUMMY_DISTRIBUTION.to_string()),
+        release: None,
+        component: None,
+        name: Some(DUMMY_SOURCE_PACKAGE.to_string()),
+        version: None,
+        architecture: None,
+        required_labels: required_labels
+            .iter()
+            .map(|label| label.to_string())
+            .collect(),
+    }
+}
+
+fn labeled_job_request(labels: &[&str]) -> PopQueuedJobRequest {
+    PopQueuedJobRequest {
+        labels: labels.iter().map(|label| label.to_string()).collect(),
+        ..job_request()
+    }
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn fails_if_no_admin_authentication_is_provided(mut isolated_server: IsolatedServer) {
+    let client = &mut isolated_server.client;
+
+    import_single_package(client).await;
+
+    // zero out key
+    client.auth_cookie("");
+    let result = client
+        .set_required_labels(&labels_request(&["large-memory"]))
+        .await;
+
+    assert!(result.is_err());
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn fails_without_filter(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    register_worker(client).await;
+    import_single_package(client).await;
+
+    let result = client
+        .set_required_labels(&RequiredLabelsRequest {
+            distribution: None,
+            name: None,
+            ..labels_request(&["large-memory"])
+        })
+        .await;
+    assert!(result.is_err());
+
+    let job = client.request_work(job_request()).await.unwrap();
+    assert!(matches!(job, JobAssignment::Rebuild(_)));
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn required_labels_restrict_work(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    register_worker(client).await;
+    import_single_package(client).await;
+
+    client
+        .set_required_labels(&labels_request(&["large-memory"]))
+        .await
+        .unwrap();
+
+    let job = client.request_work(job_request()).await.unwrap();
+    assert!(matches!(job, JobAssignment::Nothing));
+
+    let job = client
+        .request_work(labeled_job_request(&["large-memory"]))
+        .await
+        .unwrap();
+    assert!(matches!(job, JobAssignment::Rebuild(_)));
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn empty_labels_remove_requirement(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    register_worker(client).await;
+    import_single_package(client).await;
+
+    client
+        .set_required_labels(&labels_request(&["large-memory"]))
+        .await
+        .unwrap();
+    client
+        .set_required_labels(&labels_request(&[]))
+        .await
+        .unwrap();
+
+    let job = client.request_work(job_request()).await.unwrap();
+    assert!(matches!(job, JobAssignment::Rebuild(_)));
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn labels_are_kept_for_new_versions(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    register_worker(client).await;
+    import_single_package(client).await;
+
+    client
+        .set_required_labels(&labels_request(&["large-memory"]))
+        .await
+        .unwrap();
+
+    let mut report = single_package_report();
+    report.packages[0].version = "2".to_string();
+    report.packages[0].url = "https://placeholder.org/foo-2.buildinfo.txt".to_string();
+    report.packages[0].artifacts[0].version = "2".to_string();
+    report.packages[0].artifacts[0].url = "https://placeholder.org/foo-2.tar.zst".to_string();
+    client.submit_package_report(&report).await.unwrap();
+
+    let job = client.request_work(job_request()).await.unwrap();
+    assert!(matches!(job, JobAssignment::Nothing));
+
+    let job = client
+        .request_work(labeled_job_request(&["large-memory"]))
+        .await
+        .unwrap();
+    let JobAssignment::Rebuild(job) = job else {
+        panic!("Expected a job to be assigned");
+    };
+    assert_eq!(job.job.version, "2");
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn labels_are_not_kept_for_other_releases(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    register_worker(client).await;
+    import_single_package(client).await;
+
+    client
+        .set_required_labels(&RequiredLabelsRequest {
+            release: Some(DUMMY_RELEASE.to_string()),
+            ..labels_request(&["large-memory"])
+        })
+        .await
+        .unwrap();
+
+    // a distinct build input, otherwise it isn't queued next to the one of the first release
+    let mut report = single_package_report_from_different_release();
+    report.packages[0].url = "https://placeholder.org/foo-other.buildinfo.txt".to_string();
+    client.submit_package_report(&report).await.unwrap();
+
+    let job = client.request_work(job_request()).await.unwrap();
+    let JobAssignment::Rebuild(job) = job else {
+        panic!("Expected a job to be assigned");
+    };
+    assert_eq!(job.job.release.as_deref(), Some(DUMMY_OTHER_RELEASE));
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn sync_can_replace_labels(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    register_worker(client).await;
+    import_single_package(client).await;
+
+    client
+        .set_required_labels(&labels_request(&["large-memory"]))
+        .await
+        .unwrap();
+
+    // a report without labels keeps them
+    import_single_package(client).await;
+    let job = client.request_work(job_request()).await.unwrap();
+    assert!(matches!(job, JobAssignment::Nothing));
+
+    let mut report = single_package_report();
+    report.packages[0].required_labels = Some(Vec::new());
+    client.submit_package_report(&report).await.unwrap();
+
+    let job = client.request_work(job_request()).await.unwrap();
+    assert!(matches!(job, JobAssignment::Rebuild(_)));
+
+    isolated_server.shutdown().await;
+}
@@ -525,7 +525,7 @@ pub async fn existing_rebuilds_are_copied_from_friends(
     client.submit_package_report(&extra_packages).await.unwrap();
 
     let mut rebuilds = client
-        .get_builds(None, Some(&origin_filter), Some(&friend_identity))
+        .get_builds(None, Some(&origin_filter), Some(&friend_identity), None)
         .await
         .unwrap()
         .records;
@@ -534,6 +534,7 @@ pub async fn existing_rebuilds_are_copied_from_friends(
 
     let rebuild = rebuilds.pop().unwrap();
     assert_eq!(BuildStatus::Good, rebuild.status.unwrap());
+    assert_eq!(Some(DUMMY_WORKER), rebuild.worker_name.as_deref());
 
     isolated_server.shutdown().await;
 }
@@ -572,7 +573,7 @@ pub async fn existing_rebuilds_are_not_copied_from_nonfriends(
     client.submit_package_report(&extra_packages).await.unwrap();
 
     let rebuilds = client
-        .get_builds(None, Some(&origin_filter), Some(&friend_identity))
+        .get_builds(None, Some(&origin_filter), Some(&friend_identity), None)
         .await
         .unwrap()
         .records;
@@ -661,7 +662,7 @@ pub async fn retry_count_is_independent_of_friends(
     client.submit_package_report(&extra_packages).await.unwrap();
 
     let mut builds = client
-        .get_builds(None, Some(&origin_filter), None)
+        .get_builds(None, Some(&origin_filter), None, None)
         .await
         .unwrap()
         .records;
@@ -3,5 +3,6 @@ mod drop_queued_jobs;
 mod get_queued_job;
 mod get_queued_jobs;
 mod ping_job;
+mod queued_job_log;
 mod request_rebuild;
 mod request_work;
@@ -1,7 +1,12 @@
 use crate::actions::*;
+use crate::data::*;
 use crate::fixtures::server::IsolatedServer;
 use crate::fixtures::*;
-use rebuilderd_common::api::v1::QueueRestApi;
+use rebuilderd_common::api::is_lease_lost;
+use rebuilderd_common::api::v1::{
+    PackageReport, PackageRestApi, PingQueuedJobRequest, PingQueuedJobResponse, QueueRestApi,
+};
+use rebuilderd_common::config::ConfigFile;
 use rstest::rstest;
 
 #[rstest]
@@ -14,7 +19,64 @@ pub async fn can_ping_running_job(mut isolated_server: IsolatedServer) {
 
     let job = pick_up_job(client).await;
 
-    client.ping_job(job.job.id).await.unwrap();
+    let response = client.ping_job(job.job.id, &job.lease).await.unwrap();
+    assert_eq!(response, PingQueuedJobResponse::Continue);
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn dropped_job_is_cancelled(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    register_worker(client).await;
+    import_single_package(client).await;
+
+    let job = pick_up_job(client).await;
+    client.drop_queued_job(job.job.id).await.unwrap();
+
+    let response = client.ping_job(job.job.id, &job.lease).await.unwrap();
+    assert_eq!(response, PingQueuedJobResponse::Cancel);
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn job_of_package_removed_by_sync_is_cancelled(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    register_worker(client).await;
+    import_single_package(client).await;
+
+    let job = pick_up_job(client).await;
+
+    // the package is gone in the next sync
+    let report = PackageReport {
+        packages: vec![],
+        ..single_package_report()
+    };
+    client.submit_package_report(&report).await.unwrap();
+
+    let response = client.ping_job(job.job.id, &job.lease).await.unwrap();
+    assert_eq!(response, PingQueuedJobResponse::Cancel);
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn can_not_ping_with_unknown_lease(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    register_worker(client).await;
+    import_single_package(client).await;
+
+    let job = pick_up_job(client).await;
+
+    let err = client.ping_job(job.job.id, "invalid").await.unwrap_err();
+    assert!(is_lease_lost(&err));
 
     isolated_server.shutdown().await;
 }
@@ -29,7 +91,7 @@ pub async fn can_not_ping_available_job(mut isolated_server: IsolatedServer) {
 
     let job = client.get_queued_job(1).await.unwrap();
 
-    let result = client.ping_job(job.id).await;
+    let result = client.ping_job(job.id, "").await;
 
     assert!(result.is_err());
 
@@ -38,15 +100,14 @@ pub async fn can_not_ping_available_job(mut isolated_server: IsolatedServer) {
 
 #[rstest]
 #[tokio::test]
-pub async fn can_not_ping_nonexistent_job(mut isolated_server: IsolatedServer) {
+pub async fn nonexistent_job_is_cancelled(mut isolated_server: IsolatedServer) {
     let client = &isolated_server.client;
 
     register_worker(client).await;
     import_single_package(client).await;
 
-    let result = client.ping_job(99999).await;
-
-    assert!(result.is_err());
+    let response = client.ping_job(99999, "").await.unwrap();
+    assert_eq!(response, PingQueuedJobResponse::Cancel);
 
     isolated_server.shutdown().await;
 }
@@ -61,9 +122,106 @@ pub async fn fails_if_no_worker_authentication_is_provided(mut isolated_server:
 
     // zero out key
     client.worker_key("");
-    let result = client.ping_job(1).await;
+    let result = client.ping_job(1, "").await;
+
+    assert!(result.is_err());
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn fails_if_request_is_signed_with_another_key(mut isolated_server: IsolatedServer) {
+    let client = &mut isolated_server.client;
+
+    register_worker(client).await;
+    import_single_package(client).await;
+
+    let job = pick_up_job(client).await;
+
+    client.worker_signer(signer(private_key()));
+    let result = client.ping_job(job.job.id, &job.lease).await;
 
     assert!(result.is_err());
 
     isolated_server.shutdown().await;
 }
+
+#[rstest]
+#[tokio::test]
+pub async fn fails_if_signed_request_is_replayed(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    register_worker(client).await;
+    import_single_package(client).await;
+
+    let job = pick_up_job(client).await;
+
+    let path = format!("/api/v1/queue/{}/ping", job.job.id);
+    let body = serde_json::to_vec(&PingQueuedJobRequest {
+        lease: job.lease.clone(),
+    })
+    .unwrap();
+    let headers = dummy_worker_request_headers("POST", &path, "", &body);
+
+    let http = rebuilderd_common::http::client().unwrap();
+    for expected_status in [200, 403] {
+        let mut request = http
+            .post(format!("{}{path}", isolated_server.endpoint))
+            .body(body.clone());
+        for (name, value) in &headers {
+            request = request.header(*name, value);
+        }
+        let response = request.send().await.unwrap();
+        assert_eq!(response.status().as_u16(), expected_status);
+    }
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn fails_if_request_is_not_signed(
+    config_file: ConfigFile,
+    #[with(config_file.clone())] mut isolated_server: IsolatedServer,
+) {
+    let client = &mut isolated_server.client;
+    let worker_key = use_new_worker_key(client);
+
+    register_worker(client).await;
+    import_single_package(client).await;
+
+    let job = pick_up_job(client).await;
+
+    let mut unsigned_client = make_unsigned_client(config_file, isolated_server.endpoint.clone());
+    unsigned_client.worker_key(worker_key);
+    let result = unsigned_client.ping_job(job.job.id, &job.lease).await;
+
+    assert!(result.is_err());
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn unsigned_request_is_accepted_if_allowed(
+    #[with(None, None, None, Some(true))] config_file: ConfigFile,
+    #[with(config_file.clone())] mut isolated_server: IsolatedServer,
+) {
+    let client = &mut isolated_server.client;
+    let worker_key = use_new_worker_key(client);
+
+    register_worker(client).await;
+    import_single_package(client).await;
+
+    let job = pick_up_job(client).await;
+
+    let mut unsigned_client = make_unsigned_client(config_file, isolated_server.endpoint.clone());
+    unsigned_client.worker_key(worker_key);
+    unsigned_client
+        .ping_job(job.job.id, &job.lease)
+        .await
+        .unwrap();
+
+    isolated_server.shutdown().await;
+}
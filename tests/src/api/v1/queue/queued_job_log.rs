@@ -0,0 +1,263 @@
+use crate::actions::*;
+use crate::data::*;
+use crate::fixtures::server::IsolatedServer;
+use crate::fixtures::*;
+use rebuilderd_common::api::is_lease_lost;
+use rebuilderd_common::api::v1::{BuildRestApi, QueueRestApi};
+use rebuilderd_common::config::ConfigFile;
+use rstest::rstest;
+
+#[rstest]
+#[tokio::test]
+pub async fn can_push_and_get_log_of_running_job(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    register_worker(client).await;
+    import_single_package(client).await;
+
+    let job = pick_up_job(client).await;
+
+    client
+        .push_queued_job_log(job.job.id, &job.lease, b"hello ".to_vec())
+        .await
+        .unwrap();
+    client
+        .push_queued_job_log(job.job.id, &job.lease, b"world\n".to_vec())
+        .await
+        .unwrap();
+
+    let log = client.get_queued_job_log(job.job.id).await.unwrap();
+    assert_eq!(log, "hello world\n");
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn log_is_truncated_at_size_limit(
+    #[with(None, None, None, None, None, Some(8))] config_file: ConfigFile,
+    #[with(config_file.clone())] mut isolated_server: IsolatedServer,
+) {
+    let client = &isolated_server.client;
+    let _config_file = config_file;
+
+    register_worker(client).await;
+    import_single_package(client).await;
+
+    let job = pick_up_job(client).await;
+
+    for chunk in ["hello ", "world\n", "again\n"] {
+        client
+            .push_queued_job_log(job.job.id, &job.lease, chunk.as_bytes().to_vec())
+            .await
+            .unwrap();
+    }
+
+    let log = client.get_queued_job_log(job.job.id).await.unwrap();
+    assert_eq!(log, "hello wo");
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn log_of_new_job_is_empty(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    register_worker(client).await;
+    import_single_package(client).await;
+
+    let log = client.get_queued_job_log(1).await.unwrap();
+    assert_eq!(log, "");
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn can_not_get_log_of_nonexistent_job(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    let result = client.get_queued_job_log(99999).await;
+    assert!(result.is_err());
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn can_not_push_log_of_available_job(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    register_worker(client).await;
+    import_single_package(client).await;
+
+    let result = client.push_queued_job_log(1, "", b"hello\n".to_vec()).await;
+    assert!(result.is_err());
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn can_not_push_log_of_job_assigned_to_another_worker(
+    mut isolated_server: IsolatedServer,
+) {
+    let client = &mut isolated_server.client;
+
+    register_worker(client).await;
+    import_single_package(client).await;
+
+    let job = pick_up_job(client).await;
+
+    use_new_worker_key(client);
+    register_other_worker(client).await;
+
+    let result = client
+        .push_queued_job_log(job.job.id, &job.lease, b"hello\n".to_vec())
+        .await;
+    assert!(result.is_err());
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn can_not_push_log_with_unknown_lease(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    register_worker(client).await;
+    import_single_package(client).await;
+
+    let job = pick_up_job(client).await;
+
+    let err = client
+        .push_queued_job_log(job.job.id, "invalid", b"hello\n".to_vec())
+        .await
+        .unwrap_err();
+    assert!(is_lease_lost(&err));
+
+    let log = client.get_queued_job_log(job.job.id).await.unwrap();
+    assert_eq!(log, "");
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn fails_if_query_does_not_match_signature(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    register_worker(client).await;
+    import_single_package(client).await;
+
+    let job = pick_up_job(client).await;
+
+    let path = format!("/api/v1/queue/{}/log", job.job.id);
+    let headers = dummy_worker_request_headers("POST", &path, "lease=invalid", b"hello\n");
+
+    let http = rebuilderd_common::http::client().unwrap();
+    let mut request = http
+        .post(format!(
+            "{}{path}?lease={}",
+            isolated_server.endpoint, job.lease
+        ))
+        .body(b"hello\n".to_vec());
+    for (name, value) in &headers {
+        request = request.header(*name, value);
+    }
+    let response = request.send().await.unwrap();
+    assert_eq!(response.status().as_u16(), 403);
+
+    let log = client.get_queued_job_log(job.job.id).await.unwrap();
+    assert_eq!(log, "");
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn fails_if_no_worker_authentication_is_provided(mut isolated_server: IsolatedServer) {
+    let client = &mut isolated_server.client;
+
+    register_worker(client).await;
+    import_single_package(client).await;
+
+    let job = pick_up_job(client).await;
+
+    // zero out key
+    client.worker_key("");
+    let result = client
+        .push_queued_job_log(job.job.id, &job.lease, b"hello\n".to_vec())
+        .await;
+
+    assert!(result.is_err());
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn can_follow_log_until_build_is_reported(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    register_worker(client).await;
+    import_single_package(client).await;
+
+    let job = pick_up_job(client).await;
+
+    client
+        .push_queued_job_log(job.job.id, &job.lease, b"first line\n".to_vec())
+        .await
+        .unwrap();
+
+    let mut stream = client.follow_queued_job_log(job.job.id).await.unwrap();
+    let output = stream.next_output().await.unwrap();
+    assert_eq!(output.as_deref(), Some("first line\n"));
+
+    client
+        .push_queued_job_log(job.job.id, &job.lease, b"second line\n".to_vec())
+        .await
+        .unwrap();
+    let output = stream.next_output().await.unwrap();
+    assert_eq!(output.as_deref(), Some("second line\n"));
+
+    client
+        .submit_build_report(good_rebuild_report(&job))
+        .await
+        .unwrap();
+    let output = stream.next_output().await.unwrap();
+    assert_eq!(output, None);
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn following_log_does_not_split_characters(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    register_worker(client).await;
+    import_single_package(client).await;
+
+    let job = pick_up_job(client).await;
+
+    // the second byte of é is only pushed with the next chunk
+    client
+        .push_queued_job_log(job.job.id, &job.lease, b"caf\xc3".to_vec())
+        .await
+        .unwrap();
+
+    let mut stream = client.follow_queued_job_log(job.job.id).await.unwrap();
+    let output = stream.next_output().await.unwrap();
+    assert_eq!(output.as_deref(), Some("caf"));
+
+    client
+        .push_queued_job_log(job.job.id, &job.lease, b"\xa9\n".to_vec())
+        .await
+        .unwrap();
+    let output = stream.next_output().await.unwrap();
+    assert_eq!(output.as_deref(), Some("é\n"));
+
+    isolated_server.shutdown().await;
+}
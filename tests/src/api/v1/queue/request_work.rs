@@ -4,9 +4,9 @@ use crate::fixtures::server::IsolatedServer;
 use crate::fixtures::*;
 use crate::setup;
 use rebuilderd_common::api::v1::{
-    JobAssignment, PopQueuedJobRequest, Priority, QueueJobRequest, QueueRestApi,
+    JobAssignment, PackageRestApi, PopQueuedJobRequest, Priority, QueueJobRequest, QueueRestApi,
 };
-use rebuilderd_common::config::ConfigFile;
+use rebuilderd_common::config::{ConfigFile, SchedulePolicy};
 use rstest::rstest;
 
 #[rstest]
@@ -172,3 +172,160 @@ pub async fn manually_queued_item_past_max_retries_is_available(
 
     isolated_server.shutdown().await;
 }
+
+#[rstest]
+#[tokio::test]
+pub async fn worker_without_required_labels_gets_no_work(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    register_worker(client).await;
+
+    let mut report = single_package_report();
+    report.packages[0].required_labels = Some(vec!["large-memory".to_string()]);
+    client.submit_package_report(&report).await.unwrap();
+
+    let job = client
+        .request_work(PopQueuedJobRequest {
+            labels: vec!["kvm".to_string()],
+            ..job_request()
+        })
+        .await
+        .unwrap();
+
+    assert!(matches!(job, JobAssignment::Nothing));
+
+    let job = client
+        .request_work(PopQueuedJobRequest {
+            labels: vec!["kvm".to_string(), "large-memory".to_string()],
+            ..job_request()
+        })
+        .await
+        .unwrap();
+
+    assert!(matches!(job, JobAssignment::Rebuild(_)));
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn worker_with_labels_gets_work_without_required_labels(
+    mut isolated_server: IsolatedServer,
+) {
+    let client = &isolated_server.client;
+
+    register_worker(client).await;
+    import_single_package(client).await;
+
+    let job = client
+        .request_work(PopQueuedJobRequest {
+            labels: vec!["large-memory".to_string()],
+            ..job_request()
+        })
+        .await
+        .unwrap();
+
+    assert!(matches!(job, JobAssignment::Rebuild(_)));
+
+    isolated_server.shutdown().await;
+}
+
+fn fair_share_config(config_file: &ConfigFile, shares: &[(&str, u32)]) -> ConfigFile {
+    let mut config_file = config_file.clone();
+    config_file.schedule.policy = Some(SchedulePolicy::FairShare);
+    config_file.schedule.shares = shares
+        .iter()
+        .map(|(scope, weight)| (scope.to_string(), *weight))
+        .collect();
+    config_file
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn fair_share_serves_scope_without_share_last(
+    config_file: ConfigFile,
+    #[with(fair_share_config(&config_file, &[(DUMMY_DISTRIBUTION, 0)]))]
+    mut isolated_server: IsolatedServer,
+) {
+    let client = &isolated_server.client;
+    let _config_file = config_file;
+
+    register_worker(client).await;
+    import_multiple_packages(client).await;
+    client
+        .submit_package_report(&single_package_report_from_different_distribution())
+        .await
+        .unwrap();
+
+    let job = pick_up_job(client).await;
+    assert_eq!(job.job.distribution, DUMMY_OTHER_DISTRIBUTION);
+
+    let job = pick_up_job(client).await;
+    assert_eq!(job.job.distribution, DUMMY_DISTRIBUTION);
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn fair_share_can_give_releases_their_own_share(
+    config_file: ConfigFile,
+    #[with(fair_share_config(&config_file, &[("distribution/release", 0)]))]
+    mut isolated_server: IsolatedServer,
+) {
+    let client = &isolated_server.client;
+    let _config_file = config_file;
+
+    register_worker(client).await;
+    import_single_package(client).await;
+
+    // a build input that is shared with the first release would not be queued again
+    let mut report = single_package_report_from_different_release();
+    report.packages[0].url = format!("{}.other", report.packages[0].url);
+    client.submit_package_report(&report).await.unwrap();
+
+    let job = pick_up_job(client).await;
+    assert_eq!(job.job.release.as_deref(), Some(DUMMY_OTHER_RELEASE));
+
+    let job = pick_up_job(client).await;
+    assert_eq!(job.job.release.as_deref(), Some(DUMMY_RELEASE));
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn fair_share_keeps_priority_order(
+    config_file: ConfigFile,
+    #[with(fair_share_config(&config_file, &[(DUMMY_DISTRIBUTION, 0)]))]
+    mut isolated_server: IsolatedServer,
+) {
+    let client = &isolated_server.client;
+    let _config_file = config_file;
+
+    register_worker(client).await;
+    import_single_package(client).await;
+    client
+        .submit_package_report(&single_package_report_from_different_distribution())
+        .await
+        .unwrap();
+
+    client
+        .request_rebuild(QueueJobRequest {
+            distribution: Some(DUMMY_DISTRIBUTION.to_string()),
+            release: None,
+            component: None,
+            name: None,
+            version: None,
+            architecture: None,
+            status: None,
+            priority: Some(Priority::manual()),
+        })
+        .await
+        .unwrap();
+
+    let job = pick_up_job(client).await;
+    assert_eq!(job.job.distribution, DUMMY_DISTRIBUTION);
+
+    isolated_server.shutdown().await;
+}
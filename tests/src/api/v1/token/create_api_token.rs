@@ -0,0 +1,113 @@
+use crate::actions::*;
+use crate::fixtures::server::IsolatedServer;
+use crate::fixtures::*;
+use chrono::{Duration, SubsecRound, Utc};
+use rebuilderd_common::api::v1::{ApiTokenScope, CreateApiTokenRequest, TokenRestApi};
+use rebuilderd_common::config::ConfigFile;
+use rstest::rstest;
+
+#[rstest]
+#[tokio::test]
+pub async fn can_create_token(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    let expires_at = (Utc::now() + Duration::days(30))
+        .naive_utc()
+        .trunc_subsecs(0);
+    let created = create_api_token(
+        client,
+        "sync-timer",
+        &[ApiTokenScope::Sync, ApiTokenScope::QueueWrite],
+        Some(expires_at),
+    )
+    .await;
+
+    assert_eq!(created.token.name, "sync-timer");
+    assert_eq!(
+        created.token.scopes,
+        vec![ApiTokenScope::QueueWrite, ApiTokenScope::Sync]
+    );
+    assert_eq!(created.token.expires_at, Some(expires_at));
+    assert_eq!(created.token.last_used_at, None);
+    assert!(created.secret.starts_with("rebuilderd_"));
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn fails_if_name_is_taken(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    create_api_token(client, "sync-timer", &[ApiTokenScope::Sync], None).await;
+
+    let result = client
+        .create_api_token(CreateApiTokenRequest {
+            name: "sync-timer".to_string(),
+            scopes: vec![ApiTokenScope::QueueWrite],
+            expires_at: None,
+        })
+        .await;
+
+    assert!(result.is_err());
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn fails_without_scopes(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    let result = client
+        .create_api_token(CreateApiTokenRequest {
+            name: "nothing".to_string(),
+            scopes: vec![],
+            expires_at: None,
+        })
+        .await;
+
+    assert!(result.is_err());
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn fails_if_authenticated_with_token(
+    config_file: ConfigFile,
+    #[with(config_file.clone())] mut isolated_server: IsolatedServer,
+) {
+    let client = &isolated_server.client;
+
+    let created = create_api_token(
+        client,
+        "operator",
+        &[
+            ApiTokenScope::Sync,
+            ApiTokenScope::QueueWrite,
+            ApiTokenScope::WorkersAdmin,
+            ApiTokenScope::ReadPrivate,
+        ],
+        None,
+    )
+    .await;
+
+    // only the auth cookie can hand out tokens
+    let token_client = make_token_client(
+        config_file,
+        isolated_server.endpoint.clone(),
+        &created.secret,
+    );
+    let result = token_client
+        .create_api_token(CreateApiTokenRequest {
+            name: "escalated".to_string(),
+            scopes: vec![ApiTokenScope::Sync],
+            expires_at: None,
+        })
+        .await;
+
+    assert!(result.is_err());
+
+    isolated_server.shutdown().await;
+}
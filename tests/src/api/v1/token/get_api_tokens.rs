@@ -0,0 +1,64 @@
+use crate::actions::*;
+use crate::fixtures::server::IsolatedServer;
+use crate::fixtures::*;
+use rebuilderd_common::api::v1::{ApiTokenScope, TokenRestApi};
+use rebuilderd_common::config::ConfigFile;
+use rstest::rstest;
+
+#[rstest]
+#[tokio::test]
+pub async fn lists_tokens_without_secrets(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    create_api_token(client, "sync-timer", &[ApiTokenScope::Sync], None).await;
+    create_api_token(client, "monitoring", &[ApiTokenScope::ReadPrivate], None).await;
+
+    let tokens = client.get_api_tokens().await.unwrap();
+    let names = tokens.iter().map(|t| t.name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, vec!["sync-timer", "monitoring"]);
+
+    let json = serde_json::to_string(&tokens).unwrap();
+    assert!(!json.contains("rebuilderd_"));
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn tracks_last_use(
+    config_file: ConfigFile,
+    #[with(config_file.clone())] mut isolated_server: IsolatedServer,
+) {
+    let client = &isolated_server.client;
+
+    let created = create_api_token(client, "sync-timer", &[ApiTokenScope::Sync], None).await;
+    assert_eq!(client.get_api_tokens().await.unwrap()[0].last_used_at, None);
+
+    let token_client = make_token_client(
+        config_file,
+        isolated_server.endpoint.clone(),
+        &created.secret,
+    );
+    import_single_package(&token_client).await;
+
+    assert!(
+        client.get_api_tokens().await.unwrap()[0]
+            .last_used_at
+            .is_some()
+    );
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn fails_if_no_admin_authentication_is_provided(mut isolated_server: IsolatedServer) {
+    let client = &mut isolated_server.client;
+
+    client.auth_cookie("");
+    let result = client.get_api_tokens().await;
+
+    assert!(result.is_err());
+
+    isolated_server.shutdown().await;
+}
@@ -0,0 +1,4 @@
+mod create_api_token;
+mod get_api_tokens;
+mod revoke_api_token;
+mod scoped_access;
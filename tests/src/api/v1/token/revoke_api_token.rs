@@ -0,0 +1,46 @@
+use crate::actions::*;
+use crate::data::*;
+use crate::fixtures::server::IsolatedServer;
+use crate::fixtures::*;
+use rebuilderd_common::api::v1::{ApiTokenScope, PackageRestApi, TokenRestApi};
+use rebuilderd_common::config::ConfigFile;
+use rstest::rstest;
+
+#[rstest]
+#[tokio::test]
+pub async fn revoked_token_is_rejected(
+    config_file: ConfigFile,
+    #[with(config_file.clone())] mut isolated_server: IsolatedServer,
+) {
+    let client = &isolated_server.client;
+
+    let created = create_api_token(client, "sync-timer", &[ApiTokenScope::Sync], None).await;
+    let token_client = make_token_client(
+        config_file,
+        isolated_server.endpoint.clone(),
+        &created.secret,
+    );
+    import_single_package(&token_client).await;
+
+    client.revoke_api_token(created.token.id).await.unwrap();
+    assert!(client.get_api_tokens().await.unwrap().is_empty());
+
+    let result = token_client
+        .submit_package_report(&single_package_report())
+        .await;
+    assert!(result.is_err());
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn fails_if_token_does_not_exist(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    let result = client.revoke_api_token(9999).await;
+
+    assert!(result.is_err());
+
+    isolated_server.shutdown().await;
+}
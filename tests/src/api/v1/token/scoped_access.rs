@@ -0,0 +1,100 @@
+use crate::actions::*;
+use crate::data::*;
+use crate::fixtures::server::IsolatedServer;
+use crate::fixtures::*;
+use chrono::{Duration, Utc};
+use rebuilderd_common::api::v1::{ApiTokenScope, PackageRestApi, QueueRestApi, WorkerRestApi};
+use rebuilderd_common::config::ConfigFile;
+use rstest::rstest;
+
+#[rstest]
+#[tokio::test]
+pub async fn token_can_use_granted_scope(
+    config_file: ConfigFile,
+    #[with(config_file.clone())] mut isolated_server: IsolatedServer,
+) {
+    let client = &isolated_server.client;
+
+    import_single_package(client).await;
+
+    let created = create_api_token(client, "janitor", &[ApiTokenScope::QueueWrite], None).await;
+    let token_client = make_token_client(
+        config_file,
+        isolated_server.endpoint.clone(),
+        &created.secret,
+    );
+
+    token_client.drop_queued_job(1).await.unwrap();
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn token_is_limited_to_its_scopes(
+    config_file: ConfigFile,
+    #[with(config_file.clone())] mut isolated_server: IsolatedServer,
+) {
+    let client = &isolated_server.client;
+
+    let created = create_api_token(client, "janitor", &[ApiTokenScope::QueueWrite], None).await;
+    let token_client = make_token_client(
+        config_file,
+        isolated_server.endpoint.clone(),
+        &created.secret,
+    );
+
+    let result = token_client
+        .submit_package_report(&single_package_report())
+        .await;
+    assert!(result.is_err());
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn expired_token_is_rejected(
+    config_file: ConfigFile,
+    #[with(config_file.clone())] mut isolated_server: IsolatedServer,
+) {
+    let client = &isolated_server.client;
+
+    let expires_at = (Utc::now() - Duration::minutes(1)).naive_utc();
+    let created = create_api_token(client, "old", &[ApiTokenScope::Sync], Some(expires_at)).await;
+    let token_client = make_token_client(
+        config_file,
+        isolated_server.endpoint.clone(),
+        &created.secret,
+    );
+
+    let result = token_client
+        .submit_package_report(&single_package_report())
+        .await;
+    assert!(result.is_err());
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn workers_admin_can_unregister_workers(
+    config_file: ConfigFile,
+    #[with(config_file.clone())] mut isolated_server: IsolatedServer,
+) {
+    let client = &isolated_server.client;
+
+    register_worker(client).await;
+
+    let created = create_api_token(client, "operator", &[ApiTokenScope::WorkersAdmin], None).await;
+    let token_client = make_token_client(
+        config_file,
+        isolated_server.endpoint.clone(),
+        &created.secret,
+    );
+
+    token_client.unregister_worker(1).await.unwrap();
+    assert!(client.get_workers(None).await.unwrap().records.is_empty());
+
+    isolated_server.shutdown().await;
+}
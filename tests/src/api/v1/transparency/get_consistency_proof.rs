@@ -0,0 +1,93 @@
+use crate::actions::*;
+use crate::fixtures::server::IsolatedServer;
+use crate::fixtures::*;
+use rebuilderd_common::api::v1::{ConsistencyProofQuery, TransparencyRestApi};
+use rstest::rstest;
+
+#[rstest]
+#[tokio::test]
+pub async fn empty_log_is_consistent_with_itself(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    let proof = client
+        .get_consistency_proof(&ConsistencyProofQuery {
+            first: 0,
+            second: None,
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(proof.second, 0);
+    assert!(proof.proof.is_empty());
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn proof_verifies_between_tree_heads(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    register_worker(client).await;
+    import_multiple_packages(client).await;
+
+    report_good_rebuild_with_signed_attestation(client).await;
+    fetch_all_attestations(client).await;
+    let first = client.get_tree_head().await.unwrap();
+
+    report_good_rebuild_with_signed_attestation(client).await;
+    fetch_all_attestations(client).await;
+    let second = client.get_tree_head().await.unwrap();
+
+    assert!(first.tree_size < second.tree_size);
+
+    let proof = client
+        .get_consistency_proof(&ConsistencyProofQuery {
+            first: first.tree_size,
+            second: Some(second.tree_size),
+        })
+        .await
+        .unwrap();
+
+    assert!(
+        proof
+            .verify(&first.root_hash().unwrap(), &second.root_hash().unwrap())
+            .is_ok()
+    );
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn rejects_first_larger_than_second(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    let result = client
+        .get_consistency_proof(&ConsistencyProofQuery {
+            first: 1,
+            second: Some(0),
+        })
+        .await;
+
+    assert!(result.is_err());
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn rejects_second_larger_than_log(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    let result = client
+        .get_consistency_proof(&ConsistencyProofQuery {
+            first: 0,
+            second: Some(1),
+        })
+        .await;
+
+    assert!(result.is_err());
+
+    isolated_server.shutdown().await;
+}
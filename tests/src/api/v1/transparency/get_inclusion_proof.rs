@@ -0,0 +1,159 @@
+use crate::actions::*;
+use crate::fixtures::server::IsolatedServer;
+use crate::fixtures::*;
+use rebuilderd_common::api::v1::{InclusionProofQuery, TransparencyRestApi};
+use rebuilderd_common::transparency;
+use rstest::rstest;
+
+async fn setup_log(client: &rebuilderd_common::api::Client) -> Vec<Vec<u8>> {
+    register_worker(client).await;
+    import_multiple_packages(client).await;
+    report_good_rebuild_with_signed_attestation(client).await;
+    report_good_rebuild_with_signed_attestation(client).await;
+    fetch_all_attestations(client).await
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn returns_no_result_for_empty_database(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    let result = client
+        .get_inclusion_proof(&InclusionProofQuery {
+            leaf_index: Some(0),
+            ..Default::default()
+        })
+        .await;
+
+    assert!(result.is_err());
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn proof_by_index_verifies_against_tree_head(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    let attestations = setup_log(client).await;
+    let tree_head = client.get_tree_head().await.unwrap();
+    let root_hash = tree_head.root_hash().unwrap();
+
+    for (leaf_index, attestation) in attestations.iter().enumerate() {
+        let proof = client
+            .get_inclusion_proof(&InclusionProofQuery {
+                leaf_index: Some(leaf_index as u64),
+                tree_size: Some(tree_head.tree_size),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(
+            proof.leaf_hash,
+            transparency::hash_to_hex(&transparency::leaf_hash(attestation))
+        );
+        assert!(proof.verify(&root_hash).is_ok());
+    }
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn proof_by_hash_verifies_against_tree_head(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    let attestations = setup_log(client).await;
+    let tree_head = client.get_tree_head().await.unwrap();
+    let leaf_hash = transparency::hash_to_hex(&transparency::leaf_hash(&attestations[1]));
+
+    let proof = client
+        .get_inclusion_proof(&InclusionProofQuery {
+            leaf_hash: Some(leaf_hash),
+            tree_size: Some(tree_head.tree_size),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(proof.leaf_index, 1);
+    assert!(proof.verify(&tree_head.root_hash().unwrap()).is_ok());
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn proof_does_not_verify_against_other_root(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    setup_log(client).await;
+
+    let proof = client
+        .get_inclusion_proof(&InclusionProofQuery {
+            leaf_index: Some(0),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+    assert!(proof.verify(&transparency::leaf_hash(b"")).is_err());
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn returns_no_result_for_unknown_hash(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    setup_log(client).await;
+
+    let result = client
+        .get_inclusion_proof(&InclusionProofQuery {
+            leaf_hash: Some(transparency::hash_to_hex(&transparency::leaf_hash(b""))),
+            ..Default::default()
+        })
+        .await;
+
+    assert!(result.is_err());
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn requires_leaf_selector(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    setup_log(client).await;
+
+    let result = client
+        .get_inclusion_proof(&InclusionProofQuery::default())
+        .await;
+
+    assert!(result.is_err());
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn rejects_tree_size_larger_than_log(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    setup_log(client).await;
+
+    let result = client
+        .get_inclusion_proof(&InclusionProofQuery {
+            leaf_index: Some(0),
+            tree_size: Some(100),
+            ..Default::default()
+        })
+        .await;
+
+    assert!(result.is_err());
+
+    isolated_server.shutdown().await;
+}
@@ -0,0 +1,69 @@
+use crate::actions::*;
+use crate::fixtures::server::IsolatedServer;
+use crate::fixtures::*;
+use crate::setup;
+use rebuilderd_common::api::v1::TransparencyRestApi;
+use rebuilderd_common::transparency;
+use rstest::rstest;
+
+#[rstest]
+#[tokio::test]
+pub async fn returns_no_result_for_empty_database(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    let result = client.get_transparency_log_entry(0).await;
+
+    assert!(result.is_err());
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn entry_is_countersigned_attestation(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    setup::single_good_rebuild_with_signed_attestation(client).await;
+    let attestations = fetch_all_attestations(client).await;
+
+    let entry = client.get_transparency_log_entry(0).await.unwrap();
+
+    assert_eq!(entry, attestations[0]);
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn entry_matches_leaf_hash(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    setup::single_good_rebuild_with_signed_attestation(client).await;
+    fetch_all_attestations(client).await;
+
+    let entry = client.get_transparency_log_entry(0).await.unwrap();
+    let tree_head = client.get_tree_head().await.unwrap();
+
+    // with a single leaf, the root hash is the leaf hash
+    assert_eq!(
+        transparency::hash_to_hex(&transparency::leaf_hash(&entry)),
+        tree_head.root_hash
+    );
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn returns_no_result_for_index_past_end(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    setup::single_good_rebuild_with_signed_attestation(client).await;
+    fetch_all_attestations(client).await;
+
+    let result = client.get_transparency_log_entry(1).await;
+
+    assert!(result.is_err());
+
+    isolated_server.shutdown().await;
+}
@@ -0,0 +1,141 @@
+use crate::actions::*;
+use crate::fixtures::server::IsolatedServer;
+use crate::fixtures::*;
+use crate::setup;
+use rebuilderd_common::api::v1::{BuildRestApi, TransparencyRestApi};
+use rstest::rstest;
+use std::time::Duration;
+
+const EMPTY_ROOT_HASH: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+#[rstest]
+#[tokio::test]
+pub async fn returns_empty_tree_for_empty_database(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    let tree_head = client.get_tree_head().await.unwrap();
+
+    assert_eq!(tree_head.tree_size, 0);
+    assert_eq!(tree_head.root_hash, EMPTY_ROOT_HASH);
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn tree_head_is_signed_by_server(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    setup::single_good_rebuild_with_signed_attestation(client).await;
+    fetch_all_attestations(client).await;
+
+    let public_key = get_server_public_key(client).await;
+    let tree_head = client.get_tree_head().await.unwrap();
+
+    assert!(tree_head.verify(&public_key).is_ok());
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn tampered_tree_head_does_not_verify(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    setup::single_good_rebuild_with_signed_attestation(client).await;
+    fetch_all_attestations(client).await;
+
+    let public_key = get_server_public_key(client).await;
+    let mut tree_head = client.get_tree_head().await.unwrap();
+    tree_head.tree_size += 1;
+
+    assert!(tree_head.verify(&public_key).is_err());
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn grows_when_attestation_is_countersigned(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    setup::single_good_rebuild_with_signed_attestation(client).await;
+
+    // the attestation is only countersigned once it's fetched
+    assert_eq!(client.get_tree_head().await.unwrap().tree_size, 0);
+
+    fetch_all_attestations(client).await;
+
+    let tree_head = client.get_tree_head().await.unwrap();
+    assert_eq!(tree_head.tree_size, 1);
+    assert_ne!(tree_head.root_hash, EMPTY_ROOT_HASH);
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn does_not_grow_when_attestation_is_fetched_again(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    setup::single_good_rebuild_with_signed_attestation(client).await;
+    fetch_all_attestations(client).await;
+    fetch_all_attestations(client).await;
+
+    assert_eq!(client.get_tree_head().await.unwrap().tree_size, 1);
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn does_not_need_authentication(mut isolated_server: IsolatedServer) {
+    let client = &mut isolated_server.client;
+
+    // zero out keys
+    client.auth_cookie("");
+    client.worker_key("");
+    client.signup_secret("");
+
+    let result = client.get_tree_head().await;
+
+    assert!(result.is_ok());
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn grows_when_verdict_is_countersigned(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    register_worker(client).await;
+    import_single_package(client).await;
+    report_bad_rebuild_with_signed_verdict(client).await;
+
+    client.get_build_artifact_attestation(1, 1).await.unwrap();
+
+    let tree_head = client.get_tree_head().await.unwrap();
+    assert_eq!(tree_head.tree_size, 1);
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn is_only_signed_once_per_tree_size(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    setup::single_good_rebuild_with_signed_attestation(client).await;
+    fetch_all_attestations(client).await;
+
+    let first = client.get_tree_head().await.unwrap();
+    // the timestamp only has second precision
+    tokio::time::sleep(Duration::from_secs(1)).await;
+    let second = client.get_tree_head().await.unwrap();
+
+    assert_eq!(first.timestamp, second.timestamp);
+    assert_eq!(first.signature, second.signature);
+
+    isolated_server.shutdown().await;
+}
@@ -0,0 +1,4 @@
+mod get_consistency_proof;
+mod get_inclusion_proof;
+mod get_transparency_log_entry;
+mod get_tree_head;
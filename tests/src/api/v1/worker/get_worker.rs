@@ -1,7 +1,10 @@
 use crate::actions::*;
+use crate::data::*;
 use crate::fixtures::server::IsolatedServer;
 use crate::fixtures::*;
-use rebuilderd_common::api::v1::WorkerRestApi;
+use rebuilderd_common::api::v1::{
+    BuildRestApi, PingQueuedJobResponse, QueueRestApi, WorkerRestApi,
+};
 use rstest::rstest;
 
 #[rstest]
@@ -56,3 +59,48 @@ pub async fn does_not_need_authentication(mut isolated_server: IsolatedServer) {
 
     isolated_server.shutdown().await;
 }
+
+#[rstest]
+#[tokio::test]
+pub async fn status_lists_every_running_job(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    register_worker(client).await;
+    import_multiple_packages(client).await;
+    let first = pick_up_job(client).await;
+    let second = pick_up_job(client).await;
+    assert_ne!(first.lease, second.lease);
+
+    // a single worker can hold several jobs at the same time
+    assert_eq!(
+        client.ping_job(first.job.id, &first.lease).await.unwrap(),
+        PingQueuedJobResponse::Continue
+    );
+    assert_eq!(
+        client.ping_job(second.job.id, &second.lease).await.unwrap(),
+        PingQueuedJobResponse::Continue
+    );
+
+    let status = client.get_worker(1).await.unwrap().status.unwrap();
+    assert!(status.contains(&first.job.name));
+    assert!(status.contains(&second.job.name));
+
+    client
+        .submit_build_report(failed_rebuild_report(&first))
+        .await
+        .unwrap();
+
+    let status = client.get_worker(1).await.unwrap().status.unwrap();
+    assert!(!status.contains(&first.job.name));
+    assert!(status.contains(&second.job.name));
+
+    client
+        .submit_build_report(failed_rebuild_report(&second))
+        .await
+        .unwrap();
+
+    let worker = client.get_worker(1).await.unwrap();
+    assert_eq!(worker.status, None);
+
+    isolated_server.shutdown().await;
+}
@@ -1,7 +1,6 @@
 use crate::actions::*;
 use crate::fixtures::server::IsolatedServer;
 use crate::fixtures::*;
-use rand::distr::{Alphanumeric, SampleString};
 use rebuilderd_common::api::v1::WorkerRestApi;
 use rstest::rstest;
 
@@ -43,8 +42,7 @@ pub async fn returns_multiple_results_for_database_with_multiple_workers(
     register_worker(client).await;
 
     // create a new key for the new worker
-    let worker_key = Alphanumeric.sample_string(&mut rand::rng(), 32);
-    client.worker_key(worker_key);
+    use_new_worker_key(client);
     register_other_worker(client).await;
 
     let results = client.get_workers(None).await.unwrap().records;
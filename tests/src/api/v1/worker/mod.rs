@@ -1,4 +1,5 @@
 mod get_worker;
 mod get_workers;
 mod register_worker;
+mod set_worker_state;
 mod unregister_worker;
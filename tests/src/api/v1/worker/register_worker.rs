@@ -1,7 +1,11 @@
+use crate::actions::*;
 use crate::data::*;
 use crate::fixtures::server::IsolatedServer;
 use crate::fixtures::*;
+use data_encoding::BASE64;
+use rebuilderd_common::api::SIGNUP_SECRET_HEADER;
 use rebuilderd_common::api::v1::{RegisterWorkerRequest, WorkerRestApi};
+use rebuilderd_common::config::ConfigFile;
 use rstest::rstest;
 
 #[rstest]
@@ -19,6 +23,23 @@ pub async fn worker_can_sign_up(mut isolated_server: IsolatedServer) {
     isolated_server.shutdown().await;
 }
 
+#[rstest]
+#[tokio::test]
+pub async fn signing_up_again_keeps_status_of_running_jobs(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    register_worker(client).await;
+    import_single_package(client).await;
+    let job = pick_up_job(client).await;
+
+    register_worker(client).await;
+
+    let status = client.get_worker(1).await.unwrap().status.unwrap();
+    assert!(status.contains(&job.job.name));
+
+    isolated_server.shutdown().await;
+}
+
 #[rstest]
 #[tokio::test]
 pub async fn fails_if_no_signup_authentication_is_provided(mut isolated_server: IsolatedServer) {
@@ -36,3 +57,91 @@ pub async fn fails_if_no_signup_authentication_is_provided(mut isolated_server:
 
     isolated_server.shutdown().await;
 }
+
+#[rstest]
+#[tokio::test]
+pub async fn fails_if_request_is_not_signed(
+    config_file: ConfigFile,
+    #[with(config_file.clone())] mut isolated_server: IsolatedServer,
+) {
+    let mut client = make_unsigned_client(config_file, isolated_server.endpoint.clone());
+    client.worker_key(BASE64.encode(private_key().public().as_bytes()));
+
+    let result = client
+        .register_worker(RegisterWorkerRequest {
+            name: DUMMY_WORKER.to_string(),
+        })
+        .await;
+
+    assert!(result.is_err());
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn fails_if_request_is_signed_with_another_key(mut isolated_server: IsolatedServer) {
+    let client = &mut isolated_server.client;
+
+    client.worker_signer(signer(private_key()));
+    let result = client
+        .register_worker(RegisterWorkerRequest {
+            name: DUMMY_WORKER.to_string(),
+        })
+        .await;
+
+    assert!(result.is_err());
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn unsigned_worker_can_sign_up_if_allowed(
+    #[with(None, None, None, Some(true))] config_file: ConfigFile,
+    #[with(config_file.clone())] mut isolated_server: IsolatedServer,
+) {
+    let mut client = make_unsigned_client(config_file, isolated_server.endpoint.clone());
+    client.worker_key(BASE64.encode(private_key().public().as_bytes()));
+
+    client
+        .register_worker(RegisterWorkerRequest {
+            name: DUMMY_WORKER.to_string(),
+        })
+        .await
+        .unwrap();
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn nonce_is_only_unique_per_worker(
+    config_file: ConfigFile,
+    #[with(config_file.clone())] mut isolated_server: IsolatedServer,
+) {
+    let signup_secret = config_file.worker.signup_secret.unwrap();
+
+    let path = "/api/v1/workers";
+    let body = serde_json::to_vec(&RegisterWorkerRequest {
+        name: DUMMY_WORKER.to_string(),
+    })
+    .unwrap();
+
+    let http = rebuilderd_common::http::client().unwrap();
+    for private_key in [dummy_worker_private_key(), private_key()] {
+        let headers = worker_request_headers(&private_key, "POST", path, "", "nonce", &body);
+        let mut request = http
+            .post(format!("{}{path}", isolated_server.endpoint))
+            .header(SIGNUP_SECRET_HEADER, &signup_secret)
+            .header("Content-Type", "application/json")
+            .body(body.clone());
+        for (name, value) in &headers {
+            request = request.header(*name, value);
+        }
+        let response = request.send().await.unwrap();
+        assert_eq!(response.status().as_u16(), 204);
+    }
+
+    isolated_server.shutdown().await;
+}
@@ -0,0 +1,181 @@
+use crate::actions::*;
+use crate::data::*;
+use crate::fixtures::server::IsolatedServer;
+use crate::fixtures::*;
+use rebuilderd_common::api::v1::{
+    BuildRestApi, JobAssignment, QueueRestApi, RegisterWorkerRequest, WorkerRestApi, WorkerState,
+};
+use rstest::rstest;
+
+#[rstest]
+#[tokio::test]
+pub async fn draining_worker_finishes_current_job(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    register_worker(client).await;
+    import_multiple_packages(client).await;
+    let job = pick_up_job(client).await;
+
+    let worker = client
+        .set_worker_state(1, WorkerState::Draining)
+        .await
+        .unwrap();
+    assert_eq!(worker.state, WorkerState::Draining);
+
+    // the current job can still be finished
+    client.ping_job(job.job.id, &job.lease).await.unwrap();
+    client
+        .submit_build_report(good_rebuild_report(&job))
+        .await
+        .unwrap();
+
+    let assignment = client.request_work(job_request()).await.unwrap();
+    assert!(matches!(assignment, JobAssignment::Nothing));
+
+    let worker = client.get_worker(1).await.unwrap();
+    assert_eq!(worker.state, WorkerState::Paused);
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn draining_worker_finishes_all_current_jobs(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    register_worker(client).await;
+    import_multiple_packages(client).await;
+    let first = pick_up_job(client).await;
+    let second = pick_up_job(client).await;
+
+    client
+        .set_worker_state(1, WorkerState::Draining)
+        .await
+        .unwrap();
+
+    client
+        .submit_build_report(good_rebuild_report(&first))
+        .await
+        .unwrap();
+    let assignment = client.request_work(job_request()).await.unwrap();
+    assert!(matches!(assignment, JobAssignment::Nothing));
+
+    // the other job is still running
+    let worker = client.get_worker(1).await.unwrap();
+    assert_eq!(worker.state, WorkerState::Draining);
+
+    client
+        .submit_build_report(good_rebuild_report(&second))
+        .await
+        .unwrap();
+    let assignment = client.request_work(job_request()).await.unwrap();
+    assert!(matches!(assignment, JobAssignment::Nothing));
+
+    let worker = client.get_worker(1).await.unwrap();
+    assert_eq!(worker.state, WorkerState::Paused);
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn idle_worker_is_drained_immediately(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    register_worker(client).await;
+
+    let worker = client
+        .set_worker_state(1, WorkerState::Draining)
+        .await
+        .unwrap();
+    assert_eq!(worker.state, WorkerState::Paused);
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn paused_worker_gets_work_after_resume(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    register_worker(client).await;
+    import_single_package(client).await;
+
+    client
+        .set_worker_state(1, WorkerState::Paused)
+        .await
+        .unwrap();
+    let assignment = client.request_work(job_request()).await.unwrap();
+    assert!(matches!(assignment, JobAssignment::Nothing));
+
+    client
+        .set_worker_state(1, WorkerState::Active)
+        .await
+        .unwrap();
+    let assignment = client.request_work(job_request()).await.unwrap();
+    assert!(matches!(assignment, JobAssignment::Rebuild(_)));
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn disabled_worker_is_rejected(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    register_worker(client).await;
+    import_single_package(client).await;
+    let job = pick_up_job(client).await;
+
+    client
+        .set_worker_state(1, WorkerState::Disabled)
+        .await
+        .unwrap();
+
+    assert!(client.request_work(job_request()).await.is_err());
+    assert!(client.ping_job(job.job.id, &job.lease).await.is_err());
+
+    // signing up again doesn't bring it back
+    let result = client
+        .register_worker(RegisterWorkerRequest {
+            name: DUMMY_WORKER.to_string(),
+        })
+        .await;
+    assert!(result.is_err());
+
+    // the job is released for other workers
+    let queued = client.get_queued_job(job.job.id).await.unwrap();
+    assert_eq!(queued.started_at, None);
+
+    let worker = client.get_worker(1).await.unwrap();
+    assert_eq!(worker.status, None);
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn fails_if_worker_does_not_exist(mut isolated_server: IsolatedServer) {
+    let client = &isolated_server.client;
+
+    let result = client.set_worker_state(9999, WorkerState::Paused).await;
+
+    assert!(result.is_err());
+
+    isolated_server.shutdown().await;
+}
+
+#[rstest]
+#[tokio::test]
+pub async fn fails_if_no_admin_authentication_is_provided(mut isolated_server: IsolatedServer) {
+    let client = &mut isolated_server.client;
+
+    register_worker(client).await;
+
+    client.auth_cookie("");
+    let result = client.set_worker_state(1, WorkerState::Paused).await;
+
+    assert!(result.is_err());
+
+    isolated_server.shutdown().await;
+}
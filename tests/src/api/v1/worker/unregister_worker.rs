@@ -2,7 +2,6 @@ use crate::actions::*;
 use crate::data::*;
 use crate::fixtures::server::IsolatedServer;
 use crate::fixtures::*;
-use rand::distr::{Alphanumeric, SampleString};
 use rebuilderd_common::api::v1::WorkerRestApi;
 use rstest::rstest;
 
@@ -30,8 +29,7 @@ pub async fn does_not_unregister_other_workers(mut isolated_server: IsolatedServ
     register_worker(client).await;
 
     // create a new key for the new worker
-    let worker_key = Alphanumeric.sample_string(&mut rand::rng(), 32);
-    client.worker_key(worker_key);
+    use_new_worker_key(client);
     register_other_worker(client).await;
 
     client.unregister_worker(1).await.unwrap();
@@ -65,8 +63,9 @@ pub async fn fails_if_no_worker_authentication_is_provided(mut isolated_server:
 
     register_worker(client).await;
 
-    // zero out key
+    // zero out key, the auth cookie would be allowed to unregister workers too
     client.worker_key("");
+    client.auth_cookie("");
     let result = client.unregister_worker(1).await;
 
     assert!(result.is_err());
@@ -1,12 +1,28 @@
-use crate::data::{create_dummy_signed_attestation, create_dummy_unsigned_attestation};
+use crate::data::{
+    create_dummy_signed_attestation, create_dummy_signed_statement_attestation,
+    create_dummy_signed_verdict_attestation, create_dummy_unsigned_attestation,
+};
 use chrono::Utc;
 use rebuilderd_common::api::v1::{
-    ArtifactStatus, BuildStatus, QueuedJobWithArtifacts, RebuildArtifactReport, RebuildReport,
+    ArtifactStatus, BuildStatus, QueuedJobArtifact, QueuedJobWithArtifacts, RebuildArtifactReport,
+    RebuildReport,
 };
 use rebuilderd_common::utils::zstd_compress;
 
 pub const DUMMY_BUILD_LOG: &str = "build-log";
 pub const DUMMY_DIFFOSCOPE: &str = "diffoscope";
+// the digests of an empty file, which is what the dummy in-toto links are recorded over
+pub const DUMMY_UPSTREAM_SHA256: &str =
+    "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+pub const DUMMY_MISMATCHED_SHA256: &str =
+    "9df2f9a721f5016874c5f78ae88d3df77f9e49ea6070f935bfeeb438cd73a158";
+pub const DUMMY_UPSTREAM_SHA512: &str = "cf83e1357eefb8bdf1542850d66d8007d620e4050b5715dc83f4a921d36ce9ce47d0d13c5d85f2b0ff8318d2877eec2f63b931bd47417a81a538327af927da3e";
+pub const DUMMY_MISMATCHED_SHA512: &str = "fe15672891f00f25690526ef5f0ef27c9ae630f8fb7b6986ba91e2a0c4d255b692f486779d7abb6784c4439345812e3a967a7ed59f67488bd373288916ce314f";
+
+/// The filename the worker downloads an artifact to, its attestation's subject is named after it
+fn artifact_filename(artifact: &QueuedJobArtifact) -> &str {
+    artifact.url.rsplit_once("/").unwrap().1
+}
 
 pub fn bad_rebuild_report(job: &QueuedJobWithArtifacts) -> RebuildReport {
     let mut artifacts = Vec::new();
@@ -16,14 +32,20 @@ pub fn bad_rebuild_report(job: &QueuedJobWithArtifacts) -> RebuildReport {
             diffoscope: Some(DUMMY_DIFFOSCOPE.to_string().into_bytes()),
             status: ArtifactStatus::Bad,
             attestation: None,
+            upstream_sha256: Some(DUMMY_UPSTREAM_SHA256.to_string()),
+            rebuilt_sha256: Some(DUMMY_MISMATCHED_SHA256.to_string()),
+            upstream_sha512: Some(DUMMY_UPSTREAM_SHA512.to_string()),
+            rebuilt_sha512: Some(DUMMY_MISMATCHED_SHA512.to_string()),
         });
     }
 
     RebuildReport {
         queue_id: job.job.id,
+        lease: job.lease.clone(),
         built_at: Utc::now().naive_utc(),
         build_log: DUMMY_BUILD_LOG.to_string().into_bytes(),
         status: BuildStatus::Bad,
+        failure_reason: None,
         artifacts,
     }
 }
@@ -31,13 +53,85 @@ pub fn bad_rebuild_report(job: &QueuedJobWithArtifacts) -> RebuildReport {
 pub fn failed_rebuild_report(job: &QueuedJobWithArtifacts) -> RebuildReport {
     RebuildReport {
         queue_id: job.job.id,
+        lease: job.lease.clone(),
         built_at: Utc::now().naive_utc(),
         build_log: DUMMY_BUILD_LOG.to_string().into_bytes(),
         status: BuildStatus::Fail,
+        failure_reason: None,
         artifacts: vec![],
     }
 }
 
+pub async fn bad_rebuild_report_with_signed_verdict(job: &QueuedJobWithArtifacts) -> RebuildReport {
+    let input = job.job.url.rsplit_once("/").unwrap().1;
+
+    let mut artifacts = Vec::new();
+    for artifact in job.artifacts.clone() {
+        let attestation = create_dummy_signed_verdict_attestation(
+            input,
+            artifact_filename(&artifact),
+            BuildStatus::Bad,
+        );
+
+        artifacts.push(RebuildArtifactReport {
+            name: artifact.name.clone(),
+            diffoscope: Some(DUMMY_DIFFOSCOPE.to_string().into_bytes()),
+            status: ArtifactStatus::Bad,
+            attestation: Some(zstd_compress(attestation.as_bytes()).await.unwrap()),
+            upstream_sha256: Some(DUMMY_UPSTREAM_SHA256.to_string()),
+            rebuilt_sha256: Some(DUMMY_MISMATCHED_SHA256.to_string()),
+            upstream_sha512: Some(DUMMY_UPSTREAM_SHA512.to_string()),
+            rebuilt_sha512: Some(DUMMY_MISMATCHED_SHA512.to_string()),
+        });
+    }
+
+    RebuildReport {
+        queue_id: job.job.id,
+        lease: job.lease.clone(),
+        built_at: Utc::now().naive_utc(),
+        build_log: DUMMY_BUILD_LOG.to_string().into_bytes(),
+        status: BuildStatus::Bad,
+        failure_reason: None,
+        artifacts,
+    }
+}
+
+pub async fn failed_rebuild_report_with_signed_verdict(
+    job: &QueuedJobWithArtifacts,
+) -> RebuildReport {
+    let input = job.job.url.rsplit_once("/").unwrap().1;
+
+    let mut artifacts = Vec::new();
+    for artifact in job.artifacts.clone() {
+        let attestation = create_dummy_signed_verdict_attestation(
+            input,
+            artifact_filename(&artifact),
+            BuildStatus::Fail,
+        );
+
+        artifacts.push(RebuildArtifactReport {
+            name: artifact.name.clone(),
+            diffoscope: None,
+            status: ArtifactStatus::Unknown,
+            attestation: Some(zstd_compress(attestation.as_bytes()).await.unwrap()),
+            upstream_sha256: Some(DUMMY_UPSTREAM_SHA256.to_string()),
+            rebuilt_sha256: None,
+            upstream_sha512: Some(DUMMY_UPSTREAM_SHA512.to_string()),
+            rebuilt_sha512: None,
+        });
+    }
+
+    RebuildReport {
+        queue_id: job.job.id,
+        lease: job.lease.clone(),
+        built_at: Utc::now().naive_utc(),
+        build_log: DUMMY_BUILD_LOG.to_string().into_bytes(),
+        status: BuildStatus::Fail,
+        failure_reason: None,
+        artifacts,
+    }
+}
+
 pub fn good_rebuild_report(job: &QueuedJobWithArtifacts) -> RebuildReport {
     let mut artifacts = Vec::new();
     for artifact in job.artifacts.clone() {
@@ -46,14 +140,20 @@ pub fn good_rebuild_report(job: &QueuedJobWithArtifacts) -> RebuildReport {
             diffoscope: None,
             status: ArtifactStatus::Good,
             attestation: None,
+            upstream_sha256: Some(DUMMY_UPSTREAM_SHA256.to_string()),
+            rebuilt_sha256: Some(DUMMY_UPSTREAM_SHA256.to_string()),
+            upstream_sha512: Some(DUMMY_UPSTREAM_SHA512.to_string()),
+            rebuilt_sha512: Some(DUMMY_UPSTREAM_SHA512.to_string()),
         });
     }
 
     RebuildReport {
         queue_id: job.job.id,
+        lease: job.lease.clone(),
         built_at: Utc::now().naive_utc(),
         build_log: DUMMY_BUILD_LOG.to_string().into_bytes(),
         status: BuildStatus::Good,
+        failure_reason: None,
         artifacts,
     }
 }
@@ -65,21 +165,27 @@ pub async fn good_rebuild_report_with_signed_attestation(
 
     let mut artifacts = Vec::new();
     for artifact in job.artifacts.clone() {
-        let attestation = create_dummy_signed_attestation(input, &artifact.name);
+        let attestation = create_dummy_signed_attestation(input, artifact_filename(&artifact));
 
         artifacts.push(RebuildArtifactReport {
             name: artifact.name.clone(),
             diffoscope: None,
             status: ArtifactStatus::Good,
             attestation: Some(zstd_compress(attestation.as_bytes()).await.unwrap()),
+            upstream_sha256: Some(DUMMY_UPSTREAM_SHA256.to_string()),
+            rebuilt_sha256: Some(DUMMY_UPSTREAM_SHA256.to_string()),
+            upstream_sha512: Some(DUMMY_UPSTREAM_SHA512.to_string()),
+            rebuilt_sha512: Some(DUMMY_UPSTREAM_SHA512.to_string()),
         });
     }
 
     RebuildReport {
         queue_id: job.job.id,
+        lease: job.lease.clone(),
         built_at: Utc::now().naive_utc(),
         build_log: DUMMY_BUILD_LOG.to_string().into_bytes(),
         status: BuildStatus::Good,
+        failure_reason: None,
         artifacts,
     }
 }
@@ -91,21 +197,60 @@ pub async fn good_rebuild_report_with_unsigned_attestation(
 
     let mut artifacts = Vec::new();
     for artifact in job.artifacts.clone() {
-        let attestation = create_dummy_unsigned_attestation(input, &artifact.name);
+        let attestation = create_dummy_unsigned_attestation(input, artifact_filename(&artifact));
+
+        artifacts.push(RebuildArtifactReport {
+            name: artifact.name.clone(),
+            diffoscope: None,
+            status: ArtifactStatus::Good,
+            attestation: Some(zstd_compress(attestation.as_bytes()).await.unwrap()),
+            upstream_sha256: Some(DUMMY_UPSTREAM_SHA256.to_string()),
+            rebuilt_sha256: Some(DUMMY_UPSTREAM_SHA256.to_string()),
+            upstream_sha512: Some(DUMMY_UPSTREAM_SHA512.to_string()),
+            rebuilt_sha512: Some(DUMMY_UPSTREAM_SHA512.to_string()),
+        });
+    }
+
+    RebuildReport {
+        queue_id: job.job.id,
+        lease: job.lease.clone(),
+        built_at: Utc::now().naive_utc(),
+        build_log: DUMMY_BUILD_LOG.to_string().into_bytes(),
+        status: BuildStatus::Good,
+        failure_reason: None,
+        artifacts,
+    }
+}
+
+pub async fn good_rebuild_report_with_signed_statement_attestation(
+    job: &QueuedJobWithArtifacts,
+) -> RebuildReport {
+    let input = job.job.url.rsplit_once("/").unwrap().1;
+
+    let mut artifacts = Vec::new();
+    for artifact in job.artifacts.clone() {
+        let attestation =
+            create_dummy_signed_statement_attestation(input, artifact_filename(&artifact));
 
         artifacts.push(RebuildArtifactReport {
             name: artifact.name.clone(),
             diffoscope: None,
             status: ArtifactStatus::Good,
             attestation: Some(zstd_compress(attestation.as_bytes()).await.unwrap()),
+            upstream_sha256: Some(DUMMY_UPSTREAM_SHA256.to_string()),
+            rebuilt_sha256: Some(DUMMY_UPSTREAM_SHA256.to_string()),
+            upstream_sha512: Some(DUMMY_UPSTREAM_SHA512.to_string()),
+            rebuilt_sha512: Some(DUMMY_UPSTREAM_SHA512.to_string()),
         });
     }
 
     RebuildReport {
         queue_id: job.job.id,
+        lease: job.lease.clone(),
         built_at: Utc::now().naive_utc(),
         build_log: DUMMY_BUILD_LOG.to_string().into_bytes(),
         status: BuildStatus::Good,
+        failure_reason: None,
         artifacts,
     }
 }
@@ -6,5 +6,6 @@ pub fn job_request() -> PopQueuedJobRequest {
         supported_backends: vec![DUMMY_BACKEND.to_string()],
         architecture: DUMMY_ARCHITECTURE.to_string(),
         supported_architectures: vec![DUMMY_ARCHITECTURE.to_string()],
+        labels: Vec::new(),
     }
 }
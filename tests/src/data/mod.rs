@@ -3,11 +3,19 @@ mod job_requests;
 mod package_reports;
 
 pub use build_reports::*;
-use in_toto::crypto::{KeyType, PrivateKey, SignatureScheme};
+use data_encoding::BASE64;
+use in_toto::crypto::{PrivateKey, SignatureScheme};
 use in_toto::runlib::in_toto_run;
 pub use job_requests::*;
 pub use package_reports::*;
-use std::os::unix;
+use rebuilderd_common::api::v1::BuildStatus;
+use rebuilderd_common::dsse::{Envelope, IN_TOTO_PAYLOAD_TYPE};
+use rebuilderd_common::statement::{
+    REBUILD_PREDICATE_TYPE_V1, RebuildPredicate, ResourceDescriptor, Statement,
+    VERDICT_PREDICATE_TYPE_V1, VerdictPredicate,
+};
+use std::collections::BTreeMap;
+use std::fs;
 use tempfile::TempDir;
 
 pub const DUMMY_DISTRIBUTION: &str = "distribution";
@@ -25,20 +33,24 @@ pub const DUMMY_OTHER_BACKEND: &str = DUMMY_OTHER_DISTRIBUTION;
 pub const DUMMY_WORKER: &str = "worker";
 pub const DUMMY_OTHER_WORKER: &str = "other-worker";
 
+/// The key of the worker the default test client registers as, it also signs the dummy attestations.
+const DUMMY_WORKER_PRIVATE_KEY: &str = "MFECAQEwBQYDK2VwBCIEINOWEV/DNN+AsZ+pLoixusXNmgS5x0TNXvkLQUnKz92kgSEAB5ySaw+WE9Ut06fYlPf2V4+5gbFHA5HZJK7n2WWAGvA=";
+
+pub fn dummy_worker_private_key() -> PrivateKey {
+    let pkcs8 = BASE64.decode(DUMMY_WORKER_PRIVATE_KEY.as_bytes()).unwrap();
+    PrivateKey::from_pkcs8(&pkcs8, SignatureScheme::Ed25519).unwrap()
+}
+
 pub fn create_dummy_signed_attestation(input_name: &str, output_name: &str) -> String {
-    let private_key = PrivateKey::from_pkcs8(
-        PrivateKey::new(KeyType::Ed25519).unwrap().as_slice(),
-        SignatureScheme::Ed25519,
-    )
-    .unwrap();
+    let private_key = dummy_worker_private_key();
 
     let temp_dir = TempDir::new().unwrap();
 
     let input_path = temp_dir.path().join(input_name);
     let output_path = temp_dir.path().join(output_name);
 
-    unix::fs::symlink("/dev/null", &input_path).unwrap();
-    unix::fs::symlink("/dev/null", &output_path).unwrap();
+    fs::write(&input_path, b"").unwrap();
+    fs::write(&output_path, b"").unwrap();
 
     let signature = in_toto_run(
         &format!("rebuild {}", output_name),
@@ -48,7 +60,7 @@ pub fn create_dummy_signed_attestation(input_name: &str, output_name: &str) -> S
         &[],
         Some(&private_key),
         Some(&["sha256", "sha512"]),
-        Some(&[temp_dir.path().to_str().unwrap()]),
+        Some(&[&format!("{}/", temp_dir.path().to_str().unwrap())]),
     )
     .unwrap();
 
@@ -61,8 +73,8 @@ pub fn create_dummy_unsigned_attestation(input_name: &str, output_name: &str) ->
     let input_path = temp_dir.path().join(input_name);
     let output_path = temp_dir.path().join(output_name);
 
-    unix::fs::symlink("/dev/null", &input_path).unwrap();
-    unix::fs::symlink("/dev/null", &output_path).unwrap();
+    fs::write(&input_path, b"").unwrap();
+    fs::write(&output_path, b"").unwrap();
 
     let signature = in_toto_run(
         &format!("rebuild {}", output_name),
@@ -72,9 +84,84 @@ pub fn create_dummy_unsigned_attestation(input_name: &str, output_name: &str) ->
         &[],
         None,
         Some(&["sha256", "sha512"]),
-        Some(&[temp_dir.path().to_str().unwrap()]),
+        Some(&[&format!("{}/", temp_dir.path().to_str().unwrap())]),
     )
     .unwrap();
 
     serde_json::to_string(&signature).unwrap()
 }
+
+/// Describes the upstream artifact, which is also the subject of the dummy statements.
+fn dummy_upstream(output_name: &str) -> ResourceDescriptor {
+    ResourceDescriptor {
+        name: Some(output_name.to_string()),
+        uri: None,
+        digest: BTreeMap::from([
+            ("sha256".to_string(), DUMMY_UPSTREAM_SHA256.to_string()),
+            ("sha512".to_string(), DUMMY_UPSTREAM_SHA512.to_string()),
+        ]),
+    }
+}
+
+fn dummy_build_input(input_name: &str) -> ResourceDescriptor {
+    ResourceDescriptor {
+        name: Some(input_name.to_string()),
+        ..Default::default()
+    }
+}
+
+/// Wraps the statement in a DSSE envelope signed with the dummy worker key.
+fn sign_dummy_statement(statement: &Statement) -> String {
+    let payload = serde_json::to_vec(statement).unwrap();
+    let mut envelope = Envelope::new(IN_TOTO_PAYLOAD_TYPE, &payload);
+    envelope.sign(&dummy_worker_private_key()).unwrap();
+
+    serde_json::to_string(&envelope).unwrap()
+}
+
+pub fn create_dummy_signed_statement_attestation(input_name: &str, output_name: &str) -> String {
+    let statement = Statement::new(
+        vec![dummy_upstream(output_name)],
+        REBUILD_PREDICATE_TYPE_V1,
+        &RebuildPredicate {
+            build_input: dummy_build_input(input_name),
+            upstream: dummy_upstream(output_name),
+            backend: DUMMY_BACKEND.to_string(),
+            worker: DUMMY_WORKER.to_string(),
+        },
+    )
+    .unwrap();
+
+    sign_dummy_statement(&statement)
+}
+
+pub fn create_dummy_signed_verdict_attestation(
+    input_name: &str,
+    output_name: &str,
+    status: BuildStatus,
+) -> String {
+    let rebuilt = (status == BuildStatus::Bad).then(|| ResourceDescriptor {
+        name: None,
+        uri: None,
+        digest: BTreeMap::from([
+            ("sha256".to_string(), DUMMY_MISMATCHED_SHA256.to_string()),
+            ("sha512".to_string(), DUMMY_MISMATCHED_SHA512.to_string()),
+        ]),
+    });
+
+    let statement = Statement::new(
+        vec![dummy_upstream(output_name)],
+        VERDICT_PREDICATE_TYPE_V1,
+        &VerdictPredicate {
+            status,
+            build_input: dummy_build_input(input_name),
+            upstream: dummy_upstream(output_name),
+            rebuilt,
+            backend: DUMMY_BACKEND.to_string(),
+            worker: DUMMY_WORKER.to_string(),
+        },
+    )
+    .unwrap();
+
+    sign_dummy_statement(&statement)
+}
@@ -24,6 +24,7 @@ pub fn single_package_report() -> PackageReport {
                 architecture: DUMMY_ARCHITECTURE.to_string(),
                 url: DUMMY_BINARY_PACKAGE_URL.to_string(),
             }],
+            required_labels: None,
         }],
     }
 }
@@ -65,6 +66,7 @@ pub fn single_package_with_multiple_artifacts_report() -> PackageReport {
                     url: DUMMY_MULTI_ARTIFACT_BINARY_PACKAGE_2_URL.to_string(),
                 },
             ],
+            required_labels: None,
         }],
     }
 }
@@ -136,6 +138,7 @@ pub fn multiple_package_report() -> PackageReport {
                     architecture: DUMMY_ARCHITECTURE.to_string(),
                     url: DUMMY_BINARY_PACKAGE_URL.to_string(),
                 }],
+                required_labels: None,
             },
             SourcePackageReport {
                 name: DUMMY_MULTI_ARTIFACT_SOURCE_PACKAGE.to_string(),
@@ -157,6 +160,7 @@ pub fn multiple_package_report() -> PackageReport {
                         url: DUMMY_MULTI_ARTIFACT_BINARY_PACKAGE_2_URL.to_string(),
                     },
                 ],
+                required_labels: None,
             },
         ],
     }
@@ -1,12 +1,18 @@
 pub mod server;
 
 use crate::args::Args;
+use crate::data::dummy_worker_private_key;
 use crate::fixtures::server::{IsolatedServer, ServerHolder};
+use chrono::Utc;
 use clap::Parser;
+use data_encoding::BASE64;
 use in_toto::crypto::{KeyType, PrivateKey, SignatureScheme};
 use rand::distr::{Alphanumeric, SampleString};
 use rebuilderd::db;
-use rebuilderd_common::api::Client;
+use rebuilderd_common::api::{
+    Client, RequestSigner, WORKER_KEY_HEADER, WORKER_NONCE_HEADER, WORKER_SIGNATURE_HEADER,
+    WORKER_TIMESTAMP_HEADER, worker_request_message,
+};
 use rebuilderd_common::config::{ConfigFile, EndpointConfig};
 use rebuilderd_common::errors::info;
 use rstest::fixture;
@@ -22,6 +28,9 @@ pub fn config_file(
     #[default(None)] retry_delay_base: Option<i64>,
     #[default(None)] max_retries: Option<i32>,
     #[default(None)] initial_delay: Option<i64>,
+    #[default(None)] allow_unsigned_requests: Option<bool>,
+    #[default(None)] transparently_sign_attestations: Option<bool>,
+    #[default(None)] max_log_bytes: Option<usize>,
     program_arguments: Args,
 ) -> ConfigFile {
     let mut config = ConfigFile::default();
@@ -52,6 +61,10 @@ pub fn config_file(
     config.schedule.max_retries = max_retries;
     config.schedule.initial_delay = initial_delay;
 
+    config.worker.allow_unsigned_requests = allow_unsigned_requests;
+    config.worker.max_log_bytes = max_log_bytes;
+    config.http.transparently_sign_attestations = transparently_sign_attestations;
+
     config
 }
 
@@ -63,7 +76,75 @@ pub fn private_key() -> PrivateKey {
         .expect("Failed to use generated private key")
 }
 
-fn make_client(config_file: ConfigFile, endpoint: String) -> Client {
+pub fn signer(private_key: PrivateKey) -> impl RequestSigner {
+    move |message: &[u8]| Ok(private_key.sign(message)?.value().as_bytes().to_vec())
+}
+
+/// Uses the given worker key for the client and signs all further worker requests with it. Returns the public key.
+pub fn use_worker_key(client: &mut Client, private_key: PrivateKey) -> String {
+    let worker_key = BASE64.encode(private_key.public().as_bytes());
+
+    client.worker_key(worker_key.clone());
+    client.worker_signer(signer(private_key));
+
+    worker_key
+}
+
+/// Signs a worker request with the dummy worker key by hand and returns the headers the client would send with it, so
+/// the very same request can be sent more than once.
+pub fn dummy_worker_request_headers(
+    method: &str,
+    path: &str,
+    query: &str,
+    body: &[u8],
+) -> Vec<(&'static str, String)> {
+    let nonce = Alphanumeric.sample_string(&mut rand::rng(), 32);
+    worker_request_headers(
+        &dummy_worker_private_key(),
+        method,
+        path,
+        query,
+        &nonce,
+        body,
+    )
+}
+
+/// Signs a worker request with the given key and nonce by hand and returns the headers the client would send with it
+pub fn worker_request_headers(
+    private_key: &PrivateKey,
+    method: &str,
+    path: &str,
+    query: &str,
+    nonce: &str,
+    body: &[u8],
+) -> Vec<(&'static str, String)> {
+    let timestamp = Utc::now().timestamp();
+
+    let message = worker_request_message(method, path, query, timestamp, nonce, body);
+    let signature = private_key.sign(&message).unwrap();
+
+    vec![
+        (
+            WORKER_KEY_HEADER,
+            BASE64.encode(private_key.public().as_bytes()),
+        ),
+        (WORKER_TIMESTAMP_HEADER, timestamp.to_string()),
+        (WORKER_NONCE_HEADER, nonce.to_string()),
+        (
+            WORKER_SIGNATURE_HEADER,
+            BASE64.encode(signature.value().as_bytes()),
+        ),
+    ]
+}
+
+/// Generates a new worker key for the client and signs all further worker requests with it. Returns the public key.
+pub fn use_new_worker_key(client: &mut Client) -> String {
+    use_worker_key(client, private_key())
+}
+
+/// Creates a client that doesn't sign its requests and has no worker key set, like workers from before request
+/// signing was introduced.
+pub fn make_unsigned_client(config_file: ConfigFile, endpoint: String) -> Client {
     info!("Setting up client for {:?}", endpoint);
     let mut client = Client::new(config_file.clone(), Some(endpoint)).unwrap();
 
@@ -71,9 +152,20 @@ fn make_client(config_file: ConfigFile, endpoint: String) -> Client {
     client.auth_cookie(config_file.auth.cookie.unwrap());
     client.signup_secret(config_file.worker.signup_secret.unwrap());
 
-    let worker_key = Alphanumeric.sample_string(&mut rand::rng(), 32);
-    client.worker_key(worker_key);
+    client
+}
+
+/// Creates a client that authenticates with an api token instead of the auth cookie.
+pub fn make_token_client(config_file: ConfigFile, endpoint: String, secret: &str) -> Client {
+    let mut client = make_unsigned_client(config_file, endpoint);
+    client.auth_cookie(secret);
+    client
+}
 
+fn make_client(config_file: ConfigFile, endpoint: String) -> Client {
+    let mut client = make_unsigned_client(config_file, endpoint);
+    // the dummy attestations are signed with this key too
+    use_worker_key(&mut client, dummy_worker_private_key());
     client
 }
 
@@ -110,7 +202,7 @@ pub fn isolated_server(
         (None, None, endpoint)
     };
 
-    let client = make_client(config_file, endpoint);
+    let client = make_client(config_file, endpoint.clone());
 
-    IsolatedServer::new(server, tmp_dir, public_key, client)
+    IsolatedServer::new(server, tmp_dir, endpoint, public_key, client)
 }
@@ -2,6 +2,7 @@ use actix_web::dev::{Server, ServerHandle};
 use in_toto::crypto::{PrivateKey, PublicKey};
 use rebuilderd::config::Config;
 use rebuilderd::db::Pool;
+use rebuilderd::keyring::KeyRing;
 use rebuilderd_common::api::Client;
 use rebuilderd_common::errors::bail;
 use std::io;
@@ -24,7 +25,7 @@ impl ServerHolder {
         config: Config,
         private_key: PrivateKey,
     ) -> rebuilderd_common::errors::Result<Self> {
-        let (server, address) = rebuilderd::build_server(pool, config, private_key)?;
+        let (server, address) = rebuilderd::build_server(pool, config, KeyRing::new(private_key))?;
 
         Ok(Self {
             server: Some(server),
@@ -76,6 +77,7 @@ impl Drop for ServerHolder {
 pub struct IsolatedServer {
     server: Option<ServerHolder>,
     pub _tmp_dir: Option<TempDir>,
+    pub endpoint: String,
     pub public_key: PublicKey,
     pub client: Client,
 }
@@ -84,12 +86,14 @@ impl IsolatedServer {
     pub fn new(
         server: Option<ServerHolder>,
         tmp_dir: Option<TempDir>,
+        endpoint: String,
         public_key: PublicKey,
         client: Client,
     ) -> Self {
         Self {
             server,
             _tmp_dir: tmp_dir,
+            endpoint,
             public_key,
             client,
         }
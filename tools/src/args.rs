@@ -37,6 +37,15 @@ pub enum SubCommand {
     /// Queue related subcommands
     #[command(subcommand)]
     Queue(Queue),
+    /// Take workers out of rotation and bring them back
+    #[command(subcommand)]
+    Workers(Workers),
+    /// Verify local package files with attestations from the configured rebuilders
+    Verify(Verify),
+    /// Check the packages installed on this system against the rebuilder
+    AuditSystem(AuditSystem),
+    /// Generate an in-toto layout that requires artifacts to be reproduced by multiple rebuilders
+    Layout(Layout),
     /// Generate shell completions
     Completions(Completions),
 }
@@ -55,8 +64,10 @@ pub enum Pkgs {
     Log(PkgsLog),
     /// Access the diffoscope of the last rebuild (if there is any)
     Diffoscope(PkgsDiffoscope),
-    /// Access the attestation of the last rebuild (if there is any)
+    /// Access the attestation of the last rebuild (if there is any), or a bundle of all of them
     Attestation(PkgsAttestation),
+    /// Set the labels a worker needs to be assigned a package
+    Labels(PkgsLabels),
 }
 
 #[derive(Debug, Parser)]
@@ -106,6 +117,59 @@ pub struct PkgsSync {
     pub sync_method: Option<String>,
 }
 
+#[derive(Debug, Parser)]
+pub struct Verify {
+    /// The package files to verify
+    #[arg(required = true)]
+    pub files: Vec<PathBuf>,
+    /// How many rebuilders need to agree, overrides the threshold from the config file
+    #[arg(short, long)]
+    pub threshold: Option<usize>,
+}
+
+#[derive(Debug, Parser)]
+pub struct AuditSystem {
+    /// Read installed packages from a pacman database directory, like /var/lib/pacman/local
+    #[arg(long, value_name = "DIR", conflicts_with_all = ["dpkg", "rpm"])]
+    pub pacman: Option<PathBuf>,
+    /// Read installed packages from a dpkg status file, like /var/lib/dpkg/status
+    #[arg(long, value_name = "FILE", conflicts_with = "rpm")]
+    pub dpkg: Option<PathBuf>,
+    /// Read installed packages from the output of `rpm -qa --qf '%{NAME} %{VERSION}-%{RELEASE} %{ARCH}\n'`
+    #[arg(long, value_name = "FILE")]
+    pub rpm: Option<PathBuf>,
+    /// The distribution name used by the rebuilder, derived from the package database by default
+    #[arg(long)]
+    pub distro: Option<String>,
+    /// Print the report as json
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Debug, Parser)]
+pub struct Layout {
+    /// The distribution whose artifacts should be covered by the layout
+    pub distro: String,
+    /// Only include artifacts from this suite
+    #[arg(long)]
+    pub suite: Option<String>,
+    /// Only include artifacts with this architecture
+    #[arg(long)]
+    pub architecture: Option<String>,
+    /// Only include artifacts of packages with this name
+    #[arg(long)]
+    pub name: Option<String>,
+    /// The rebuilders to trust, defaults to the rebuilders from the config file
+    #[arg(long = "rebuilder", value_name = "ENDPOINT")]
+    pub rebuilders: Vec<String>,
+    /// How many rebuilders need to agree, defaults to the threshold in the config file or all rebuilders
+    #[arg(short, long)]
+    pub threshold: Option<usize>,
+    /// After how many days the layout expires
+    #[arg(long, value_name = "DAYS", default_value = "365")]
+    pub expires: i64,
+}
+
 #[derive(Debug, Parser)]
 pub struct PkgsFilter {
     /// Filter packages matching this name
@@ -161,6 +225,28 @@ pub struct PkgsDiffoscope {
 pub struct PkgsAttestation {
     #[command(flatten)]
     pub filter: PkgsFilter,
+    /// Export the attestations of all GOOD packages matching --distro, --release, --suite and --architecture as one
+    /// bundle
+    #[arg(long, conflicts_with_all = ["name", "status"])]
+    pub all: bool,
+    /// Only export the attestations of packages in this release
+    #[arg(long, requires = "all")]
+    pub release: Option<String>,
+}
+
+#[derive(Debug, Parser)]
+pub struct PkgsLabels {
+    pub distro: String,
+    pub component: String,
+
+    pub name: String,
+    pub version: Option<String>,
+
+    #[arg(long)]
+    pub architecture: Option<String>,
+    /// A label the worker needs to advertise, can be used multiple times. Without any the requirement is removed
+    #[arg(long = "label")]
+    pub labels: Vec<String>,
 }
 
 #[derive(Debug, Parser)]
@@ -172,6 +258,8 @@ pub enum Queue {
     /// Drop packages from queue matching given filter
     #[command(name = "drop")]
     Delete(QueueDrop),
+    /// Show the build log of a running job
+    Log(QueueLog),
 }
 
 #[derive(Debug, Parser)]
@@ -209,6 +297,33 @@ pub struct QueueDrop {
     pub version: Option<String>,
 }
 
+#[derive(Debug, Parser)]
+pub struct QueueLog {
+    /// The id of the queued job, as shown by `queue ls`
+    pub id: i32,
+    /// Keep printing new output until the build has finished
+    #[arg(short, long)]
+    pub follow: bool,
+}
+
+#[derive(Debug, Parser)]
+pub enum Workers {
+    /// Let the worker finish its current job, then stop assigning it new ones
+    Drain(WorkerSelect),
+    /// Stop assigning new jobs to the worker
+    Pause(WorkerSelect),
+    /// Start assigning jobs to the worker again
+    Resume(WorkerSelect),
+    /// Reject all requests of the worker, its current job is handed to another worker
+    Disable(WorkerSelect),
+}
+
+#[derive(Debug, Parser)]
+pub struct WorkerSelect {
+    /// The id or name of the worker, as shown by `status`
+    pub worker: String,
+}
+
 #[derive(Debug, Parser)]
 pub struct Completions {
     pub shell: Shell,
@@ -0,0 +1,503 @@
+use crate::args::AuditSystem;
+use colored::*;
+use futures_util::{StreamExt, TryStreamExt, stream};
+use rebuilderd_common::api::Client;
+use rebuilderd_common::api::v1::{
+    ArtifactStatus, BinaryIdentityFilter, BinaryPackage, OriginFilter, PackageRestApi,
+};
+use rebuilderd_common::errors::*;
+use serde::Serialize;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::iter;
+use std::path::{Path, PathBuf};
+
+const PACMAN_LOCAL_DB: &str = "/var/lib/pacman/local";
+const DPKG_STATUS: &str = "/var/lib/dpkg/status";
+/// How many packages are looked up on the rebuilder at the same time
+const LOOKUP_CONCURRENCY: usize = 8;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct InstalledPackage {
+    pub name: String,
+    pub version: String,
+    pub architecture: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditStatus {
+    Unreproduced,
+    /// Some rebuilds have reproduced the package and others haven't
+    Conflicting,
+    Unknown,
+    Verified,
+}
+
+impl AuditStatus {
+    fn fancy(&self) -> ColoredString {
+        match self {
+            AuditStatus::Unreproduced => "UNREPRODUCED".red(),
+            AuditStatus::Conflicting => "CONFLICTING".magenta(),
+            AuditStatus::Unknown => "UNKNOWN".yellow(),
+            AuditStatus::Verified => "VERIFIED".green(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuditEntry {
+    #[serde(flatten)]
+    pub package: InstalledPackage,
+    pub status: AuditStatus,
+    pub build_id: Option<i32>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum PackageDatabase {
+    Pacman(PathBuf),
+    Dpkg(PathBuf),
+    Rpm(PathBuf),
+}
+
+impl PackageDatabase {
+    pub fn from_args(args: &AuditSystem) -> Result<Self> {
+        if let Some(path) = &args.pacman {
+            Ok(PackageDatabase::Pacman(path.clone()))
+        } else if let Some(path) = &args.dpkg {
+            Ok(PackageDatabase::Dpkg(path.clone()))
+        } else if let Some(path) = &args.rpm {
+            Ok(PackageDatabase::Rpm(path.clone()))
+        } else if Path::new(PACMAN_LOCAL_DB).is_dir() {
+            Ok(PackageDatabase::Pacman(PathBuf::from(PACMAN_LOCAL_DB)))
+        } else if Path::new(DPKG_STATUS).is_file() {
+            Ok(PackageDatabase::Dpkg(PathBuf::from(DPKG_STATUS)))
+        } else {
+            bail!("Failed to detect package database, use --pacman, --dpkg or --rpm")
+        }
+    }
+
+    /// The distribution name rebuilderd uses for packages from this database
+    pub fn distribution(&self) -> &'static str {
+        match self {
+            PackageDatabase::Pacman(_) => "archlinux",
+            PackageDatabase::Dpkg(_) => "debian",
+            PackageDatabase::Rpm(_) => "fedora",
+        }
+    }
+
+    pub fn read(&self) -> Result<Vec<InstalledPackage>> {
+        let mut pkgs = match self {
+            PackageDatabase::Pacman(path) => read_pacman_db(path),
+            PackageDatabase::Dpkg(path) => read_dpkg_status(path),
+            PackageDatabase::Rpm(path) => read_rpm_export(path),
+        }?;
+        pkgs.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(pkgs)
+    }
+}
+
+/// Reads the `desc` file of every package in a pacman database directory like /var/lib/pacman/local
+pub fn read_pacman_db(path: &Path) -> Result<Vec<InstalledPackage>> {
+    let mut pkgs = Vec::new();
+    for entry in fs::read_dir(path).with_context(|| anyhow!("Failed to read {:?}", path))? {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+
+        let desc = entry.path().join("desc");
+        let content =
+            fs::read_to_string(&desc).with_context(|| anyhow!("Failed to read {:?}", desc))?;
+        let pkg = parse_pacman_desc(&content).with_context(|| anyhow!("Invalid {:?}", desc))?;
+        pkgs.push(pkg);
+    }
+    Ok(pkgs)
+}
+
+fn parse_pacman_desc(content: &str) -> Result<InstalledPackage> {
+    let mut name = None;
+    let mut version = None;
+    let mut architecture = None;
+
+    let mut iter = content.split('\n');
+    while let Some(key) = iter.next() {
+        let mut values = Vec::new();
+        for value in &mut iter {
+            if !value.is_empty() {
+                values.push(value.to_string());
+            } else {
+                break;
+            }
+        }
+
+        match key {
+            "%NAME%" => name = values.into_iter().next(),
+            "%VERSION%" => version = values.into_iter().next(),
+            "%ARCH%" => architecture = values.into_iter().next(),
+            _ => (),
+        }
+    }
+
+    Ok(InstalledPackage {
+        name: name.context("Missing name field")?,
+        version: version.context("Missing version field")?,
+        architecture: architecture.context("Missing architecture field")?,
+    })
+}
+
+/// Reads a dpkg status file like /var/lib/dpkg/status, packages that are not fully installed are skipped
+pub fn read_dpkg_status(path: &Path) -> Result<Vec<InstalledPackage>> {
+    let file = fs::File::open(path).with_context(|| anyhow!("Failed to open {:?}", path))?;
+    parse_dpkg_status(BufReader::new(file))
+}
+
+fn parse_dpkg_status<R: BufRead>(r: R) -> Result<Vec<InstalledPackage>> {
+    let mut pkgs = Vec::new();
+    let mut pkg = NewDpkgPkg::default();
+
+    // an empty line at the end makes sure the last paragraph is processed too
+    for line in r.lines().chain(iter::once(Ok(String::new()))) {
+        let line = line?;
+        if line.is_empty() {
+            if let Some(installed) = pkg.try_into_installed()? {
+                pkgs.push(installed);
+            }
+            pkg = NewDpkgPkg::default();
+        } else if let Some((key, value)) = line.split_once(": ") {
+            match key {
+                "Package" => pkg.name = Some(value.to_string()),
+                "Version" => pkg.version = Some(value.to_string()),
+                "Architecture" => pkg.architecture = Some(value.to_string()),
+                "Status" => pkg.status = Some(value.to_string()),
+                _ => (),
+            }
+        }
+    }
+
+    Ok(pkgs)
+}
+
+#[derive(Debug, Default)]
+struct NewDpkgPkg {
+    name: Option<String>,
+    version: Option<String>,
+    architecture: Option<String>,
+    status: Option<String>,
+}
+
+impl NewDpkgPkg {
+    /// Returns None for empty paragraphs and packages that are not fully installed, like removed packages with
+    /// configuration files left behind
+    fn try_into_installed(self) -> Result<Option<InstalledPackage>> {
+        let Some(name) = self.name else {
+            return Ok(None);
+        };
+        if self.status.as_deref() != Some("install ok installed") {
+            return Ok(None);
+        }
+        Ok(Some(InstalledPackage {
+            version: self
+                .version
+                .with_context(|| anyhow!("Missing version field for {:?}", name))?,
+            architecture: self
+                .architecture
+                .with_context(|| anyhow!("Missing architecture field for {:?}", name))?,
+            name,
+        }))
+    }
+}
+
+/// Reads a list of installed packages exported with
+/// `rpm -qa --qf '%{NAME} %{VERSION}-%{RELEASE} %{ARCH}\n'`
+pub fn read_rpm_export(path: &Path) -> Result<Vec<InstalledPackage>> {
+    let content = fs::read_to_string(path).with_context(|| anyhow!("Failed to read {:?}", path))?;
+    parse_rpm_export(&content)
+}
+
+fn parse_rpm_export(content: &str) -> Result<Vec<InstalledPackage>> {
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let mut fields = line.split_whitespace();
+            match (fields.next(), fields.next(), fields.next(), fields.next()) {
+                (Some(name), Some(version), Some(architecture), None) => Ok(InstalledPackage {
+                    name: name.to_string(),
+                    version: version.to_string(),
+                    architecture: architecture.to_string(),
+                }),
+                _ => bail!(
+                    "Expected `<name> <version>-<release> <arch>`, got {:?}",
+                    line
+                ),
+            }
+        })
+        .collect()
+}
+
+/// Decides on the status of an installed package, based on what the rebuilder knows about this name and version.
+/// If rebuilds disagree, the build id of one that failed to reproduce the package is returned for investigation.
+pub fn classify(pkg: &InstalledPackage, records: &[BinaryPackage]) -> (AuditStatus, Option<i32>) {
+    let mut good = None;
+    let mut bad = None;
+    for record in records {
+        if record.name != pkg.name
+            || record.version != pkg.version
+            || record.architecture != pkg.architecture
+        {
+            continue;
+        }
+        match record.status {
+            Some(ArtifactStatus::Good) => good = good.or(Some(record.build_id)),
+            Some(ArtifactStatus::Bad) => bad = bad.or(Some(record.build_id)),
+            _ => (),
+        }
+    }
+    match (good, bad) {
+        (Some(_), Some(build_id)) => (AuditStatus::Conflicting, build_id),
+        (Some(build_id), None) => (AuditStatus::Verified, build_id),
+        (None, Some(build_id)) => (AuditStatus::Unreproduced, build_id),
+        (None, None) => (AuditStatus::Unknown, None),
+    }
+}
+
+pub async fn audit(
+    client: &Client,
+    distribution: &str,
+    pkgs: Vec<InstalledPackage>,
+) -> Result<Vec<AuditEntry>> {
+    let origin_filter = OriginFilter {
+        distribution: Some(distribution.to_string()),
+        release: None,
+        component: None,
+        architecture: None,
+    };
+
+    stream::iter(pkgs)
+        .map(|pkg| async {
+            let binary_identity_filter = BinaryIdentityFilter {
+                name: Some(pkg.name.clone()),
+                version: Some(pkg.version.clone()),
+                source_name: None,
+            };
+            let results = client
+                .get_binary_packages(None, Some(&origin_filter), Some(&binary_identity_filter))
+                .await
+                .with_context(|| anyhow!("Failed to look up {:?}", pkg.name))?;
+
+            let (status, build_id) = classify(&pkg, &results.records);
+            debug!(
+                "{} {} ({}): {:?}",
+                pkg.name, pkg.version, pkg.architecture, status
+            );
+            Ok(AuditEntry {
+                package: pkg,
+                status,
+                build_id,
+            })
+        })
+        .buffered(LOOKUP_CONCURRENCY)
+        .try_collect()
+        .await
+}
+
+pub fn print_report(entries: &[AuditEntry]) -> Result<()> {
+    let mut sorted = entries.iter().collect::<Vec<_>>();
+    sorted.sort_by_key(|entry| entry.status);
+
+    let mut stdout = io::stdout();
+    for entry in &sorted {
+        let pkg = &entry.package;
+        writeln!(
+            stdout,
+            "{:12} {} {} ({})",
+            entry.status.fancy(),
+            pkg.name.bold(),
+            pkg.version,
+            pkg.architecture
+        )?;
+    }
+
+    let count = |status| entries.iter().filter(|e| e.status == status).count();
+    writeln!(
+        stdout,
+        "\n{} verified, {} unreproduced, {} conflicting, {} unknown",
+        count(AuditStatus::Verified),
+        count(AuditStatus::Unreproduced),
+        count(AuditStatus::Conflicting),
+        count(AuditStatus::Unknown)
+    )?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDateTime;
+
+    fn pkg(name: &str, version: &str, architecture: &str) -> InstalledPackage {
+        InstalledPackage {
+            name: name.to_string(),
+            version: version.to_string(),
+            architecture: architecture.to_string(),
+        }
+    }
+
+    fn record(
+        pkg: &InstalledPackage,
+        status: Option<ArtifactStatus>,
+        build_id: i32,
+    ) -> BinaryPackage {
+        BinaryPackage {
+            id: build_id,
+            name: pkg.name.clone(),
+            version: pkg.version.clone(),
+            distribution: "archlinux".to_string(),
+            release: None,
+            component: Some("core".to_string()),
+            architecture: pkg.architecture.clone(),
+            url: "https://example.com/pkg".to_string(),
+            status,
+            build_id: Some(build_id),
+            artifact_id: Some(build_id),
+            diffoscope_log_id: None,
+            attestation_log_id: None,
+            upstream_sha256: None,
+            rebuilt_sha256: None,
+            upstream_sha512: None,
+            rebuilt_sha512: None,
+            last_seen: NaiveDateTime::default(),
+            seen_in_last_sync: true,
+        }
+    }
+
+    #[test]
+    fn test_read_pacman_db() {
+        let dir = tempfile::tempdir().unwrap();
+        for (name, version, arch) in [
+            ("rebuilderd", "0.27.0-1", "x86_64"),
+            ("archlinux-keyring", "20251116-1", "any"),
+        ] {
+            let pkg_dir = dir.path().join(format!("{name}-{version}"));
+            fs::create_dir(&pkg_dir).unwrap();
+            fs::write(
+                pkg_dir.join("desc"),
+                format!(
+                    "%NAME%\n{name}\n\n%VERSION%\n{version}\n\n%BASE%\n{name}\n\n%DESC%\nSome package\n\n%ARCH%\n{arch}\n\n%LICENSE%\nGPL-3.0-or-later\nMIT\n\n"
+                ),
+            )
+            .unwrap();
+            fs::write(pkg_dir.join("files"), "%FILES%\nusr/\n\n").unwrap();
+        }
+        fs::write(dir.path().join("ALPM_DB_VERSION"), "9\n").unwrap();
+
+        let database = PackageDatabase::Pacman(dir.path().to_owned());
+        assert_eq!(
+            database.read().unwrap(),
+            vec![
+                pkg("archlinux-keyring", "20251116-1", "any"),
+                pkg("rebuilderd", "0.27.0-1", "x86_64"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_dpkg_status() {
+        let status = b"Package: sniffglue
+Status: install ok installed
+Priority: optional
+Section: net
+Installed-Size: 2766
+Maintainer: Debian Rust Maintainers <pkg-rust-maintainers@alioth-lists.debian.net>
+Architecture: amd64
+Version: 0.14.0-2
+Depends: libc6 (>= 2.34), libpcap0.8 (>= 1.5.1), libseccomp2 (>= 0.0.0~20120605)
+Description: Secure multithreaded packet sniffer
+ sniffglue is a network sniffer written in rust.
+
+Package: mariadb-common
+Status: deinstall ok config-files
+Architecture: all
+Version: 1:10.5.12-1
+
+Package: tzdata
+Status: install ok installed
+Architecture: all
+Version: 2025b-4";
+        let pkgs = parse_dpkg_status(&status[..]).unwrap();
+        assert_eq!(
+            pkgs,
+            vec![
+                pkg("sniffglue", "0.14.0-2", "amd64"),
+                pkg("tzdata", "2025b-4", "all"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_rpm_export() {
+        let pkgs =
+            parse_rpm_export("rust-sniffglue 0.16.1-2.fc41 x86_64\n\ntzdata 2025b-1.fc41 noarch\n")
+                .unwrap();
+        assert_eq!(
+            pkgs,
+            vec![
+                pkg("rust-sniffglue", "0.16.1-2.fc41", "x86_64"),
+                pkg("tzdata", "2025b-1.fc41", "noarch"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_rpm_export_invalid() {
+        parse_rpm_export("rust-sniffglue 0.16.1-2.fc41\n").unwrap_err();
+    }
+
+    #[test]
+    fn test_classify() {
+        let installed = pkg("rebuilderd", "0.27.0-1", "x86_64");
+        let other_version = pkg("rebuilderd", "0.26.0-1", "x86_64");
+
+        assert_eq!(classify(&installed, &[]), (AuditStatus::Unknown, None));
+        assert_eq!(
+            classify(
+                &installed,
+                &[record(&other_version, Some(ArtifactStatus::Good), 1)]
+            ),
+            (AuditStatus::Unknown, None)
+        );
+        assert_eq!(
+            classify(&installed, &[record(&installed, None, 1)]),
+            (AuditStatus::Unknown, None)
+        );
+        assert_eq!(
+            classify(
+                &installed,
+                &[record(&installed, Some(ArtifactStatus::Bad), 1)]
+            ),
+            (AuditStatus::Unreproduced, Some(1))
+        );
+        assert_eq!(
+            classify(
+                &installed,
+                &[
+                    record(&installed, Some(ArtifactStatus::Bad), 1),
+                    record(&installed, Some(ArtifactStatus::Good), 2),
+                ]
+            ),
+            (AuditStatus::Conflicting, Some(1))
+        );
+        assert_eq!(
+            classify(
+                &installed,
+                &[
+                    record(&installed, Some(ArtifactStatus::Good), 1),
+                    record(&installed, Some(ArtifactStatus::Good), 2),
+                ]
+            ),
+            (AuditStatus::Verified, Some(1))
+        );
+    }
+}
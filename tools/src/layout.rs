@@ -0,0 +1,326 @@
+use crate::args;
+use chrono::{Duration, Utc};
+use in_toto::crypto::{KeyId, PublicKey};
+use in_toto::models::rule::ArtifactRule;
+use in_toto::models::step::Step;
+use in_toto::models::{LayoutMetadataBuilder, Metablock, MetadataWrapper};
+use rebuilderd_common::api::Client;
+use rebuilderd_common::api::v1::{
+    BinaryIdentityFilter, MetaRestApi, OriginFilter, PackageRestApi, Page,
+};
+use rebuilderd_common::attestation;
+use rebuilderd_common::config::ConfigFile;
+use rebuilderd_common::errors::*;
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+pub struct TrustedRebuilder {
+    pub endpoint: String,
+    pub public_key: PublicKey,
+    /// Keys the rebuilder has retired, attestations made before the rotation are signed with them
+    pub previous_keys: Vec<PublicKey>,
+}
+
+impl TrustedRebuilder {
+    /// Fetches the current and previous signing keys of the rebuilder. If a key is pinned for this endpoint in the
+    /// config file, it has to be one of them.
+    pub async fn fetch(config: &ConfigFile, endpoint: &str) -> Result<Self> {
+        let client = Client::new(config.clone(), Some(endpoint.to_string()))?;
+        let keys = client
+            .get_public_keys()
+            .await
+            .with_context(|| anyhow!("Failed to fetch public keys from {:?}", endpoint))?;
+        let pem = keys
+            .current
+            .first()
+            .with_context(|| anyhow!("Rebuilder {:?} has no current signing key", endpoint))?;
+        let public_key = attestation::pem_to_pubkeys(pem.as_bytes())?
+            .next()
+            .with_context(|| anyhow!("Rebuilder {:?} sent an invalid public key", endpoint))??;
+
+        let mut previous_keys = Vec::new();
+        for retired in &keys.previous {
+            let previous_key = attestation::pem_to_pubkeys(retired.key.as_bytes())?
+                .next()
+                .with_context(|| {
+                    anyhow!("Rebuilder {:?} sent an invalid public key", endpoint)
+                })??;
+            debug!(
+                "Rebuilder {:?} has retired key {} at {}",
+                endpoint,
+                attestation::key_id(&previous_key)?,
+                retired.retired_at
+            );
+            previous_keys.push(previous_key);
+        }
+
+        let pinned = config
+            .verify
+            .rebuilders
+            .iter()
+            .find(|rebuilder| rebuilder.endpoint == endpoint);
+        if let Some(pinned) = pinned {
+            let pinned = attestation::pem_to_pubkeys(pinned.public_key.as_bytes())?
+                .next()
+                .with_context(|| anyhow!("No public key configured for {:?}", endpoint))??;
+            let known = std::iter::once(&public_key)
+                .chain(&previous_keys)
+                .any(|key| key.key_id() == pinned.key_id());
+            if !known {
+                bail!(
+                    "Rebuilder {:?} doesn't know the key pinned in the config file",
+                    endpoint
+                );
+            }
+        }
+
+        Ok(TrustedRebuilder {
+            endpoint: endpoint.to_string(),
+            public_key,
+            previous_keys,
+        })
+    }
+}
+
+/// Lists the filenames of all artifacts the rebuilder knows for this distribution
+async fn list_artifacts(client: &Client, args: &args::Layout) -> Result<BTreeSet<String>> {
+    let origin_filter = OriginFilter {
+        distribution: Some(args.distro.clone()),
+        release: None,
+        component: args.suite.clone(),
+        architecture: args.architecture.clone(),
+    };
+    let binary_identity_filter = BinaryIdentityFilter {
+        name: args.name.clone(),
+        version: None,
+        source_name: None,
+    };
+    let mut page = Page {
+        limit: Some(1000),
+        before: None,
+        after: None,
+        sort: Some("name".to_string()),
+        direction: None,
+    };
+
+    let mut artifacts = BTreeSet::new();
+    loop {
+        let results = client
+            .get_binary_packages(
+                Some(&page),
+                Some(&origin_filter),
+                Some(&binary_identity_filter),
+            )
+            .await
+            .context("Failed to list packages")?;
+
+        let Some(last) = results.records.last() else {
+            break;
+        };
+        page.after = Some(last.id);
+
+        for pkg in results.records {
+            artifacts.insert(attestation::artifact_filename(&pkg.url)?);
+        }
+    }
+
+    Ok(artifacts)
+}
+
+/// Builds a layout with one step per artifact, each requiring `threshold` of the rebuilders to have reproduced it.
+/// Rebuilders sharing a key only count once. The previous keys of the rebuilders are trusted too, so attestations
+/// made before a key rotation still count.
+pub fn build_layout(
+    rebuilders: &[TrustedRebuilder],
+    artifacts: &BTreeSet<String>,
+    threshold: usize,
+    expires_in: Duration,
+) -> Result<Metablock> {
+    let independent = rebuilders
+        .iter()
+        .map(|rebuilder| rebuilder.public_key.key_id())
+        .collect::<HashSet<_>>()
+        .len();
+    let keys = rebuilders
+        .iter()
+        .flat_map(|rebuilder| {
+            std::iter::once(&rebuilder.public_key).chain(&rebuilder.previous_keys)
+        })
+        .map(|public_key| (public_key.key_id().clone(), public_key.clone()))
+        .collect::<HashMap<KeyId, PublicKey>>();
+
+    if threshold == 0 {
+        bail!("Threshold needs to be at least 1");
+    }
+    if threshold > independent {
+        bail!(
+            "Threshold of {} can never be reached with {} independent rebuilders",
+            threshold,
+            independent
+        );
+    }
+    if artifacts.is_empty() {
+        bail!("No artifacts found to include in the layout");
+    }
+
+    let mut key_ids = keys.keys().cloned().collect::<Vec<_>>();
+    key_ids.sort();
+
+    let endpoints = rebuilders
+        .iter()
+        .map(|rebuilder| rebuilder.endpoint.as_str())
+        .collect::<Vec<_>>();
+    let mut layout = LayoutMetadataBuilder::new()
+        .expires(Utc::now() + expires_in)
+        .readme(format!(
+            "Each artifact needs to be reproduced by {} of these rebuilders: {}",
+            threshold,
+            endpoints.join(", ")
+        ));
+
+    for public_key in keys.into_values() {
+        layout = layout.add_key(public_key);
+    }
+
+    for filename in artifacts {
+        let mut step = Step::new(&format!("rebuild {filename}"))
+            .threshold(threshold as u32)
+            .add_expected_product(ArtifactRule::Allow(filename.as_str().into()))
+            .add_expected_product(ArtifactRule::Disallow("*".into()));
+        for key_id in &key_ids {
+            step = step.add_key(key_id.clone());
+        }
+        layout = layout.add_step(step);
+    }
+
+    let layout = layout.build()?;
+    // the layout is meant to be signed by the project owner, eg. with in-toto-sign
+    Ok(Metablock {
+        signatures: Vec::new(),
+        metadata: MetadataWrapper::Layout(layout),
+    })
+}
+
+pub async fn run(client: &Client, config: &ConfigFile, args: &args::Layout) -> Result<Metablock> {
+    let endpoints = if args.rebuilders.is_empty() {
+        config
+            .verify
+            .rebuilders
+            .iter()
+            .map(|rebuilder| rebuilder.endpoint.clone())
+            .collect()
+    } else {
+        args.rebuilders.clone()
+    };
+    if endpoints.is_empty() {
+        bail!(
+            "No rebuilders given, use --rebuilder or add them to the [verify] section of rebuilderd.conf"
+        );
+    }
+
+    let mut rebuilders = Vec::new();
+    for endpoint in &endpoints {
+        let rebuilder = TrustedRebuilder::fetch(config, endpoint).await?;
+        info!(
+            "Using key {} and {} previous keys for {:?}",
+            attestation::key_id(&rebuilder.public_key)?,
+            rebuilder.previous_keys.len(),
+            rebuilder.endpoint
+        );
+        rebuilders.push(rebuilder);
+    }
+
+    let artifacts = list_artifacts(client, args).await?;
+    info!("Adding {} artifacts to layout", artifacts.len());
+
+    let threshold = args
+        .threshold
+        .or(config.verify.threshold)
+        .unwrap_or(rebuilders.len());
+    build_layout(
+        &rebuilders,
+        &artifacts,
+        threshold,
+        Duration::days(args.expires),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use in_toto::crypto::{KeyType, PrivateKey, SignatureScheme};
+
+    fn rebuilder(endpoint: &str) -> TrustedRebuilder {
+        let privkey = PrivateKey::new(KeyType::Ed25519).unwrap();
+        let privkey = PrivateKey::from_pkcs8(&privkey, SignatureScheme::Ed25519).unwrap();
+        TrustedRebuilder {
+            endpoint: endpoint.to_string(),
+            public_key: privkey.public().clone(),
+            previous_keys: Vec::new(),
+        }
+    }
+
+    fn artifacts() -> BTreeSet<String> {
+        ["empty_0.1.0-1_amd64.deb".to_string()].into()
+    }
+
+    #[test]
+    fn test_build_layout() {
+        let rebuilders = vec![
+            rebuilder("https://a.example.com"),
+            rebuilder("https://b.example.com"),
+        ];
+        let layout = build_layout(&rebuilders, &artifacts(), 2, Duration::days(365)).unwrap();
+        assert!(layout.signatures.is_empty());
+
+        let json = serde_json::to_value(&layout).unwrap();
+        let signed = &json["signed"];
+        assert_eq!(signed["_type"], "layout");
+        assert_eq!(signed["keys"].as_object().unwrap().len(), 2);
+
+        let steps = signed["steps"].as_array().unwrap();
+        assert_eq!(steps.len(), 1);
+        assert_eq!(steps[0]["name"], "rebuild empty_0.1.0-1_amd64.deb");
+        assert_eq!(steps[0]["threshold"], 2);
+        assert_eq!(steps[0]["pubkeys"].as_array().unwrap().len(), 2);
+        assert_eq!(
+            steps[0]["expected_products"],
+            serde_json::json!([["ALLOW", "empty_0.1.0-1_amd64.deb"], ["DISALLOW", "*"]])
+        );
+    }
+
+    #[test]
+    fn test_build_layout_shared_key_counts_once() {
+        let a = rebuilder("https://a.example.com");
+        let b = TrustedRebuilder {
+            endpoint: "https://b.example.com".to_string(),
+            public_key: a.public_key.clone(),
+            previous_keys: Vec::new(),
+        };
+        let rebuilders = vec![a, b];
+        build_layout(&rebuilders, &artifacts(), 2, Duration::days(365)).unwrap_err();
+        build_layout(&rebuilders, &artifacts(), 1, Duration::days(365)).unwrap();
+    }
+
+    #[test]
+    fn test_build_layout_includes_previous_keys() {
+        let mut a = rebuilder("https://a.example.com");
+        a.previous_keys
+            .push(rebuilder("https://a.example.com").public_key);
+        let rebuilders = vec![a, rebuilder("https://b.example.com")];
+        // the previous key doesn't make the rebuilder count twice
+        build_layout(&rebuilders, &artifacts(), 3, Duration::days(365)).unwrap_err();
+        let layout = build_layout(&rebuilders, &artifacts(), 2, Duration::days(365)).unwrap();
+
+        let json = serde_json::to_value(&layout).unwrap();
+        let signed = &json["signed"];
+        assert_eq!(signed["keys"].as_object().unwrap().len(), 3);
+        assert_eq!(signed["steps"][0]["pubkeys"].as_array().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_build_layout_invalid_threshold() {
+        let rebuilders = vec![rebuilder("https://a.example.com")];
+        build_layout(&rebuilders, &artifacts(), 0, Duration::days(365)).unwrap_err();
+        build_layout(&rebuilders, &BTreeSet::new(), 1, Duration::days(365)).unwrap_err();
+    }
+}
@@ -8,10 +8,12 @@ use env_logger::Env;
 use glob::Pattern;
 use rebuilderd_common::api::Client;
 use rebuilderd_common::api::v1::{
-    ArtifactStatus, BinaryIdentityFilter, BinaryPackage, BuildRestApi, OriginFilter, PackageReport,
-    PackageRestApi, Page, Priority, QueueJobRequest, QueueRestApi, SourceIdentityFilter,
-    WorkerRestApi,
+    ArtifactStatus, BinaryIdentityFilter, BinaryPackage, BuildRestApi, MetaRestApi, OriginFilter,
+    PackageReport, PackageRestApi, Page, Priority, QueueJobRequest, QueueRestApi,
+    RequiredLabelsRequest, SourceIdentityFilter, WorkerRestApi, WorkerState,
 };
+use rebuilderd_common::attestation;
+use rebuilderd_common::bundle::BundleVerifier;
 use rebuilderd_common::errors::*;
 use rebuilderd_common::http;
 use rebuilderd_common::utils;
@@ -21,12 +23,15 @@ use std::io::prelude::*;
 use tokio::io::AsyncReadExt;
 
 pub mod args;
+pub mod audit;
 pub mod config;
 pub mod decompress;
 pub mod fancy;
+pub mod layout;
 pub mod pager;
 pub mod rules;
 pub mod schedule;
+pub mod verify;
 
 fn patterns_from(patterns: &[String]) -> Result<Vec<Pattern>> {
     patterns
@@ -124,6 +129,74 @@ async fn lookup_package(client: &Client, filter: PkgsFilter) -> Result<BinaryPac
     Ok(results.records.pop().unwrap())
 }
 
+/// Finds the id of a worker by its id or name
+async fn lookup_worker(client: &Client, worker: &str) -> Result<i32> {
+    if let Ok(id) = worker.parse::<i32>() {
+        return Ok(id);
+    }
+
+    let mut matches = client
+        .get_workers(None)
+        .await
+        .context("Failed to list workers")?
+        .records
+        .into_iter()
+        .filter(|w| w.name == worker)
+        .collect::<Vec<_>>();
+
+    match matches.len() {
+        0 => bail!("No worker named {worker:?}"),
+        1 => Ok(matches.remove(0).id),
+        _ => bail!("Multiple workers are named {worker:?}, please use the id instead"),
+    }
+}
+
+/// Writes the attestation bundle to stdout, verifying the signed manifest at the end against the public key of the
+/// rebuilder.
+async fn export_attestation_bundle(
+    client: &Client,
+    filter: PkgsFilter,
+    release: Option<String>,
+) -> Result<()> {
+    let origin_filter = OriginFilter {
+        distribution: filter.distro,
+        release,
+        component: filter.suite,
+        architecture: filter.architecture,
+    };
+
+    let public_keys = client
+        .get_public_keys()
+        .await
+        .context("Failed to fetch public keys")?
+        .current
+        .iter()
+        .map(|pem| attestation::pem_to_pubkeys(pem.as_bytes()))
+        .collect::<Result<Vec<_>>>()?
+        .into_iter()
+        .flatten()
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut bundle = client
+        .get_attestation_bundle(Some(&origin_filter))
+        .await
+        .context("Failed to fetch attestation bundle")?;
+
+    let mut verifier = BundleVerifier::new();
+    let mut stdout = io::stdout();
+    while let Some(line) = bundle.next_line().await? {
+        verifier.push(&line, &public_keys)?;
+        stdout.write_all(line.to_line()?.as_bytes())?;
+    }
+    let manifest = verifier.finish()?;
+    info!(
+        "Exported {} attestations with a valid manifest",
+        manifest.attestations.len()
+    );
+
+    Ok(())
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     let args = Args::parse();
@@ -143,7 +216,7 @@ async fn main() -> Result<()> {
 
     let config =
         rebuilderd_common::config::load(args.config).context("Failed to load config file")?;
-    let mut client = Client::new(config, args.endpoint)?;
+    let mut client = Client::new(config.clone(), args.endpoint)?;
 
     match args.subcommand {
         SubCommand::Status => {
@@ -155,6 +228,11 @@ async fn main() -> Result<()> {
                 } else {
                     "idle".blue()
                 };
+                let status = if worker.state != WorkerState::Active {
+                    format!("{} [{}]", status, worker.state.as_str().red())
+                } else {
+                    status.to_string()
+                };
                 if writeln!(stdout, "{:-40} => {}", label, status).is_err() {
                     break;
                 }
@@ -306,6 +384,9 @@ async fn main() -> Result<()> {
 
             pager::write(diffoscope.as_bytes())?;
         }
+        SubCommand::Pkgs(Pkgs::Attestation(args)) if args.all => {
+            export_attestation_bundle(&client, args.filter, args.release).await?;
+        }
         SubCommand::Pkgs(Pkgs::Attestation(args)) => {
             let package = lookup_package(&client, args.filter).await?;
             if package.build_id.is_none() || package.artifact_id.is_none() {
@@ -373,7 +454,8 @@ async fn main() -> Result<()> {
                         // Print the queue item
                         if writeln!(
                             stdout,
-                            "{} {:-60} {:>11} {:19} {:?} {:?} {:?}",
+                            "{:>6} {} {:-60} {:>11} {:19} {:?} {:?} {:?}",
+                            job.id.to_string().bright_black(),
                             job.queued_at
                                 .format("%Y-%m-%d %H:%M:%S")
                                 .to_string()
@@ -393,6 +475,20 @@ async fn main() -> Result<()> {
                 }
             }
         }
+        SubCommand::Pkgs(Pkgs::Labels(args)) => {
+            client
+                .with_auth_cookie()?
+                .set_required_labels(&RequiredLabelsRequest {
+                    distribution: Some(args.distro),
+                    release: None,
+                    component: Some(args.component),
+                    name: Some(args.name),
+                    version: args.version,
+                    architecture: args.architecture,
+                    required_labels: args.labels,
+                })
+                .await?;
+        }
         SubCommand::Queue(Queue::Push(push)) => {
             client
                 .with_auth_cookie()?
@@ -426,6 +522,67 @@ async fn main() -> Result<()> {
                 .drop_queued_jobs(Some(&origin_filter), Some(&source_identity_filter))
                 .await?;
         }
+        SubCommand::Queue(Queue::Log(log)) => {
+            if log.follow {
+                let mut stream = client
+                    .follow_queued_job_log(log.id)
+                    .await
+                    .context("Failed to follow build log")?;
+
+                let mut stdout = io::stdout();
+                while let Some(output) = stream.next_output().await? {
+                    stdout.write_all(output.as_bytes())?;
+                    stdout.flush()?;
+                }
+            } else {
+                let output = client
+                    .get_queued_job_log(log.id)
+                    .await
+                    .context("Failed to fetch build log")?;
+                pager::write(output.as_bytes())?;
+            }
+        }
+        SubCommand::Workers(workers) => {
+            let (select, state) = match workers {
+                Workers::Drain(select) => (select, WorkerState::Draining),
+                Workers::Pause(select) => (select, WorkerState::Paused),
+                Workers::Resume(select) => (select, WorkerState::Active),
+                Workers::Disable(select) => (select, WorkerState::Disabled),
+            };
+            let client = client.with_auth_cookie()?;
+            let id = lookup_worker(client, &select.worker).await?;
+            let worker = client
+                .set_worker_state(id, state)
+                .await
+                .context("Failed to change state of worker")?;
+            println!("{} is now {}", worker.name, worker.state);
+        }
+        SubCommand::Verify(verify) => {
+            if !verify::run(&config, &verify).await? {
+                bail!("Not enough rebuilders could vouch for all files");
+            }
+        }
+        SubCommand::AuditSystem(audit_system) => {
+            let database = audit::PackageDatabase::from_args(&audit_system)?;
+            let distribution = audit_system
+                .distro
+                .as_deref()
+                .unwrap_or_else(|| database.distribution());
+
+            let pkgs = database.read()?;
+            info!("Found {} installed packages", pkgs.len());
+
+            let entries = audit::audit(&client, distribution, pkgs).await?;
+            if audit_system.json {
+                print_json(&entries)?;
+            } else {
+                audit::print_report(&entries)?;
+            }
+        }
+        SubCommand::Layout(layout) => {
+            let layout = layout::run(&client, &config, &layout).await?;
+            print_json(&layout)?;
+        }
         SubCommand::Completions(completions) => args::gen_completions(&completions)?,
     }
 
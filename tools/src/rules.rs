@@ -1,5 +1,6 @@
 use crate::args::PkgsSync;
 use crate::schedule::Pkg;
+use rebuilderd_common::api::v1::SourcePackageReport;
 use serde::Deserialize;
 use serde_with::{DisplayFromStr, serde_as};
 
@@ -17,6 +18,9 @@ pub struct IncludeRule {
     pub source_pkgs: Vec<glob::Pattern>,
     #[serde(default)]
     pub maintainer: Option<String>,
+    /// Labels a worker needs to be assigned the packages included by this rule
+    #[serde(default)]
+    pub required_labels: Option<Vec<String>>,
 }
 
 impl IncludeRule {
@@ -74,6 +78,36 @@ pub fn matches(sync: &PkgsSync, pkg: &dyn Pkg, component: &str) -> bool {
     }
 }
 
+/// The labels required by the include rule that selected this package, if it sets any
+pub fn required_labels<'a>(
+    sync: &'a PkgsSync,
+    pkg: &dyn Pkg,
+    component: &str,
+) -> Option<&'a [String]> {
+    let rule = sync
+        .include
+        .iter()
+        .find(|rule| rule.matches(pkg, component))?;
+    if rule.exclude {
+        return None;
+    }
+    rule.required_labels.as_deref()
+}
+
+/// Adds the labels required for one of the artifacts to its source package
+pub fn add_required_labels(group: &mut SourcePackageReport, labels: Option<&[String]>) {
+    let Some(labels) = labels else {
+        return;
+    };
+
+    let required_labels = group.required_labels.get_or_insert_with(Vec::new);
+    for label in labels {
+        if !required_labels.contains(label) {
+            required_labels.push(label.clone());
+        }
+    }
+}
+
 /// Match if intersection of maintainers and filter is not empty
 fn matches_maintainers(pkg: &dyn Pkg, filter: &[String]) -> bool {
     filter.iter().any(|filter| matches_maintainer(pkg, filter))
@@ -401,6 +435,7 @@ mod tests {
                     binary_pkgs: to_patterns(&["rebuilderd"]),
                     source_pkgs: to_patterns(&["rebuilderd"]),
                     maintainer: Some("kpcyrd <kpcyrd@archlinux.org>".to_string()),
+                    required_labels: None,
                 }],
                 maintainers: Vec::new(),
                 pkgs: &[],
@@ -424,6 +459,7 @@ mod tests {
                         binary_pkgs: to_patterns(&["rebuilderd"]),
                         source_pkgs: Vec::new(),
                         maintainer: None,
+                        required_labels: None,
                     },
                     // This is an exclude=false rule
                     IncludeRule {
@@ -432,6 +468,7 @@ mod tests {
                         binary_pkgs: to_patterns(&["rebuilderd"]),
                         source_pkgs: Vec::new(),
                         maintainer: None,
+                        required_labels: None,
                     },
                 ],
                 maintainers: Vec::new(),
@@ -454,6 +491,7 @@ mod tests {
                     binary_pkgs: to_patterns(&["linux"]),
                     source_pkgs: Vec::new(),
                     maintainer: None,
+                    required_labels: None,
                 }],
                 maintainers: Vec::new(),
                 pkgs: &[],
@@ -475,6 +513,7 @@ mod tests {
                     binary_pkgs: to_patterns(&["linux"]),
                     source_pkgs: Vec::new(),
                     maintainer: None,
+                    required_labels: None,
                 }],
                 maintainers: vec!["kpcyrd <kpcyrd@archlinux.org>".to_string()],
                 pkgs: &[],
@@ -496,6 +535,7 @@ mod tests {
                     binary_pkgs: Vec::new(),
                     source_pkgs: Vec::new(),
                     maintainer: None,
+                    required_labels: None,
                 }],
                 maintainers: Vec::new(),
                 pkgs: &[],
@@ -517,6 +557,7 @@ mod tests {
                     binary_pkgs: Vec::new(),
                     source_pkgs: Vec::new(),
                     maintainer: None,
+                    required_labels: None,
                 }],
                 maintainers: Vec::new(),
                 pkgs: &[],
@@ -538,6 +579,7 @@ mod tests {
                     binary_pkgs: Vec::new(),
                     source_pkgs: Vec::new(),
                     maintainer: Some("kpcyrd".to_string()),
+                    required_labels: None,
                 }],
                 maintainers: Vec::new(),
                 pkgs: &[],
@@ -559,6 +601,7 @@ mod tests {
                     binary_pkgs: Vec::new(),
                     source_pkgs: Vec::new(),
                     maintainer: Some("Levente Polyak".to_string()),
+                    required_labels: None,
                 }],
                 maintainers: Vec::new(),
                 pkgs: &[],
@@ -580,6 +623,7 @@ mod tests {
                     binary_pkgs: to_patterns(&["*"]),
                     source_pkgs: Vec::new(),
                     maintainer: None,
+                    required_labels: None,
                 }],
                 maintainers: Vec::new(),
                 pkgs: &[],
@@ -601,6 +645,7 @@ mod tests {
                     binary_pkgs: Vec::new(),
                     source_pkgs: to_patterns(&["*"]),
                     maintainer: None,
+                    required_labels: None,
                 }],
                 maintainers: Vec::new(),
                 pkgs: &[],
@@ -611,4 +656,68 @@ mod tests {
         );
         assert!(m);
     }
+
+    #[test]
+    fn include_rule_required_labels() {
+        let labels = vec!["large-memory".to_string()];
+        let sync = gen_filter(Filter {
+            include: vec![
+                IncludeRule {
+                    exclude: false,
+                    component: None,
+                    binary_pkgs: to_patterns(&["rebuilderd"]),
+                    source_pkgs: Vec::new(),
+                    maintainer: None,
+                    required_labels: Some(labels.clone()),
+                },
+                IncludeRule {
+                    exclude: false,
+                    component: None,
+                    binary_pkgs: to_patterns(&["*"]),
+                    source_pkgs: Vec::new(),
+                    maintainer: None,
+                    required_labels: None,
+                },
+            ],
+            maintainers: Vec::new(),
+            pkgs: &[],
+            excludes: &[],
+        });
+
+        let pkg = gen_pkg();
+        assert_eq!(
+            required_labels(&sync, &pkg, "extra"),
+            Some(labels.as_slice())
+        );
+
+        let other = ArchPkg {
+            name: "sniffglue".to_string(),
+            ..gen_pkg()
+        };
+        assert_eq!(required_labels(&sync, &other, "extra"), None);
+    }
+
+    #[test]
+    fn add_required_labels_merges_artifacts() {
+        let mut group = SourcePackageReport {
+            name: "rebuilderd".to_string(),
+            version: "0.2.1-1".to_string(),
+            url: "https://example.com/rebuilderd-0.2.1-1-x86_64.pkg.tar.zst".to_string(),
+            artifacts: Vec::new(),
+            required_labels: None,
+        };
+
+        add_required_labels(&mut group, None);
+        assert_eq!(group.required_labels, None);
+
+        add_required_labels(&mut group, Some(&["kvm".to_string()]));
+        add_required_labels(
+            &mut group,
+            Some(&["kvm".to_string(), "large-memory".to_string()]),
+        );
+        assert_eq!(
+            group.required_labels,
+            Some(vec!["kvm".to_string(), "large-memory".to_string()])
+        );
+    }
 }
@@ -184,6 +184,7 @@ pub async fn sync(http: &http::Client, sync: &PkgsSync) -> Result<Vec<PackageRep
                 if !rules::matches(sync, &pkg, component) {
                     continue;
                 }
+                let required_labels = rules::required_labels(sync, &pkg, component);
 
                 let url = mirror_to_url(source, component, arch, &pkg.filename)?;
                 let artifact = BinaryPackageReport {
@@ -197,15 +198,18 @@ pub async fn sync(http: &http::Client, sync: &PkgsSync) -> Result<Vec<PackageRep
                 if let Some(group) = bases.get_mut(&pkg.base) {
                     // TODO: multiple architectures could have the exact same package with arch=any
                     group.artifacts.push(artifact);
+                    rules::add_required_labels(group, required_labels);
                 } else {
                     let mut group = SourcePackageReport {
                         name: pkg.base.clone(),
                         version: pkg.version.clone(),
                         url: url.clone(), // use first artifact's url as the source URL for now
                         artifacts: Vec::new(),
+                        required_labels: None,
                     };
 
                     group.artifacts.push(artifact);
+                    rules::add_required_labels(&mut group, required_labels);
                     bases.insert(pkg.base, group);
                 }
             }
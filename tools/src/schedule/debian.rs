@@ -329,6 +329,7 @@ impl SyncState {
                     version: src.version.clone(),
                     url: src.buildinfo_url(architecture),
                     artifacts: Vec::new(),
+                    required_labels: None,
                 };
 
                 report.packages.push(source_report);
@@ -385,7 +386,12 @@ impl SyncState {
                 );
 
                 let source = release.source(&sync.source);
+                let required_labels = rules::required_labels(sync, &pkg, component);
+                let architecture = pkg.architecture.clone();
                 self.push(&source_pkg, pkg, source, release.name(), component);
+
+                let group = self.get_mut_group(&source_pkg, release.name(), &architecture);
+                rules::add_required_labels(group, required_labels);
             }
             Err(e) => {
                 warn!("{}, skipping", e)
@@ -766,6 +772,7 @@ Section: misc
                             url: "https://deb.debian.org/debian/pool/main/m/mariadb-10.5/mariadb-server_10.5.12-1_all.deb".to_string(),
                         }
                     ],
+                    required_labels: None,
                 },
             ],
         });
@@ -885,6 +892,7 @@ Section: misc
                             url: "https://deb.debian.org/debian/pool/main/r/rust-sniffglue/sniffglue_0.14.0-2_amd64.deb".to_string(),
                         }
                     ],
+                    required_labels: None,
                 },
             ],
         });
@@ -1272,6 +1280,7 @@ Section: mail
                             url: "https://deb.debian.org/debian/pool/main/c/courier/sqwebmail_6.0.5+1.0.16-3+b1_amd64.deb".to_string(),
                         },
                     ],
+                    required_labels: None,
                 },
             ],
         });
@@ -1294,6 +1303,7 @@ Section: mail
                             url: "https://deb.debian.org/debian/pool/main/c/courier/courier-doc_1.0.16-3_all.deb".to_string(),
                         }
                     ],
+                    required_labels: None,
                 },
             ],
         });
@@ -1586,7 +1596,8 @@ SHA256: cc2081a6b2f6dcb82039b5097405b5836017a7bfc54a78eba36b656549e17c92
                             architecture: "amd64".to_string(),
                             url: "http://deb.debian.org/debian/pool/main/r/rust-repro-env/repro-env_0.4.3-2_amd64.deb".to_string(),
                         },
-                    ]
+                    ],
+                    required_labels: None,
                 }
             ],
         });
@@ -1608,7 +1619,8 @@ SHA256: cc2081a6b2f6dcb82039b5097405b5836017a7bfc54a78eba36b656549e17c92
                             architecture: "amd64".to_string(),
                             url: "http://deb.debian.org/debian/pool/main/r/rust-repro-env/repro-env_0.4.3-2_amd64.deb".to_string(),
                         },
-                    ]
+                    ],
+                    required_labels: None,
                 }
             ],
         });
@@ -1841,7 +1853,8 @@ SHA256: 89c378d37058ea2a6c5d4bb2c1d47c4810f7504bde9e4d8142ac9781ce9df002
                                 architecture: "all".to_string(),
                                 url: "http://deb.debian.org/debian/pool/main/n/novnc/novnc_1.6.0-2_all.deb".to_string(),
                             },
-                        ]
+                        ],
+                        required_labels: None,
                    },
                    SourcePackageReport {
                         name: "novnc".to_string(),
@@ -1855,7 +1868,8 @@ SHA256: 89c378d37058ea2a6c5d4bb2c1d47c4810f7504bde9e4d8142ac9781ce9df002
                                 architecture: "all".to_string(),
                                 url: "http://deb.debian.org/debian/pool/main/n/novnc/python3-novnc_1.6.0-1_all.deb".to_string(),
                             },
-                        ]
+                        ],
+                        required_labels: None,
                     },
                ],
            });
@@ -1885,7 +1899,8 @@ SHA256: 89c378d37058ea2a6c5d4bb2c1d47c4810f7504bde9e4d8142ac9781ce9df002
                                 architecture: "all".to_string(),
                                 url: "http://deb.debian.org/debian/pool/main/n/novnc/python3-novnc_1.6.0-1_all.deb".to_string(),
                             },
-                        ]
+                        ],
+                        required_labels: None,
                     },
                 ],
             },
@@ -2002,7 +2017,8 @@ Filename: pool/main/r/rust-sniffglue/sniffglue-dbgsym_0.14.0-2_amd64.deb
                             component: Some("main".to_string()),
                             architecture: "amd64".to_string(),
                             url: "http://deb.debian.org/debian/pool/main/r/rust-sniffglue/sniffglue_0.14.0-2_amd64.deb".to_string(),
-                        }]
+                        }],
+                        required_labels: None,
                     }]
                 },
                 PackageReport {
@@ -2019,7 +2035,8 @@ Filename: pool/main/r/rust-sniffglue/sniffglue-dbgsym_0.14.0-2_amd64.deb
                             component: Some("main".to_string()),
                             architecture: "amd64".to_string(),
                             url: "http://deb.debian.org/debian-debug/pool/main/r/rust-sniffglue/sniffglue-dbgsym_0.14.0-2_amd64.deb".to_string()
-                        }]
+                        }],
+                        required_labels: None,
                     }]
                 },
             ]
@@ -2243,7 +2260,9 @@ SHA256: b5b0a2543abcf5c6f07fb30a574bd7f5ac0b6a831f82e11137f71e3738fefb91
                             architecture: "amd64".to_string(),
                             url: "http://deb.debian.org/debian/pool/main/a/acl/libacl1-udeb_2.3.2-2+b1_amd64.udeb".to_string()
                         },
-                    ]}]
+                        ],
+                        required_labels: None,
+                    }]
                 },
             ]
         );
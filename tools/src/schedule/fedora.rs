@@ -47,6 +47,7 @@ pub async fn sync(http: &http::Client, sync: &PkgsSync) -> Result<Vec<PackageRep
                     if !rules::matches(sync, &pkg, component) {
                         continue;
                     }
+                    let required_labels = rules::required_labels(sync, &pkg, component);
 
                     let url = format!("{base_url}/{}", pkg.location.href);
                     let version = format!("{}-{}", pkg.version.ver, pkg.version.rel);
@@ -60,15 +61,18 @@ pub async fn sync(http: &http::Client, sync: &PkgsSync) -> Result<Vec<PackageRep
 
                     if let Some(group) = bases.get_mut(&pkg.format.sourcerpm) {
                         group.artifacts.push(artifact);
+                        rules::add_required_labels(group, required_labels);
                     } else {
                         let mut group = SourcePackageReport {
                             name: pkg.format.sourcerpm.clone(),
                             version: format!("{}-{}", pkg.version.ver, pkg.version.rel),
                             url: url.clone(), // use first artifact's url as the source URL for now
                             artifacts: Vec::new(),
+                            required_labels: None,
                         };
 
                         group.artifacts.push(artifact);
+                        rules::add_required_labels(&mut group, required_labels);
                         bases.insert(pkg.format.sourcerpm, group);
                     }
                 }
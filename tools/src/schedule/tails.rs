@@ -82,6 +82,7 @@ pub async fn sync(http: &http::Client, sync: &PkgsSync) -> Result<Vec<PackageRep
                         version: version.to_string(),
                         url: url.clone(), // use first artifact's url as the source URL for now
                         artifacts: vec![artifact],
+                        required_labels: None,
                     };
 
                     group = Some(new_group);
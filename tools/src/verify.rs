@@ -0,0 +1,368 @@
+use crate::args;
+use colored::*;
+use data_encoding::HEXLOWER;
+use in_toto::crypto::{KeyId, PublicKey};
+use rebuilderd_common::api::Client;
+use rebuilderd_common::api::v1::{
+    ArtifactDigestQuery, ArtifactStatus, BuildRestApi, PackageRestApi,
+};
+use rebuilderd_common::attestation::{self, Attestation};
+use rebuilderd_common::config::{ConfigFile, VerifyConfig};
+use rebuilderd_common::errors::*;
+use rebuilderd_common::statement::ResourceDescriptor;
+use rebuilderd_common::utils::{is_zstd_compressed, zstd_decompress};
+use sha2::{Digest, Sha256, Sha512};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+pub struct LocalArtifact {
+    pub sha256: String,
+    pub sha512: String,
+}
+
+impl LocalArtifact {
+    pub fn hash(path: &Path) -> Result<Self> {
+        let mut buf = [0u8; 4096];
+        let mut sha256 = Sha256::new();
+        let mut sha512 = Sha512::new();
+
+        let mut f = File::open(path).with_context(|| anyhow!("Failed to open {:?}", path))?;
+        loop {
+            let n = f.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            sha256.update(&buf[..n]);
+            sha512.update(&buf[..n]);
+        }
+
+        Ok(LocalArtifact {
+            sha256: HEXLOWER.encode(&sha256.finalize()),
+            sha512: HEXLOWER.encode(&sha512.finalize()),
+        })
+    }
+
+    /// Compares the hashes of an attested artifact with this file, hash algorithms we don't know are ignored but at
+    /// least one known hash needs to be present.
+    fn matches(&self, descriptor: &ResourceDescriptor) -> bool {
+        let mut known = descriptor
+            .digest
+            .iter()
+            .filter_map(|(algorithm, value)| self.compare(algorithm, value))
+            .peekable();
+        known.peek().is_some() && known.all(|matches| matches)
+    }
+
+    fn compare(&self, algorithm: &str, value: &str) -> Option<bool> {
+        let expected = match algorithm {
+            "sha256" => &self.sha256,
+            "sha512" => &self.sha512,
+            _ => return None,
+        };
+        Some(value.eq_ignore_ascii_case(expected))
+    }
+}
+
+pub struct Rebuilder {
+    pub endpoint: String,
+    /// All keys pinned for this rebuilder, eg. its current key and the ones it has rotated away from
+    pub public_keys: Vec<PublicKey>,
+}
+
+impl Rebuilder {
+    pub fn load_all(config: &VerifyConfig) -> Result<Vec<Rebuilder>> {
+        config
+            .rebuilders
+            .iter()
+            .map(|rebuilder| {
+                let public_keys = attestation::pem_to_pubkeys(rebuilder.public_key.as_bytes())?
+                    .collect::<Result<Vec<_>>>()?;
+                if public_keys.is_empty() {
+                    bail!("No public key configured for {:?}", rebuilder.endpoint);
+                }
+                Ok(Rebuilder {
+                    endpoint: rebuilder.endpoint.clone(),
+                    public_keys,
+                })
+            })
+            .collect()
+    }
+
+    /// The first pinned key identifies the rebuilder, rebuilders sharing it are not independent
+    pub fn key_id(&self) -> &KeyId {
+        self.public_keys[0].key_id()
+    }
+
+    /// Asks the rebuilder for attestations of this file, returns true if one of them is signed by any pinned key
+    pub async fn vouches_for(&self, config: &ConfigFile, artifact: &LocalArtifact) -> Result<bool> {
+        let client = Client::new(config.clone(), Some(self.endpoint.clone()))?;
+        let query = ArtifactDigestQuery {
+            sha256: Some(artifact.sha256.clone()),
+            ..Default::default()
+        };
+        let results = client
+            .lookup_binary_packages(&query)
+            .await
+            .context("Failed to look up artifact")?;
+
+        for result in results {
+            let package = result.package;
+            if package.status != Some(ArtifactStatus::Good) || package.attestation_log_id.is_none()
+            {
+                continue;
+            }
+            let (Some(build_id), Some(artifact_id)) = (package.build_id, package.artifact_id)
+            else {
+                continue;
+            };
+
+            let bytes = client
+                .get_build_artifact_attestation(build_id, artifact_id)
+                .await
+                .context("Failed to fetch attestation")?;
+            let bytes = if is_zstd_compressed(&bytes) {
+                zstd_decompress(&bytes).await?
+            } else {
+                bytes
+            };
+
+            let attestation = Attestation::parse(&bytes)?;
+            match attestation_vouches_for(&attestation, &self.public_keys, artifact) {
+                Ok(true) => return Ok(true),
+                Ok(false) => debug!(
+                    "Attestation for {:?} from {:?} does not list this file as product",
+                    package.name, self.endpoint
+                ),
+                Err(err) => warn!(
+                    "Attestation for {:?} from {:?} failed to verify: {:#}",
+                    package.name, self.endpoint, err
+                ),
+            }
+        }
+
+        Ok(false)
+    }
+}
+
+/// Verifies the attestation is signed by one of the given keys and lists a product with the same hashes as our file
+pub fn attestation_vouches_for(
+    attestation: &Attestation,
+    public_keys: &[PublicKey],
+    artifact: &LocalArtifact,
+) -> Result<bool> {
+    let products = attestation.verify(1, public_keys)?;
+    Ok(products
+        .iter()
+        .any(|descriptor| artifact.matches(descriptor)))
+}
+
+/// Returns true if every file has been vouched for by enough rebuilders
+pub async fn run(config: &ConfigFile, args: &args::Verify) -> Result<bool> {
+    let rebuilders = Rebuilder::load_all(&config.verify)?;
+    if rebuilders.is_empty() {
+        bail!("No rebuilders configured, add them to the [verify] section of rebuilderd.conf");
+    }
+
+    let threshold = args.threshold.unwrap_or_else(|| config.verify.threshold());
+    if threshold == 0 {
+        bail!("Threshold needs to be at least 1");
+    }
+    // rebuilders that share a signing key are not independent and only count once
+    let independent = rebuilders
+        .iter()
+        .map(Rebuilder::key_id)
+        .collect::<HashSet<_>>();
+    if threshold > independent.len() {
+        bail!(
+            "Threshold of {} can never be reached with {} independent rebuilders",
+            threshold,
+            independent.len()
+        );
+    }
+
+    let mut all_verified = true;
+    for path in &args.files {
+        let artifact = LocalArtifact::hash(path)?;
+
+        let mut agreeing = HashSet::<&KeyId>::new();
+        for rebuilder in &rebuilders {
+            match rebuilder.vouches_for(config, &artifact).await {
+                Ok(true) => {
+                    info!(
+                        "{:?} has a valid attestation for {:?}",
+                        rebuilder.endpoint, path
+                    );
+                    agreeing.insert(rebuilder.key_id());
+                }
+                Ok(false) => info!("{:?} has no attestation for {:?}", rebuilder.endpoint, path),
+                Err(err) => warn!("Failed to query {:?}: {:#}", rebuilder.endpoint, err),
+            }
+        }
+
+        let status = if agreeing.len() >= threshold {
+            "OK".green()
+        } else {
+            all_verified = false;
+            "FAILED".red()
+        };
+        println!(
+            "{:<6} {} ({} of {} rebuilders agree, {} required)",
+            status,
+            path.display(),
+            agreeing.len(),
+            independent.len(),
+            threshold
+        );
+    }
+
+    Ok(all_verified)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use in_toto::crypto::{KeyType, PrivateKey, SignatureScheme};
+    use rebuilderd_common::dsse::{Envelope, IN_TOTO_PAYLOAD_TYPE};
+
+    const EMPTY_SHA256: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+    const EMPTY_SHA512: &str = "cf83e1357eefb8bdf1542850d66d8007d620e4050b5715dc83f4a921d36ce9ce47d0d13c5d85f2b0ff8318d2877eec2f63b931bd47417a81a538327af927da3e";
+
+    fn empty_file() -> LocalArtifact {
+        LocalArtifact {
+            sha256: EMPTY_SHA256.to_string(),
+            sha512: EMPTY_SHA512.to_string(),
+        }
+    }
+
+    fn privkey() -> PrivateKey {
+        let privkey = PrivateKey::new(KeyType::Ed25519).unwrap();
+        PrivateKey::from_pkcs8(&privkey, SignatureScheme::Ed25519).unwrap()
+    }
+
+    fn attestation(product: &str) -> Attestation {
+        let json = format!(
+            r#"{{"signatures":[],"signed":{{"_type":"link","name":"rebuild empty_0.1.0-1_amd64.deb","materials":{{}},"products":{{"empty_0.1.0-1_amd64.deb":{product}}},"environment":null,"byproducts":{{}},"command":[]}}}}"#
+        );
+        Attestation::parse(json.as_bytes()).unwrap()
+    }
+
+    fn statement_attestation(digest: &str) -> Attestation {
+        let statement = format!(
+            r#"{{"_type":"https://in-toto.io/Statement/v1","subject":[{{"name":"empty_0.1.0-1_amd64.deb","digest":{digest}}}],"predicateType":"https://github.com/kpcyrd/rebuilderd/rebuild/v1","predicate":{{}}}}"#
+        );
+        Attestation::Dsse(Envelope::new(IN_TOTO_PAYLOAD_TYPE, statement.as_bytes()))
+    }
+
+    #[test]
+    fn test_hash_file() {
+        let artifact = LocalArtifact::hash(Path::new("/dev/null")).unwrap();
+        assert_eq!(artifact.sha256, EMPTY_SHA256);
+        assert_eq!(artifact.sha512, EMPTY_SHA512);
+    }
+
+    #[test]
+    fn test_verify_signed_attestation() {
+        let privkey = privkey();
+        let mut attestation = attestation(&format!(
+            r#"{{"sha256":"{EMPTY_SHA256}","sha512":"{EMPTY_SHA512}"}}"#
+        ));
+        attestation.sign(&privkey).unwrap();
+
+        let vouched =
+            attestation_vouches_for(&attestation, &[privkey.public().clone()], &empty_file());
+        assert!(vouched.unwrap());
+    }
+
+    #[test]
+    fn test_verify_unsigned_attestation() {
+        let privkey = privkey();
+        let attestation = attestation(&format!(r#"{{"sha256":"{EMPTY_SHA256}"}}"#));
+
+        attestation_vouches_for(&attestation, &[privkey.public().clone()], &empty_file())
+            .unwrap_err();
+    }
+
+    #[test]
+    fn test_verify_attestation_signed_by_other_key() {
+        let mut attestation = attestation(&format!(r#"{{"sha256":"{EMPTY_SHA256}"}}"#));
+        attestation.sign(&privkey()).unwrap();
+
+        attestation_vouches_for(&attestation, &[privkey().public().clone()], &empty_file())
+            .unwrap_err();
+    }
+
+    #[test]
+    fn test_verify_attestation_signed_by_previous_key() {
+        let previous = privkey();
+        let mut attestation = attestation(&format!(r#"{{"sha256":"{EMPTY_SHA256}"}}"#));
+        attestation.sign(&previous).unwrap();
+
+        let public_keys = [privkey().public().clone(), previous.public().clone()];
+        let vouched = attestation_vouches_for(&attestation, &public_keys, &empty_file());
+        assert!(vouched.unwrap());
+    }
+
+    #[test]
+    fn test_verify_attestation_for_other_file() {
+        let privkey = privkey();
+        let mut attestation = attestation(
+            r#"{"sha256":"58a7d451d5d59fda6284a05418b99e34fab32d07e63d0b164404eaaed1317edd"}"#,
+        );
+        attestation.sign(&privkey).unwrap();
+
+        let vouched =
+            attestation_vouches_for(&attestation, &[privkey.public().clone()], &empty_file());
+        assert!(!vouched.unwrap());
+    }
+
+    #[test]
+    fn test_verify_attestation_with_partially_matching_hashes() {
+        let privkey = privkey();
+        let mut attestation = attestation(&format!(
+            r#"{{"sha256":"{EMPTY_SHA256}","sha512":"{}"}}"#,
+            "0".repeat(128)
+        ));
+        attestation.sign(&privkey).unwrap();
+
+        let vouched =
+            attestation_vouches_for(&attestation, &[privkey.public().clone()], &empty_file());
+        assert!(!vouched.unwrap());
+    }
+
+    #[test]
+    fn test_verify_signed_statement() {
+        let privkey = privkey();
+        let mut attestation = statement_attestation(&format!(
+            r#"{{"sha256":"{EMPTY_SHA256}","sha512":"{EMPTY_SHA512}"}}"#
+        ));
+        attestation.sign(&privkey).unwrap();
+
+        let vouched =
+            attestation_vouches_for(&attestation, &[privkey.public().clone()], &empty_file());
+        assert!(vouched.unwrap());
+    }
+
+    #[test]
+    fn test_verify_unsigned_statement() {
+        let privkey = privkey();
+        let attestation = statement_attestation(&format!(r#"{{"sha256":"{EMPTY_SHA256}"}}"#));
+
+        attestation_vouches_for(&attestation, &[privkey.public().clone()], &empty_file())
+            .unwrap_err();
+    }
+
+    #[test]
+    fn test_verify_statement_for_other_file() {
+        let privkey = privkey();
+        let mut attestation = statement_attestation(
+            r#"{"sha256":"58a7d451d5d59fda6284a05418b99e34fab32d07e63d0b164404eaaed1317edd"}"#,
+        );
+        attestation.sign(&privkey).unwrap();
+
+        let vouched =
+            attestation_vouches_for(&attestation, &[privkey.public().clone()], &empty_file());
+        assert!(!vouched.unwrap());
+    }
+}
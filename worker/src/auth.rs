@@ -7,10 +7,11 @@ use rebuilderd_common::utils;
 use std::fs;
 use std::io::ErrorKind;
 use std::path::Path;
+use std::sync::Arc;
 
 pub struct Profile {
     pub pubkey: String,
-    pub privkey: PrivateKey,
+    pub privkey: Arc<PrivateKey>,
 }
 
 impl Profile {
@@ -23,6 +24,13 @@ impl Profile {
     ) -> Result<Client> {
         let mut client = Client::new(config, Some(endpoint))?;
         client.worker_key(self.pubkey.clone());
+
+        let privkey = self.privkey.clone();
+        client.worker_signer(move |message: &[u8]| {
+            let signature = privkey.sign(message)?;
+            Ok(signature.value().as_bytes().to_vec())
+        });
+
         if let Some(signup_secret) = signup_secret {
             client.signup_secret(signup_secret);
         } else if let Some(auth_cookie) = auth_cookie {
@@ -51,5 +59,8 @@ fn load_key<P: AsRef<Path>>(path: P) -> Result<Profile> {
     let privkey = PrivateKey::from_pkcs8(&privkey, SignatureScheme::Ed25519)?;
     let pubkey = BASE64.encode(privkey.public().as_bytes());
 
-    Ok(Profile { pubkey, privkey })
+    Ok(Profile {
+        pubkey,
+        privkey: Arc::new(privkey),
+    })
 }
@@ -0,0 +1,204 @@
+use crate::config;
+use rebuilderd_common::errors::*;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::time;
+
+const CGROUP_MOUNT: &str = "/sys/fs/cgroup";
+const REMOVE_ATTEMPTS: usize = 50;
+const REMOVE_DELAY: Duration = Duration::from_millis(100);
+
+/// The cgroup v2 subtree that has been delegated to the worker, e.g. by systemd with `Delegate=yes`
+pub struct Delegation {
+    root: PathBuf,
+    build: config::Build,
+    counter: AtomicUsize,
+}
+
+impl Delegation {
+    /// Moves the worker into a leaf cgroup of its own and enables the controllers needed for the configured limits.
+    /// cgroup v2 only allows controllers to be enabled for children of a cgroup without processes in it.
+    pub fn setup(build: &config::Build) -> Result<Delegation> {
+        let own =
+            fs::read_to_string("/proc/self/cgroup").context("Failed to read /proc/self/cgroup")?;
+        let root = own
+            .lines()
+            .find_map(|line| line.strip_prefix("0::"))
+            .context("Worker is not running in a cgroup v2 hierarchy")?;
+        let mut root = Path::new(CGROUP_MOUNT).join(root.trim_start_matches('/'));
+        if !root.join("cgroup.controllers").exists() {
+            bail!("No cgroup v2 hierarchy mounted at {:?}", CGROUP_MOUNT);
+        }
+
+        // the worker has already moved itself in a previous setup
+        if root.ends_with("supervisor") {
+            root.pop();
+        }
+
+        let supervisor = root.join("supervisor");
+        create_supervisor_dir(&supervisor)?;
+        write(&supervisor.join("cgroup.procs"), "0")?;
+
+        for controller in build.cgroup_controllers() {
+            write(
+                &root.join("cgroup.subtree_control"),
+                &format!("+{controller}"),
+            )?;
+        }
+
+        info!("Enforcing resource limits with cgroups in {:?}", root);
+        Ok(Delegation {
+            root,
+            build: build.clone(),
+            counter: AtomicUsize::new(0),
+        })
+    }
+
+    /// Creates a new cgroup next to the worker and applies the configured limits
+    pub async fn create(&self) -> Result<BuildCgroup> {
+        let n = self.counter.fetch_add(1, Ordering::SeqCst);
+        let path = self.root.join(format!("build-{n}"));
+        match fs::create_dir(&path) {
+            Ok(()) => (),
+            // left behind by a previous worker process, whatever still runs in it must not end up in our build
+            Err(err) if err.kind() == ErrorKind::AlreadyExists => {
+                warn!("Replacing leftover cgroup {:?}", path);
+                BuildCgroup { path: path.clone() }.remove().await?;
+                fs::create_dir(&path)
+                    .with_context(|| anyhow!("Failed to create cgroup {:?}", path))?;
+            }
+            Err(err) => {
+                return Err(err).with_context(|| anyhow!("Failed to create cgroup {:?}", path));
+            }
+        }
+
+        let cgroup = BuildCgroup { path };
+        if let Err(err) = cgroup.apply(&self.build) {
+            // the cgroup is still empty, so this isn't expected to fail
+            if let Err(err) = fs::remove_dir(&cgroup.path) {
+                warn!("Failed to remove cgroup {:?}: {:#}", cgroup.path, err);
+            }
+            return Err(err);
+        }
+
+        Ok(cgroup)
+    }
+}
+
+/// A transient cgroup that the processes of a single build are running in
+pub struct BuildCgroup {
+    path: PathBuf,
+}
+
+impl BuildCgroup {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn apply(&self, build: &config::Build) -> Result<()> {
+        if let Some(memory_max) = build.memory_max {
+            write(&self.path.join("memory.max"), &memory_max.to_string())?;
+        }
+        if let Some(cpu_weight) = build.cpu_weight {
+            write(&self.path.join("cpu.weight"), &cpu_weight.to_string())?;
+        }
+        if let Some(pids_max) = build.pids_max {
+            write(&self.path.join("pids.max"), &pids_max.to_string())?;
+        }
+        for io_max in &build.io_max {
+            write(&self.path.join("io.max"), io_max)?;
+        }
+        Ok(())
+    }
+
+    /// Number of processes that have been killed by the kernel because the memory limit has been exceeded
+    pub fn oom_kills(&self) -> Result<u64> {
+        let path = self.path.join("memory.events");
+        match fs::read_to_string(&path) {
+            Ok(events) => Ok(parse_oom_kills(&events)),
+            // the memory controller is not enabled for this cgroup
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(0),
+            Err(err) => Err(err).with_context(|| anyhow!("Failed to read {:?}", path)),
+        }
+    }
+
+    /// Kills processes that have been left behind by the build and removes the cgroup
+    pub async fn remove(self) -> Result<()> {
+        for _ in 0..REMOVE_ATTEMPTS {
+            match fs::remove_dir(&self.path) {
+                Ok(()) => return Ok(()),
+                Err(err) if err.kind() == ErrorKind::NotFound => return Ok(()),
+                Err(err) => {
+                    debug!("Failed to remove cgroup {:?}: {:#}", self.path, err);
+                    if let Err(err) = fs::write(self.path.join("cgroup.kill"), "1") {
+                        debug!(
+                            "Failed to kill processes in cgroup {:?}: {:#}",
+                            self.path, err
+                        );
+                    }
+                    time::sleep(REMOVE_DELAY).await;
+                }
+            }
+        }
+
+        bail!("Failed to remove cgroup {:?}, it's still in use", self.path)
+    }
+}
+
+fn create_supervisor_dir(path: &Path) -> Result<()> {
+    match fs::create_dir(path) {
+        Ok(()) => Ok(()),
+        // created by a previous worker process, the worker moves itself into it anyway
+        Err(err) if err.kind() == ErrorKind::AlreadyExists => Ok(()),
+        Err(err) => Err(err).with_context(|| anyhow!("Failed to create cgroup {:?}", path)),
+    }
+}
+
+fn write(path: &Path, value: &str) -> Result<()> {
+    debug!("Writing {:?} to {:?}", value, path);
+    fs::write(path, value).with_context(|| anyhow!("Failed to write {:?} to {:?}", value, path))
+}
+
+fn parse_oom_kills(events: &str) -> u64 {
+    events
+        .lines()
+        .find_map(|line| line.strip_prefix("oom_kill "))
+        .and_then(|n| n.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_memory_events() {
+        let events = "low 0\nhigh 0\nmax 12\noom 1\noom_kill 1\noom_group_kill 0\n";
+        assert_eq!(parse_oom_kills(events), 1);
+    }
+
+    #[tokio::test]
+    async fn create_replaces_leftover_cgroup() {
+        let root = tempfile::tempdir().unwrap();
+        fs::create_dir(root.path().join("build-0")).unwrap();
+
+        let delegation = Delegation {
+            root: root.path().to_owned(),
+            build: config::Build::default(),
+            counter: AtomicUsize::new(0),
+        };
+        let cgroup = delegation.create().await.unwrap();
+
+        assert_eq!(cgroup.path(), root.path().join("build-0"));
+        assert!(cgroup.path().is_dir());
+    }
+
+    #[test]
+    fn parse_memory_events_without_oom_kills() {
+        let events = "low 0\nhigh 0\nmax 0\noom 0\noom_kill 0\n";
+        assert_eq!(parse_oom_kills(events), 0);
+    }
+}
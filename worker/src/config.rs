@@ -17,7 +17,17 @@ pub struct ConfigFile {
     pub backends: HashMap<String, Backend>,
     #[serde(default)]
     pub supported_architectures: Vec<String>,
+    #[serde(default)]
+    pub labels: Vec<String>,
     pub idle_delay: Option<u64>,
+    pub max_parallel_jobs: Option<usize>,
+}
+
+impl ConfigFile {
+    /// How many jobs are built at the same time, defaults to one
+    pub fn max_parallel_jobs(&self) -> usize {
+        self.max_parallel_jobs.unwrap_or(1).max(1)
+    }
 }
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
@@ -26,6 +36,75 @@ pub struct Build {
     pub max_bytes: Option<usize>,
     #[serde(default)]
     pub silent: bool,
+    #[serde(default)]
+    pub attestation_format: AttestationFormat,
+    /// Written to `memory.max` of the build cgroup, in bytes
+    pub memory_max: Option<u64>,
+    /// Written to `cpu.weight` of the build cgroup, between 1 and 10000
+    pub cpu_weight: Option<u64>,
+    /// Written to `pids.max` of the build cgroup
+    pub pids_max: Option<u64>,
+    /// Lines written to `io.max` of the build cgroup, like `8:0 rbps=1048576 wbps=1048576`
+    #[serde(default)]
+    pub io_max: Vec<String>,
+    #[serde(default)]
+    pub sandbox: Sandbox,
+}
+
+impl Build {
+    /// The cgroup v2 controllers needed to enforce the configured limits, empty if no limits are configured
+    pub fn cgroup_controllers(&self) -> Vec<&'static str> {
+        let mut controllers = Vec::new();
+        if self.memory_max.is_some() {
+            controllers.push("memory");
+        }
+        if self.cpu_weight.is_some() {
+            controllers.push("cpu");
+        }
+        if self.pids_max.is_some() {
+            controllers.push("pids");
+        }
+        if !self.io_max.is_empty() {
+            controllers.push("io");
+        }
+        controllers
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AttestationFormat {
+    /// Signed in-toto link metadata
+    #[default]
+    Link,
+    /// in-toto Statement v1 in a DSSE envelope
+    Statement,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Sandbox {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Path to the bubblewrap binary, looked up in `$PATH` by default
+    pub bwrap: Option<PathBuf>,
+    /// Paths that are mounted read-only at the same location inside the sandbox
+    #[serde(default)]
+    pub ro_binds: Vec<PathBuf>,
+    /// Paths that are mounted writable at the same location inside the sandbox
+    #[serde(default)]
+    pub binds: Vec<PathBuf>,
+    #[serde(default)]
+    pub network: NetworkPolicy,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NetworkPolicy {
+    /// Only a loopback interface in a network namespace of its own
+    #[default]
+    None,
+    /// The network of the worker
+    Host,
 }
 
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
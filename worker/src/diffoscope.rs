@@ -5,8 +5,14 @@ use std::collections::HashMap;
 use std::ffi::OsString;
 use std::path::Path;
 use std::time::Duration;
+use tokio::sync::watch;
 
-pub async fn diffoscope(a: &Path, b: &Path, settings: &config::Diffoscope) -> Result<String> {
+pub async fn diffoscope(
+    a: &Path,
+    b: &Path,
+    settings: &config::Diffoscope,
+    cancel: Option<watch::Receiver<bool>>,
+) -> Result<String> {
     let mut args = settings.args.iter().map(OsString::from).collect::<Vec<_>>();
     let timeout = settings.timeout.unwrap_or(3600); // 1h
 
@@ -21,6 +27,10 @@ pub async fn diffoscope(a: &Path, b: &Path, settings: &config::Diffoscope) -> Re
         kill_at_size_limit: true,
         passthrough: false,
         envs: HashMap::new(),
+        log_stream: None,
+        cancel,
+        cgroup: None,
+        sandbox: None,
     };
     let bin = Path::new("diffoscope");
 
@@ -1,4 +1,5 @@
 use async_trait::async_trait;
+use rebuilderd_common::api::v1::PingQueuedJobResponse;
 use rebuilderd_common::errors::*;
 use std::time::Duration;
 
@@ -6,5 +7,5 @@ use std::time::Duration;
 pub trait HeartBeat {
     fn interval(&self) -> Duration;
 
-    async fn ping(&self) -> Result<()>;
+    async fn ping(&self) -> Result<PingQueuedJobResponse>;
 }
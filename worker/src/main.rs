@@ -6,12 +6,14 @@ use async_trait::async_trait;
 use chrono::Utc;
 use clap::Parser;
 use env_logger::Env;
+use futures_util::future;
 use in_toto::crypto::PrivateKey;
-use rebuilderd_common::api::Client;
 use rebuilderd_common::api::v1::{
-    ArtifactStatus, BuildRestApi, BuildStatus, JobAssignment, PopQueuedJobRequest, QueueRestApi,
-    QueuedJobArtifact, RebuildReport, RegisterWorkerRequest, WorkerRestApi,
+    ArtifactStatus, BuildRestApi, BuildStatus, JobAssignment, PingQueuedJobResponse,
+    PopQueuedJobRequest, QueueRestApi, QueuedJobArtifact, RebuildReport, RegisterWorkerRequest,
+    WorkerRestApi,
 };
+use rebuilderd_common::api::{Client, is_lease_lost};
 use rebuilderd_common::auth::find_auth_cookie;
 use rebuilderd_common::config::*;
 use rebuilderd_common::errors::Context as _;
@@ -20,22 +22,49 @@ use rebuilderd_common::utils::zstd_compress;
 use std::fs;
 use std::io::{self, Write};
 use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
+use tokio::sync::{Mutex, mpsc, watch};
 use tokio::time;
 
 pub mod args;
 pub mod auth;
+pub mod cgroup;
 pub mod config;
 pub mod diffoscope;
 pub mod download;
 pub mod heartbeat;
 pub mod proc;
 pub mod rebuild;
+pub mod sandbox;
 pub mod setup;
 
 pub struct HttpHeartBeat<'a> {
     client: &'a Client,
     queue_id: i32,
+    lease: String,
+    log_stream: Mutex<mpsc::UnboundedReceiver<Vec<u8>>>,
+}
+
+impl HttpHeartBeat<'_> {
+    /// Uploads the output the build has produced since the last push
+    async fn push_log(&self) {
+        let mut chunk = Vec::new();
+        {
+            let mut log_stream = self.log_stream.lock().await;
+            while let Ok(data) = log_stream.try_recv() {
+                chunk.extend(data);
+            }
+        }
+        if !chunk.is_empty()
+            && let Err(err) = self
+                .client
+                .push_queued_job_log(self.queue_id, &self.lease, chunk)
+                .await
+        {
+            warn!("Failed to upload build log: {}", err);
+        }
+    }
 }
 
 #[async_trait]
@@ -44,15 +73,30 @@ impl heartbeat::HeartBeat for HttpHeartBeat<'_> {
         Duration::from_secs(PING_INTERVAL)
     }
 
-    async fn ping(&self) -> Result<()> {
-        if let Err(err) = self.client.ping_job(self.queue_id).await {
-            warn!("Failed to ping: {}", err);
+    async fn ping(&self) -> Result<PingQueuedJobResponse> {
+        self.push_log().await;
+
+        match self.client.ping_job(self.queue_id, &self.lease).await {
+            Ok(response) => Ok(response),
+            Err(err) if is_lease_lost(&err) => {
+                warn!("Job has been assigned to another worker, cancelling");
+                Ok(PingQueuedJobResponse::Cancel)
+            }
+            Err(err) => {
+                warn!("Failed to ping: {}", err);
+                Ok(PingQueuedJobResponse::Continue)
+            }
         }
-        Ok(())
     }
 }
 
-async fn rebuild(client: &Client, privkey: &PrivateKey, config: &config::ConfigFile) -> Result<()> {
+async fn rebuild(
+    client: &Client,
+    name: &str,
+    privkey: &PrivateKey,
+    config: &config::ConfigFile,
+    cgroups: Option<&cgroup::Delegation>,
+) -> Result<()> {
     info!("Requesting work from rebuilderd...");
     let supported_backends = config.backends.keys().map(String::from).collect::<Vec<_>>();
 
@@ -68,6 +112,7 @@ async fn rebuild(client: &Client, privkey: &PrivateKey, config: &config::ConfigF
             supported_backends,
             architecture: std::env::consts::ARCH.to_string(),
             supported_architectures,
+            labels: config.labels.clone(),
         })
         .await?
     {
@@ -77,7 +122,10 @@ async fn rebuild(client: &Client, privkey: &PrivateKey, config: &config::ConfigF
             time::sleep(Duration::from_secs(idle_delay)).await;
         }
         JobAssignment::Rebuild(rb) => {
-            info!("Starting rebuild of {:?} {:?}", rb.job.name, rb.job.version);
+            info!(
+                "Starting rebuild of {:?} {:?} (job #{})",
+                rb.job.name, rb.job.version, rb.job.id
+            );
 
             let backend = config
                 .backends
@@ -85,33 +133,42 @@ async fn rebuild(client: &Client, privkey: &PrivateKey, config: &config::ConfigF
                 .cloned()
                 .ok_or_else(|| anyhow!("No backend for {:?} configured", rb.job.distribution))?;
 
+            let (log_tx, log_rx) = mpsc::unbounded_channel();
+
             let ctx = Context {
                 artifacts: rb.artifacts.clone(),
                 input_url: Some(rb.job.url.clone()),
+                backend_name: rb.job.backend.clone(),
                 backend,
+                worker_name: name.to_string(),
                 build: config.build.clone(),
                 diffoscope: config.diffoscope.clone(),
                 privkey,
+                log_stream: Some(log_tx),
+                cancel: Some(watch::channel(false).0),
+                cgroups,
             };
 
             let hb = HttpHeartBeat {
                 client,
                 queue_id: rb.job.id,
+                lease: rb.lease.clone(),
+                log_stream: Mutex::new(log_rx),
             };
 
             let mut log = Vec::new();
 
-            let (overall_status, rebuilds) =
+            let (overall_status, failure_reason, rebuilds) =
                 match rebuild::rebuild_with_heartbeat(&ctx, &mut log, &hb).await {
-                    Ok(res) => {
-                        let overall_status = if res.iter().all(|r| r.status == ArtifactStatus::Good)
-                        {
-                            BuildStatus::Good
-                        } else {
-                            BuildStatus::Bad
-                        };
-
-                        (overall_status, res)
+                    Ok(Some(outcome)) => {
+                        (outcome.status, outcome.failure_reason, outcome.artifacts)
+                    }
+                    Ok(None) => {
+                        info!(
+                            "Rebuild of job #{} has been cancelled, moving on to the next job",
+                            rb.job.id
+                        );
+                        return Ok(());
                     }
                     Err(err) => {
                         error!(
@@ -129,9 +186,11 @@ async fn rebuild(client: &Client, privkey: &PrivateKey, config: &config::ConfigF
                         }
 
                         log.extend(msg.as_bytes());
-                        (BuildStatus::Fail, vec![]) // TODO: good or bad idea? no artifact results from failed builds
+                        (BuildStatus::Fail, None, vec![]) // TODO: good or bad idea? no artifact results from failed builds
                     }
                 };
+            // the output since the last ping would be missing from the live log otherwise
+            hb.push_log().await;
 
             let utf8_sanitized_log = String::from_utf8_lossy(&log).into_owned();
             let encoded_log = zstd_compress(utf8_sanitized_log.as_bytes())
@@ -140,29 +199,41 @@ async fn rebuild(client: &Client, privkey: &PrivateKey, config: &config::ConfigF
 
             let report = RebuildReport {
                 queue_id: rb.job.id,
+                lease: rb.lease,
                 built_at: Utc::now().naive_utc(),
                 build_log: encoded_log,
                 status: overall_status,
+                failure_reason,
                 artifacts: rebuilds,
             };
 
-            info!("Sending build report to rebuilderd...");
-            client
-                .submit_build_report(report)
-                .await
-                .context("Failed to report build to rebuilderd")?;
+            info!(
+                "Sending build report for job #{} to rebuilderd...",
+                rb.job.id
+            );
+            if let Err(err) = client.submit_build_report(report).await {
+                if is_lease_lost(&err) {
+                    warn!("Build report was rejected, the job has been assigned to another worker");
+                } else {
+                    return Err(err.context("Failed to report build to rebuilderd"));
+                }
+            }
         }
     }
     Ok(())
 }
 
-async fn run_worker_loop(
+/// Keeps requesting and building jobs until a restart has been requested. Every slot of the worker runs one of these.
+async fn run_worker_slot(
     client: &Client,
+    name: &str,
     privkey: &PrivateKey,
     config: &config::ConfigFile,
+    cgroups: Option<&cgroup::Delegation>,
+    restart: &AtomicBool,
 ) -> Result<()> {
     loop {
-        if let Err(err) = rebuild(client, privkey, config).await {
+        if let Err(err) = rebuild(client, name, privkey, config, cgroups).await {
             error!(
                 "Unexpected error, sleeping for {}s: {:#}",
                 API_ERROR_DELAY, err
@@ -170,9 +241,15 @@ async fn run_worker_loop(
             time::sleep(Duration::from_secs(API_ERROR_DELAY)).await;
         }
 
+        // another slot has already found the restart flag
+        if restart.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+
         let restart_flag = Path::new("rebuilderd.restart");
         if restart_flag.exists() {
             info!("Restart flag exists, initiating shutdown");
+            restart.store(true, Ordering::SeqCst);
             if let Err(err) = fs::remove_file(restart_flag) {
                 error!("Failed to remove restart flag: {:#}", err);
             }
@@ -183,6 +260,38 @@ async fn run_worker_loop(
     }
 }
 
+async fn run_worker_loop(
+    client: &Client,
+    name: &str,
+    privkey: &PrivateKey,
+    config: &config::ConfigFile,
+    cgroups: Option<&cgroup::Delegation>,
+) -> Result<()> {
+    let max_parallel_jobs = config.max_parallel_jobs();
+    if max_parallel_jobs > 1 {
+        info!("Building up to {} jobs at the same time", max_parallel_jobs);
+    }
+
+    // the slots finish their current job before shutting down
+    let restart = AtomicBool::new(false);
+    let slots = (0..max_parallel_jobs)
+        .map(|_| run_worker_slot(client, name, privkey, config, cgroups, &restart))
+        .collect::<Vec<_>>();
+    future::try_join_all(slots).await?;
+
+    Ok(())
+}
+
+/// Resource limits are enforced with cgroups, the worker only needs a delegated cgroup subtree if any are configured
+fn setup_cgroups(build: &config::Build) -> Result<Option<cgroup::Delegation>> {
+    if build.cgroup_controllers().is_empty() {
+        return Ok(None);
+    }
+    let delegation =
+        cgroup::Delegation::setup(build).context("Failed to setup cgroups for resource limits")?;
+    Ok(Some(delegation))
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     let args = Args::parse();
@@ -206,6 +315,7 @@ async fn main() -> Result<()> {
         setup::run(name).context("Failed to setup worker")?;
     }
     let profile = auth::load()?;
+    let name = args.name.unwrap_or_else(|| "worker".to_string());
 
     match args.subcommand {
         SubCommand::Connect(connect) => {
@@ -228,13 +338,12 @@ async fn main() -> Result<()> {
             )?;
 
             client
-                .register_worker(RegisterWorkerRequest {
-                    name: args.name.unwrap_or("worker".to_string()),
-                })
+                .register_worker(RegisterWorkerRequest { name: name.clone() })
                 .await
                 .context("Failed to register worker with rebuilderd daemon")?;
 
-            run_worker_loop(&client, &profile.privkey, &config).await?;
+            let cgroups = setup_cgroups(&config.build)?;
+            run_worker_loop(&client, &name, &profile.privkey, &config, cgroups.as_ref()).await?;
         }
         // this is only really for debugging
         SubCommand::Build(build) => {
@@ -255,9 +364,10 @@ async fn main() -> Result<()> {
                 ..Default::default()
             };
 
+            let cgroups = setup_cgroups(&config.build)?;
             let mut log = Vec::new();
 
-            let res = rebuild::rebuild(
+            let outcome = rebuild::rebuild(
                 &Context {
                     artifacts: vec![QueuedJobArtifact {
                         name: "anonymous".to_string(),
@@ -266,16 +376,25 @@ async fn main() -> Result<()> {
                         url: build.artifact_url,
                     }],
                     input_url: build.input_url,
+                    backend_name: build.distro,
                     backend,
+                    worker_name: name,
                     build: config.build,
                     diffoscope,
                     privkey: &profile.privkey,
+                    log_stream: None,
+                    cancel: None,
+                    cgroups: cgroups.as_ref(),
                 },
                 &mut log,
             )
             .await?;
 
-            for res in res {
+            if outcome.status == BuildStatus::Fail {
+                bail!("Failed to rebuild package");
+            }
+
+            for res in outcome.artifacts {
                 trace!("rebuild result object {:?}", res);
 
                 if res.status == ArtifactStatus::Good {
@@ -290,7 +409,8 @@ async fn main() -> Result<()> {
         }
         SubCommand::Diffoscope(diffoscope) => {
             let output =
-                diffoscope::diffoscope(&diffoscope.a, &diffoscope.b, &config.diffoscope).await?;
+                diffoscope::diffoscope(&diffoscope.a, &diffoscope.b, &config.diffoscope, None)
+                    .await?;
             print!("{}", output);
         }
         SubCommand::CheckConfig => {
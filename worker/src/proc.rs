@@ -1,3 +1,4 @@
+use crate::sandbox::Sandbox;
 use futures_util::FutureExt;
 use nix::sys::signal::{self, Signal};
 use nix::unistd::Pid;
@@ -6,12 +7,16 @@ use std::cmp;
 use std::collections::HashMap;
 use std::ffi::OsStr;
 use std::fmt;
-use std::path::Path;
+use std::fs::OpenOptions;
+use std::future;
+use std::os::fd::{AsRawFd, BorrowedFd};
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
 use std::time::{Duration, Instant};
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::process::{Child, Command};
 use tokio::select;
+use tokio::sync::{mpsc, watch};
 use tokio::time;
 
 const SIGKILL_DELAY: u64 = 10;
@@ -22,6 +27,14 @@ pub struct Options {
     pub kill_at_size_limit: bool,
     pub passthrough: bool,
     pub envs: HashMap<String, String>,
+    /// Receives a copy of everything that's appended to the captured output, while the process is still running.
+    pub log_stream: Option<mpsc::UnboundedSender<Vec<u8>>>,
+    /// Once this is set to true the process group is terminated, like after a timeout.
+    pub cancel: Option<watch::Receiver<bool>>,
+    /// The process is moved into this cgroup before it's executed, so all of its children are subject to its limits.
+    pub cgroup: Option<PathBuf>,
+    /// Executes the process with bubblewrap, so it can only see what has been mounted into the sandbox.
+    pub sandbox: Option<Sandbox>,
 }
 
 pub struct Capture<'a> {
@@ -32,6 +45,7 @@ pub struct Capture<'a> {
     start: Instant,
     sigterm_sent: Option<Instant>,
     truncated: bool,
+    log_stream: Option<mpsc::UnboundedSender<Vec<u8>>>,
 }
 
 pub fn capture(output: &mut Vec<u8>, opts: Options) -> Capture<'_> {
@@ -44,10 +58,19 @@ pub fn capture(output: &mut Vec<u8>, opts: Options) -> Capture<'_> {
         start,
         sigterm_sent: None,
         truncated: false,
+        log_stream: opts.log_stream,
     }
 }
 
 impl Capture<'_> {
+    fn append(&mut self, data: &[u8]) {
+        self.output.extend(data);
+        if let Some(log_stream) = &self.log_stream {
+            // the receiving end going away is not a reason to stop the build
+            log_stream.send(data.to_vec()).ok();
+        }
+    }
+
     pub async fn push_bytes(&mut self, child: &mut Child, mut slice: &[u8]) -> Result<()> {
         if !self.truncated {
             if let Some(size_limit) = &self.size_limit {
@@ -68,7 +91,7 @@ impl Capture<'_> {
                 }
             }
 
-            self.output.extend(slice);
+            self.append(slice);
         }
 
         Ok(())
@@ -90,11 +113,20 @@ impl Capture<'_> {
             self.sigterm_sent = Some(Instant::now());
         }
 
-        self.output.extend(format!("\n\n{}\n\n", reason).as_bytes());
+        self.append(format!("\n\n{}\n\n", reason).as_bytes());
         self.truncated = true;
         Ok(())
     }
 
+    pub async fn cancel(&mut self, child: &mut Child) -> Result<()> {
+        if self.sigterm_sent.is_none() {
+            warn!("child has been cancelled, killing...");
+            self.truncate(child, "CANCELLED BY REBUILDERD", true)
+                .await?;
+        }
+        Ok(())
+    }
+
     pub async fn next_wakeup(
         &mut self,
         child: &mut Child,
@@ -134,31 +166,66 @@ impl Capture<'_> {
     }
 }
 
-pub async fn run<I, S>(bin: &Path, args: I, opts: Options, log: &mut Vec<u8>) -> Result<bool>
+/// Resolves once the process is supposed to be cancelled, never resolves if there's nobody who could cancel it
+async fn cancelled(cancel: &mut Option<watch::Receiver<bool>>) {
+    if let Some(cancel) = cancel
+        && cancel.wait_for(|cancelled| *cancelled).await.is_ok()
+    {
+        return;
+    }
+    future::pending().await
+}
+
+pub async fn run<I, S>(bin: &Path, args: I, mut opts: Options, log: &mut Vec<u8>) -> Result<bool>
 where
     I: IntoIterator<Item = S> + fmt::Debug,
     S: AsRef<OsStr>,
 {
     info!("Running {:?} {:?}", bin, args);
-    let mut cmd = Command::new(bin);
+    let mut cmd = if let Some(sandbox) = &opts.sandbox {
+        debug!("Running in sandbox: {:?}", sandbox);
+        sandbox.command(bin, &opts.envs)
+    } else {
+        let mut cmd = Command::new(bin);
+        cmd.envs(&opts.envs);
+        cmd
+    };
     cmd.args(args)
         .stdin(Stdio::null())
         .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .envs(&opts.envs);
+        .stderr(Stdio::piped());
+
+    // opened before forking, the file is closed on exec
+    let cgroup_procs = opts
+        .cgroup
+        .as_ref()
+        .map(|cgroup| {
+            let path = cgroup.join("cgroup.procs");
+            OpenOptions::new()
+                .write(true)
+                .open(&path)
+                .with_context(|| anyhow!("Failed to open {:?}", path))
+        })
+        .transpose()?;
+    let cgroup_fd = cgroup_procs.as_ref().map(|file| file.as_raw_fd());
 
     unsafe {
-        cmd.pre_exec(|| {
+        cmd.pre_exec(move || {
             // create a new process group
             let pid = nix::unistd::getpid();
             if let Err(err) = nix::unistd::setpgid(pid, Pid::from_raw(0)) {
                 warn!("Failed to create new process group: {:#?}", err);
             }
+            // the build must not run without its limits, so this is fatal
+            if let Some(fd) = cgroup_fd {
+                nix::unistd::write(BorrowedFd::borrow_raw(fd), b"0")?;
+            }
             Ok(())
         });
     }
 
     let mut child = cmd.spawn()?;
+    drop(cgroup_procs);
 
     let mut child_stdout = child.stdout.take().unwrap();
     let mut child_stderr = child.stderr.take().unwrap();
@@ -172,8 +239,14 @@ where
 
     let mut stdout_open = true;
     let mut stderr_open = true;
+    let mut cancel = opts.cancel.take();
     let mut cap = capture(log, opts);
     let success = loop {
+        if cancel.as_ref().is_some_and(|cancel| *cancel.borrow()) {
+            cancel = None;
+            cap.cancel(&mut child).await?;
+        }
+
         let remaining = cap
             .next_wakeup(&mut child, &mut stdout_open, &mut stderr_open)
             .await?;
@@ -205,6 +278,7 @@ where
                     }
                 },
                 _ = time::sleep(remaining).fuse() => continue,
+                _ = cancelled(&mut cancel).fuse() => continue,
             }
         } else {
             select! {
@@ -214,6 +288,7 @@ where
                     break status.success();
                 }
                 _ = time::sleep(remaining).fuse() => continue,
+                _ = cancelled(&mut cancel).fuse() => continue,
             }
         }
     };
@@ -245,6 +320,10 @@ mod tests {
                 kill_at_size_limit: false,
                 passthrough: false,
                 envs: HashMap::new(),
+                log_stream: None,
+                cancel: None,
+                cgroup: None,
+                sandbox: None,
             },
         )
         .await
@@ -267,6 +346,10 @@ mod tests {
                 kill_at_size_limit: false,
                 passthrough: false,
                 envs: HashMap::new(),
+                log_stream: None,
+                cancel: None,
+                cgroup: None,
+                sandbox: None,
             },
         )
         .await
@@ -293,6 +376,10 @@ mod tests {
                 kill_at_size_limit: true,
                 passthrough: false,
                 envs: HashMap::new(),
+                log_stream: None,
+                cancel: None,
+                cgroup: None,
+                sandbox: None,
             },
         )
         .await
@@ -321,6 +408,10 @@ mod tests {
                 kill_at_size_limit: false,
                 passthrough: false,
                 envs: HashMap::new(),
+                log_stream: None,
+                cancel: None,
+                cgroup: None,
+                sandbox: None,
             },
         )
         .await
@@ -349,6 +440,10 @@ mod tests {
                 kill_at_size_limit: false,
                 passthrough: false,
                 envs: HashMap::new(),
+                log_stream: None,
+                cancel: None,
+                cgroup: None,
+                sandbox: None,
             },
         )
         .await
@@ -361,4 +456,44 @@ mod tests {
         assert!(duration > Duration::from_secs(1));
         assert!(duration < Duration::from_secs(2));
     }
+
+    #[tokio::test]
+    async fn cancel() {
+        let (cancel_tx, cancel_rx) = watch::channel(false);
+        tokio::spawn(async move {
+            time::sleep(Duration::from_millis(1500)).await;
+            cancel_tx.send_replace(true);
+            // keep the sender around until the process is gone
+            time::sleep(Duration::from_secs(5)).await;
+        });
+
+        let (success, output, duration) = script(
+            "
+        for x in `seq 100`; do
+            /bin/echo AAAAAAAAAAAAAAAAAAAAAAAA
+            sleep 1
+        done
+        ",
+            Options {
+                timeout: Duration::from_secs(600),
+                size_limit: None,
+                kill_at_size_limit: false,
+                passthrough: false,
+                envs: HashMap::new(),
+                log_stream: None,
+                cancel: Some(cancel_rx),
+                cgroup: None,
+                sandbox: None,
+            },
+        )
+        .await
+        .unwrap();
+        assert!(!success);
+        assert_eq!(
+            output,
+            "AAAAAAAAAAAAAAAAAAAAAAAA\nAAAAAAAAAAAAAAAAAAAAAAAA\n\n\nCANCELLED BY REBUILDERD\n\n"
+        );
+        assert!(duration > Duration::from_secs(1));
+        assert!(duration < Duration::from_secs(3));
+    }
 }
@@ -1,15 +1,28 @@
+use crate::cgroup;
 use crate::config;
 use crate::diffoscope::diffoscope;
 use crate::download::download;
 use crate::heartbeat::HeartBeat;
 use crate::proc;
+use crate::sandbox::Sandbox;
+use data_encoding::HEXLOWER;
 use in_toto::crypto::PrivateKey;
 use in_toto::runlib::in_toto_run;
-use rebuilderd_common::api::v1::{ArtifactStatus, QueuedJobArtifact, RebuildArtifactReport};
+use rebuilderd_common::api::v1::{
+    ArtifactStatus, BuildStatus, FailureReason, PingQueuedJobResponse, QueuedJobArtifact,
+    RebuildArtifactReport,
+};
+use rebuilderd_common::dsse::{Envelope, IN_TOTO_PAYLOAD_TYPE};
 use rebuilderd_common::errors::Context as _;
 use rebuilderd_common::errors::*;
+use rebuilderd_common::statement::{
+    REBUILD_PREDICATE_TYPE_V1, RebuildPredicate, ResourceDescriptor, Statement,
+    VERDICT_PREDICATE_TYPE_V1, VerdictPredicate,
+};
 use rebuilderd_common::utils::zstd_compress;
-use std::collections::HashMap;
+use sha2::{Digest, Sha256, Sha512};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
 use std::fs;
 use std::io::ErrorKind;
 use std::path::Path;
@@ -17,15 +30,29 @@ use std::time::Duration;
 use tokio::fs::File;
 use tokio::io::AsyncReadExt;
 use tokio::select;
+use tokio::sync::{mpsc, watch};
 use tokio::time;
 
 pub struct Context<'a> {
     pub artifacts: Vec<QueuedJobArtifact>,
     pub input_url: Option<String>,
+    pub backend_name: String,
     pub backend: config::Backend,
+    pub worker_name: String,
     pub build: config::Build,
     pub diffoscope: config::Diffoscope,
     pub privkey: &'a PrivateKey,
+    pub log_stream: Option<mpsc::UnboundedSender<Vec<u8>>>,
+    /// Terminates the processes of the build once it's set to true.
+    pub cancel: Option<watch::Sender<bool>>,
+    /// Runs the backend in a cgroup with resource limits, if any have been configured.
+    pub cgroups: Option<&'a cgroup::Delegation>,
+}
+
+impl Context<'_> {
+    fn is_cancelled(&self) -> bool {
+        self.cancel.as_ref().is_some_and(|cancel| *cancel.borrow())
+    }
 }
 
 fn path_to_string(path: &Path) -> Result<String> {
@@ -95,23 +122,100 @@ pub async fn compare_files(a: &Path, b: &Path) -> Result<bool> {
     }
 }
 
+pub struct FileDigests {
+    pub sha256: String,
+    pub sha512: String,
+}
+
+impl FileDigests {
+    fn to_map(&self) -> BTreeMap<String, String> {
+        [
+            ("sha256".to_string(), self.sha256.clone()),
+            ("sha512".to_string(), self.sha512.clone()),
+        ]
+        .into()
+    }
+}
+
+pub async fn digest_file(path: &Path) -> Result<FileDigests> {
+    let mut buf = [0u8; 4096];
+    let mut sha256 = Sha256::new();
+    let mut sha512 = Sha512::new();
+
+    let mut f = File::open(path)
+        .await
+        .with_context(|| anyhow!("Failed to open {:?}", path))?;
+    loop {
+        let n = f.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        sha256.update(&buf[..n]);
+        sha512.update(&buf[..n]);
+    }
+
+    Ok(FileDigests {
+        sha256: HEXLOWER.encode(&sha256.finalize()),
+        sha512: HEXLOWER.encode(&sha512.finalize()),
+    })
+}
+
+/// The result of a rebuild that got as far as running the build
+pub struct RebuildOutcome {
+    pub status: BuildStatus,
+    pub failure_reason: Option<FailureReason>,
+    pub artifacts: Vec<RebuildArtifactReport>,
+}
+
+/// The kernel has killed the build for exceeding the memory limit of its cgroup
+#[derive(Debug)]
+struct MemoryLimitExceeded {
+    limit: u64,
+}
+
+impl fmt::Display for MemoryLimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Build has been killed for exceeding the memory limit of {} bytes",
+            self.limit
+        )
+    }
+}
+
+impl std::error::Error for MemoryLimitExceeded {}
+
+/// Returns `None` if rebuilderd has cancelled the job while it was running, the build processes are terminated then.
 pub async fn rebuild_with_heartbeat(
     ctx: &Context<'_>,
     log: &mut Vec<u8>,
     hb: &dyn HeartBeat,
-) -> Result<Vec<RebuildArtifactReport>> {
+) -> Result<Option<RebuildOutcome>> {
     let mut rebuild = Box::pin(rebuild(ctx, log));
     loop {
         select! {
             res = &mut rebuild => {
-                return res;
+                return res.map(Some);
+            },
+            _ = time::sleep(hb.interval()) => {
+                if hb.ping().await? == PingQueuedJobResponse::Cancel {
+                    break;
+                }
             },
-            _ = time::sleep(hb.interval()) => hb.ping().await?,
         }
     }
+
+    if let Some(cancel) = &ctx.cancel {
+        cancel.send_replace(true);
+    }
+    // wait for the process group to be gone before starting with the next job
+    if let Err(err) = rebuild.await {
+        debug!("Cancelled rebuild has failed: {:#}", err);
+    }
+    Ok(None)
 }
 
-pub async fn rebuild(ctx: &Context<'_>, log: &mut Vec<u8>) -> Result<Vec<RebuildArtifactReport>> {
+pub async fn rebuild(ctx: &Context<'_>, log: &mut Vec<u8>) -> Result<RebuildOutcome> {
     // setup
     let tmp = tempfile::Builder::new().prefix("rebuilderd").tempdir()?;
 
@@ -150,24 +254,104 @@ pub async fn rebuild(ctx: &Context<'_>, log: &mut Vec<u8>) -> Result<Vec<Rebuild
     let input_path = inputs_dir.join(&input_filename);
 
     // rebuild
-    verify(ctx, log, &out_dir, &input_path).await?;
+    let result = verify(ctx, log, &out_dir, &input_path).await;
+    // the job is gone from the queue, don't bother with signing or diffing the results
+    if ctx.is_cancelled() {
+        bail!("Rebuild has been cancelled");
+    }
+    if let Err(err) = result {
+        error!("Rebuild failed: {:#}", err);
+
+        if !log.is_empty() {
+            log.extend(b"\n\n");
+        }
+        log.extend(format!("rebuilderd: rebuild failed: {:#}\n", err).as_bytes());
+        let failure_reason = err
+            .downcast_ref::<MemoryLimitExceeded>()
+            .map(|_| FailureReason::Oom);
+
+        // we can't tell if the artifacts are reproducible, but we can sign that we tried
+        let mut results = Vec::new();
+        for (artifact, artifact_filename, artifact_path) in artifacts {
+            let upstream = digest_file(&artifact_path).await?;
+
+            info!("Generating signed verdict");
+            let attestation = verdict_attestation(
+                ctx,
+                &artifact,
+                &artifact_filename,
+                &input_path,
+                BuildStatus::Fail,
+                &upstream,
+                None,
+            )
+            .await;
+
+            results.push(RebuildArtifactReport {
+                name: artifact.name,
+                diffoscope: None,
+                attestation: encode_attestation(attestation).await?,
+                status: ArtifactStatus::Unknown,
+                upstream_sha256: Some(upstream.sha256),
+                rebuilt_sha256: None,
+                upstream_sha512: Some(upstream.sha512),
+                rebuilt_sha512: None,
+            });
+        }
+
+        return Ok(RebuildOutcome {
+            status: BuildStatus::Fail,
+            failure_reason,
+            artifacts: results,
+        });
+    }
 
     // process results
     let mut results = Vec::new();
     for (artifact, artifact_filename, artifact_path) in artifacts {
         let output_path = out_dir.join(&artifact_filename);
 
-        let result = if !output_path.exists() {
+        let upstream = digest_file(&artifact_path).await?;
+        let rebuilt = if output_path.exists() {
+            Some(digest_file(&output_path).await?)
+        } else {
+            None
+        };
+
+        let upstream_sha256 = Some(upstream.sha256.clone());
+        let upstream_sha512 = Some(upstream.sha512.clone());
+        let (rebuilt_sha256, rebuilt_sha512) = match &rebuilt {
+            Some(rebuilt) => (Some(rebuilt.sha256.clone()), Some(rebuilt.sha512.clone())),
+            None => (None, None),
+        };
+
+        let result = if rebuilt.is_none() {
             info!(
                 "No output artifact found, marking as BAD: {:?}",
                 output_path
             );
 
+            info!("Generating signed verdict");
+            let attestation = verdict_attestation(
+                ctx,
+                &artifact,
+                &artifact_filename,
+                &input_path,
+                BuildStatus::Bad,
+                &upstream,
+                None,
+            )
+            .await;
+
             RebuildArtifactReport {
                 name: artifact.name,
                 diffoscope: None,
-                attestation: None,
+                attestation: encode_attestation(attestation).await?,
                 status: ArtifactStatus::Bad,
+                upstream_sha256,
+                rebuilt_sha256,
+                upstream_sha512,
+                rebuilt_sha512,
             }
         } else if compare_files(&artifact_path, &output_path).await? {
             info!(
@@ -175,62 +359,70 @@ pub async fn rebuild(ctx: &Context<'_>, log: &mut Vec<u8>) -> Result<Vec<Rebuild
                 output_path
             );
 
-            let mut res = RebuildArtifactReport {
+            let attestation = match ctx.build.attestation_format {
+                config::AttestationFormat::Link => {
+                    info!("Generating signed link");
+                    link_attestation(ctx, &artifact_filename, &input_path, &output_path)
+                }
+                config::AttestationFormat::Statement => {
+                    info!("Generating signed statement");
+                    statement_attestation(
+                        ctx,
+                        &artifact,
+                        &artifact_filename,
+                        &input_path,
+                        &upstream,
+                    )
+                    .await
+                }
+            };
+
+            RebuildArtifactReport {
                 name: artifact.name,
                 diffoscope: None,
-                attestation: None,
+                attestation: encode_attestation(attestation).await?,
                 status: ArtifactStatus::Good,
-            };
-
-            info!("Generating signed link");
-            match in_toto_run(
-                &format!("rebuild {}", artifact_filename.to_str().unwrap()),
-                None,
-                &[input_path
-                    .to_str()
-                    .ok_or_else(|| anyhow!("Input path contains invalid characters"))?],
-                &[output_path
-                    .to_str()
-                    .ok_or_else(|| anyhow!("Output path contains invalid characters"))?],
-                &[],
-                Some(ctx.privkey),
-                Some(&["sha512", "sha256"]),
-                Some(&[
-                    &format!("{}/", inputs_dir.to_str().unwrap()),
-                    &format!("{}/", out_dir.to_str().unwrap()),
-                ]),
-            ) {
-                Ok(signed_link) => {
-                    info!("Signed link generated");
-
-                    let attestation = serde_json::to_string(&signed_link)
-                        .context("Failed to serialize attestation")?;
-
-                    let encoded_attestation = zstd_compress(attestation.as_bytes())
-                        .await
-                        .map_err(Error::from)?;
-
-                    res.attestation = Some(encoded_attestation);
-                }
-                Err(err) => warn!("Failed to generate in-toto attestation: {:#?}", err),
+                upstream_sha256,
+                rebuilt_sha256,
+                upstream_sha512,
+                rebuilt_sha512,
             }
-
-            res
         } else {
             info!("Output artifact differs, marking as BAD: {:?}", output_path);
 
+            info!("Generating signed verdict");
+            let attestation = verdict_attestation(
+                ctx,
+                &artifact,
+                &artifact_filename,
+                &input_path,
+                BuildStatus::Bad,
+                &upstream,
+                rebuilt.as_ref(),
+            )
+            .await;
+
             let mut res = RebuildArtifactReport {
                 name: artifact.name,
                 diffoscope: None,
-                attestation: None,
+                attestation: encode_attestation(attestation).await?,
                 status: ArtifactStatus::Bad,
+                upstream_sha256,
+                rebuilt_sha256,
+                upstream_sha512,
+                rebuilt_sha512,
             };
 
             // generate diffoscope diff if enabled
             if ctx.diffoscope.enabled {
-                let diff = diffoscope(&artifact_path, &output_path, &ctx.diffoscope)
-                    .await
-                    .context("Failed to run diffoscope")?;
+                let diff = diffoscope(
+                    &artifact_path,
+                    &output_path,
+                    &ctx.diffoscope,
+                    ctx.cancel.as_ref().map(watch::Sender::subscribe),
+                )
+                .await
+                .context("Failed to run diffoscope")?;
 
                 let encoded_diffoscope =
                     zstd_compress(diff.as_bytes()).await.map_err(Error::from)?;
@@ -244,7 +436,159 @@ pub async fn rebuild(ctx: &Context<'_>, log: &mut Vec<u8>) -> Result<Vec<Rebuild
         results.push(result);
     }
 
-    Ok(results)
+    let status = if results.iter().all(|r| r.status == ArtifactStatus::Good) {
+        BuildStatus::Good
+    } else {
+        BuildStatus::Bad
+    };
+
+    Ok(RebuildOutcome {
+        status,
+        failure_reason: None,
+        artifacts: results,
+    })
+}
+
+/// Compresses a generated attestation. Failing to create one is logged, but the rebuild result is reported anyway.
+async fn encode_attestation(attestation: Result<String>) -> Result<Option<Vec<u8>>> {
+    match attestation {
+        Ok(attestation) => {
+            info!("Signed attestation generated");
+
+            let encoded_attestation = zstd_compress(attestation.as_bytes())
+                .await
+                .map_err(Error::from)?;
+            Ok(Some(encoded_attestation))
+        }
+        Err(err) => {
+            warn!("Failed to generate in-toto attestation: {:#}", err);
+            Ok(None)
+        }
+    }
+}
+
+fn link_attestation(
+    ctx: &Context<'_>,
+    artifact_filename: &Path,
+    input_path: &Path,
+    output_path: &Path,
+) -> Result<String> {
+    let input_dir = input_path
+        .parent()
+        .context("Input path has no parent directory")?;
+    let output_dir = output_path
+        .parent()
+        .context("Output path has no parent directory")?;
+
+    let signed_link = in_toto_run(
+        &format!("rebuild {}", path_to_string(artifact_filename)?),
+        None,
+        &[&path_to_string(input_path)?],
+        &[&path_to_string(output_path)?],
+        &[],
+        Some(ctx.privkey),
+        Some(&["sha512", "sha256"]),
+        Some(&[
+            &format!("{}/", path_to_string(input_dir)?),
+            &format!("{}/", path_to_string(output_dir)?),
+        ]),
+    )?;
+
+    serde_json::to_string(&signed_link).context("Failed to serialize attestation")
+}
+
+async fn build_input_descriptor(
+    ctx: &Context<'_>,
+    input_path: &Path,
+) -> Result<ResourceDescriptor> {
+    let input = digest_file(input_path).await?;
+    let input_url = ctx
+        .input_url
+        .clone()
+        .or_else(|| ctx.artifacts.first().map(|a| a.url.clone()));
+
+    Ok(ResourceDescriptor {
+        name: None,
+        uri: input_url,
+        digest: input.to_map(),
+    })
+}
+
+fn upstream_descriptor(artifact: &QueuedJobArtifact, upstream: &FileDigests) -> ResourceDescriptor {
+    ResourceDescriptor {
+        name: None,
+        uri: Some(artifact.url.clone()),
+        digest: upstream.to_map(),
+    }
+}
+
+fn sign_statement(ctx: &Context<'_>, statement: &Statement) -> Result<String> {
+    let payload = serde_json::to_vec(statement).context("Failed to serialize statement")?;
+
+    let mut envelope = Envelope::new(IN_TOTO_PAYLOAD_TYPE, &payload);
+    envelope.sign(ctx.privkey)?;
+
+    serde_json::to_string(&envelope).context("Failed to serialize attestation")
+}
+
+/// Creates an in-toto statement about the artifact and wraps it in a signed DSSE envelope. Only call this for
+/// artifacts that have been reproduced, the rebuilt file is identical to the upstream one.
+async fn statement_attestation(
+    ctx: &Context<'_>,
+    artifact: &QueuedJobArtifact,
+    artifact_filename: &Path,
+    input_path: &Path,
+    upstream: &FileDigests,
+) -> Result<String> {
+    let statement = Statement::new(
+        vec![ResourceDescriptor {
+            name: Some(path_to_string(artifact_filename)?),
+            uri: None,
+            digest: upstream.to_map(),
+        }],
+        REBUILD_PREDICATE_TYPE_V1,
+        &RebuildPredicate {
+            build_input: build_input_descriptor(ctx, input_path).await?,
+            upstream: upstream_descriptor(artifact, upstream),
+            backend: ctx.backend_name.clone(),
+            worker: ctx.worker_name.clone(),
+        },
+    )?;
+    sign_statement(ctx, &statement)
+}
+
+/// Creates a signed statement that we've tried and failed to reproduce the artifact. These are always in-toto
+/// statements, links can't express the outcome.
+async fn verdict_attestation(
+    ctx: &Context<'_>,
+    artifact: &QueuedJobArtifact,
+    artifact_filename: &Path,
+    input_path: &Path,
+    status: BuildStatus,
+    upstream: &FileDigests,
+    rebuilt: Option<&FileDigests>,
+) -> Result<String> {
+    let statement = Statement::new(
+        vec![ResourceDescriptor {
+            name: Some(path_to_string(artifact_filename)?),
+            uri: None,
+            digest: upstream.to_map(),
+        }],
+        VERDICT_PREDICATE_TYPE_V1,
+        &VerdictPredicate {
+            status,
+            build_input: build_input_descriptor(ctx, input_path).await?,
+            upstream: upstream_descriptor(artifact, upstream),
+            rebuilt: rebuilt.map(|rebuilt| ResourceDescriptor {
+                name: None,
+                uri: None,
+                digest: rebuilt.to_map(),
+            }),
+            backend: ctx.backend_name.clone(),
+            worker: ctx.worker_name.clone(),
+        },
+    )?;
+    sign_statement(ctx, &statement)
 }
 
 async fn verify(
@@ -253,28 +597,119 @@ async fn verify(
     out_dir: &Path,
     input_path: &Path,
 ) -> Result<()> {
-    let bin = &ctx.backend.path;
+    let mut bin = ctx.backend.path.clone();
+    let sandbox = if ctx.build.sandbox.enabled {
+        // the backend is mounted at its canonical path, the sandbox has a working directory of its own
+        bin = fs::canonicalize(&bin)
+            .with_context(|| anyhow!("Failed to resolve backend path {:?}", bin))?;
+        let inputs_dir = input_path
+            .parent()
+            .context("Input path has no parent directory")?;
+
+        Some(
+            Sandbox::new(&ctx.build.sandbox)
+                .ro_bind(&bin)
+                .ro_bind(inputs_dir)
+                .bind(out_dir),
+        )
+    } else {
+        None
+    };
     let timeout = ctx.build.timeout.unwrap_or(3600 * 24); // 24h
 
     let mut envs = HashMap::new();
     envs.insert("REBUILDERD_OUTDIR".into(), path_to_string(out_dir)?);
 
+    let cgroup = match ctx.cgroups {
+        Some(cgroups) => Some(
+            cgroups
+                .create()
+                .await
+                .context("Failed to create cgroup for build")?,
+        ),
+        None => None,
+    };
+
     let opts = proc::Options {
         timeout: Duration::from_secs(timeout),
         size_limit: ctx.build.max_bytes,
         kill_at_size_limit: false,
         passthrough: !ctx.build.silent,
         envs,
+        log_stream: ctx.log_stream.clone(),
+        cancel: ctx.cancel.as_ref().map(watch::Sender::subscribe),
+        cgroup: cgroup.as_ref().map(|cgroup| cgroup.path().to_owned()),
+        sandbox,
     };
 
-    proc::run(bin.as_ref(), &[input_path], opts, log).await?;
+    let result = proc::run(&bin, &[input_path], opts, log).await;
+
+    if let Some(cgroup) = cgroup {
+        let oom_kills = cgroup.oom_kills();
+        if let Err(err) = cgroup.remove().await {
+            warn!("{:#}", err);
+        }
+
+        if oom_kills? > 0 {
+            return Err(MemoryLimitExceeded {
+                limit: ctx.build.memory_max.unwrap_or_default(),
+            }
+            .into());
+        }
+    }
 
+    result?;
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use async_trait::async_trait;
+    use in_toto::crypto::{KeyType, SignatureScheme};
+    use rebuilderd_common::attestation::Attestation;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::os::unix::fs::PermissionsExt;
+    use std::time::Instant;
+
+    struct CancellingHeartBeat;
+
+    #[async_trait]
+    impl HeartBeat for CancellingHeartBeat {
+        fn interval(&self) -> Duration {
+            Duration::from_millis(100)
+        }
+
+        async fn ping(&self) -> Result<PingQueuedJobResponse> {
+            Ok(PingQueuedJobResponse::Cancel)
+        }
+    }
+
+    /// Serves the same body for every request, returns the url it's listening on
+    fn serve_file(body: &'static [u8]) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let mut request = Vec::new();
+                let mut buf = [0u8; 1024];
+                while !request.ends_with(b"\r\n\r\n") {
+                    let Ok(n @ 1..) = stream.read(&mut buf) else {
+                        break;
+                    };
+                    request.extend(&buf[..n]);
+                }
+                let header = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    body.len()
+                );
+                let _ = stream.write_all(header.as_bytes());
+                let _ = stream.write_all(body);
+            }
+        });
+        format!("http://{addr}")
+    }
 
     #[tokio::test]
     async fn compare_files_equal() {
@@ -321,4 +756,200 @@ mod tests {
             .unwrap();
         assert!(!equal);
     }
+
+    #[tokio::test]
+    async fn digest_empty_file() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a"), b"").unwrap();
+        let digests = digest_file(&dir.path().join("a")).await.unwrap();
+        assert_eq!(
+            digests.sha256,
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            digests.sha512,
+            "cf83e1357eefb8bdf1542850d66d8007d620e4050b5715dc83f4a921d36ce9ce47d0d13c5d85f2b0ff8318d2877eec2f63b931bd47417a81a538327af927da3e"
+        );
+    }
+
+    #[tokio::test]
+    async fn digest_large_file() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a"), [0u8; 4096 * 100 + 1]).unwrap();
+        let digests = digest_file(&dir.path().join("a")).await.unwrap();
+        assert_eq!(
+            digests.sha256,
+            "4874b1c97efb20beb516c7d6ede2e318bf0fece60f78ac3ce2894f3c7f45cc65"
+        );
+        assert_eq!(
+            digests.sha512,
+            "fe48896018dac4d0883e269e78c0ce81d68df564c4356c1b54227aa03d53c578f54b76ea531da004f9c763a0f240cbae19676f0983e82a7d824f1cf0f7f915f9"
+        );
+    }
+
+    #[tokio::test]
+    async fn rebuild_is_stopped_when_job_is_cancelled() {
+        let privkey = PrivateKey::new(KeyType::Ed25519).unwrap();
+        let privkey = PrivateKey::from_pkcs8(&privkey, SignatureScheme::Ed25519).unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let backend = dir.path().join("backend");
+        fs::write(&backend, b"#!/bin/sh\nexec sleep 60\n").unwrap();
+        fs::set_permissions(&backend, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let endpoint = serve_file(b"");
+        let ctx = Context {
+            artifacts: vec![QueuedJobArtifact {
+                name: "empty".to_string(),
+                version: "0.1.0-1".to_string(),
+                architecture: "amd64".to_string(),
+                url: format!("{endpoint}/empty_0.1.0-1_amd64.deb"),
+            }],
+            input_url: Some(format!("{endpoint}/empty_0.1.0-1_amd64.buildinfo")),
+            backend_name: "debian".to_string(),
+            backend: config::Backend { path: backend },
+            worker_name: "worker".to_string(),
+            build: config::Build {
+                silent: true,
+                ..Default::default()
+            },
+            diffoscope: Default::default(),
+            privkey: &privkey,
+            log_stream: None,
+            cancel: Some(watch::channel(false).0),
+            cgroups: None,
+        };
+
+        let started = Instant::now();
+        let mut log = Vec::new();
+        let outcome = rebuild_with_heartbeat(&ctx, &mut log, &CancellingHeartBeat)
+            .await
+            .unwrap();
+
+        assert!(outcome.is_none());
+        assert!(ctx.is_cancelled());
+        assert!(started.elapsed() < Duration::from_secs(30));
+    }
+
+    #[tokio::test]
+    async fn statement_attestation_is_signed() {
+        let privkey = PrivateKey::new(KeyType::Ed25519).unwrap();
+        let privkey = PrivateKey::from_pkcs8(&privkey, SignatureScheme::Ed25519).unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let input_path = dir.path().join("empty_0.1.0-1_amd64.buildinfo");
+        fs::write(&input_path, b"").unwrap();
+
+        let artifact = QueuedJobArtifact {
+            name: "empty".to_string(),
+            version: "0.1.0-1".to_string(),
+            architecture: "amd64".to_string(),
+            url: "https://example.com/empty_0.1.0-1_amd64.deb".to_string(),
+        };
+        let ctx = Context {
+            artifacts: vec![artifact.clone()],
+            input_url: Some("https://example.com/empty_0.1.0-1_amd64.buildinfo".to_string()),
+            backend_name: "debian".to_string(),
+            backend: config::Backend {
+                path: "/bin/true".into(),
+            },
+            worker_name: "worker".to_string(),
+            build: Default::default(),
+            diffoscope: Default::default(),
+            privkey: &privkey,
+            log_stream: None,
+            cancel: None,
+            cgroups: None,
+        };
+        let upstream = digest_file(&input_path).await.unwrap();
+
+        let json = statement_attestation(
+            &ctx,
+            &artifact,
+            Path::new("empty_0.1.0-1_amd64.deb"),
+            &input_path,
+            &upstream,
+        )
+        .await
+        .unwrap();
+
+        let attestation = Attestation::parse(json.as_bytes()).unwrap();
+        let subject = attestation.verify(1, [privkey.public()]).unwrap();
+        assert_eq!(subject.len(), 1);
+        assert_eq!(subject[0].name.as_deref(), Some("empty_0.1.0-1_amd64.deb"));
+        assert_eq!(subject[0].digest["sha256"], upstream.sha256);
+
+        let Attestation::Dsse(envelope) = attestation else {
+            panic!("Expected a DSSE envelope");
+        };
+        let statement = Statement::parse(&envelope.payload().unwrap()).unwrap();
+        let predicate = statement.rebuild_predicate().unwrap().unwrap();
+        assert_eq!(predicate.backend, "debian");
+        assert_eq!(predicate.worker, "worker");
+        assert_eq!(
+            predicate.upstream.uri.as_deref(),
+            Some(artifact.url.as_str())
+        );
+    }
+
+    #[tokio::test]
+    async fn verdict_attestation_is_signed_but_does_not_vouch() {
+        let privkey = PrivateKey::new(KeyType::Ed25519).unwrap();
+        let privkey = PrivateKey::from_pkcs8(&privkey, SignatureScheme::Ed25519).unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let input_path = dir.path().join("empty_0.1.0-1_amd64.buildinfo");
+        fs::write(&input_path, b"").unwrap();
+        let rebuilt_path = dir.path().join("empty_0.1.0-1_amd64.deb");
+        fs::write(&rebuilt_path, b"different").unwrap();
+
+        let artifact = QueuedJobArtifact {
+            name: "empty".to_string(),
+            version: "0.1.0-1".to_string(),
+            architecture: "amd64".to_string(),
+            url: "https://example.com/empty_0.1.0-1_amd64.deb".to_string(),
+        };
+        let ctx = Context {
+            artifacts: vec![artifact.clone()],
+            input_url: Some("https://example.com/empty_0.1.0-1_amd64.buildinfo".to_string()),
+            backend_name: "debian".to_string(),
+            backend: config::Backend {
+                path: "/bin/true".into(),
+            },
+            worker_name: "worker".to_string(),
+            build: Default::default(),
+            diffoscope: Default::default(),
+            privkey: &privkey,
+            log_stream: None,
+            cancel: None,
+            cgroups: None,
+        };
+        let upstream = digest_file(&input_path).await.unwrap();
+        let rebuilt = digest_file(&rebuilt_path).await.unwrap();
+
+        let json = verdict_attestation(
+            &ctx,
+            &artifact,
+            Path::new("empty_0.1.0-1_amd64.deb"),
+            &input_path,
+            BuildStatus::Bad,
+            &upstream,
+            Some(&rebuilt),
+        )
+        .await
+        .unwrap();
+
+        let attestation = Attestation::parse(json.as_bytes()).unwrap();
+        let subject = attestation.verify(1, [privkey.public()]).unwrap();
+        assert!(subject.is_empty());
+
+        let Attestation::Dsse(envelope) = attestation else {
+            panic!("Expected a DSSE envelope");
+        };
+        let statement = Statement::parse(&envelope.payload().unwrap()).unwrap();
+        assert_eq!(statement.subject[0].digest["sha256"], upstream.sha256);
+        let predicate = statement.verdict_predicate().unwrap().unwrap();
+        assert_eq!(predicate.status, BuildStatus::Bad);
+        assert_eq!(predicate.rebuilt.unwrap().digest["sha256"], rebuilt.sha256);
+    }
 }
@@ -0,0 +1,193 @@
+use crate::config;
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+
+const BWRAP: &str = "bwrap";
+/// The environment of the worker is not passed on, the backend gets a fixed PATH instead
+const PATH: &str = "/usr/local/sbin:/usr/local/bin:/usr/bin:/usr/sbin:/bin:/sbin";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Mount {
+    ReadOnly(PathBuf),
+    Writable(PathBuf),
+}
+
+/// Runs a process with bubblewrap in new user, mount, pid, ipc, uts and cgroup namespaces and a new session. Only /proc,
+/// /dev, an empty /tmp and the paths that have been bound explicitly are visible inside, and only PATH and the given
+/// variables are set in its environment.
+#[derive(Debug, Clone)]
+pub struct Sandbox {
+    bwrap: PathBuf,
+    mounts: Vec<Mount>,
+    network: config::NetworkPolicy,
+}
+
+impl Sandbox {
+    pub fn new(config: &config::Sandbox) -> Sandbox {
+        let mounts = config
+            .ro_binds
+            .iter()
+            .cloned()
+            .map(Mount::ReadOnly)
+            .chain(config.binds.iter().cloned().map(Mount::Writable))
+            .collect();
+
+        Sandbox {
+            bwrap: config.bwrap.clone().unwrap_or_else(|| PathBuf::from(BWRAP)),
+            mounts,
+            network: config.network,
+        }
+    }
+
+    /// Mounts a path read-only at the same location inside the sandbox
+    pub fn ro_bind<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.mounts.push(Mount::ReadOnly(path.into()));
+        self
+    }
+
+    /// Mounts a path writable at the same location inside the sandbox
+    pub fn bind<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.mounts.push(Mount::Writable(path.into()));
+        self
+    }
+
+    fn args(&self, bin: &Path, envs: &HashMap<String, String>) -> Vec<OsString> {
+        let mut args = vec![
+            "--die-with-parent".into(),
+            "--new-session".into(),
+            "--unshare-all".into(),
+        ];
+        if self.network == config::NetworkPolicy::Host {
+            args.push("--share-net".into());
+        }
+        args.extend(
+            ["--proc", "/proc", "--dev", "/dev", "--tmpfs", "/tmp"]
+                .into_iter()
+                .map(OsString::from),
+        );
+
+        for mount in &self.mounts {
+            let (flag, path) = match mount {
+                Mount::ReadOnly(path) => ("--ro-bind", path),
+                Mount::Writable(path) => ("--bind", path),
+            };
+            args.push(flag.into());
+            args.push(path.into());
+            args.push(path.into());
+        }
+
+        args.extend(["--clearenv", "--setenv", "PATH", PATH].map(OsString::from));
+        let mut envs = envs.iter().collect::<Vec<_>>();
+        envs.sort();
+        for (key, value) in envs {
+            args.extend(["--setenv", key, value].map(OsString::from));
+        }
+
+        // the working directory of the worker holds its private key
+        args.extend(["--chdir", "/tmp", "--"].into_iter().map(OsString::from));
+        args.push(bin.into());
+        args
+    }
+
+    /// Creates a command that executes `bin` inside the sandbox with `envs` as its environment, arguments for `bin` can
+    /// be added to it afterwards
+    pub fn command(&self, bin: &Path, envs: &HashMap<String, String>) -> Command {
+        let mut cmd = Command::new(&self.bwrap);
+        cmd.args(self.args(bin, envs));
+        cmd
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(sandbox: &Sandbox) -> Vec<String> {
+        let envs = HashMap::from([(
+            "REBUILDERD_OUTDIR".to_string(),
+            "/tmp/rebuilderd1234/out".to_string(),
+        )]);
+        sandbox
+            .args(
+                Path::new("/usr/libexec/rebuilderd/rebuilder-archlinux.sh"),
+                &envs,
+            )
+            .into_iter()
+            .map(|arg| arg.into_string().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn sandbox_without_network() {
+        let sandbox = Sandbox::new(&config::Sandbox {
+            enabled: true,
+            bwrap: None,
+            ro_binds: vec!["/usr".into()],
+            binds: vec!["/var/cache/pacman".into()],
+            network: config::NetworkPolicy::None,
+        })
+        .ro_bind("/tmp/rebuilderd1234/inputs")
+        .bind("/tmp/rebuilderd1234/out");
+
+        assert_eq!(
+            args(&sandbox),
+            [
+                "--die-with-parent",
+                "--new-session",
+                "--unshare-all",
+                "--proc",
+                "/proc",
+                "--dev",
+                "/dev",
+                "--tmpfs",
+                "/tmp",
+                "--ro-bind",
+                "/usr",
+                "/usr",
+                "--bind",
+                "/var/cache/pacman",
+                "/var/cache/pacman",
+                "--ro-bind",
+                "/tmp/rebuilderd1234/inputs",
+                "/tmp/rebuilderd1234/inputs",
+                "--bind",
+                "/tmp/rebuilderd1234/out",
+                "/tmp/rebuilderd1234/out",
+                "--clearenv",
+                "--setenv",
+                "PATH",
+                PATH,
+                "--setenv",
+                "REBUILDERD_OUTDIR",
+                "/tmp/rebuilderd1234/out",
+                "--chdir",
+                "/tmp",
+                "--",
+                "/usr/libexec/rebuilderd/rebuilder-archlinux.sh",
+            ]
+        );
+    }
+
+    #[test]
+    fn sandbox_with_host_network() {
+        let sandbox = Sandbox::new(&config::Sandbox {
+            enabled: true,
+            bwrap: Some("/usr/bin/bwrap".into()),
+            network: config::NetworkPolicy::Host,
+            ..Default::default()
+        });
+
+        assert_eq!(sandbox.bwrap, Path::new("/usr/bin/bwrap"));
+        assert_eq!(
+            args(&sandbox)[..4],
+            [
+                "--die-with-parent",
+                "--new-session",
+                "--unshare-all",
+                "--share-net"
+            ]
+        );
+    }
+}